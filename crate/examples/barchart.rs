@@ -1,10 +1,12 @@
 extern crate gdl;
 use gdl::diagrams::Chart;
+use gdl::fonts::FontRegistry;
 use gdl::{diagrams, new_with_background, Rgb};
 use std::time::Instant;
 
-fn main() {
+fn main() -> gdl::Result<()> {
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
 
     let _white = Rgb {
         r: 255,
@@ -42,12 +44,14 @@ fn main() {
         1500,
     );
 
-    diagrams::draw_vertical_gradient_barchart(&mut img, &barchart, "lemongrass");
-    gdl::helpers::save_image(img, "example_output/barchart.png");
+    diagrams::draw_vertical_gradient_barchart(&mut img, &fonts, &barchart, "lemongrass")?;
+    gdl::helpers::save_image(img, "example_output/barchart.png")?;
 
     println!(
         "Took {} seconds to create image.",
         start.elapsed().as_secs()
     );
     println!("You'll find the output image in examples/example_output");
+
+    Ok(())
 }
@@ -1,11 +1,13 @@
 extern crate gdl;
 use gdl::elements::*;
+use gdl::fonts::{FontDescriptor, FontRegistry};
 use gdl::text::*;
 use gdl::{new_with_background, Rgb};
 use std::time::Instant;
 
-fn main() {
+fn main() -> gdl::Result<()> {
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
 
     // Create black and white Rgb colours.
     let white = Rgb {
@@ -27,37 +29,54 @@ fn main() {
     let mut img = new_with_background(width, height, &black);
 
     // Draw gradients
-    draw_preset_rect_gradient(&mut img, 300, 300, 30, 30, "pinkblue");
-    draw_preset_rect_gradient(&mut img, 300, 300, 330, 30, "pink_pastel");
-    draw_preset_rect_gradient(&mut img, 300, 300, 630, 30, "lemongrass");
+    draw_preset_rect_gradient(&mut img, 300, 300, 30, 30, "pinkblue")?;
+    draw_preset_rect_gradient(&mut img, 300, 300, 330, 30, "pink_pastel")?;
+    draw_preset_rect_gradient(&mut img, 300, 300, 630, 30, "lemongrass")?;
 
     // Draw text
-    draw_text(&mut img, "pink_blue", 90, 350, "Roboto-Light", 60.0, &white);
     draw_text(
         &mut img,
+        &fonts,
+        "pink_blue",
+        90,
+        350,
+        &FontDescriptor::new("Roboto").weight(300),
+        60.0,
+        &white,
+    )?;
+    draw_text(
+        &mut img,
+        &fonts,
         "pink_pastel",
         350,
         350,
-        "Roboto-Light",
+        &FontDescriptor::new("Roboto").weight(300),
         60.0,
         &white,
-    );
+    )?;
     draw_text(
         &mut img,
+        &fonts,
         "lemongrass",
         650,
         350,
-        "Roboto-Light",
+        &FontDescriptor::new("Roboto").weight(300),
         60.0,
         &white,
-    );
+    )?;
 
     // Write the contents of this image in PNG format.
-    gdl::helpers::save_image(img, "example_output/gradients.png");
+    gdl::helpers::save_image(img, "example_output/gradients.png")?;
 
     println!(
         "Took {} seconds to create image.",
         start.elapsed().as_secs()
     );
+    println!(
+        "Gradient presets: {}",
+        gdl::palettes::registry().gradient_names().join(", ")
+    );
     println!("You'll find the output image in examples/example_output");
+
+    Ok(())
 }
@@ -1,12 +1,13 @@
 extern crate gdl;
 use gdl::diagrams::Chart;
+use gdl::fonts::FontRegistry;
 use gdl::{diagrams, new_with_background, Rgb};
 use std::time::Instant;
 
-fn main() {
+fn main() -> gdl::Result<()> {
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
 
-    let _beach = gdl::helpers::open_image("examples/input_images/beach.jpg");
     let _white = Rgb {
         r: 255,
         g: 255,
@@ -37,12 +38,14 @@ fn main() {
         1500,
     );
 
-    diagrams::draw_linechart(&mut img, &barchart);
-    gdl::helpers::save_image(img, "postcard.png");
+    diagrams::draw_linechart(&mut img, &fonts, &barchart)?;
+    gdl::helpers::save_image(img, "postcard.png")?;
 
     println!(
         "Took {} seconds to create image.",
         start.elapsed().as_secs()
     );
     println!("You'll find the output image in examples/example_output");
+
+    Ok(())
 }
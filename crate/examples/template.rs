@@ -0,0 +1,51 @@
+extern crate gdl;
+use gdl::fonts::FontRegistry;
+use gdl::template::Template;
+use std::collections::HashMap;
+use std::time::Instant;
+
+fn main() -> gdl::Result<()> {
+    let start = Instant::now();
+    let fonts = FontRegistry::embedded();
+
+    // Load a quote card, which is checked as it is loaded.
+    let template = Template::from_file("examples/templates/quote_card.toml")?;
+    println!("Placeholders: {}", template.placeholders().join(", "));
+
+    // Fill in the placeholders, making one card for each quote.
+    let quotes = [
+        (
+            "The best way to **predict** the future is to invent it.",
+            "Alan Kay",
+            "pinkblue",
+            "#6a1b9a",
+        ),
+        (
+            "Simplicity is **prerequisite** for reliability.",
+            "Edsger W. Dijkstra",
+            "lemongrass",
+            "#2e7d32",
+        ),
+    ];
+    for (index, (quote, author, gradient, accent)) in quotes.iter().enumerate() {
+        let mut variables = HashMap::new();
+        variables.insert("quote".to_string(), quote.to_string());
+        variables.insert("author".to_string(), author.to_string());
+        variables.insert("gradient".to_string(), gradient.to_string());
+        variables.insert("accent".to_string(), accent.to_string());
+
+        let img = template.render(&fonts, &variables)?;
+        img.save(format!("example_output/quote_card_{}.png", index + 1))?;
+    }
+
+    // Templates can be written back out, such as to convert them from TOML to JSON.
+    std::fs::write("example_output/quote_card.json", template.to_json()?)?;
+
+    println!(
+        "Took {} seconds to create images.",
+        start.elapsed().as_secs()
+    );
+    println!("You'll find the output images in examples/example_output");
+
+    Ok(())
+}
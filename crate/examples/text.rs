@@ -1,10 +1,12 @@
 extern crate gdl;
+use gdl::fonts::{FontDescriptor, FontRegistry};
 use gdl::text::*;
 use gdl::{new_with_background, Rgb};
 use std::time::Instant;
 
-fn main() {
+fn main() -> gdl::Result<()> {
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
 
     // Create black and white Rgb colours.
     let white = Rgb {
@@ -23,52 +25,67 @@ fn main() {
     // to maintain simplicity and showcase the fundamentals of GDL.
     draw_text(
         &mut img,
+        &fonts,
         "Roboto Black",
         20,
         30,
-        "Roboto-Black",
+        &FontDescriptor::new("Roboto").weight(900),
         110.0,
         &white,
-    );
+    )?;
 
     draw_text(
         &mut img,
+        &fonts,
         "Roboto Bold",
         20,
         120,
-        "Roboto-Bold",
+        &FontDescriptor::new("Roboto").bold(),
         90.0,
         &white,
-    );
+    )?;
 
     draw_text(
         &mut img,
+        &fonts,
         "Roboto Regular",
         20,
         210,
-        "Roboto-Regular",
+        &FontDescriptor::new("Roboto"),
         80.0,
         &white,
-    );
+    )?;
 
     draw_text(
         &mut img,
+        &fonts,
         "Roboto Light",
         20,
         300,
-        "Roboto-Light",
+        &FontDescriptor::new("Roboto").weight(300),
         70.0,
         &white,
-    );
+    )?;
 
-    draw_text(&mut img, "Bebas Kai", 20, 390, "BebasKai", 60.0, &white);
+    draw_text(
+        &mut img,
+        &fonts,
+        "Bebas Kai",
+        20,
+        390,
+        &FontDescriptor::new("Bebas Kai"),
+        60.0,
+        &white,
+    )?;
 
     // Write the contents of this image in PNG format.
-    gdl::helpers::save_image(img, "example_output/text_output.png");
+    gdl::helpers::save_image(img, "example_output/text_output.png")?;
 
     println!(
         "Took {} seconds to create image.",
         start.elapsed().as_secs()
     );
     println!("You'll find the output image in examples/example_output");
+
+    Ok(())
 }
@@ -1,10 +1,14 @@
 extern crate gdl;
+use gdl::fonts::{FontDescriptor, FontRegistry};
+use gdl::style::{Fill, Glow, Shadow, TextStyle};
 use gdl::text::*;
 use gdl::{new_with_background, Rgb};
+use image::Rgba;
 use std::time::Instant;
 
-fn main() {
+fn main() -> gdl::Result<()> {
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
 
     // Create black and white Rgb colours.
     let _white = Rgb {
@@ -28,61 +32,89 @@ fn main() {
 
     draw_vertical_text(
         &mut img,
-        "Roboto Bold",
+        &fonts,
+        "Roboto-Bold",
         50,
         200,
-        "Roboto-Bold",
+        &FontDescriptor::new("Roboto").bold(),
         90.0,
         "left",
         &rgb3,
-    );
+    )?;
 
     draw_vertical_text(
         &mut img,
+        &fonts,
         "Roboto Regular",
         width - 150,
         220,
-        "Roboto-Regular",
+        &FontDescriptor::new("Roboto"),
         90.0,
         "right",
         &rgb3,
-    );
+    )?;
 
     draw_upsidedown_text(
         &mut img,
+        &fonts,
         "Break The Rules.",
         10,
         120,
-        "Roboto Bold",
+        &FontDescriptor::new("Roboto").bold(),
         90.0,
         &rgb3,
-    );
+    )?;
 
     draw_vertical_text_single(
         &mut img,
+        &fonts,
         "Roboto",
         width / 2,
         height / 3 - 100,
-        "Roboto-Regular",
+        &FontDescriptor::new("Roboto"),
         90.0,
         &rgb3,
-    );
+    )?;
     draw_vertical_text_single(
         &mut img,
+        &fonts,
         "HELLO",
         (width / 2) + 100,
         height / 3 - 100,
-        "BebasKai",
+        &FontDescriptor::new("Bebas Kai"),
         90.0,
         &rgb3,
-    );
+    )?;
+
+    // Text can be styled with an outline, drop shadow, glow and gradient fill.
+    let style = TextStyle::new()
+        .fill(Fill::LinearGradient {
+            start: Rgba([255, 226, 98, 255]),
+            end: Rgba([234, 23, 123, 255]),
+            angle: 90.0,
+        })
+        .stroke(3, Rgba([255, 255, 255, 255]))
+        .shadow(Shadow::new(6, 6).blur(6.0).opacity(0.8))
+        .glow(Glow::new(12).color(Rgba([234, 23, 123, 255])).opacity(0.6));
+    draw_text(
+        &mut img,
+        &fonts,
+        "Styled",
+        200,
+        height - 120,
+        &FontDescriptor::new("Bebas Kai"),
+        100.0,
+        &style,
+    )?;
 
     // Write the contents of this image in PNG format.
-    img.save("example_output/text_effects.png").unwrap();
+    img.save("example_output/text_effects.png")?;
 
     println!(
         "Took {} seconds to create image.",
         start.elapsed().as_secs()
     );
     println!("You'll find the output image in examples/example_output");
+
+    Ok(())
 }
@@ -1,12 +1,12 @@
 //! Create various backgrounds comprising patterns, gradients, imagery, etc.,
 
-use image::{DynamicImage, GenericImage};
+use image::DynamicImage;
 // use wasm_bindgen::prelude::*;
+use crate::compositing::{composite, BlendMode};
+use crate::elements::create_gradient;
 use crate::Rgb;
-use image::{GenericImageView, ImageBuffer, RgbaImage};
+use image::{GenericImageView, ImageBuffer};
 use imageproc::drawing::{draw_filled_circle_mut, draw_line_segment_mut};
-use palette::rgb::LinSrgba;
-use palette::{FromColor, Gradient, Lch, Pixel, Srgba};
 use std::convert::TryInto;
 
 /// Create a background image containing circles.
@@ -157,64 +157,32 @@ pub fn pattern_from_img(
 
     for x in 0..8 {
         for y in 0..10 {
-            image::imageops::overlay(
+            composite(
                 &mut rgba_img,
                 &img,
-                x + img.width() * 3,
-                y * img.height() + 50,
+                (x + img.width() * 3) as i32,
+                (y * img.height() + 50) as i32,
+                BlendMode::Normal,
+                1.0,
             );
         }
     }
     return rgba_img;
 }
 
-/// Create a gradient background.
-/// /// Returns a DynamicImage.
+/// Create a gradient background, running from red through blue to green.
+/// Returns a DynamicImage.
 ///
 /// # Arguments
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
 
 pub fn gradient_background(width: u32, height: u32) -> DynamicImage {
-    let mut image = RgbaImage::new(width, height);
-    let grad1 = Gradient::new(vec![
-        LinSrgba::new(1.0, 0.1, 0.1, 1.0),
-        LinSrgba::new(0.1, 0.1, 1.0, 1.0),
-        LinSrgba::new(0.1, 1.0, 0.1, 1.0),
-    ]);
-
-    //The same colors and offsets as in grad1, but in a color space where the hue
-    // is a component
-    let _grad3 = Gradient::new(vec![
-        Lch::from_color(LinSrgba::new(1.0, 0.1, 0.1, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 0.1, 1.0, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 1.0, 0.1, 1.0)),
-    ]);
-
-    for (i, c1) in grad1.take(width as usize).enumerate() {
-        let c1 = Srgba::from_linear(c1).into_format().into_raw();
-        {
-            let mut sub_image = image.sub_image(i as u32, 0, 1, height);
-            let (width, height) = sub_image.dimensions();
-            for x in 0..width {
-                for y in 0..height {
-                    sub_image.put_pixel(x, y, image::Rgba(c1));
-                }
-            }
-        }
-    }
-    let rgba_img = image::DynamicImage::ImageRgba8(image);
-    let _raw_pixels = rgba_img.to_bytes();
-    return rgba_img;
+    create_gradient(width, height)
 }
 
 fn create_image_from_pixel(background_color: Rgb, width: u32, height: u32) -> DynamicImage {
-    let pixel = image::Rgba([
-        background_color.r,
-        background_color.g,
-        background_color.b,
-        255,
-    ]);
+    let pixel = image::Rgba::from(background_color);
     let image_buffer = ImageBuffer::from_pixel(width, height, pixel);
     let rgba_img = image::DynamicImage::ImageRgba8(image_buffer);
     return rgba_img;
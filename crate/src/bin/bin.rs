@@ -1,11 +1,14 @@
+use gdl::fonts::{FontDescriptor, FontRegistry};
+use gdl::presets::PresetOptions;
 use gdl::text::*;
 use gdl::{new_with_background, presets, Rgb};
 use std::time::Instant;
 
 // See the examples dir for more examples
-fn main() {
+fn main() -> gdl::Result<()> {
     // GRAPHIC 1
     let start = Instant::now();
+    let fonts = FontRegistry::embedded();
     let yellow = Rgb {
         r: 255,
         g: 226,
@@ -15,10 +18,11 @@ fn main() {
 
     presets::text_banner(
         &mut img,
+        &fonts,
         "The Lemonade Co.",
-        "Making great lemonade since 2002.",
-    );
-    gdl::helpers::save_image(img, "output_graphic1.png");
+        &PresetOptions::new().subtitle("Making great lemonade since 2002."),
+    )?;
+    gdl::helpers::save_image(img, "output_graphic1.png")?;
 
     println!(
         "Took {} seconds to create Graphic 1.",
@@ -26,25 +30,35 @@ fn main() {
     );
 
     // GRAPHIC 2
-    let mut alleyway = gdl::helpers::open_image("examples/input_images/city_square.jpg");
+    let mut alleyway = gdl::helpers::open_image("examples/input_images/city_square.jpg")?;
     let white = Rgb {
         r: 255,
         g: 255,
         b: 255,
     };
 
-    draw_text(&mut alleyway, "Visit", 230, 270, "BebasKai", 140.0, &white);
     draw_text(
         &mut alleyway,
+        &fonts,
+        "Visit",
+        230,
+        270,
+        &FontDescriptor::new("Bebas Kai"),
+        140.0,
+        &white,
+    )?;
+    draw_text(
+        &mut alleyway,
+        &fonts,
         "Stockholm",
         230,
         390,
-        "BebasKai",
+        &FontDescriptor::new("Bebas Kai"),
         140.0,
         &white,
-    );
+    )?;
 
-    gdl::helpers::save_image(alleyway, "output_graphic2.png");
+    gdl::helpers::save_image(alleyway, "output_graphic2.png")?;
 
     println!(
         "Took {} seconds to create Graphic 2.",
@@ -52,7 +66,7 @@ fn main() {
     );
 
     // GRAPHIC 3
-    let mut night = gdl::helpers::open_image("examples/input_images/drive.jpg");
+    let mut night = gdl::helpers::open_image("examples/input_images/drive.jpg")?;
     let white = Rgb {
         r: 255,
         g: 255,
@@ -61,18 +75,21 @@ fn main() {
 
     draw_vertical_text(
         &mut night,
+        &fonts,
         "BUCHAREST BY NIGHT",
         0,
         0,
-        "BebasKai",
+        &FontDescriptor::new("Bebas Kai"),
         110.0,
         "right",
         &white,
-    );
-    gdl::helpers::save_image(night, "output_graphic3.png");
+    )?;
+    gdl::helpers::save_image(night, "output_graphic3.png")?;
 
     println!(
         "Took {} seconds to create Graphic 3.",
         start.elapsed().as_secs()
     );
+
+    Ok(())
 }
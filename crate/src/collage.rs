@@ -1,10 +1,12 @@
 //! Create image collages.
 
+use crate::compositing::{composite, fill_rect, BlendMode};
 use crate::elements::*;
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::layout::ParagraphOptions;
 use crate::text::*;
-use crate::Rgb;
+use crate::{Result, Rgb};
 use image::{DynamicImage, GenericImageView, Rgba};
-use imageproc::drawing::draw_filled_rect_mut;
 use imageproc::rect::Rect;
 
 /// Two grid collage.
@@ -28,8 +30,8 @@ pub fn two_grid(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
 
     // return the collage
     return container_img;
@@ -39,15 +41,17 @@ pub fn two_grid(
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `img2` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `main_text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
 pub fn two_grid_text(
     image: DynamicImage,
     image2: DynamicImage,
+    fonts: &FontRegistry,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     // distribute the width evenly by allocating the same space to both images
     let img_width = width / 2;
     let img_height = height / 2;
@@ -68,8 +72,8 @@ pub fn two_grid_text(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &image, 0, 0);
-    image::imageops::overlay(&mut container_img, &image2, image.width(), image.height());
+    place(&mut container_img, &image, 0, 0);
+    place(&mut container_img, &image2, image.width(), image.height());
 
     let lilac = Rgb {
         r: 204,
@@ -103,17 +107,19 @@ pub fn two_grid_text(
         b: 255,
     };
 
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         "Daisies In the Underground",
         image.width() + 30,
         img_height / 2,
-        "Roboto-Bold",
+        &FontDescriptor::new("Roboto").bold(),
         30.0,
         &rgb_white,
-    );
+        &ParagraphOptions::new().max_width(img_width as f32 - 60.0),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
 /// Split-pane collage, with text on LHS and collage on RHS.
@@ -121,15 +127,17 @@ pub fn two_grid_text(
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `img2` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `main_text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
 pub fn split_imgs_text(
     image: DynamicImage,
     image2: DynamicImage,
+    fonts: &FontRegistry,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     // distribute the width evenly by allocating the same space to both images
     let img_width = width / 2;
     let img_height = height / 2;
@@ -150,8 +158,8 @@ pub fn split_imgs_text(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &image, img_width, 0);
-    image::imageops::overlay(&mut container_img, &image2, image.width(), image.height());
+    place(&mut container_img, &image, img_width, 0);
+    place(&mut container_img, &image2, image.width(), image.height());
 
     let white = Rgb {
         r: 255,
@@ -161,17 +169,19 @@ pub fn split_imgs_text(
 
     draw_solid_rect(&mut container_img, &white, img_height * 2, img_width, 0, 0);
 
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         "Life Is An Adventure",
         45,
         img_height / 2,
-        "BebasKai",
+        &FontDescriptor::new("Bebas Kai"),
         80.0,
         &white,
-    );
+        &ParagraphOptions::new().max_width(img_width as f32 - 90.0),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
 /// Four grid collage.
@@ -201,10 +211,10 @@ pub fn four_grid(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[2], 0, img_height);
-    image::imageops::overlay(&mut container_img, &imgs[3], img_width, img_height);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[2], 0, img_height);
+    place(&mut container_img, &imgs[3], img_width, img_height);
 
     // return the collage
     return container_img;
@@ -233,9 +243,9 @@ pub fn triple_grid(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[2], img_width * 2, 0);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[2], img_width * 2, 0);
 
     // return the collage
     return container_img;
@@ -248,18 +258,21 @@ pub fn triple_grid(
 /// * `img2` - A mutable ref to a DynamicImage.
 /// * `img3` - A mutable ref to a DynamicImage.
 /// * `img4` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
+#[allow(clippy::too_many_arguments)]
 pub fn four_grid_center_square(
     photon_img: &DynamicImage,
     photon_img2: &DynamicImage,
     photon_img3: &DynamicImage,
     photon_img4: &DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     let imgs = vec![photon_img, photon_img2, photon_img3, photon_img4];
 
     // distribute the width evenly by allocating the same space to both images
@@ -270,10 +283,10 @@ pub fn four_grid_center_square(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[2], 0, img_height);
-    image::imageops::overlay(&mut container_img, &imgs[3], img_width, img_height);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[2], 0, img_height);
+    place(&mut container_img, &imgs[3], img_width, img_height);
 
     let white_rgb = Rgb {
         r: 255,
@@ -283,18 +296,6 @@ pub fn four_grid_center_square(
     let black_rgb = Rgb { r: 0, g: 0, b: 0 };
     // Draw a square in the center
 
-    let mut height_mul: f32 = 0.2;
-    let mut word_vec = vec![];
-    // Split words
-    for word in text.split_whitespace() {
-        println!("> {}", word);
-        if word.len() > 7 {
-            word_vec.push(word);
-            continue;
-        } else {
-            word_vec.push(word);
-        }
-    }
     draw_solid_rect(
         &mut container_img,
         &white_rgb,
@@ -304,20 +305,19 @@ pub fn four_grid_center_square(
         (height as f32 * 0.15) as i32,
     );
 
-    for word in word_vec {
-        draw_text(
-            &mut container_img,
-            word,
-            (width as f32 * 0.32) as u32,
-            (height as f32 * height_mul) as u32,
-            "BebasKai",
-            100.0,
-            &black_rgb,
-        );
-        height_mul += 0.15;
-    }
+    draw_text_block(
+        &mut container_img,
+        fonts,
+        text,
+        (width as f32 * 0.32) as u32,
+        (height as f32 * 0.2) as u32,
+        &FontDescriptor::new("Bebas Kai"),
+        100.0,
+        &black_rgb,
+        &ParagraphOptions::new().max_width(width as f32 * 0.26),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
 /// Create a moodboard style graphic with 4 images within a collage.
@@ -327,18 +327,21 @@ pub fn four_grid_center_square(
 /// * `img2` - A mutable ref to a DynamicImage.
 /// * `img3` - A mutable ref to a DynamicImage.
 /// * `img4` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
+#[allow(clippy::too_many_arguments)]
 pub fn moodboard(
     photon_img: &DynamicImage,
     photon_img2: &DynamicImage,
     photon_img3: &DynamicImage,
     photon_img4: &DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     // Exclude the first image, since it will have different dimensions when resized.
     let imgs = vec![photon_img2, photon_img3, photon_img4];
 
@@ -360,10 +363,10 @@ pub fn moodboard(
 
     let mut container_img: DynamicImage = DynamicImage::new_rgba8(width, height);
 
-    image::imageops::overlay(&mut container_img, &image, 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[0], first_img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], first_img_width, img_height);
-    image::imageops::overlay(
+    place(&mut container_img, &image, 0, 0);
+    place(&mut container_img, &imgs[0], first_img_width, 0);
+    place(&mut container_img, &imgs[1], first_img_width, img_height);
+    place(
         &mut container_img,
         &imgs[2],
         first_img_width,
@@ -389,17 +392,19 @@ pub fn moodboard(
         (height as f32 * 0.75) as i32,
     );
 
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         text,
         (width as f32 * 0.10) as u32,
         (height as f32 * height_mul) as u32,
-        "Oswald-Regular",
+        &FontDescriptor::new("Oswald"),
         100.0,
         &black_rgb,
-    );
+        &ParagraphOptions::new().max_width(width as f32 * 0.8),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
 /// Three-image collage containing main text, and a feature-style grid.
@@ -408,6 +413,7 @@ pub fn moodboard(
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `img2` - A mutable ref to a DynamicImage.
 /// * `img3` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
@@ -415,10 +421,11 @@ pub fn feature_grid(
     photon_img: &DynamicImage,
     photon_img2: &DynamicImage,
     photon_img3: &DynamicImage,
+    fonts: &FontRegistry,
     main_text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     let imgs = vec![photon_img, photon_img2, photon_img3];
 
     // distribute the width evenly by allocating the same space to all images
@@ -434,27 +441,31 @@ pub fn feature_grid(
         b: 255,
     };
 
-    draw_filled_rect_mut(
+    fill_rect(
         &mut container_img,
         Rect::at(0, 0).of_size((width / 2) as u32, (height / 2) as u32),
-        Rgba([white.r, white.g, white.b, 255u8]),
+        Rgba::from(white),
+        BlendMode::Normal,
+        1.0,
     );
 
-    image::imageops::overlay(&mut container_img, &imgs[0], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], 0, img_height);
-    image::imageops::overlay(&mut container_img, &imgs[2], img_width, img_height);
+    place(&mut container_img, &imgs[0], img_width, 0);
+    place(&mut container_img, &imgs[1], 0, img_height);
+    place(&mut container_img, &imgs[2], img_width, img_height);
 
     let black_rgb = Rgb { r: 0, g: 0, b: 0 };
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         main_text,
         (width as f32 * 0.1) as u32,
         (height as f32 * 0.3) as u32,
-        "BebasKai",
+        &FontDescriptor::new("Bebas Kai"),
         100.0,
         &black_rgb,
-    );
-    return container_img;
+        &ParagraphOptions::new().max_width(width as f32 * 0.35),
+    )?;
+    Ok(container_img)
 }
 
 /// Triple-image collage with a centre square containing text.
@@ -464,6 +475,7 @@ pub fn feature_grid(
 /// * `img2` - A mutable ref to a DynamicImage.
 /// * `img3` - A mutable ref to a DynamicImage.
 /// * `img4` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `text` - Main heading for the graphic.
 /// * `width` - u32 - Desired width of final graphic
 /// * `height` - u32 - Desired height of final graphic
@@ -471,10 +483,11 @@ pub fn triple_grid_text(
     photon_img: &DynamicImage,
     photon_img2: &DynamicImage,
     photon_img3: &DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     let imgs = vec![photon_img, photon_img2, photon_img3];
 
     // distribute the width evenly by allocating the same space to both images
@@ -489,9 +502,9 @@ pub fn triple_grid_text(
         b: 255,
     };
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[2], img_width * 2, 0);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[2], img_width * 2, 0);
 
     let black_rgb = Rgb { r: 0, g: 0, b: 0 };
 
@@ -503,19 +516,22 @@ pub fn triple_grid_text(
         0 as i32,
         (height as f32 * 0.75) as i32,
     );
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         text,
         (width as f32 * 0.05) as u32,
         (height as f32 * 0.8) as u32,
-        "Montserrat-Regular",
+        &FontDescriptor::new("Montserrat"),
         90.0,
         &black_rgb,
-    );
+        &ParagraphOptions::new().max_width(width as f32 * 0.9),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn six_grid_text(
     photon_img: &DynamicImage,
     photon_img2: &DynamicImage,
@@ -523,10 +539,11 @@ pub fn six_grid_text(
     photon_img4: &DynamicImage,
     photon_img5: &DynamicImage,
     photon_img6: &DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     let imgs = vec![
         photon_img,
         photon_img2,
@@ -548,12 +565,12 @@ pub fn six_grid_text(
         b: 255,
     };
 
-    image::imageops::overlay(&mut container_img, &imgs[0], 0, 0);
-    image::imageops::overlay(&mut container_img, &imgs[1], img_width, 0);
-    image::imageops::overlay(&mut container_img, &imgs[2], img_width * 2, 0);
-    image::imageops::overlay(&mut container_img, &imgs[3], 0, img_height * 2);
-    image::imageops::overlay(&mut container_img, &imgs[4], img_width, img_height * 2);
-    image::imageops::overlay(&mut container_img, &imgs[5], img_width * 2, img_height * 2);
+    place(&mut container_img, &imgs[0], 0, 0);
+    place(&mut container_img, &imgs[1], img_width, 0);
+    place(&mut container_img, &imgs[2], img_width * 2, 0);
+    place(&mut container_img, &imgs[3], 0, img_height * 2);
+    place(&mut container_img, &imgs[4], img_width, img_height * 2);
+    place(&mut container_img, &imgs[5], img_width * 2, img_height * 2);
 
     let black_rgb = Rgb { r: 0, g: 0, b: 0 };
 
@@ -565,17 +582,19 @@ pub fn six_grid_text(
         0 as i32,
         img_height as i32,
     );
-    draw_text(
+    draw_text_block(
         &mut container_img,
+        fonts,
         text,
         (width as f32 * 0.05) as u32,
         (img_height + (img_height as f32 * 0.3) as u32) as u32,
-        "Montserrat-Regular",
+        &FontDescriptor::new("Montserrat"),
         90.0,
         &black_rgb,
-    );
+        &ParagraphOptions::new().max_width(width as f32 * 0.9),
+    )?;
 
-    return container_img;
+    Ok(container_img)
 }
 
 // Resize images in a vec, returns a new vec with resized images.
@@ -597,3 +616,15 @@ fn resize_imgs(imgs: Vec<&DynamicImage>, img_width: u32, img_height: u32) -> Vec
 
     return resized_imgs;
 }
+
+// Draw an image onto the collage, blending any transparent parts with what is underneath.
+fn place(container_img: &mut DynamicImage, img: &DynamicImage, x: u32, y: u32) {
+    composite(
+        container_img,
+        img,
+        x as i32,
+        y as i32,
+        BlendMode::Normal,
+        1.0,
+    );
+}
@@ -0,0 +1,760 @@
+//! A colour with an alpha channel, which can be parsed from and formatted as CSS.
+//!
+//! [`Rgba`] can be written the same way as colours on a canvas or in a stylesheet, as hex codes,
+//! `rgb()`, `hsl()`, `lch()`, `oklab()` and `oklch()` functions, or named colours, so the same
+//! strings can be used natively and on the web. It converts to and from HSL, HSV, Lch and Oklab,
+//! and to the colour types of the `image` and `palette` crates.
+//!
+//! ```
+//! use gdl::Rgba;
+//!
+//! let coral: Rgba = "#ff7f50".parse().unwrap();
+//! assert_eq!(coral, Rgba::parse("coral").unwrap());
+//! assert_eq!(coral, Rgba::parse("rgb(255 127 80)").unwrap());
+//!
+//! assert_eq!(coral.with_alpha(0.4).to_css(), "rgba(255, 127, 80, 0.4)");
+//! assert_eq!(coral.darken(0.2).to_hex(), "#b93f00");
+//! assert_eq!(Rgba::parse("hsl(120, 100%, 25%)").unwrap().to_hex(), "#008000");
+//!
+//! let pixel: image::Rgba<u8> = coral.into();
+//! assert_eq!(pixel, image::Rgba([255, 127, 80, 255]));
+//! ```
+
+use crate::gradient::{
+    linear_to_oklab, linear_to_srgb, mix, oklab_to_linear, srgb_to_linear, Interpolation,
+};
+use crate::{Error, Result, Rgb};
+use palette::{FromColor, Lch, LinSrgb, LinSrgba, Srgb, Srgba};
+use std::fmt;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// An sRGB colour with an alpha channel, which is 0 for transparent and 255 for opaque.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[wasm_bindgen]
+impl Rgba {
+    /// Create a new colour.
+    #[wasm_bindgen(constructor)]
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+        Rgba { r, g, b, a }
+    }
+
+    /// Create a new opaque colour.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Rgba {
+        Rgba { r, g, b, a: 255 }
+    }
+
+    /// Parse a CSS colour, for use from JavaScript. See [`Rgba::parse`].
+    #[wasm_bindgen(js_name = parse)]
+    pub fn parse_web(css: &str) -> std::result::Result<Rgba, JsValue> {
+        Ok(Rgba::parse(css)?)
+    }
+
+    /// Create an opaque colour from hue in degrees, and saturation and lightness from 0 to 1.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Rgba {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// Create an opaque colour from hue in degrees, and saturation and value from 0 to 1.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Rgba {
+        let chroma = value * saturation;
+        from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Create an opaque colour from CIE Lch lightness (0 to 100), chroma and hue in degrees.
+    /// Colours outside the sRGB gamut lose chroma until they fit.
+    pub fn from_lch(lightness: f32, chroma: f32, hue: f32) -> Rgba {
+        let to_linear = |chroma: f32| {
+            let linear = LinSrgb::from_color(Lch::new(lightness, chroma, hue));
+            [linear.red, linear.green, linear.blue]
+        };
+        from_linear(fit_chroma(chroma, to_linear))
+    }
+
+    /// Create an opaque colour from Oklab lightness (0 to 1) and its a and b axes.
+    /// Colours outside the sRGB gamut lose chroma until they fit.
+    pub fn from_oklab(lightness: f32, a: f32, b: f32) -> Rgba {
+        let chroma = a.hypot(b);
+        let hue = b.atan2(a);
+        Rgba::from_oklch(lightness, chroma, hue.to_degrees())
+    }
+
+    /// Create an opaque colour from Oklch lightness (0 to 1), chroma and hue in degrees.
+    /// Colours outside the sRGB gamut lose chroma until they fit.
+    pub fn from_oklch(lightness: f32, chroma: f32, hue: f32) -> Rgba {
+        let (sin, cos) = hue.to_radians().sin_cos();
+        let lightness = lightness.clamp(0.0, 1.0);
+        from_linear(fit_chroma(chroma, |chroma| {
+            oklab_to_linear([lightness, chroma * cos, chroma * sin])
+        }))
+    }
+
+    /// The colour as a hex code: "#rrggbb" if it's opaque, and "#rrggbbaa" if not.
+    pub fn to_hex(&self) -> String {
+        match self.a {
+            255 => format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
+            a => format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, a),
+        }
+    }
+
+    /// The colour as a CSS function: "rgb(r, g, b)" if it's opaque, and "rgba(r, g, b, a)" if
+    /// not, with alpha from 0 to 1.
+    pub fn to_css(&self) -> String {
+        match self.a {
+            255 => format!("rgb({}, {}, {})", self.r, self.g, self.b),
+            _ => format!(
+                "rgba({}, {}, {}, {})",
+                self.r,
+                self.g,
+                self.b,
+                (self.alpha() * 1000.0).round() / 1000.0
+            ),
+        }
+    }
+
+    /// Opacity of the colour, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        self.a as f32 / 255.0
+    }
+
+    /// This colour with the given opacity, from 0 to 1.
+    pub fn with_alpha(&self, alpha: f32) -> Rgba {
+        Rgba {
+            a: (alpha.clamp(0.0, 1.0) * 255.0).round() as u8,
+            ..*self
+        }
+    }
+
+    /// This colour with its opacity multiplied by `amount`, from 0 to 1.
+    pub fn fade(&self, amount: f32) -> Rgba {
+        self.with_alpha(self.alpha() * amount)
+    }
+
+    /// A lighter colour with the same hue, made by raising the Oklab lightness by `amount`,
+    /// from 0 to 1.
+    pub fn lighten(&self, amount: f32) -> Rgba {
+        let [lightness, a, b] = self.to_oklab();
+        Rgba::from_oklab(lightness + amount, a, b).with_alpha(self.alpha())
+    }
+
+    /// A darker colour with the same hue, made by lowering the Oklab lightness by `amount`,
+    /// from 0 to 1.
+    pub fn darken(&self, amount: f32) -> Rgba {
+        self.lighten(-amount)
+    }
+
+    /// Mix this colour with another, in Oklab. `amount` is how much of the other colour to
+    /// take, from 0 for this colour to 1 for the other.
+    pub fn mix(&self, other: &Rgba, amount: f32) -> Rgba {
+        mix(
+            (*self).into(),
+            (*other).into(),
+            amount.clamp(0.0, 1.0),
+            Interpolation::Oklab,
+        )
+        .into()
+    }
+}
+
+impl Rgba {
+    /// Parse a CSS colour: a hex code such as "#f12711" or "#f127", a function such as
+    /// "rgb(241, 39, 17)", "rgba(241 39 17 / 50%)", "hsl(6deg 89% 51%)", "lch(52 80 40)",
+    /// "oklab(0.6 0.2 0.1)" or "oklch(0.6 0.2 30)", a named colour such as "tomato", or
+    /// "transparent".
+    ///
+    /// Returns [`Error::InvalidColor`] if the string isn't a colour.
+    pub fn parse(css: &str) -> Result<Rgba> {
+        css.parse()
+    }
+
+    /// Hue in degrees, and saturation and lightness from 0 to 1.
+    pub fn to_hsl(&self) -> [f32; 3] {
+        let (hue, max, min) = hue_max_min(self);
+        let lightness = (max + min) / 2.0;
+        let saturation = match lightness {
+            l if l <= 0.0 || l >= 1.0 => 0.0,
+            l => ((max - min) / (1.0 - (2.0 * l - 1.0).abs())).min(1.0),
+        };
+        [hue, saturation, lightness]
+    }
+
+    /// Hue in degrees, and saturation and value from 0 to 1.
+    pub fn to_hsv(&self) -> [f32; 3] {
+        let (hue, max, min) = hue_max_min(self);
+        let saturation = if max <= 0.0 { 0.0 } else { (max - min) / max };
+        [hue, saturation, max]
+    }
+
+    /// CIE Lch lightness (0 to 100), chroma and hue in degrees.
+    pub fn to_lch(&self) -> [f32; 3] {
+        let lch = Lch::from_color(LinSrgb::from(*self));
+        [lch.l, lch.chroma, lch.hue.to_positive_degrees()]
+    }
+
+    /// Oklab lightness (0 to 1) and its a and b axes.
+    pub fn to_oklab(&self) -> [f32; 3] {
+        linear_to_oklab(self.linear())
+    }
+
+    // The colour's channels in linear sRGB.
+    fn linear(&self) -> [f32; 3] {
+        [self.r, self.g, self.b].map(|channel| srgb_to_linear(channel as f32 / 255.0))
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = Error;
+
+    fn from_str(css: &str) -> Result<Rgba> {
+        let css = css.trim().to_ascii_lowercase();
+        let invalid = || Error::InvalidColor(css.clone());
+
+        if let Some(hex) = css.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(invalid);
+        }
+        if css == "transparent" {
+            return Ok(Rgba::new(0, 0, 0, 0));
+        }
+        if let Ok(index) = NAMED_COLORS.binary_search_by(|(name, _)| name.cmp(&css.as_str())) {
+            let [_, r, g, b] = NAMED_COLORS[index].1.to_be_bytes();
+            return Ok(Rgba::rgb(r, g, b));
+        }
+        let (function, arguments) = css
+            .strip_suffix(')')
+            .and_then(|css| css.split_once('('))
+            .ok_or_else(invalid)?;
+        parse_function(function.trim(), arguments).ok_or_else(invalid)
+    }
+}
+
+impl From<Rgba> for image::Rgba<u8> {
+    fn from(color: Rgba) -> image::Rgba<u8> {
+        image::Rgba([color.r, color.g, color.b, color.a])
+    }
+}
+
+impl From<image::Rgba<u8>> for Rgba {
+    fn from(color: image::Rgba<u8>) -> Rgba {
+        Rgba::new(color[0], color[1], color[2], color[3])
+    }
+}
+
+impl From<Rgb> for Rgba {
+    fn from(color: Rgb) -> Rgba {
+        Rgba::rgb(color.r, color.g, color.b)
+    }
+}
+
+impl From<&Rgb> for Rgba {
+    fn from(color: &Rgb) -> Rgba {
+        Rgba::from(*color)
+    }
+}
+
+impl From<Rgba> for Rgb {
+    /// Drop the alpha channel.
+    fn from(color: Rgba) -> Rgb {
+        Rgb {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+        }
+    }
+}
+
+impl From<Rgb> for image::Rgba<u8> {
+    fn from(color: Rgb) -> image::Rgba<u8> {
+        image::Rgba([color.r, color.g, color.b, 255])
+    }
+}
+
+impl From<&Rgb> for image::Rgba<u8> {
+    fn from(color: &Rgb) -> image::Rgba<u8> {
+        image::Rgba::from(*color)
+    }
+}
+
+impl From<Srgb<u8>> for Rgba {
+    fn from(color: Srgb<u8>) -> Rgba {
+        Rgba::rgb(color.red, color.green, color.blue)
+    }
+}
+
+impl From<Srgba<u8>> for Rgba {
+    fn from(color: Srgba<u8>) -> Rgba {
+        Rgba::new(color.red, color.green, color.blue, color.alpha)
+    }
+}
+
+impl From<Rgba> for Srgba<u8> {
+    fn from(color: Rgba) -> Srgba<u8> {
+        Srgba::new(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl From<Rgba> for LinSrgb {
+    fn from(color: Rgba) -> LinSrgb {
+        let [red, green, blue] = color.linear();
+        LinSrgb::new(red, green, blue)
+    }
+}
+
+impl From<Rgba> for LinSrgba {
+    fn from(color: Rgba) -> LinSrgba {
+        let [red, green, blue] = color.linear();
+        LinSrgba::new(red, green, blue, color.alpha())
+    }
+}
+
+// Hue in degrees, and the largest and smallest channels from 0 to 1.
+fn hue_max_min(color: &Rgba) -> (f32, f32, f32) {
+    let [r, g, b] = [color.r, color.g, color.b].map(|channel| channel as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, max, min)
+}
+
+// Build a colour from its hue, chroma and the value of its smallest channel, which is how
+// HSL and HSV both turn into RGB.
+fn from_hue_chroma(hue: f32, chroma: f32, min: f32) -> Rgba {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let [r, g, b] = [r, g, b].map(|channel| to_u8(channel + min));
+    Rgba::rgb(r, g, b)
+}
+
+// Find the largest chroma, up to the one asked for, whose colour is within the sRGB gamut.
+fn fit_chroma<F: Fn(f32) -> [f32; 3]>(chroma: f32, to_linear: F) -> [f32; 3] {
+    let in_gamut = |linear: [f32; 3]| linear.iter().all(|c| (-0.0001..=1.0001).contains(c));
+    let linear = to_linear(chroma);
+    if in_gamut(linear) {
+        return linear;
+    }
+    let (mut low, mut high) = (0.0, chroma);
+    for _ in 0..16 {
+        let middle = (low + high) / 2.0;
+        if in_gamut(to_linear(middle)) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    to_linear(low)
+}
+
+fn from_linear(linear: [f32; 3]) -> Rgba {
+    let [r, g, b] = linear.map(|channel| to_u8(linear_to_srgb(channel)));
+    Rgba::rgb(r, g, b)
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+// Parse the digits of a "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa" hex code.
+fn parse_hex(digits: &str) -> Option<Rgba> {
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i * 2..i * 2 + 2], 16).ok();
+    Some(Rgba::new(
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        if expanded.len() == 8 {
+            channel(3)?
+        } else {
+            255
+        },
+    ))
+}
+
+// Parse the arguments of a CSS colour function, which may be separated by commas, or by
+// spaces with the alpha after a slash.
+fn parse_function(function: &str, arguments: &str) -> Option<Rgba> {
+    let arguments: Vec<&str> = arguments
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|argument| !argument.is_empty())
+        .collect();
+    let (channels, alpha) = match arguments.len() {
+        3 => (&arguments[..], 1.0),
+        4 => (&arguments[..3], parse_number(arguments[3], 1.0)?),
+        _ => return None,
+    };
+    let [first, second, third] = [channels[0], channels[1], channels[2]];
+
+    let color = match function {
+        "rgb" | "rgba" => {
+            let channel = |argument| parse_number(argument, 255.0).map(|value| value / 255.0);
+            Rgba::rgb(
+                to_u8(channel(first)?),
+                to_u8(channel(second)?),
+                to_u8(channel(third)?),
+            )
+        }
+        "hsl" | "hsla" => Rgba::from_hsl(
+            parse_hue(first)?,
+            parse_percentage(second)?,
+            parse_percentage(third)?,
+        ),
+        "hwb" => {
+            let (white, black) = (parse_percentage(second)?, parse_percentage(third)?);
+            let value = 1.0 - black;
+            let saturation = if value <= 0.0 {
+                0.0
+            } else {
+                1.0 - white / value
+            };
+            Rgba::from_hsv(parse_hue(first)?, saturation, value)
+        }
+        "lch" => Rgba::from_lch(
+            parse_number(first, 100.0)?,
+            parse_number(second, 150.0)?,
+            parse_hue(third)?,
+        ),
+        "oklab" => Rgba::from_oklab(
+            parse_number(first, 1.0)?,
+            parse_number(second, 0.4)?,
+            parse_number(third, 0.4)?,
+        ),
+        "oklch" => Rgba::from_oklch(
+            parse_number(first, 1.0)?,
+            parse_number(second, 0.4)?,
+            parse_hue(third)?,
+        ),
+        _ => return None,
+    };
+    Some(color.with_alpha(alpha))
+}
+
+// Parse a number, or a percentage of `full`.
+fn parse_number(argument: &str, full: f32) -> Option<f32> {
+    match argument.strip_suffix('%') {
+        Some(percentage) => parse_finite(percentage).map(|p| p / 100.0 * full),
+        None => parse_finite(argument),
+    }
+}
+
+// Parse a percentage from 0% to 100%, which can also be given as a plain number from 0 to 100.
+fn parse_percentage(argument: &str) -> Option<f32> {
+    let value = parse_finite(argument.trim_end_matches('%'))?;
+    Some((value / 100.0).clamp(0.0, 1.0))
+}
+
+// Parse a hue, in degrees unless it has another CSS angle unit.
+fn parse_hue(argument: &str) -> Option<f32> {
+    let units = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ];
+    for (unit, degrees) in units {
+        if let Some(value) = argument.strip_suffix(unit) {
+            return parse_finite(value).map(|value| value * degrees);
+        }
+    }
+    parse_finite(argument)
+}
+
+// Parse a number, rejecting "inf" and "NaN", which Rust accepts but CSS doesn't.
+fn parse_finite(argument: &str) -> Option<f32> {
+    argument
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+// The CSS named colours, in alphabetical order so that they can be searched.
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(css: &str) -> Result<Rgba> {
+        css.parse()
+    }
+
+    #[test]
+    fn parse_returns_native_errors() {
+        assert_eq!(Rgba::parse("coral").unwrap(), Rgba::rgb(255, 127, 80));
+        assert!(matches!(Rgba::parse("nope"), Err(Error::InvalidColor(_))));
+        assert_eq!(Rgb::parse("#ff7f5080").unwrap(), Rgb::new(255, 127, 80));
+        assert!(matches!(Rgb::parse("nope"), Err(Error::InvalidColor(_))));
+    }
+
+    #[test]
+    fn hex_codes() {
+        assert_eq!(parse("#f00").unwrap(), Rgba::rgb(255, 0, 0));
+        assert_eq!(parse("#F008").unwrap(), Rgba::new(255, 0, 0, 0x88));
+        assert_eq!(parse("  #12AbEf ").unwrap(), Rgba::rgb(0x12, 0xab, 0xef));
+        assert_eq!(
+            parse("#12abef80").unwrap(),
+            Rgba::new(0x12, 0xab, 0xef, 0x80)
+        );
+    }
+
+    #[test]
+    fn functions_and_names() {
+        let red = Rgba::rgb(255, 0, 0);
+        assert_eq!(parse("rgb(255, 0, 0)").unwrap(), red);
+        assert_eq!(parse("RGB(100% 0% 0%)").unwrap(), red);
+        assert_eq!(parse("rgba(255 0 0 / 50%)").unwrap(), red.with_alpha(0.5));
+        assert_eq!(
+            parse("hsl(0.5turn, 100%, 50%)").unwrap(),
+            Rgba::rgb(0, 255, 255)
+        );
+        assert_eq!(parse("hwb(0 0% 0%)").unwrap(), red);
+        assert_eq!(parse("oklch(0.628 0.2577 29.23)").unwrap(), red);
+        assert_eq!(parse("Tomato").unwrap(), Rgba::rgb(255, 99, 71));
+        assert_eq!(parse("transparent").unwrap(), Rgba::new(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn malformed_colours_are_rejected() {
+        for css in &[
+            "",
+            "#",
+            "#12",
+            "#12345",
+            "#1234567",
+            "#ggg",
+            "#ééé",
+            "red!",
+            "notacolor",
+            "rgb",
+            "rgb()",
+            "rgb(1, 2)",
+            "rgb(1, 2, 3",
+            "rgb 1, 2, 3)",
+            "rgb(1, 2, 3, 4, 5)",
+            "rgb(a, b, c)",
+            "rgb(nan, 0, 0)",
+            "rgb(inf, 0, 0)",
+            "hsl(0, 100%, NaN%)",
+            "hsl(1e40deg, 100%, 50%)",
+            "cmyk(0, 0, 0)",
+        ] {
+            assert!(
+                matches!(parse(css), Err(Error::InvalidColor(_))),
+                "{:?} was accepted",
+                css
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        assert_eq!(parse("rgb(300, -20, 0)").unwrap(), Rgba::rgb(255, 0, 0));
+        assert_eq!(parse("rgba(0, 0, 0, 2)").unwrap(), Rgba::new(0, 0, 0, 255));
+        assert_eq!(parse("hsl(-120, 150%, 50%)").unwrap(), Rgba::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let color = Rgba::new(20, 60, 120, 200);
+        let [l, a, b] = color.to_oklab();
+        assert_eq!(Rgba::from_oklab(l, a, b), color.with_alpha(1.0));
+        let [l, c, h] = color.to_lch();
+        assert_eq!(Rgba::from_lch(l, c, h), color.with_alpha(1.0));
+        assert_eq!(parse(&color.to_css()).unwrap(), color);
+        assert_eq!(parse(&color.to_hex()).unwrap(), color);
+        assert_eq!(Rgba::from(image::Rgba::from(color)), color);
+    }
+
+    #[test]
+    fn out_of_gamut_colours_lose_chroma() {
+        let vivid = Rgba::from_lch(50.0, 500.0, 140.0);
+        let [_, chroma, hue] = vivid.to_lch();
+        assert!(chroma < 500.0);
+        assert!((hue - 140.0).abs() < 5.0, "{}", hue);
+    }
+}
@@ -0,0 +1,323 @@
+//! Colour schemes generated from a single colour.
+//!
+//! Each scheme is built from colour harmonies on the colour wheel: the main colour's hue is
+//! rotated in Lch, which keeps the lightness and colourfulness of the other colours close to
+//! the main colour's, as the eye sees them. Colours that can't be shown in sRGB lose chroma
+//! until they can, rather than being clipped to a different hue.
+//!
+//! ```
+//! use gdl::color_scheme::{ColorScheme, Harmony};
+//! use gdl::Rgb;
+//!
+//! let scheme = ColorScheme::new(Rgb { r: 226, g: 88, b: 34 });
+//! let triadic = scheme.triadic();
+//! assert_eq!(triadic.len(), 3);
+//! assert_eq!(triadic[0], scheme.main_color());
+//! assert_eq!(scheme.harmony(Harmony::Square).len(), 4);
+//! ```
+
+#[cfg(feature = "embedded-fonts")]
+use crate::fonts::with_embedded_fonts;
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::text::draw_text;
+use crate::{Result, Rgb};
+#[cfg(feature = "embedded-fonts")]
+use image::ImageOutputFormat;
+use image::{DynamicImage, ImageBuffer, Rgba};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+use wasm_bindgen::prelude::*;
+
+// Number of colours in a monochromatic palette made by `ColorScheme::harmony`.
+const MONOCHROMATIC_COUNT: usize = 5;
+
+// Lightness of the darkest shade and lightest tint in a monochromatic palette.
+const MIN_LIGHTNESS: f32 = 15.0;
+const MAX_LIGHTNESS: f32 = 95.0;
+
+// Size of the swatches drawn by `ColorScheme::swatch_image`, and the space around them.
+const SWATCH_SIZE: u32 = 120;
+const SWATCH_GAP: u32 = 20;
+const LABEL_WIDTH: u32 = 300;
+const ROW_HEIGHT: u32 = SWATCH_SIZE + 60;
+
+/// A colour harmony: a set of colours related by their positions on the colour wheel.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Harmony {
+    /// The main colour and the colour opposite it.
+    Complementary,
+    /// The main colour and the two colours either side of its complement.
+    SplitComplementary,
+    /// The main colour and its neighbours on either side.
+    Analogous,
+    /// Three colours spaced evenly around the wheel.
+    Triadic,
+    /// Two pairs of complementary colours, forming a rectangle on the wheel.
+    Tetradic,
+    /// Four colours spaced evenly around the wheel.
+    Square,
+    /// Tints and shades of the main colour.
+    Monochromatic,
+}
+
+impl Harmony {
+    /// Every harmony, in the order they are drawn in a swatch image.
+    pub const ALL: [Harmony; 7] = [
+        Harmony::Complementary,
+        Harmony::SplitComplementary,
+        Harmony::Analogous,
+        Harmony::Triadic,
+        Harmony::Tetradic,
+        Harmony::Square,
+        Harmony::Monochromatic,
+    ];
+
+    /// The name of the harmony, such as "split-complementary".
+    pub fn name(self) -> &'static str {
+        match self {
+            Harmony::Complementary => "complementary",
+            Harmony::SplitComplementary => "split-complementary",
+            Harmony::Analogous => "analogous",
+            Harmony::Triadic => "triadic",
+            Harmony::Tetradic => "tetradic",
+            Harmony::Square => "square",
+            Harmony::Monochromatic => "monochromatic",
+        }
+    }
+
+    // Hue rotations, in degrees, of the colours of the harmony around the wheel.
+    fn hue_shifts(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::SplitComplementary => &[0.0, 150.0, 210.0],
+            Harmony::Analogous => &[-30.0, 0.0, 30.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+            Harmony::Tetradic => &[0.0, 60.0, 180.0, 240.0],
+            Harmony::Square => &[0.0, 90.0, 180.0, 270.0],
+            Harmony::Monochromatic => &[0.0],
+        }
+    }
+}
+
+/// Generate color schemes from a single input color.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct ColorScheme {
+    main_color: Rgb,
+    // The main colour's Lch lightness, chroma and hue.
+    primary: [f32; 3],
+}
+
+#[wasm_bindgen]
+impl ColorScheme {
+    pub fn new(main_color: Rgb) -> ColorScheme {
+        let primary = crate::Rgba::from(main_color).to_lch();
+        ColorScheme {
+            main_color,
+            primary,
+        }
+    }
+
+    /// The colour the scheme was generated from.
+    pub fn main_color(&self) -> Rgb {
+        self.main_color
+    }
+
+    /// The colours of a harmony, starting with the main colour. Monochromatic palettes run
+    /// from dark to light instead.
+    pub fn harmony(&self, harmony: Harmony) -> Vec<Rgb> {
+        match harmony {
+            Harmony::Monochromatic => self.monochromatic(MONOCHROMATIC_COUNT),
+            _ => harmony
+                .hue_shifts()
+                .iter()
+                .map(|&shift| {
+                    if shift == 0.0 {
+                        self.main_color
+                    } else {
+                        let [lightness, chroma, hue] = self.primary;
+                        crate::Rgba::from_lch(lightness, chroma, hue + shift).into()
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The main colour and its complement.
+    pub fn complementary(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::Complementary)
+    }
+
+    /// The main colour and the two colours 30° either side of its complement.
+    pub fn split_complementary(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::SplitComplementary)
+    }
+
+    /// The colour 30° before the main colour, the main colour, and the colour 30° after it.
+    pub fn analogous(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::Analogous)
+    }
+
+    /// The main colour and the colours 120° and 240° around the wheel from it.
+    pub fn triadic(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::Triadic)
+    }
+
+    /// The main colour, the colour 60° from it, and the complements of both.
+    pub fn tetradic(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::Tetradic)
+    }
+
+    /// The main colour and the colours 90°, 180° and 270° around the wheel from it.
+    pub fn square(&self) -> Vec<Rgb> {
+        self.harmony(Harmony::Square)
+    }
+
+    /// Tints and shades of the main colour, from dark to light.
+    ///
+    /// The colours have the main colour's hue and chroma, with lightnesses spread evenly
+    /// between a dark shade and a light tint. The one closest to the main colour is replaced
+    /// by the main colour itself.
+    ///
+    /// ### Arguments
+    /// * `count` - Number of colours in the palette.
+    pub fn monochromatic(&self, count: usize) -> Vec<Rgb> {
+        if count <= 1 {
+            return vec![self.main_color; count];
+        }
+        let step = (MAX_LIGHTNESS - MIN_LIGHTNESS) / (count - 1) as f32;
+        let closest = ((self.primary[0] - MIN_LIGHTNESS) / step)
+            .round()
+            .clamp(0.0, (count - 1) as f32) as usize;
+        (0..count)
+            .map(|i| match i {
+                i if i == closest => self.main_color,
+                _ => {
+                    let [_, chroma, hue] = self.primary;
+                    crate::Rgba::from_lch(MIN_LIGHTNESS + step * i as f32, chroma, hue).into()
+                }
+            })
+            .collect()
+    }
+
+    /// Create a swatch image in PNG format, for use from JavaScript. See
+    /// [`ColorScheme::create_swatch_image`].
+    #[cfg(feature = "embedded-fonts")]
+    #[wasm_bindgen(js_name = create_swatch_image)]
+    pub fn create_swatch_image_web(&self) -> std::result::Result<Vec<u8>, JsValue> {
+        Ok(self.create_swatch_image()?)
+    }
+}
+
+impl ColorScheme {
+    /// Create a swatch image in PNG format of the colour swatches generated
+    /// from the main colour, with each harmony's name and the hex code of each colour.
+    /// Requires the `embedded-fonts` feature.
+    #[cfg(feature = "embedded-fonts")]
+    pub fn create_swatch_image(&self) -> Result<Vec<u8>> {
+        let image = with_embedded_fonts(|fonts| self.swatch_image(fonts))?;
+        let mut bytes = Vec::new();
+        image.write_to(&mut bytes, ImageOutputFormat::Png)?;
+        Ok(bytes)
+    }
+
+    /// Draw every harmony of the scheme as a row of labelled swatches.
+    ///
+    /// ### Arguments
+    /// * `fonts` - FontRegistry the labels' font, Roboto, is taken from.
+    pub fn swatch_image(&self, fonts: &FontRegistry) -> Result<DynamicImage> {
+        let columns = Harmony::ALL
+            .iter()
+            .map(|&harmony| self.harmony(harmony).len() as u32)
+            .max()
+            .unwrap_or(0);
+        let width = LABEL_WIDTH + columns * (SWATCH_SIZE + SWATCH_GAP) + SWATCH_GAP;
+        let height = Harmony::ALL.len() as u32 * ROW_HEIGHT + SWATCH_GAP;
+        let mut image = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
+            width,
+            height,
+            Rgba([255, 255, 255, 255]),
+        ));
+        let label_color = Rgb {
+            r: 40,
+            g: 40,
+            b: 40,
+        };
+        let label_font = FontDescriptor::new("Roboto");
+
+        for (row, &harmony) in Harmony::ALL.iter().enumerate() {
+            let y = SWATCH_GAP + row as u32 * ROW_HEIGHT;
+            draw_text(
+                &mut image,
+                fonts,
+                harmony.name(),
+                SWATCH_GAP,
+                y + SWATCH_SIZE / 2 - 5,
+                &label_font.clone().weight(500),
+                32.0,
+                &label_color,
+            )?;
+
+            for (column, color) in self.harmony(harmony).iter().enumerate() {
+                let x = LABEL_WIDTH + column as u32 * (SWATCH_SIZE + SWATCH_GAP);
+                draw_filled_rect_mut(
+                    &mut image,
+                    Rect::at(x as i32, y as i32).of_size(SWATCH_SIZE, SWATCH_SIZE),
+                    Rgba::from(color),
+                );
+                draw_text(
+                    &mut image,
+                    fonts,
+                    &color.to_hex(),
+                    x.saturating_sub(10),
+                    y + SWATCH_SIZE + 14,
+                    &label_font,
+                    24.0,
+                    &label_color,
+                )?;
+            }
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monochromatic_handles_small_counts() {
+        let scheme = ColorScheme::new(Rgb::new(226, 88, 34));
+        assert!(scheme.monochromatic(0).is_empty());
+        assert_eq!(scheme.monochromatic(1), [scheme.main_color()]);
+        let two = scheme.monochromatic(2);
+        assert_eq!(two.len(), 2);
+        assert!(two.contains(&scheme.main_color()));
+    }
+
+    #[test]
+    fn greys_black_and_white_have_every_harmony() {
+        for &color in &[
+            Rgb::new(0, 0, 0),
+            Rgb::new(128, 128, 128),
+            Rgb::new(255, 255, 255),
+        ] {
+            let scheme = ColorScheme::new(color);
+            for &harmony in Harmony::ALL.iter() {
+                let colors = scheme.harmony(harmony);
+                assert!(!colors.is_empty());
+                assert!(colors.contains(&color), "{:?} {:?}", color, harmony);
+            }
+        }
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn swatch_image_is_a_png() {
+        let bytes = ColorScheme::new(Rgb::new(20, 120, 200))
+            .create_swatch_image()
+            .unwrap();
+        assert_eq!(&bytes[..4], b"\x89PNG");
+    }
+}
@@ -0,0 +1,421 @@
+//! Composite images and colours onto images, with alpha and blend modes.
+//!
+//! Pixels are composited with premultiplied alpha, following the W3C
+//! [Compositing and Blending](https://www.w3.org/TR/compositing-1/) specification: the source is
+//! blended with the backdrop using a [`BlendMode`], then drawn over it with source-over, scaled
+//! by a global opacity. Translucent pixels on either side are handled correctly, so images can be
+//! built up on transparent layers and composited later.
+//!
+//! ```
+//! use gdl::compositing::{composite, draw_blended, BlendMode};
+//! use gdl::elements::draw_solid_rect;
+//! use gdl::Rgb;
+//! use image::{DynamicImage, Rgba, RgbaImage};
+//!
+//! let mut image = gdl::new_with_background(200, 200, &Rgb { r: 40, g: 120, b: 200 });
+//! let sticker = DynamicImage::ImageRgba8(RgbaImage::from_pixel(50, 50, Rgba([255, 200, 0, 128])));
+//! composite(&mut image, &sticker, 20, 20, BlendMode::Normal, 1.0);
+//!
+//! // Any element can be drawn with a blend mode and opacity.
+//! draw_blended(&mut image, BlendMode::Multiply, 0.5, |layer| {
+//!     draw_solid_rect(layer, &Rgb { r: 255, g: 0, b: 0 }, 100, 100, 50, 50);
+//! });
+//! ```
+
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Rgba};
+use imageproc::rect::Rect;
+use wasm_bindgen::prelude::*;
+
+/// How the colours of a source are combined with the colours of the backdrop it is drawn on.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// The source colour replaces the backdrop.
+    #[default]
+    Normal,
+    /// Multiplies the colours, which always darkens.
+    Multiply,
+    /// Multiplies the inverse of the colours, which always lightens.
+    Screen,
+    /// Multiplies or screens, depending on the backdrop, keeping its highlights and shadows.
+    Overlay,
+    /// Keeps the darker of the colours.
+    Darken,
+    /// Keeps the lighter of the colours.
+    Lighten,
+    /// Brightens the backdrop to reflect the source.
+    ColorDodge,
+    /// Darkens the backdrop to reflect the source.
+    ColorBurn,
+    /// Multiplies or screens, depending on the source.
+    HardLight,
+    /// Darkens or lightens, depending on the source, like a diffused spotlight.
+    SoftLight,
+    /// Subtracts the darker colour from the lighter.
+    Difference,
+    /// Like `Difference`, with lower contrast.
+    Exclusion,
+}
+
+impl BlendMode {
+    /// Every blend mode, in the order they are declared.
+    pub const ALL: [BlendMode; 12] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::HardLight,
+        BlendMode::SoftLight,
+        BlendMode::Difference,
+        BlendMode::Exclusion,
+    ];
+
+    /// The mode's name in CSS, such as "multiply" or "color-dodge".
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Darken => "darken",
+            BlendMode::Lighten => "lighten",
+            BlendMode::ColorDodge => "color-dodge",
+            BlendMode::ColorBurn => "color-burn",
+            BlendMode::HardLight => "hard-light",
+            BlendMode::SoftLight => "soft-light",
+            BlendMode::Difference => "difference",
+            BlendMode::Exclusion => "exclusion",
+        }
+    }
+
+    /// Find a mode by its CSS name. Underscores and the British spelling "colour" are accepted.
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        let name = name
+            .trim()
+            .to_lowercase()
+            .replace('_', "-")
+            .replace("colour", "color");
+        BlendMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name() == name)
+    }
+
+    // Blend one channel of the backdrop and source, each between 0 and 1.
+    fn blend_channel(self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Overlay => BlendMode::HardLight.blend_channel(source, backdrop),
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::ColorDodge => {
+                if backdrop <= 0.0 {
+                    0.0
+                } else if source >= 1.0 {
+                    1.0
+                } else {
+                    (backdrop / (1.0 - source)).min(1.0)
+                }
+            }
+            BlendMode::ColorBurn => {
+                if backdrop >= 1.0 {
+                    1.0
+                } else if source <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - backdrop) / source).min(1.0)
+                }
+            }
+            BlendMode::HardLight => {
+                if source <= 0.5 {
+                    BlendMode::Multiply.blend_channel(backdrop, 2.0 * source)
+                } else {
+                    BlendMode::Screen.blend_channel(backdrop, 2.0 * source - 1.0)
+                }
+            }
+            BlendMode::SoftLight => {
+                if source <= 0.5 {
+                    backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop)
+                } else {
+                    let d = if backdrop <= 0.25 {
+                        ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop
+                    } else {
+                        backdrop.sqrt()
+                    };
+                    backdrop + (2.0 * source - 1.0) * (d - backdrop)
+                }
+            }
+            BlendMode::Difference => (backdrop - source).abs(),
+            BlendMode::Exclusion => backdrop + source - 2.0 * backdrop * source,
+        }
+    }
+}
+
+/// Composite a source pixel onto a backdrop pixel.
+///
+/// ### Arguments
+/// * `backdrop` - The pixel underneath. It may be translucent.
+/// * `source` - The pixel drawn on top. Its alpha channel sets how much of it covers the backdrop.
+/// * `mode` - How the colours are blended where both are present.
+/// * `opacity` - Opacity the source is drawn with, from 0 to 1, on top of its own alpha.
+pub fn blend_pixel(
+    backdrop: Rgba<u8>,
+    source: Rgba<u8>,
+    mode: BlendMode,
+    opacity: f32,
+) -> Rgba<u8> {
+    let source_alpha = source[3] as f32 / 255.0 * opacity.clamp(0.0, 1.0);
+    if source_alpha <= 0.0 {
+        return backdrop;
+    }
+    let backdrop_alpha = backdrop[3] as f32 / 255.0;
+    let out_alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+    let mut out = [0u8; 4];
+    for channel in 0..3 {
+        let cb = backdrop[channel] as f32 / 255.0;
+        let cs = source[channel] as f32 / 255.0;
+        // Where the backdrop is transparent the source colour shows unblended.
+        let blended = (1.0 - backdrop_alpha) * cs + backdrop_alpha * mode.blend_channel(cb, cs);
+        // Source-over, with premultiplied colours.
+        let premultiplied = source_alpha * blended + (1.0 - source_alpha) * backdrop_alpha * cb;
+        out[channel] = (premultiplied / out_alpha * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    out[3] = (out_alpha * 255.0).round() as u8;
+    Rgba(out)
+}
+
+/// Composite an image onto another.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to the DynamicImage drawn onto.
+/// * `source` - The image to draw.
+/// * `x` - X-coordinate of the left of the source. May be negative.
+/// * `y` - Y-coordinate of the top of the source. May be negative.
+/// * `mode` - How the source's colours are blended with the image's.
+/// * `opacity` - Opacity of the source, from 0 to 1.
+pub fn composite<I: GenericImageView<Pixel = Rgba<u8>>>(
+    image: &mut DynamicImage,
+    source: &I,
+    x: i32,
+    y: i32,
+    mode: BlendMode,
+    opacity: f32,
+) {
+    let (width, height) = image.dimensions();
+    let (source_width, source_height) = source.dimensions();
+    // Only visit the part of the source that lands on the image.
+    let left = (-x).max(0) as u32;
+    let top = (-y).max(0) as u32;
+    let right = (width as i64 - x as i64).clamp(0, source_width as i64) as u32;
+    let bottom = (height as i64 - y as i64).clamp(0, source_height as i64) as u32;
+
+    for sy in top..bottom {
+        for sx in left..right {
+            let (image_x, image_y) = ((x + sx as i32) as u32, (y + sy as i32) as u32);
+            let pixel = source.get_pixel(sx, sy);
+            if mode == BlendMode::Normal && opacity >= 1.0 && pixel[3] == 255 {
+                image.put_pixel(image_x, image_y, pixel);
+            } else if pixel[3] > 0 {
+                let backdrop = image.get_pixel(image_x, image_y);
+                image.put_pixel(
+                    image_x,
+                    image_y,
+                    blend_pixel(backdrop, pixel, mode, opacity),
+                );
+            }
+        }
+    }
+}
+
+/// Fill a rectangle with a colour, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `rect` - The rectangle to fill. Any part outside the image is ignored.
+/// * `color` - Fill colour. Its alpha channel sets how much it covers the image.
+/// * `mode` - How the colour is blended with the image.
+/// * `opacity` - Opacity of the fill, from 0 to 1.
+pub fn fill_rect(
+    image: &mut DynamicImage,
+    rect: Rect,
+    color: Rgba<u8>,
+    mode: BlendMode,
+    opacity: f32,
+) {
+    let (width, height) = image.dimensions();
+    let left = rect.left().max(0) as u32;
+    let top = rect.top().max(0) as u32;
+    let right = (rect.right() + 1).clamp(0, width as i32) as u32;
+    let bottom = (rect.bottom() + 1).clamp(0, height as i32) as u32;
+    for y in top..bottom {
+        for x in left..right {
+            let backdrop = image.get_pixel(x, y);
+            image.put_pixel(x, y, blend_pixel(backdrop, color, mode, opacity));
+        }
+    }
+}
+
+/// Fill the pixels covered by a mask with a colour, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `mask` - How much of each pixel is covered, from 0 to 255.
+/// * `x` - X-coordinate of the left of the mask. May be negative.
+/// * `y` - Y-coordinate of the top of the mask. May be negative.
+/// * `color` - Fill colour. Its alpha channel sets how much it covers the image.
+/// * `mode` - How the colour is blended with the image.
+/// * `opacity` - Opacity of the fill, from 0 to 1.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_mask(
+    image: &mut DynamicImage,
+    mask: &GrayImage,
+    x: i32,
+    y: i32,
+    color: Rgba<u8>,
+    mode: BlendMode,
+    opacity: f32,
+) {
+    let (width, height) = image.dimensions();
+    for (mx, my, coverage) in mask.enumerate_pixels() {
+        let (image_x, image_y) = (x + mx as i32, y + my as i32);
+        if coverage[0] == 0
+            || image_x < 0
+            || image_y < 0
+            || image_x >= width as i32
+            || image_y >= height as i32
+        {
+            continue;
+        }
+        let (image_x, image_y) = (image_x as u32, image_y as u32);
+        let backdrop = image.get_pixel(image_x, image_y);
+        let alpha = opacity * coverage[0] as f32 / 255.0;
+        image.put_pixel(image_x, image_y, blend_pixel(backdrop, color, mode, alpha));
+    }
+}
+
+/// Draw onto a transparent layer the size of an image, then composite the layer onto it.
+///
+/// This lets any drawing function, such as an element or a block of text, be drawn with a blend
+/// mode and a global opacity. Overlapping parts of the drawing are flattened first, so they don't
+/// show through each other.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `mode` - How the drawing's colours are blended with the image's.
+/// * `opacity` - Opacity of the drawing, from 0 to 1.
+/// * `draw` - Draws onto the transparent layer it is given.
+pub fn draw_blended<F: FnOnce(&mut DynamicImage)>(
+    image: &mut DynamicImage,
+    mode: BlendMode,
+    opacity: f32,
+    draw: F,
+) {
+    let (width, height) = image.dimensions();
+    let mut layer = DynamicImage::new_rgba8(width, height);
+    draw(&mut layer);
+    composite(image, &layer.to_rgba8(), 0, 0, mode, opacity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const BACKDROP: Rgba<u8> = Rgba([40, 120, 200, 255]);
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    #[test]
+    fn transparent_sources_leave_the_backdrop() {
+        for &mode in BlendMode::ALL.iter() {
+            assert_eq!(blend_pixel(BACKDROP, RED, mode, 0.0), BACKDROP);
+            assert_eq!(blend_pixel(BACKDROP, RED, mode, -1.0), BACKDROP);
+            assert_eq!(
+                blend_pixel(BACKDROP, Rgba([255, 0, 0, 0]), mode, 1.0),
+                BACKDROP
+            );
+        }
+    }
+
+    #[test]
+    fn transparent_backdrops_show_the_source_unblended() {
+        for &mode in BlendMode::ALL.iter() {
+            assert_eq!(blend_pixel(Rgba([0, 0, 0, 0]), RED, mode, 1.0), RED);
+        }
+        let half = blend_pixel(Rgba([0, 0, 0, 0]), RED, BlendMode::Multiply, 0.5);
+        assert_eq!(half, Rgba([255, 0, 0, 128]));
+    }
+
+    #[test]
+    fn mode_names_round_trip() {
+        for &mode in BlendMode::ALL.iter() {
+            assert_eq!(BlendMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(
+            BlendMode::from_name(" Colour_Dodge "),
+            Some(BlendMode::ColorDodge)
+        );
+        assert_eq!(BlendMode::from_name(""), None);
+        assert_eq!(BlendMode::from_name("add"), None);
+    }
+
+    #[test]
+    fn sources_outside_the_image_are_ignored() {
+        let mut image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, BACKDROP));
+        let source = RgbaImage::from_pixel(3, 3, RED);
+        for &(x, y) in &[(-3, 0), (0, -3), (4, 0), (0, 4), (-100, 100)] {
+            composite(&mut image, &source, x, y, BlendMode::Normal, 1.0);
+        }
+        composite(
+            &mut image,
+            &RgbaImage::new(0, 0),
+            0,
+            0,
+            BlendMode::Normal,
+            1.0,
+        );
+        fill_rect(
+            &mut image,
+            Rect::at(-10, -10).of_size(5, 5),
+            RED,
+            BlendMode::Normal,
+            1.0,
+        );
+        fill_mask(
+            &mut image,
+            &GrayImage::new(0, 0),
+            0,
+            0,
+            RED,
+            BlendMode::Normal,
+            1.0,
+        );
+        assert!(image.to_rgba8().pixels().all(|&pixel| pixel == BACKDROP));
+
+        composite(&mut image, &source, -2, 3, BlendMode::Normal, 1.0);
+        assert_eq!(image.get_pixel(0, 3), RED);
+        assert_eq!(image.get_pixel(1, 3), BACKDROP);
+    }
+
+    #[test]
+    fn blended_drawing_on_an_empty_image() {
+        let mut image = DynamicImage::new_rgba8(0, 0);
+        draw_blended(&mut image, BlendMode::Screen, 0.5, |layer| {
+            fill_rect(
+                layer,
+                Rect::at(0, 0).of_size(1, 1),
+                RED,
+                BlendMode::Normal,
+                1.0,
+            );
+        });
+    }
+}
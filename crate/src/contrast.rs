@@ -0,0 +1,423 @@
+//! Check that text is readable against what it's drawn on, using WCAG contrast ratios.
+//!
+//! The contrast ratio between two colours runs from 1:1, for the same colour, to 21:1, for
+//! black on white. WCAG asks for at least 4.5:1 for body text and 3:1 for large text to meet
+//! level AA, and 7:1 and 4.5:1 to meet level AAA. Text drawn over a photo or gradient is
+//! checked against the lightest and darkest parts of the area under it.
+//!
+//! Text styles can ask for their contrast to be fixed when it is too low, which the presets
+//! honour, either by switching to black or white text or by drawing a scrim behind the text:
+//!
+//! ```
+//! use gdl::contrast::{contrast_ratio, ContrastFix, ContrastLevel};
+//! use gdl::style::TextStyle;
+//! use gdl::Rgb;
+//!
+//! let white = Rgb { r: 255, g: 255, b: 255 };
+//! let yellow = Rgb { r: 255, g: 221, b: 0 };
+//! assert!(contrast_ratio(&white, &yellow) < 1.5);
+//! assert!(!ContrastLevel::Aa.passes(contrast_ratio(&white, &yellow), true));
+//!
+//! let style = TextStyle::new().auto_contrast(ContrastLevel::Aa, ContrastFix::PickColor);
+//! ```
+
+use crate::compositing::{fill_rect, BlendMode};
+use crate::gradient::srgb_to_linear;
+use crate::style::{Fill, TextStyle};
+use crate::Rgb;
+use image::{DynamicImage, GenericImageView, Rgba};
+use imageproc::rect::Rect;
+use wasm_bindgen::prelude::*;
+
+// Text this size, in pixels, or larger counts as large text: 18pt at 96 pixels per inch.
+const LARGE_TEXT_SIZE: f32 = 24.0;
+
+// At most this many pixels are sampled when measuring the luminance of a region.
+const MAX_SAMPLES: u32 = 16_384;
+
+// The darkest and lightest parts of a region are taken at these percentiles, so that a few
+// stray pixels don't decide the colour of the text.
+const DARK_PERCENTILE: f32 = 0.05;
+const LIGHT_PERCENTILE: f32 = 0.95;
+
+// The most opaque a scrim is drawn.
+const MAX_SCRIM_OPACITY: f32 = 0.9;
+
+/// A WCAG conformance level for the contrast of text.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ContrastLevel {
+    /// At least 4.5:1, or 3:1 for large text.
+    #[default]
+    Aa,
+    /// At least 7:1, or 4.5:1 for large text.
+    Aaa,
+}
+
+impl ContrastLevel {
+    /// The lowest contrast ratio that meets the level.
+    pub fn min_ratio(self, large_text: bool) -> f32 {
+        match (self, large_text) {
+            (ContrastLevel::Aa, false) => 4.5,
+            (ContrastLevel::Aa, true) => 3.0,
+            (ContrastLevel::Aaa, false) => 7.0,
+            (ContrastLevel::Aaa, true) => 4.5,
+        }
+    }
+
+    /// Check whether a contrast ratio meets the level.
+    pub fn passes(self, ratio: f32, large_text: bool) -> bool {
+        ratio >= self.min_ratio(large_text)
+    }
+}
+
+/// How text whose contrast is too low is made readable.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ContrastFix {
+    /// Draw the text in black or white, whichever contrasts more with what's under it.
+    #[default]
+    PickColor,
+    /// Keep the text's colour, and darken or lighten the area under it with a translucent
+    /// rectangle until the text is readable.
+    Scrim,
+}
+
+/// The contrast a text style asks for, and how to fix text that falls short of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AutoContrast {
+    pub level: ContrastLevel,
+    pub fix: ContrastFix,
+}
+
+/// The luminance of an area of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionLuminance {
+    /// Luminance of the darkest part of the region, ignoring the darkest 5% of its pixels.
+    pub darkest: f32,
+    /// Average luminance of the region.
+    pub mean: f32,
+    /// Luminance of the lightest part of the region, ignoring the lightest 5% of its pixels.
+    pub lightest: f32,
+}
+
+impl RegionLuminance {
+    /// The contrast ratio of a colour against the part of the region it contrasts least with.
+    pub fn contrast_with(&self, color: &Rgb) -> f32 {
+        let luminance = relative_luminance(color);
+        ratio(luminance, self.darkest).min(ratio(luminance, self.lightest))
+    }
+}
+
+/// The WCAG relative luminance of a colour, from 0 for black to 1 for white.
+#[wasm_bindgen]
+pub fn relative_luminance(color: &Rgb) -> f32 {
+    luminance([color.r, color.g, color.b])
+}
+
+/// The WCAG contrast ratio between two colours, from 1 to 21.
+#[wasm_bindgen]
+pub fn contrast_ratio(color1: &Rgb, color2: &Rgb) -> f32 {
+    ratio(relative_luminance(color1), relative_luminance(color2))
+}
+
+/// Black or white, whichever contrasts more with a background colour.
+#[wasm_bindgen]
+pub fn readable_text_color(background: &Rgb) -> Rgb {
+    pick_black_or_white(
+        relative_luminance(background),
+        relative_luminance(background),
+    )
+}
+
+/// Measure the luminance of an area of an image. Transparent pixels are skipped.
+///
+/// ### Arguments
+/// * `img` - The image.
+/// * `rect` - The area to measure, such as the bounding box of some text. Any part of it
+///   outside the image is ignored.
+pub fn region_luminance(img: &DynamicImage, rect: Rect) -> RegionLuminance {
+    let luminances: Vec<f32> = region_pixels(img, rect)
+        .iter()
+        .map(|pixel| luminance([pixel[0], pixel[1], pixel[2]]))
+        .collect();
+    summarise(luminances)
+}
+
+/// The contrast ratio of a colour drawn over an area of an image, against the part of the
+/// area it contrasts least with.
+pub fn region_contrast(img: &DynamicImage, rect: Rect, color: &Rgb) -> f32 {
+    region_luminance(img, rect).contrast_with(color)
+}
+
+/// Black or white, whichever contrasts more with every part of an area of an image.
+pub fn readable_text_color_over(img: &DynamicImage, rect: Rect) -> Rgb {
+    let region = region_luminance(img, rect);
+    pick_black_or_white(region.darkest, region.lightest)
+}
+
+/// Apply a style's [`AutoContrast`] to text about to be drawn over an area of an image.
+///
+/// If the style's solid fill doesn't meet the contrast level against the area, either the
+/// returned style is filled with black or white, or a scrim is drawn onto the image under the
+/// text. Styles without automatic contrast, or filled with a gradient or image, are returned
+/// unchanged.
+///
+/// ### Arguments
+/// * `img` - The image the text will be drawn on.
+/// * `rect` - The bounding box of the text.
+/// * `style` - The style the text will be drawn with.
+/// * `font_size` - Size of the text in pixels, which decides whether it counts as large text.
+pub fn ensure_contrast(
+    img: &mut DynamicImage,
+    rect: Rect,
+    style: &TextStyle,
+    font_size: f32,
+) -> TextStyle {
+    let (contrast, color) = match (style.contrast, &style.fill) {
+        (Some(contrast), Some(Fill::Solid(color))) => (contrast, *color),
+        (Some(contrast), None) => (contrast, Rgba([0, 0, 0, 255])),
+        _ => return style.clone(),
+    };
+    let text = Rgb {
+        r: color[0],
+        g: color[1],
+        b: color[2],
+    };
+    let large_text = font_size >= LARGE_TEXT_SIZE;
+    let pixels = region_pixels(img, rect);
+    let region = summarise(
+        pixels
+            .iter()
+            .map(|pixel| luminance([pixel[0], pixel[1], pixel[2]]))
+            .collect(),
+    );
+    if contrast
+        .level
+        .passes(region.contrast_with(&text), large_text)
+    {
+        return style.clone();
+    }
+
+    match contrast.fix {
+        ContrastFix::PickColor => {
+            let rgb = pick_black_or_white(region.darkest, region.lightest);
+            style.clone().color(Rgba([rgb.r, rgb.g, rgb.b, color[3]]))
+        }
+        ContrastFix::Scrim => {
+            // Darken the area under light text, and lighten it under dark text.
+            let text_luminance = relative_luminance(&text);
+            let scrim = if ratio(text_luminance, 0.0) >= ratio(text_luminance, 1.0) {
+                [0, 0, 0]
+            } else {
+                [255, 255, 255]
+            };
+            let mut opacity = 0.0;
+            while opacity < MAX_SCRIM_OPACITY {
+                opacity = (opacity + 0.05f32).min(MAX_SCRIM_OPACITY);
+                let covered = summarise(
+                    pixels
+                        .iter()
+                        .map(|pixel| {
+                            luminance([0, 1, 2].map(|i| {
+                                (pixel[i] as f32 * (1.0 - opacity) + scrim[i] as f32 * opacity)
+                                    .round() as u8
+                            }))
+                        })
+                        .collect(),
+                );
+                if contrast
+                    .level
+                    .passes(covered.contrast_with(&text), large_text)
+                {
+                    break;
+                }
+            }
+            fill_rect(
+                img,
+                rect,
+                Rgba([scrim[0], scrim[1], scrim[2], 255]),
+                BlendMode::Normal,
+                opacity,
+            );
+            style.clone()
+        }
+    }
+}
+
+fn luminance(rgb: [u8; 3]) -> f32 {
+    let [r, g, b] = rgb.map(|channel| srgb_to_linear(channel as f32 / 255.0));
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn ratio(luminance1: f32, luminance2: f32) -> f32 {
+    (luminance1.max(luminance2) + 0.05) / (luminance1.min(luminance2) + 0.05)
+}
+
+// Black or white, whichever has the higher worst-case contrast against a background whose
+// luminance runs from `darkest` to `lightest`.
+fn pick_black_or_white(darkest: f32, lightest: f32) -> Rgb {
+    let with_white = ratio(1.0, lightest);
+    let with_black = ratio(0.0, darkest);
+    if with_white >= with_black {
+        Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        }
+    } else {
+        Rgb { r: 0, g: 0, b: 0 }
+    }
+}
+
+// The opaque pixels of an area of an image, sampled evenly if there are many of them.
+fn region_pixels(img: &DynamicImage, rect: Rect) -> Vec<Rgba<u8>> {
+    let (width, height) = img.dimensions();
+    let left = rect.left().clamp(0, width as i32) as u32;
+    let top = rect.top().clamp(0, height as i32) as u32;
+    let right = (rect.right() + 1).clamp(0, width as i32) as u32;
+    let bottom = (rect.bottom() + 1).clamp(0, height as i32) as u32;
+    let area = (right - left) * (bottom - top);
+    let step = ((area as f32 / MAX_SAMPLES as f32).sqrt().ceil() as usize).max(1);
+
+    let mut pixels = Vec::new();
+    for y in (top..bottom).step_by(step) {
+        for x in (left..right).step_by(step) {
+            let pixel = img.get_pixel(x, y);
+            if pixel[3] > 0 {
+                pixels.push(pixel);
+            }
+        }
+    }
+    pixels
+}
+
+fn summarise(mut luminances: Vec<f32>) -> RegionLuminance {
+    if luminances.is_empty() {
+        // Nothing is drawn under the text, so treat it as white, like a blank page.
+        return RegionLuminance {
+            darkest: 1.0,
+            mean: 1.0,
+            lightest: 1.0,
+        };
+    }
+    luminances.sort_by(f32::total_cmp);
+    let at =
+        |percentile: f32| luminances[((luminances.len() - 1) as f32 * percentile).round() as usize];
+    RegionLuminance {
+        darkest: at(DARK_PERCENTILE),
+        mean: luminances.iter().sum::<f32>() / luminances.len() as f32,
+        lightest: at(LIGHT_PERCENTILE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const BLACK: Rgb = Rgb { r: 0, g: 0, b: 0 };
+    const WHITE: Rgb = Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    #[test]
+    fn ratios_run_from_one_to_twenty_one() {
+        assert_eq!(contrast_ratio(&WHITE, &WHITE), 1.0);
+        assert!((contrast_ratio(&BLACK, &WHITE) - 21.0).abs() < 1e-4);
+        assert_eq!(
+            contrast_ratio(&BLACK, &WHITE),
+            contrast_ratio(&WHITE, &BLACK)
+        );
+        // #767676 is the lightest grey that passes AA on white.
+        let grey = Rgb::new(0x76, 0x76, 0x76);
+        assert!(ContrastLevel::Aa.passes(contrast_ratio(&grey, &WHITE), false));
+        let lighter = Rgb::new(0x77, 0x77, 0x77);
+        assert!(!ContrastLevel::Aa.passes(contrast_ratio(&lighter, &WHITE), false));
+    }
+
+    #[test]
+    fn luminance_of_primaries() {
+        assert_eq!(relative_luminance(&BLACK), 0.0);
+        assert!((relative_luminance(&WHITE) - 1.0).abs() < 1e-6);
+        assert!((relative_luminance(&Rgb::new(255, 0, 0)) - 0.2126).abs() < 1e-4);
+    }
+
+    #[test]
+    fn text_colour_picks_the_higher_contrast() {
+        assert_eq!(readable_text_color(&WHITE), BLACK);
+        assert_eq!(readable_text_color(&BLACK), WHITE);
+        assert_eq!(readable_text_color(&Rgb::new(255, 221, 0)), BLACK);
+        assert_eq!(readable_text_color(&Rgb::new(20, 40, 120)), WHITE);
+    }
+
+    #[test]
+    fn empty_and_transparent_regions_count_as_white() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 0])));
+        let region = region_luminance(&img, Rect::at(0, 0).of_size(10, 10));
+        assert_eq!(region.darkest, 1.0);
+        let outside = region_luminance(&img, Rect::at(50, 50).of_size(5, 5));
+        assert_eq!(outside.lightest, 1.0);
+        assert_eq!(
+            readable_text_color_over(&img, Rect::at(-5, -5).of_size(3, 3)),
+            BLACK
+        );
+    }
+
+    #[test]
+    fn regions_are_judged_by_their_extremes() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(100, 10, |x, _| {
+            if x < 50 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        }));
+        let rect = Rect::at(0, 0).of_size(100, 10);
+        let region = region_luminance(&img, rect);
+        assert_eq!(region.darkest, 0.0);
+        assert!((region.lightest - 1.0).abs() < 1e-6);
+        assert!((region.mean - 0.5).abs() < 1e-3);
+        // Neither black nor white text is readable over both halves.
+        assert!(region_contrast(&img, rect, &WHITE) < 1.01);
+    }
+
+    #[test]
+    fn styles_without_auto_contrast_are_unchanged() {
+        let mut img =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255])));
+        let style = TextStyle::new().color(Rgba([250, 250, 250, 255]));
+        let rect = Rect::at(0, 0).of_size(10, 10);
+        let unchanged = ensure_contrast(&mut img, rect, &style, 12.0);
+        assert!(matches!(
+            unchanged.fill,
+            Some(Fill::Solid(Rgba([250, 250, 250, 255])))
+        ));
+    }
+
+    #[test]
+    fn contrast_is_fixed_with_a_colour_or_scrim() {
+        let rect = Rect::at(0, 0).of_size(10, 10);
+        let white_img =
+            || DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255])));
+        let pale = TextStyle::new().color(Rgba([250, 250, 250, 200]));
+
+        let mut img = white_img();
+        let style = pale
+            .clone()
+            .auto_contrast(ContrastLevel::Aa, ContrastFix::PickColor);
+        let fixed = ensure_contrast(&mut img, rect, &style, 12.0);
+        assert!(matches!(
+            fixed.fill,
+            Some(Fill::Solid(Rgba([0, 0, 0, 200])))
+        ));
+
+        let mut img = white_img();
+        let style = pale.auto_contrast(ContrastLevel::Aa, ContrastFix::Scrim);
+        ensure_contrast(&mut img, rect, &style, 12.0);
+        let under = img.get_pixel(5, 5);
+        assert!(under[0] < 128, "{:?}", under);
+    }
+}
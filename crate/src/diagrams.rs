@@ -1,61 +1,92 @@
 //! Create diagrams, flowcharts, graphs, etc.,
 
 // use wasm_bindgen::prelude::*;
+use crate::compositing::{composite, BlendMode};
 use crate::elements::{draw_preset_rect_gradient, draw_solid_rect};
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::path::{stroke_path, LineCap, LineJoin, Path, Stroke};
 use crate::text::draw_text;
-use crate::Rgb;
-use image::{DynamicImage, Rgba};
+use crate::{Error, Result, Rgb};
+use image::{DynamicImage, GenericImageView, Rgba};
 use imageproc::drawing::*;
 
 /// Draw a horizontal barchart, with a specified title and data.
 ///
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Barchart struct, which contains all data & meta-data about the barchart.
-pub fn draw_horizontal_barchart(img: &mut DynamicImage, barchart: &Chart) {
-    draw_horizontal_bars(img, &barchart, "barchart");
+pub fn draw_horizontal_barchart(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+) -> Result<()> {
+    draw_horizontal_bars(img, fonts, barchart, "barchart")
 }
 
 /// Draw a vertical barchart, with a specified title and data.
 ///
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Barchart struct, which contains all data & meta-data about the barchart.
-pub fn draw_vertical_barchart(img: &mut DynamicImage, barchart: &Chart) {
-    draw_vertical_bars(img, barchart, "barchart");
+pub fn draw_vertical_barchart(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+) -> Result<()> {
+    draw_vertical_bars(img, fonts, barchart, "barchart")
 }
 
 /// Draw a histogram with a specified title, and data.
 ///
 /// /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Barchart struct, which contains all data & meta-data about the barchart.
 
-pub fn draw_horizontal_histogram(img: &mut DynamicImage, barchart: &Chart) {
-    draw_horizontal_bars(img, &barchart, "histogram");
+pub fn draw_horizontal_histogram(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+) -> Result<()> {
+    draw_horizontal_bars(img, fonts, barchart, "histogram")
 }
 
 /// Draw a vertical barchart, with a specified title and data.
 ///
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `histogram` - Chart struct, which contains all data & meta-data about the barchart.
-pub fn draw_vertical_histogram(img: &mut DynamicImage, histogram: &Chart) {
-    draw_vertical_bars(img, histogram, "histogram");
+pub fn draw_vertical_histogram(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    histogram: &Chart,
+) -> Result<()> {
+    draw_vertical_bars(img, fonts, histogram, "histogram")
 }
 
 /// Draw a vertical barchart, where the bars are filled with a gradient.
 ///
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Barchart struct, which contains all data & meta-data about the barchart.
-/// * `preset` - Preset name for the gradient. Can be: "pinkblue", "pastel_pink", "pastel_mauve", "lemongrass"
-pub fn draw_vertical_gradient_barchart(img: &mut DynamicImage, barchart: &Chart, preset: &str) {
+/// * `preset` - Name of a gradient in the shared [`palettes`](crate::palettes) registry, such as "pinkblue",
+///   "pink_pastel", "mauve_pastel" or "lemongrass".
+pub fn draw_vertical_gradient_barchart(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+    preset: &str,
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let mut start_x: u32 = 20;
-    let start_y: u32 = barchart.height - 40;
+    let start_y: u32 = barchart.height.saturating_sub(40);
 
-    let max_item = barchart.data.iter().max().unwrap();
-    let max_bar_height: u32 = barchart.height - 2 * (barchart.height / 10);
+    let max_item = max_item(barchart)?;
+    let max_bar_height: u32 = max_bar_height(barchart, start_y);
     let num_bars: u32 = barchart.data.len() as u32;
     let bar_width: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
 
@@ -66,8 +97,7 @@ pub fn draw_vertical_gradient_barchart(img: &mut DynamicImage, barchart: &Chart,
     };
 
     for item in &barchart.data {
-        let div = max_item / item;
-        let bar_height = max_bar_height / div as u32;
+        let bar_height = bar_len(*item, max_item, max_bar_height);
         draw_preset_rect_gradient(
             img,
             bar_width as u32,
@@ -75,25 +105,32 @@ pub fn draw_vertical_gradient_barchart(img: &mut DynamicImage, barchart: &Chart,
             start_x,
             start_y - bar_height,
             preset,
-        );
+        )?;
 
         start_x += bar_width + 30;
     }
 
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 // Draw vertical bars, either as a histogram or bar chart.
 // This is a private function, but may become public in the future.
-fn draw_vertical_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &str) {
+fn draw_vertical_bars(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+    chart_type: &str,
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let bar_gap = match chart_type {
         "barchart" => 30,
         "histogram" => 0,
@@ -101,21 +138,15 @@ fn draw_vertical_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &str
     };
 
     let mut start_x: u32 = 20;
-    let start_y: u32 = barchart.height - 40;
+    let start_y: u32 = barchart.height.saturating_sub(40);
 
-    let max_item = barchart.data.iter().max().unwrap();
-    let max_bar_height: u32 = barchart.height - 2 * (barchart.height / 10);
+    let max_item = max_item(barchart)?;
+    let max_bar_height: u32 = max_bar_height(barchart, start_y);
     let num_bars: u32 = barchart.data.len() as u32;
-    println!("num_bars {}", barchart.data.len());
     let bar_width: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
 
     for item in &barchart.data {
-        let mut bar_height = 0;
-
-        if *item > 0 as u16 {
-            let div = max_item / item;
-            bar_height = max_bar_height / div as u32;
-        }
+        let bar_height = bar_len(*item, max_item, max_bar_height);
 
         draw_solid_rect(
             img,
@@ -136,26 +167,35 @@ fn draw_vertical_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &str
 
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 /// Draw a vertical barchart, where the bars are filled with a gradient.
 ///
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Barchart struct, which contains all data & meta-data about the barchart.
-/// * `preset` - Preset name for the gradient. Can be: "pinkblue", "pastel_pink", "pastel_mauve", "lemongrass"
-pub fn draw_horizontal_gradient_barchart(img: &mut DynamicImage, barchart: &Chart, preset: &str) {
+/// * `preset` - Name of a gradient in the shared [`palettes`](crate::palettes) registry, such as "pinkblue",
+///   "pink_pastel", "mauve_pastel" or "lemongrass".
+pub fn draw_horizontal_gradient_barchart(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+    preset: &str,
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let start_x: u32 = 20;
     let mut start_y: u32 = 20;
 
-    let max_item = barchart.data.iter().max().unwrap();
+    let max_item = max_item(barchart)?;
     let max_bar_width: u32 = barchart.width - 2 * (barchart.width / 10);
     let num_bars: u32 = barchart.data.len() as u32;
     let bar_height: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
@@ -168,8 +208,7 @@ pub fn draw_horizontal_gradient_barchart(img: &mut DynamicImage, barchart: &Char
 
     let bar_gap = 30;
     for item in &barchart.data {
-        let div = max_item / item;
-        let bar_width = max_bar_width / div as u32;
+        let bar_width = bar_len(*item, max_item, max_bar_width);
         draw_preset_rect_gradient(
             img,
             bar_width as u32,
@@ -177,24 +216,31 @@ pub fn draw_horizontal_gradient_barchart(img: &mut DynamicImage, barchart: &Char
             start_x,
             start_y,
             preset,
-        );
+        )?;
         start_y += bar_height + bar_gap;
     }
 
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 // Draw a horizontal chart, either as a histogram or as a barchart,
 // with horizontal bars.
-fn draw_horizontal_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &str) {
+fn draw_horizontal_bars(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    barchart: &Chart,
+    chart_type: &str,
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let bar_gap = match chart_type {
         "barchart" => 30,
         "histogram" => 0,
@@ -204,7 +250,7 @@ fn draw_horizontal_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &s
     let start_x: i32 = 20;
     let mut start_y: i32 = 20;
 
-    let max_item = barchart.data.iter().max().unwrap();
+    let max_item = max_item(barchart)?;
     let max_bar_width: u32 = barchart.width - 2 * (barchart.width / 10);
     let num_bars: u32 = barchart.data.len() as u32;
     let bar_height: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
@@ -216,8 +262,7 @@ fn draw_horizontal_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &s
     };
 
     for item in &barchart.data {
-        let div = max_item / item;
-        let bar_width = max_bar_width / div as u32;
+        let bar_width = bar_len(*item, max_item, max_bar_width);
         draw_solid_rect(
             img,
             &barchart.color,
@@ -240,13 +285,14 @@ fn draw_horizontal_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &s
 
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 /// Draw a vertical barchart, where each bar is denoted by an image.
@@ -254,17 +300,20 @@ fn draw_horizontal_bars(img: &mut DynamicImage, barchart: &Chart, chart_type: &s
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
 /// * `bar_img` - Image the bars should contain.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Chart struct, which contains all data & meta-data about the barchart.
 pub fn draw_vertical_image_barchart(
     img: &mut DynamicImage,
     bar_img: &DynamicImage,
+    fonts: &FontRegistry,
     barchart: &Chart,
-) {
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let mut start_x: u32 = 20;
-    let start_y: u32 = barchart.height - 40;
+    let start_y: u32 = barchart.height.saturating_sub(40);
 
-    let max_item = barchart.data.iter().max().unwrap();
-    let max_bar_height: u32 = barchart.height - 2 * (barchart.height / 10);
+    let max_item = max_item(barchart)?;
+    let max_bar_height: u32 = max_bar_height(barchart, start_y);
     let num_bars: u32 = barchart.data.len() as u32;
     let bar_width: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
 
@@ -275,8 +324,11 @@ pub fn draw_vertical_image_barchart(
     };
 
     for item in &barchart.data {
-        let div = max_item / item;
-        let bar_height = max_bar_height / div as u32;
+        let bar_height = bar_len(*item, max_item, max_bar_height);
+        if bar_height == 0 {
+            start_x += bar_width + 30;
+            continue;
+        }
 
         let sampling_filter = image::imageops::FilterType::Nearest;
 
@@ -287,20 +339,28 @@ pub fn draw_vertical_image_barchart(
             sampling_filter,
         ));
 
-        image::imageops::overlay(img, &resized_img, start_x, start_y - bar_height);
+        composite(
+            img,
+            &resized_img,
+            start_x as i32,
+            (start_y - bar_height) as i32,
+            BlendMode::Normal,
+            1.0,
+        );
 
         start_x += bar_width + 30;
     }
 
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 /// Draw a vertical barchart, with a specified title and data.
@@ -308,16 +368,19 @@ pub fn draw_vertical_image_barchart(
 /// ### Arguments
 /// * `img` - Image to draw the barchart onto.
 /// * `bar_img` - Image the bars should contain.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `barchart` - Chart struct, which contains all data & meta-data about the barchart.
 pub fn draw_horizontal_image_barchart(
     img: &mut DynamicImage,
     bar_img: &DynamicImage,
+    fonts: &FontRegistry,
     barchart: &Chart,
-) {
+) -> Result<()> {
+    check_bounds(img, barchart)?;
     let start_x: u32 = 20;
     let mut start_y: u32 = 20;
 
-    let max_item = barchart.data.iter().max().unwrap();
+    let max_item = max_item(barchart)?;
     let max_bar_width: u32 = barchart.width - 2 * (barchart.width / 10);
     let num_bars: u32 = barchart.data.len() as u32;
     let bar_height: u32 = ((barchart.height / num_bars) as f32 * 0.8) as u32;
@@ -329,31 +392,35 @@ pub fn draw_horizontal_image_barchart(
     };
 
     for item in &barchart.data {
-        let div = max_item / item;
-        let bar_width = max_bar_width / div as u32;
-
-        draw_image_as_bar(img, bar_img, bar_width, bar_height, start_x, start_y);
+        let bar_width = bar_len(*item, max_item, max_bar_width);
+        if bar_width > 0 {
+            draw_image_as_bar(img, bar_img, bar_width, bar_height, start_x, start_y);
+        }
 
         start_y += bar_height + 30;
     }
     draw_text(
         img,
+        fonts,
         &barchart.title,
         10,
         start_y as u32,
-        "Lato-Regular",
+        &FontDescriptor::new("Lato"),
         50.0,
         &yellow,
-    );
+    )
 }
 
 /// Draw a linechart, with a specified title and data.
 ///
 /// #### Arguments
 /// * `img` - Image to draw the linechart onto.
+/// * `fonts` - FontRegistry the fonts are taken from.
 /// * `chart` - Chart struct, which contains all data & meta-data about the barchart.
-pub fn draw_linechart(mut img: &mut DynamicImage, chart: &Chart) {
-    draw_labels(&mut img, chart);
+pub fn draw_linechart(img: &mut DynamicImage, fonts: &FontRegistry, chart: &Chart) -> Result<()> {
+    check_bounds(img, chart)?;
+    let max_item = max_item(chart)?;
+    draw_labels(img, fonts, chart)?;
     let axis_len = chart.width as f32 * 0.8;
     let y_origin = 20.0 + axis_len;
 
@@ -361,23 +428,23 @@ pub fn draw_linechart(mut img: &mut DynamicImage, chart: &Chart) {
 
     let mut start_x = 20.0;
     let line_pixel = image::Rgba([255, 167, 90, 255]);
-    let max_item = chart.data.iter().max().unwrap();
 
-    let mut start_y = y_origin;
+    let mut line = Path::new().move_to(start_x, y_origin);
 
     for item in &chart.data {
-        let div: f32 = *max_item as f32 / *item as f32;
+        let div: f32 = max_item as f32 / *item as f32;
 
         let y_dist = y_origin - (axis_len / div);
-        draw_line_segment_mut(
-            img,
-            (start_x as f32, start_y as f32),
-            (start_x + x_inc, y_dist),
-            line_pixel,
-        );
+        line = line.line_to(start_x + x_inc, y_dist);
         start_x += x_inc;
-        start_y = y_dist;
     }
+    stroke_path(
+        img,
+        &line,
+        line_pixel,
+        &Stroke::new(3.0).join(LineJoin::Round).cap(LineCap::Round),
+    );
+    Ok(())
 }
 
 /// Draw a linechart and accentuate the points, with a specified title and data.
@@ -412,7 +479,7 @@ pub fn draw_linechart(mut img: &mut DynamicImage, chart: &Chart) {
 // }
 
 // Draw labels onto the axes of a chart, typically for bar or line charts.
-fn draw_labels(img: &mut DynamicImage, chart: &Chart) {
+fn draw_labels(img: &mut DynamicImage, fonts: &FontRegistry, chart: &Chart) -> Result<()> {
     draw_axes(img, chart);
     let axis_len = chart.width as f32 * 0.8;
     let x_inc = axis_len / chart.data.len() as f32;
@@ -428,16 +495,18 @@ fn draw_labels(img: &mut DynamicImage, chart: &Chart) {
     for label in &chart.labels {
         draw_text(
             img,
+            fonts,
             label,
             start_x as u32,
             start_y as u32,
-            "Roboto-Regular",
+            &FontDescriptor::new("Roboto"),
             30.0,
             &yellow,
-        );
+        )?;
 
         start_x += x_inc;
     }
+    Ok(())
 }
 
 // Draw x and y-axes to the image, mainly for bar charts and line charts.
@@ -456,20 +525,16 @@ fn draw_axes(img: &mut DynamicImage, chart: &Chart) {
     // End point on x-axis
     let end_x_xaxis: f32 = start_x + axis_len;
 
-    // Draw y-axis
-    draw_line_segment_mut(
-        img,
-        (start_x as f32, start_y as f32),
-        (start_x, end_y_yaxis),
-        line_pixel,
-    );
-
-    // Draw x-axis
-    draw_line_segment_mut(
+    // Draw both axes as one line, so they meet in a clean corner at the origin.
+    let axes = Path::new()
+        .move_to(start_x, end_y_yaxis)
+        .line_to(start_x, start_y)
+        .line_to(end_x_xaxis, start_y);
+    stroke_path(
         img,
-        (start_x as f32, start_y as f32),
-        (end_x_xaxis, start_y),
+        &axes,
         line_pixel,
+        &Stroke::new(2.0).cap(LineCap::Square),
     );
 }
 
@@ -490,13 +555,58 @@ fn draw_image_as_bar(
         bar_height as u32,
         sampling_filter,
     ));
-    image::imageops::overlay(img, &resized_img, start_x, start_y);
+    composite(
+        img,
+        &resized_img,
+        start_x as i32,
+        start_y as i32,
+        BlendMode::Normal,
+        1.0,
+    );
+}
+
+// Get the largest value in a chart's data, which all bars are scaled against.
+fn max_item(chart: &Chart) -> Result<u16> {
+    chart
+        .data
+        .iter()
+        .copied()
+        .max()
+        .ok_or(Error::EmptyChartData)
+}
+
+// Get the height of the tallest bar of a vertical chart, whose bars rise from `start_y`. Bars
+// take up 80% of the chart's height, but no more than fits above `start_y` in short charts.
+fn max_bar_height(chart: &Chart, start_y: u32) -> u32 {
+    (chart.height - 2 * (chart.height / 10)).min(start_y)
+}
+
+// Scale a data item to a bar length, relative to the chart's largest item.
+fn bar_len(item: u16, max_item: u16, max_len: u32) -> u32 {
+    if max_item == 0 {
+        return 0;
+    }
+    (item as u64 * max_len as u64 / max_item as u64) as u32
+}
+
+// Ensure the chart fits within the image.
+fn check_bounds(img: &DynamicImage, chart: &Chart) -> Result<()> {
+    let (img_width, img_height) = GenericImageView::dimensions(img);
+    if chart.width > img_width || chart.height > img_height {
+        return Err(Error::OutOfBounds {
+            x: 0,
+            y: 0,
+            width: chart.width,
+            height: chart.height,
+        });
+    }
+    Ok(())
 }
 
 // STRUCTS
 
 /// Chart type, containing data, labels, and other metadata about a chart.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Chart {
     pub title: String,
     pub color: Rgb,
@@ -526,3 +636,80 @@ impl Chart {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(data: Vec<u16>, width: u32, height: u32) -> Chart {
+        let labels = data.iter().map(|item| item.to_string()).collect();
+        Chart::new(
+            "Sales".to_string(),
+            Rgb::new(200, 50, 80),
+            data,
+            labels,
+            height,
+            width,
+        )
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn short_charts_are_drawn() {
+        let fonts = FontRegistry::embedded();
+        let mut img = DynamicImage::new_rgba8(120, 60);
+        for height in [0, 10, 39, 40, 60] {
+            let chart = chart(vec![3, 0, 7], 120, height);
+            assert!(draw_vertical_barchart(&mut img, &fonts, &chart).is_ok());
+            assert!(draw_vertical_histogram(&mut img, &fonts, &chart).is_ok());
+            assert!(draw_horizontal_barchart(&mut img, &fonts, &chart).is_ok());
+        }
+    }
+
+    #[test]
+    fn bars_are_proportional_to_their_items() {
+        assert_eq!(bar_len(100, 100, 200), 200);
+        assert_eq!(bar_len(51, 100, 200), 102);
+        assert_eq!(bar_len(50, 100, 200), 100);
+        assert_eq!(bar_len(0, 100, 200), 0);
+        assert_eq!(bar_len(0, 0, 200), 0);
+        assert_eq!(bar_len(u16::MAX, u16::MAX, u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn bars_fit_above_the_baseline() {
+        for height in [0, 10, 40, 100, 199, 200, 600] {
+            let chart = chart(vec![1], 100, height);
+            let start_y = height.saturating_sub(40);
+            assert!(max_bar_height(&chart, start_y) <= start_y);
+        }
+    }
+
+    #[test]
+    fn charts_larger_than_the_image_are_rejected() {
+        let img = DynamicImage::new_rgba8(100, 100);
+        assert!(check_bounds(&img, &chart(vec![1], 100, 100)).is_ok());
+        assert!(matches!(
+            check_bounds(&img, &chart(vec![1], 101, 100)),
+            Err(Error::OutOfBounds { .. })
+        ));
+        assert!(check_bounds(&img, &chart(vec![1], 100, 101)).is_err());
+    }
+
+    #[test]
+    fn empty_data_is_an_error() {
+        let fonts = FontRegistry::new();
+        let mut img = DynamicImage::new_rgba8(100, 100);
+        assert!(matches!(
+            draw_vertical_barchart(&mut img, &fonts, &chart(Vec::new(), 100, 100)),
+            Err(Error::EmptyChartData)
+        ));
+    }
+
+    #[test]
+    fn bar_lengths_scale_with_the_largest_item() {
+        assert_eq!(bar_len(0, 10, 100), 0);
+        assert_eq!(bar_len(10, 10, 100), 100);
+        assert_eq!(bar_len(5, 10, 100), 50);
+    }
+}
@@ -0,0 +1,1043 @@
+//! Retained designs: documents made of layers of shapes, text, images, gradients and charts.
+//!
+//! The drawing functions elsewhere in GDL paint straight onto an image, so once a design is
+//! drawn it can't be changed. A [`Document`] keeps the design instead, as a tree of [`Node`]s
+//! inside named [`Layer`]s, which can be looked up and edited by name and rendered as often as
+//! needed, at any scale. Every node has a [`Transform`], an opacity, a blend mode and an optional
+//! clipping path. Groups render their children together, so their opacity and blend mode apply
+//! to the group as a whole.
+//!
+//! Shapes are rasterized after they are transformed, so they stay sharp at any scale or angle.
+//! Text, images, gradients and charts are drawn at the scale they are shown at, then rotated or
+//! skewed into place if the transform needs it.
+//!
+//! ```no_run
+//! use gdl::document::{Document, Layer, Node, ShapeNode, TextNode};
+//! use gdl::fonts::{FontDescriptor, FontRegistry};
+//! use gdl::path::Path;
+//! use image::Rgba;
+//!
+//! let fonts = FontRegistry::from_dir("fonts").unwrap();
+//! let mut document = Document::new(1200, 630)
+//!     .background(Rgba([20, 24, 40, 255]))
+//!     .layer(
+//!         Layer::new("card").node(
+//!             Node::new(
+//!                 ShapeNode::new(Path::new().rounded_rect(0.0, 0.0, 500.0, 300.0, 24.0))
+//!                     .fill(Rgba([255, 94, 98, 255])),
+//!             )
+//!             .name("card")
+//!             .rotate(-8.0)
+//!             .translate(120.0, 160.0)
+//!             .opacity(0.9),
+//!         ),
+//!     )
+//!     .layer(Layer::new("text").node(
+//!         Node::new(TextNode::new("Hello **world**", FontDescriptor::new("Roboto"), 72.0))
+//!             .name("title")
+//!             .translate(680.0, 260.0),
+//!     ));
+//!
+//! let preview = document.render(&fonts).unwrap();
+//!
+//! // Edit the design, and render it again at twice the size.
+//! document.find_mut("card").unwrap().opacity = 0.5;
+//! let large = document.render_scaled(&fonts, 2.0).unwrap();
+//! ```
+
+use crate::compositing::{composite, BlendMode};
+use crate::diagrams::{
+    draw_horizontal_barchart, draw_horizontal_gradient_barchart, draw_horizontal_histogram,
+    draw_linechart, draw_vertical_barchart, draw_vertical_gradient_barchart,
+    draw_vertical_histogram, Chart,
+};
+use crate::emoji::sample;
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::gradient::Gradient;
+use crate::helpers::{blend_over, dyn_to_photonimg};
+use crate::layout::ParagraphOptions;
+use crate::path::{fill_path_with, stroke_path_with, Fill, FillRule, Path, PathCommand, Stroke};
+use crate::rich_text::{draw_rich_paragraph, layout_rich_text, RichText};
+use crate::style::{StyleCanvas, TextStyle};
+use crate::{PhotonImage, Result};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use std::rc::Rc;
+
+// Transforms this close to a whole-pixel translation are drawn without resampling.
+const EPSILON: f32 = 1e-4;
+
+/// A 2D affine transform, mapping `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`.
+///
+/// The fields are in the same order as the arguments of `setTransform` on an HTML canvas.
+/// Transforms are built up with [`Transform::then`], which applies one transform after another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    /// The transform that leaves every point where it is.
+    pub fn identity() -> Transform {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Create a transform from its six coefficients.
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Transform {
+        Transform { a, b, c, d, e, f }
+    }
+
+    /// Move points by `(x, y)`.
+    pub fn translation(x: f32, y: f32) -> Transform {
+        Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// Scale points away from the origin, horizontally by `x` and vertically by `y`.
+    pub fn scaling(x: f32, y: f32) -> Transform {
+        Transform::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Rotate points clockwise around the origin, by an angle in degrees.
+    pub fn rotation(angle: f32) -> Transform {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Rotate points clockwise around `(x, y)`, by an angle in degrees.
+    pub fn rotation_around(angle: f32, x: f32, y: f32) -> Transform {
+        Transform::translation(-x, -y)
+            .then(&Transform::rotation(angle))
+            .then(&Transform::translation(x, y))
+    }
+
+    /// This transform followed by another.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    /// Where a point ends up.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// The transform that undoes this one, or `None` if it flattens shapes to a line or point.
+    pub fn inverse(&self) -> Option<Transform> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(Transform {
+            a: self.d / determinant,
+            b: -self.b / determinant,
+            c: -self.c / determinant,
+            d: self.a / determinant,
+            e: (self.c * self.f - self.d * self.e) / determinant,
+            f: (self.b * self.e - self.a * self.f) / determinant,
+        })
+    }
+
+    /// How much the transform scales lengths, on average.
+    pub fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Whether the transform only moves, and scales along the axes without flipping, so
+    /// rectangles stay upright.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b.abs() < EPSILON && self.c.abs() < EPSILON && self.a > 0.0 && self.d > 0.0
+    }
+
+    /// Apply the transform to every point of a path.
+    pub fn apply_to_path(&self, path: &Path) -> Path {
+        path.commands()
+            .iter()
+            .fold(Path::new(), |transformed, command| match *command {
+                PathCommand::MoveTo(x, y) => {
+                    let (x, y) = self.apply(x, y);
+                    transformed.move_to(x, y)
+                }
+                PathCommand::LineTo(x, y) => {
+                    let (x, y) = self.apply(x, y);
+                    transformed.line_to(x, y)
+                }
+                PathCommand::QuadTo { control, to } => {
+                    let (cx, cy) = self.apply(control.0, control.1);
+                    let (x, y) = self.apply(to.0, to.1);
+                    transformed.quad_to(cx, cy, x, y)
+                }
+                PathCommand::CubicTo {
+                    control1,
+                    control2,
+                    to,
+                } => {
+                    let (c1x, c1y) = self.apply(control1.0, control1.1);
+                    let (c2x, c2y) = self.apply(control2.0, control2.1);
+                    let (x, y) = self.apply(to.0, to.1);
+                    transformed.cubic_to(c1x, c1y, c2x, c2y, x, y)
+                }
+                PathCommand::Close => transformed.close(),
+            })
+    }
+}
+
+/// A design: a stack of layers drawn over a background, bottom layer first.
+#[derive(Clone)]
+pub struct Document {
+    /// Width of the design, in pixels at a scale of 1.
+    pub width: u32,
+    /// Height of the design, in pixels at a scale of 1.
+    pub height: u32,
+    /// Colour the layers are drawn over. The design is transparent where nothing is drawn if
+    /// this is `None`.
+    pub background: Option<Rgba<u8>>,
+    pub layers: Vec<Layer>,
+}
+
+impl Document {
+    /// An empty, transparent document.
+    pub fn new(width: u32, height: u32) -> Document {
+        Document {
+            width,
+            height,
+            background: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Set the colour the layers are drawn over.
+    pub fn background(mut self, color: Rgba<u8>) -> Document {
+        self.background = Some(color);
+        self
+    }
+
+    /// Add a layer on top of the others.
+    pub fn layer(mut self, layer: Layer) -> Document {
+        self.layers.push(layer);
+        self
+    }
+
+    /// The first layer with the given name.
+    pub fn get_layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// The first layer with the given name, for editing.
+    pub fn get_layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// The first node with the given name, searching the layers from the bottom up and each
+    /// group before the nodes after it.
+    pub fn find(&self, name: &str) -> Option<&Node> {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.nodes)
+            .find_map(|node| node.find(name))
+    }
+
+    /// The first node with the given name, for editing.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| &mut layer.nodes)
+            .find_map(|node| node.find_mut(name))
+    }
+
+    /// Render the document at its own size.
+    ///
+    /// ### Arguments
+    /// * `fonts` - FontRegistry the fonts of text and charts are taken from.
+    ///
+    /// Returns an error if a font isn't in the registry, or a chart can't be drawn.
+    pub fn render(&self, fonts: &FontRegistry) -> Result<DynamicImage> {
+        self.render_scaled(fonts, 1.0)
+    }
+
+    /// Render the document scaled up or down, such as at 2 for a high-density screen.
+    ///
+    /// Shapes and text are drawn at the new size rather than stretched, so they stay sharp.
+    ///
+    /// ### Arguments
+    /// * `fonts` - FontRegistry the fonts of text and charts are taken from.
+    /// * `scale` - How much larger than the document's own size the image is.
+    pub fn render_scaled(&self, fonts: &FontRegistry, scale: f32) -> Result<DynamicImage> {
+        let width = (self.width as f32 * scale).round().max(1.0) as u32;
+        let height = (self.height as f32 * scale).round().max(1.0) as u32;
+        let mut image = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
+            width,
+            height,
+            self.background.unwrap_or(Rgba([0, 0, 0, 0])),
+        ));
+        let transform = Transform::scaling(scale, scale);
+
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            if layer.blend_mode == BlendMode::Normal && layer.opacity >= 1.0 {
+                for node in &layer.nodes {
+                    render_node(&mut image, fonts, node, &transform)?;
+                }
+            } else {
+                let mut canvas = DynamicImage::new_rgba8(width, height);
+                for node in &layer.nodes {
+                    render_node(&mut canvas, fonts, node, &transform)?;
+                }
+                composite(
+                    &mut image,
+                    &canvas.to_rgba8(),
+                    0,
+                    0,
+                    layer.blend_mode,
+                    layer.opacity,
+                );
+            }
+        }
+        Ok(image)
+    }
+
+    /// Render the document at its own size, as a PhotonImage.
+    pub fn render_photon(&self, fonts: &FontRegistry) -> Result<PhotonImage> {
+        Ok(dyn_to_photonimg(&self.render(fonts)?))
+    }
+}
+
+/// A named set of nodes, drawn together onto the layers below.
+#[derive(Clone)]
+pub struct Layer {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    /// Opacity of the layer as a whole, from 0 to 1.
+    pub opacity: f32,
+    /// How the layer's colours are blended with the layers below.
+    pub blend_mode: BlendMode,
+    /// Hidden layers aren't rendered.
+    pub visible: bool,
+}
+
+impl Layer {
+    /// An empty, visible layer.
+    pub fn new(name: &str) -> Layer {
+        Layer {
+            name: name.to_string(),
+            nodes: Vec::new(),
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            visible: true,
+        }
+    }
+
+    /// Add a node on top of the layer's other nodes.
+    pub fn node(mut self, node: Node) -> Layer {
+        self.nodes.push(node);
+        self
+    }
+
+    /// Set the opacity of the layer, from 0 to 1.
+    pub fn opacity(mut self, opacity: f32) -> Layer {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Set how the layer is blended with the layers below.
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Layer {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Hide or show the layer.
+    pub fn visible(mut self, visible: bool) -> Layer {
+        self.visible = visible;
+        self
+    }
+}
+
+/// An element of a design, with its place in the design and how it is composited.
+#[derive(Clone)]
+pub struct Node {
+    /// Name the node can be found by with [`Document::find`].
+    pub name: Option<String>,
+    pub content: NodeContent,
+    /// Maps the node's own coordinates into its parent's.
+    pub transform: Transform,
+    /// Opacity of the node, from 0 to 1. A group's opacity applies to the group as a whole.
+    pub opacity: f32,
+    /// How the node's colours are blended with what is drawn under it.
+    pub blend_mode: BlendMode,
+    /// Only the parts of the node inside this path, in the node's own coordinates, are drawn.
+    pub clip: Option<Path>,
+    /// Hidden nodes aren't rendered.
+    pub visible: bool,
+}
+
+/// What a node draws.
+#[derive(Clone)]
+pub enum NodeContent {
+    /// Other nodes, drawn together in order.
+    Group(Vec<Node>),
+    Shape(ShapeNode),
+    Text(TextNode),
+    Image(ImageNode),
+    Gradient(GradientNode),
+    Chart(ChartNode),
+}
+
+impl Node {
+    /// A visible, untransformed node.
+    pub fn new(content: impl Into<NodeContent>) -> Node {
+        Node {
+            name: None,
+            content: content.into(),
+            transform: Transform::identity(),
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            clip: None,
+            visible: true,
+        }
+    }
+
+    /// A group of nodes, drawn in order.
+    pub fn group(children: Vec<Node>) -> Node {
+        Node::new(NodeContent::Group(children))
+    }
+
+    /// Set the name the node can be found by.
+    pub fn name(mut self, name: &str) -> Node {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Replace the node's transform.
+    pub fn transform(mut self, transform: Transform) -> Node {
+        self.transform = transform;
+        self
+    }
+
+    /// Move the node, after any transform it already has.
+    pub fn translate(self, x: f32, y: f32) -> Node {
+        self.then(Transform::translation(x, y))
+    }
+
+    /// Scale the node from its origin, after any transform it already has.
+    pub fn scale(self, x: f32, y: f32) -> Node {
+        self.then(Transform::scaling(x, y))
+    }
+
+    /// Rotate the node clockwise around its origin by an angle in degrees, after any transform
+    /// it already has.
+    pub fn rotate(self, angle: f32) -> Node {
+        self.then(Transform::rotation(angle))
+    }
+
+    /// Set the opacity of the node, from 0 to 1.
+    pub fn opacity(mut self, opacity: f32) -> Node {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Set how the node is blended with what is drawn under it.
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Node {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Clip the node to a path, in the node's own coordinates.
+    pub fn clip(mut self, path: Path) -> Node {
+        self.clip = Some(path);
+        self
+    }
+
+    /// Hide or show the node.
+    pub fn visible(mut self, visible: bool) -> Node {
+        self.visible = visible;
+        self
+    }
+
+    /// The node's children, if it is a group.
+    pub fn children(&self) -> &[Node] {
+        match &self.content {
+            NodeContent::Group(children) => children,
+            _ => &[],
+        }
+    }
+
+    /// This node, if it has the given name, or else the first of its descendants that does.
+    pub fn find(&self, name: &str) -> Option<&Node> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        self.children().iter().find_map(|child| child.find(name))
+    }
+
+    /// This node or the first of its descendants with the given name, for editing.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        match &mut self.content {
+            NodeContent::Group(children) => {
+                children.iter_mut().find_map(|child| child.find_mut(name))
+            }
+            _ => None,
+        }
+    }
+
+    fn then(mut self, transform: Transform) -> Node {
+        self.transform = self.transform.then(&transform);
+        self
+    }
+}
+
+/// A vector shape, filled, outlined or both.
+#[derive(Debug, Clone)]
+pub struct ShapeNode {
+    pub path: Path,
+    pub fill: Option<Fill>,
+    pub fill_rule: FillRule,
+    /// What the outline is painted with, and its width, joins, caps and dashes.
+    pub stroke: Option<(Fill, Stroke)>,
+}
+
+impl ShapeNode {
+    /// A shape that draws nothing until it is given a fill or stroke.
+    pub fn new(path: Path) -> ShapeNode {
+        ShapeNode {
+            path,
+            fill: None,
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+        }
+    }
+
+    /// Fill the shape with a colour, gradient or image.
+    pub fn fill(mut self, fill: impl Into<Fill>) -> ShapeNode {
+        self.fill = Some(fill.into());
+        self
+    }
+
+    /// Set which parts of a path that crosses itself are filled.
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> ShapeNode {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Outline the shape.
+    pub fn stroke(mut self, fill: impl Into<Fill>, stroke: Stroke) -> ShapeNode {
+        self.stroke = Some((fill.into(), stroke));
+        self
+    }
+}
+
+/// A paragraph of rich text, with the top-left of its box at the node's origin.
+#[derive(Clone)]
+pub struct TextNode {
+    pub text: RichText,
+    /// The base font, used by spans that don't set their own.
+    pub font: FontDescriptor,
+    /// The base font size in pixels, which spans are scaled relative to.
+    pub font_size: f32,
+    pub style: TextStyle,
+    pub options: ParagraphOptions,
+}
+
+impl TextNode {
+    /// Text parsed from [`RichText`] markup, in black and not wrapped.
+    pub fn new(markup: &str, font: FontDescriptor, font_size: f32) -> TextNode {
+        TextNode {
+            text: RichText::parse(markup),
+            font,
+            font_size,
+            style: TextStyle::new(),
+            options: ParagraphOptions::new(),
+        }
+    }
+
+    /// Set the colour, or the fill and effects, of the text.
+    pub fn style(mut self, style: impl Into<TextStyle>) -> TextNode {
+        self.style = style.into();
+        self
+    }
+
+    /// Set how the text is wrapped, spaced and aligned.
+    pub fn options(mut self, options: ParagraphOptions) -> TextNode {
+        self.options = options;
+        self
+    }
+}
+
+/// An image, with its top-left corner at the node's origin.
+#[derive(Debug, Clone)]
+pub struct ImageNode {
+    pub image: Rc<RgbaImage>,
+    /// Width the image is shown at, in the node's coordinates.
+    pub width: f32,
+    /// Height the image is shown at, in the node's coordinates.
+    pub height: f32,
+}
+
+impl ImageNode {
+    /// An image shown at its own size.
+    pub fn new(image: &DynamicImage) -> ImageNode {
+        let (width, height) = image.dimensions();
+        ImageNode {
+            image: Rc::new(image.to_rgba8()),
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+
+    /// Set the size the image is shown at.
+    pub fn size(mut self, width: f32, height: f32) -> ImageNode {
+        self.width = width;
+        self.height = height;
+        self
+    }
+}
+
+/// A rectangle filled with a gradient, with its top-left corner at the node's origin.
+#[derive(Debug, Clone)]
+pub struct GradientNode {
+    pub gradient: Gradient,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl GradientNode {
+    /// A gradient drawn across a rectangle of the given size.
+    pub fn new(gradient: Gradient, width: f32, height: f32) -> GradientNode {
+        GradientNode {
+            gradient,
+            width,
+            height,
+        }
+    }
+}
+
+/// The kinds of chart in [`crate::diagrams`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChartKind {
+    HorizontalBar,
+    VerticalBar,
+    HorizontalHistogram,
+    VerticalHistogram,
+    /// Bars filled with the named gradient preset.
+    HorizontalGradientBar(String),
+    /// Bars filled with the named gradient preset.
+    VerticalGradientBar(String),
+    Line,
+}
+
+/// A chart, with the top-left of its area at the node's origin.
+#[derive(Debug, Clone)]
+pub struct ChartNode {
+    pub kind: ChartKind,
+    /// The chart's data, labels and size.
+    pub chart: Chart,
+}
+
+impl ChartNode {
+    /// A chart of the given kind.
+    pub fn new(kind: ChartKind, chart: Chart) -> ChartNode {
+        ChartNode { kind, chart }
+    }
+}
+
+impl From<ShapeNode> for NodeContent {
+    fn from(shape: ShapeNode) -> NodeContent {
+        NodeContent::Shape(shape)
+    }
+}
+
+impl From<TextNode> for NodeContent {
+    fn from(text: TextNode) -> NodeContent {
+        NodeContent::Text(text)
+    }
+}
+
+impl From<ImageNode> for NodeContent {
+    fn from(image: ImageNode) -> NodeContent {
+        NodeContent::Image(image)
+    }
+}
+
+impl From<GradientNode> for NodeContent {
+    fn from(gradient: GradientNode) -> NodeContent {
+        NodeContent::Gradient(gradient)
+    }
+}
+
+impl From<ChartNode> for NodeContent {
+    fn from(chart: ChartNode) -> NodeContent {
+        NodeContent::Chart(chart)
+    }
+}
+
+// Render a node onto an image, given the transform from its parent's coordinates to the
+// image's pixels.
+fn render_node(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    node: &Node,
+    parent: &Transform,
+) -> Result<()> {
+    if !node.visible || node.opacity <= 0.0 {
+        return Ok(());
+    }
+    let transform = node.transform.then(parent);
+    if node.blend_mode == BlendMode::Normal && node.opacity >= 1.0 && node.clip.is_none() {
+        return render_content(image, fonts, &node.content, &transform);
+    }
+
+    // Draw the node on its own, so that it is clipped, faded and blended as a whole.
+    let (width, height) = image.dimensions();
+    let mut layer = DynamicImage::new_rgba8(width, height);
+    render_content(&mut layer, fonts, &node.content, &transform)?;
+    let mut layer = layer.into_rgba8();
+    if let Some(clip) = &node.clip {
+        let mask = transform
+            .apply_to_path(clip)
+            .mask(FillRule::NonZero, width, height);
+        for (pixel, coverage) in layer.pixels_mut().zip(mask.pixels()) {
+            pixel[3] = (pixel[3] as u32 * coverage[0] as u32 / 255) as u8;
+        }
+    }
+    composite(image, &layer, 0, 0, node.blend_mode, node.opacity);
+    Ok(())
+}
+
+fn render_content(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    content: &NodeContent,
+    transform: &Transform,
+) -> Result<()> {
+    // Raster content is drawn at the scale it will be shown at, so it is barely resampled.
+    let scale = transform.scale_factor();
+    if scale <= 0.0 {
+        return Ok(());
+    }
+    match content {
+        NodeContent::Group(children) => {
+            for child in children {
+                render_node(image, fonts, child, transform)?;
+            }
+        }
+        NodeContent::Shape(shape) => render_shape(image, shape, transform),
+        NodeContent::Text(text) => {
+            let options = ParagraphOptions {
+                max_width: text.options.max_width.map(|width| width * scale),
+                letter_spacing: text.options.letter_spacing * scale,
+                ..text.options.clone()
+            };
+            let paragraph = layout_rich_text(
+                fonts,
+                &text.text,
+                &text.font,
+                text.font_size * scale,
+                &options,
+            )?;
+            // The same room draw_rich_paragraph leaves for the glyphs and their effects.
+            let largest = paragraph
+                .lines
+                .iter()
+                .flat_map(|line| line.runs.iter().map(|run| run.font_size))
+                .fold(0.0, f32::max);
+            let overhang = (largest / 2.0).ceil() as i32;
+            let canvas = StyleCanvas::new(
+                &text.style,
+                -overhang,
+                -overhang,
+                paragraph.width.max(paragraph.widest_line()).ceil() as i32 + overhang,
+                paragraph.height.ceil() as i32 + overhang,
+            );
+            let mut raster = DynamicImage::new_rgba8(canvas.width, canvas.height);
+            draw_rich_paragraph(
+                &mut raster,
+                &paragraph,
+                canvas.origin_x,
+                canvas.origin_y,
+                &text.style,
+            );
+            let origin = (
+                -canvas.origin_x as f32 / scale,
+                -canvas.origin_y as f32 / scale,
+            );
+            draw_raster(image, &raster.into_rgba8(), transform, origin, scale);
+        }
+        NodeContent::Image(node) => {
+            let (image_width, image_height) = node.image.dimensions();
+            if image_width == 0 || image_height == 0 || node.width <= 0.0 || node.height <= 0.0 {
+                return Ok(());
+            }
+            let width = (node.width * scale).round().max(1.0) as u32;
+            let height = (node.height * scale).round().max(1.0) as u32;
+            let raster = if node.image.dimensions() == (width, height) {
+                node.image.as_ref().clone()
+            } else {
+                imageops::resize(node.image.as_ref(), width, height, FilterType::Lanczos3)
+            };
+            let scale_x = width as f32 / node.width;
+            let scale_y = height as f32 / node.height;
+            let transform = Transform::scaling(scale / scale_x, scale / scale_y).then(transform);
+            draw_raster(image, &raster, &transform, (0.0, 0.0), scale);
+        }
+        NodeContent::Gradient(node) => {
+            let width = (node.width * scale).round().max(1.0) as u32;
+            let height = (node.height * scale).round().max(1.0) as u32;
+            let raster = node.gradient.render(width, height);
+            let transform = Transform::scaling(
+                node.width * scale / width as f32,
+                node.height * scale / height as f32,
+            )
+            .then(transform);
+            draw_raster(image, &raster, &transform, (0.0, 0.0), scale);
+        }
+        NodeContent::Chart(node) => {
+            // Charts are laid out in pixels, so they are drawn at their own size.
+            let chart = &node.chart;
+            let mut raster = DynamicImage::new_rgba8(chart.width, chart.height);
+            match &node.kind {
+                ChartKind::HorizontalBar => draw_horizontal_barchart(&mut raster, fonts, chart)?,
+                ChartKind::VerticalBar => draw_vertical_barchart(&mut raster, fonts, chart)?,
+                ChartKind::HorizontalHistogram => {
+                    draw_horizontal_histogram(&mut raster, fonts, chart)?
+                }
+                ChartKind::VerticalHistogram => draw_vertical_histogram(&mut raster, fonts, chart)?,
+                ChartKind::HorizontalGradientBar(preset) => {
+                    draw_horizontal_gradient_barchart(&mut raster, fonts, chart, preset)?
+                }
+                ChartKind::VerticalGradientBar(preset) => {
+                    draw_vertical_gradient_barchart(&mut raster, fonts, chart, preset)?
+                }
+                ChartKind::Line => draw_linechart(&mut raster, fonts, chart)?,
+            }
+            draw_raster(image, &raster.into_rgba8(), transform, (0.0, 0.0), 1.0);
+        }
+    }
+    Ok(())
+}
+
+fn render_shape(image: &mut DynamicImage, shape: &ShapeNode, transform: &Transform) {
+    let solid = |fill: &Fill| matches!(fill, Fill::Solid(_));
+    let all_solid =
+        shape.fill.iter().all(solid) && shape.stroke.iter().all(|(fill, _)| solid(fill));
+    if all_solid || transform.is_axis_aligned() {
+        // Gradients and images span the shape's bounding box, which only stays the same box
+        // after the transform if it keeps the shape upright.
+        draw_shape(image, shape, transform);
+        return;
+    }
+
+    // Draw the shape upright, then turn it into place.
+    let scale = transform.scale_factor();
+    let bounds = shape.path.bounds();
+    let (left, top, right, bottom) = match bounds {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let margin = shape.stroke.as_ref().map_or(0.0, |(_, stroke)| {
+        stroke.width * stroke.miter_limit.max(1.0)
+    }) + 1.0;
+    let origin = (left - margin, top - margin);
+    let width = ((right - left + margin * 2.0) * scale).ceil() as u32;
+    let height = ((bottom - top + margin * 2.0) * scale).ceil() as u32;
+    let mut raster = DynamicImage::new_rgba8(width.max(1), height.max(1));
+    let local =
+        Transform::translation(-origin.0, -origin.1).then(&Transform::scaling(scale, scale));
+    draw_shape(&mut raster, shape, &local);
+    draw_raster(image, &raster.into_rgba8(), transform, origin, scale);
+}
+
+// Fill and stroke a shape's path after transforming it.
+fn draw_shape(image: &mut DynamicImage, shape: &ShapeNode, transform: &Transform) {
+    let path = transform.apply_to_path(&shape.path);
+    if let Some(fill) = &shape.fill {
+        fill_path_with(image, &path, fill, shape.fill_rule);
+    }
+    if let Some((fill, stroke)) = &shape.stroke {
+        let scale = transform.scale_factor();
+        let stroke = Stroke {
+            width: stroke.width * scale,
+            dashes: stroke.dashes.iter().map(|dash| dash * scale).collect(),
+            dash_offset: stroke.dash_offset * scale,
+            ..stroke.clone()
+        };
+        stroke_path_with(image, &path, fill, &stroke);
+    }
+}
+
+// Draw an image onto another through a transform. The raster's pixels are `1 / scale` units
+// wide in the coordinates the transform maps from, with its top-left corner at `origin`.
+fn draw_raster(
+    image: &mut DynamicImage,
+    raster: &RgbaImage,
+    transform: &Transform,
+    origin: (f32, f32),
+    scale: f32,
+) {
+    let placement = Transform::scaling(1.0 / scale, 1.0 / scale)
+        .then(&Transform::translation(origin.0, origin.1))
+        .then(transform);
+    let linear_is_identity = (placement.a - 1.0).abs() < EPSILON
+        && (placement.d - 1.0).abs() < EPSILON
+        && placement.b.abs() < EPSILON
+        && placement.c.abs() < EPSILON;
+    if linear_is_identity {
+        // Snap to whole pixels rather than blur the raster for a sub-pixel offset.
+        let (x, y) = (placement.e.round() as i32, placement.f.round() as i32);
+        composite(image, raster, x, y, BlendMode::Normal, 1.0);
+        return;
+    }
+    let inverse = match placement.inverse() {
+        Some(inverse) => inverse,
+        None => return,
+    };
+
+    // Only visit the pixels the raster's corners enclose.
+    let (raster_width, raster_height) = (raster.width() as f32, raster.height() as f32);
+    let corners = [
+        placement.apply(0.0, 0.0),
+        placement.apply(raster_width, 0.0),
+        placement.apply(0.0, raster_height),
+        placement.apply(raster_width, raster_height),
+    ];
+    let (width, height) = image.dimensions();
+    let min_x = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min);
+    let min_y = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min);
+    let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max);
+    let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max);
+    let clamp = |value: f32, limit: u32| value.max(0.0).min(limit as f32) as u32;
+
+    for y in clamp(min_y.floor(), height)..clamp(max_y.ceil(), height) {
+        for x in clamp(min_x.floor(), width)..clamp(max_x.ceil(), width) {
+            // Map the centre of the pixel back onto the raster.
+            let (rx, ry) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+            let color = sample(raster, rx - 0.5, ry - 0.5);
+            if color[3] > 0 {
+                let pixel = image.get_pixel(x, y);
+                image.put_pixel(x, y, blend_over(pixel, color, color[3] as f32 / 255.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    fn square(size: f32) -> Node {
+        Node::new(ShapeNode::new(Path::new().rect(0.0, 0.0, size, size)).fill(RED))
+    }
+
+    fn painted(image: &DynamicImage) -> usize {
+        image.pixels().filter(|(_, _, pixel)| pixel[3] > 0).count()
+    }
+
+    #[test]
+    fn empty_documents_render_their_background() {
+        let fonts = FontRegistry::new();
+        let image = Document::new(4, 3).render(&fonts).unwrap();
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(painted(&image), 0);
+        let image = Document::new(4, 3).background(RED).render(&fonts).unwrap();
+        assert_eq!(painted(&image), 12);
+    }
+
+    #[test]
+    fn zero_sizes_and_scales_render_one_pixel() {
+        let fonts = FontRegistry::new();
+        let document = Document::new(0, 0).layer(Layer::new("shapes").node(square(10.0)));
+        assert_eq!(document.render(&fonts).unwrap().dimensions(), (1, 1));
+        let document = Document::new(10, 10).layer(Layer::new("shapes").node(square(10.0)));
+        assert_eq!(
+            document.render_scaled(&fonts, 0.0).unwrap().dimensions(),
+            (1, 1)
+        );
+        assert_eq!(
+            document.render_scaled(&fonts, -2.0).unwrap().dimensions(),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn missing_names_are_not_found() {
+        let mut document = Document::new(10, 10).layer(
+            Layer::new("shapes").node(Node::group(vec![square(4.0).name("inner")]).name("outer")),
+        );
+        assert!(document.find("inner").is_some());
+        assert!(document.find("").is_none());
+        assert!(document.find_mut("missing").is_none());
+        assert!(document.get_layer("text").is_none());
+        assert!(Document::new(1, 1).find("inner").is_none());
+    }
+
+    #[test]
+    fn hidden_and_flattened_nodes_draw_nothing() {
+        let fonts = FontRegistry::new();
+        let nodes = [
+            square(10.0).opacity(0.0),
+            square(10.0).visible(false),
+            square(10.0).scale(0.0, 1.0),
+            square(10.0).clip(Path::new()),
+            Node::group(Vec::new()),
+            Node::new(ShapeNode::new(Path::new()).fill(RED)),
+        ];
+        for node in &nodes {
+            let document = Document::new(10, 10).layer(Layer::new("shapes").node(node.clone()));
+            assert_eq!(painted(&document.render(&fonts).unwrap()), 0);
+        }
+        let hidden = Layer::new("hidden").node(square(10.0)).visible(false);
+        let document = Document::new(10, 10).layer(hidden);
+        assert_eq!(painted(&document.render(&fonts).unwrap()), 0);
+    }
+
+    #[test]
+    fn empty_images_and_gradients_draw_nothing() {
+        let fonts = FontRegistry::new();
+        let empty = DynamicImage::new_rgba8(0, 0);
+        let red = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, RED));
+        let gradient = Gradient::linear(0.0).colors(&[RED, RED]);
+        let nodes = [
+            Node::new(ImageNode::new(&empty)),
+            Node::new(ImageNode::new(&empty).size(5.0, 5.0)),
+            Node::new(ImageNode::new(&red).size(0.0, 5.0)),
+            Node::new(GradientNode::new(gradient, 0.0, 0.0)),
+        ];
+        for node in &nodes {
+            let document = Document::new(10, 10).layer(Layer::new("images").node(node.clone()));
+            assert_eq!(painted(&document.render(&fonts).unwrap()), 0);
+        }
+    }
+
+    #[test]
+    fn singular_transforms_have_no_inverse() {
+        assert!(Transform::scaling(0.0, 2.0).inverse().is_none());
+        assert!(Transform::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0)
+            .inverse()
+            .is_none());
+        let rotation = Transform::rotation_around(30.0, 5.0, 5.0);
+        let (rotated_x, rotated_y) = rotation.apply(2.0, 3.0);
+        let (x, y) = rotation.inverse().unwrap().apply(rotated_x, rotated_y);
+        assert!((x - 2.0).abs() < 1e-4 && (y - 3.0).abs() < 1e-4);
+    }
+}
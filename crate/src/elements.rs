@@ -1,13 +1,22 @@
 //! Add shapes and other elements to images.
-
-use crate::text::draw_text;
-use crate::Rgb;
-use image::{DynamicImage, GenericImage, GenericImageView, Rgba, RgbaImage};
-use imageproc::drawing::*;
-use imageproc::point::Point;
+//!
+//! Elements are composited onto the image with [`crate::compositing`], so translucent colours
+//! blend with what is underneath. To draw an element with a blend mode or a global opacity, wrap
+//! it in [`draw_blended`](crate::compositing::draw_blended). Triangles and borders are drawn with
+//! anti-aliased edges by [`crate::path`], and gradients come from [`crate::gradient`].
+
+use crate::compositing::{composite, fill_rect, BlendMode};
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::gradient::{Gradient, Interpolation};
+use crate::layout::{ParagraphOptions, TextAlign};
+use crate::palettes;
+use crate::path::{
+    fill_path, fill_path_with, stroke_path, CornerRadii, Fill, FillRule, Path, Stroke,
+};
+use crate::text::fit_text;
+use crate::{Result, Rgb};
+use image::{DynamicImage, Rgba};
 use imageproc::rect::Rect;
-use palette::encoding::pixel::Pixel;
-use palette::{FromColor, Gradient, Lch, LinSrgba, Srgba};
 // use crate::helpers;
 use wasm_bindgen::prelude::*;
 // use wasm_bindgen::Clamped;
@@ -21,6 +30,8 @@ use wasm_bindgen::prelude::*;
 /// * `height` - u32 - Desired height of rectangle.
 /// * `x_pos` - X-coordinate of top corner of rectangle on `img`
 /// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+///
+/// Nothing is drawn if the rectangle has no area.
 pub fn draw_solid_rect(
     img: &mut DynamicImage,
     background_color: &Rgb,
@@ -29,20 +40,22 @@ pub fn draw_solid_rect(
     x_pos: i32,
     y_pos: i32,
 ) {
-    draw_filled_rect_mut(
+    if width == 0 || height == 0 {
+        return;
+    }
+    fill_rect(
         img,
         Rect::at(x_pos, y_pos).of_size(width, height),
-        Rgba([
-            background_color.r,
-            background_color.g,
-            background_color.b,
-            255u8,
-        ]),
+        Rgba::from(background_color),
+        BlendMode::Normal,
+        1.0,
     );
 }
 
 /// Draw an opaque rectangle, where the opacity is set to a certain u8 value.
 ///
+/// The rectangle is blended with the pixels underneath it.
+///
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `background_color` - Rgb color of rectangle.
 /// * `opacity` - The opacity of the rectangle.
@@ -59,7 +72,7 @@ pub fn draw_opaque_rect(
     x_pos: i32,
     y_pos: i32,
 ) {
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos, y_pos).of_size(width, height),
         Rgba([
@@ -68,40 +81,39 @@ pub fn draw_opaque_rect(
             background_color.b,
             opacity,
         ]),
+        BlendMode::Normal,
+        1.0,
     );
 }
 
-/// Draw a triangle.
+/// Draw a triangle, with anti-aliased edges.
 ///
-/// /// * `img` - A mutable ref to a DynamicImage.
+/// * `img` - A mutable ref to a DynamicImage.
 /// * `triangle` - Triangle struct.
 pub fn draw_triangle(img: &mut DynamicImage, triangle: Triangle) {
-    let point = Point::new(triangle.x1, triangle.y1);
-    let point2 = Point::new(triangle.x2, triangle.y2);
-    let point3 = Point::new(triangle.x3, triangle.y3);
-
-    let points = vec![point, point2, point3];
+    let path = Path::new().polygon(&[
+        (triangle.x1 as f32, triangle.y1 as f32),
+        (triangle.x2 as f32, triangle.y2 as f32),
+        (triangle.x3 as f32, triangle.y3 as f32),
+    ]);
 
-    draw_polygon_mut(
+    fill_path(
         img,
-        points.as_slice(),
-        Rgba([
-            triangle.background_color.r,
-            triangle.background_color.g,
-            triangle.background_color.b,
-            255u8,
-        ]),
+        &path,
+        Rgba::from(triangle.background_color),
+        FillRule::NonZero,
     );
 }
 
-/// Draw an equilateral triangle.
+/// Draw an equilateral triangle, with anti-aliased edges.
 ///
 /// Not represented by a Triangle struct, because all sides are equal, and only one value should be entered.
+/// The triangle's top side is horizontal, and it points downwards.
 ///
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `side_len` - Side of the equilateral triangle, which will constitute all 3 sides.
-/// * `x_pos` - X-coordinate of top point of triangle on `img`
-/// * `y_pos` - y-coordinate of top point of triangle on `img`
+/// * `x_pos` - X-coordinate of top-left point of triangle on `img`
+/// * `y_pos` - y-coordinate of top-left point of triangle on `img`
 pub fn draw_equilateral_triangle(
     img: &mut DynamicImage,
     side_len: u32,
@@ -109,26 +121,18 @@ pub fn draw_equilateral_triangle(
     y_pos: i32,
     background_color: &Rgb,
 ) {
-    let point = Point::new(x_pos, y_pos);
-    let point2 = Point::new(x_pos + side_len as i32, y_pos);
-    let point3 = Point::new(x_pos + (side_len / 2) as i32, y_pos * 3);
-
-    let points = vec![point, point2, point3];
+    let (x, y, side) = (x_pos as f32, y_pos as f32, side_len as f32);
+    let height = side * 3f32.sqrt() / 2.0;
+    let path = Path::new().polygon(&[(x, y), (x + side, y), (x + side / 2.0, y + height)]);
 
-    draw_polygon_mut(
-        img,
-        points.as_slice(),
-        Rgba([
-            background_color.r,
-            background_color.g,
-            background_color.b,
-            255u8,
-        ]),
-    );
+    fill_path(img, &path, Rgba::from(background_color), FillRule::NonZero);
 }
 
 /// Draw a solid rectangle with text placed in-centre.
 ///
+/// The text is drawn at the largest size that fits inside the rectangle. Give the rectangle
+/// rounded corners to draw it as a button.
+///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
 /// * `text` - Text to be placed inside the rectangle.
@@ -137,37 +141,223 @@ pub fn draw_equilateral_triangle(
 /// * `height` - u32 - Desired height of rectangle.
 /// * `x_pos` - X-coordinate of top corner of rectangle on `img`
 /// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `radii` - Radius of the corners, or of each corner. 0 gives a sharp rectangle, and half the height a pill.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_rect_text(
     img: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     background_color: &Rgb,
     height: u32,
     width: u32,
     x_pos: i32,
     y_pos: i32,
-) {
-    draw_solid_rect(
-        img,
-        &background_color,
-        height as u32,
-        width as u32,
-        x_pos,
-        y_pos,
-    );
+    radii: impl Into<CornerRadii>,
+) -> Result<()> {
+    let radii = radii.into();
+    draw_rounded_rect(img, background_color, width, height, x_pos, y_pos, radii);
     let rgb_white = Rgb {
         r: 255,
         g: 255,
         b: 255,
     };
-    draw_text(
+    // Keep the text clear of the edges of the rectangle, and out of rounded corners.
+    let corner = radii
+        .top_left
+        .max(radii.bottom_left)
+        .max(radii.top_right)
+        .max(radii.bottom_right)
+        .min(width.min(height) as f32 / 2.0);
+    let padding = (width.min(height) as f32 * 0.1) as u32;
+    let side_padding = padding.max((corner * 0.5) as u32);
+    let rect = Rect::at(x_pos + side_padding as i32, y_pos + padding as i32).of_size(
+        width.saturating_sub(side_padding * 2).max(1),
+        height.saturating_sub(padding * 2).max(1),
+    );
+    fit_text(
         img,
+        fonts,
         text,
-        (x_pos as f32 + (width as f32 * 0.05)) as u32,
-        (y_pos + 10) as u32,
-        "Roboto-Bold",
-        30.0,
+        rect,
+        &FontDescriptor::new("Roboto").bold(),
+        10.0,
+        rect.height() as f32,
         &rgb_white,
+        &ParagraphOptions::new()
+            .line_height(1.0)
+            .align(TextAlign::Centre),
+    )?;
+    Ok(())
+}
+
+/// Draw a rectangle with rounded corners, with anti-aliased edges.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `background_color` - Rgb color of rectangle.
+/// * `width` - u32 - Desired width of rectangle.
+/// * `height` - u32 - Desired height of rectangle.
+/// * `x_pos` - X-coordinate of top corner of rectangle on `img`
+/// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+/// * `radii` - Radius of the corners, or a CornerRadii with a radius for each corner.
+pub fn draw_rounded_rect(
+    img: &mut DynamicImage,
+    background_color: &Rgb,
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+    radii: impl Into<CornerRadii>,
+) {
+    let path = rounded_rect_path(width, height, x_pos, y_pos, radii);
+    fill_path(img, &path, Rgba::from(background_color), FillRule::NonZero);
+}
+
+/// Draw the border of a rectangle with rounded corners.
+///
+/// The border is drawn inside the rectangle, so its outer edge lines up with a filled rectangle
+/// of the same size.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `border_color` - Rgb color of the border.
+/// * `border_width` - Width of the border in pixels.
+/// * `width` - u32 - Desired width of rectangle.
+/// * `height` - u32 - Desired height of rectangle.
+/// * `x_pos` - X-coordinate of top corner of rectangle on `img`
+/// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+/// * `radii` - Radius of the outer corners, or a CornerRadii with a radius for each corner.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rounded_rect_border(
+    img: &mut DynamicImage,
+    border_color: &Rgb,
+    border_width: f32,
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+    radii: impl Into<CornerRadii>,
+) {
+    let radii = radii.into();
+    // A border wider than half the rectangle fills it.
+    let border_width = border_width.min(width.min(height) as f32 / 2.0);
+    if border_width <= 0.0 {
+        return;
+    }
+    let inset = border_width / 2.0;
+    let inner = CornerRadii::new(
+        (radii.top_left - inset).max(0.0),
+        (radii.top_right - inset).max(0.0),
+        (radii.bottom_right - inset).max(0.0),
+        (radii.bottom_left - inset).max(0.0),
+    );
+    let path = Path::new().rounded_rect(
+        x_pos as f32 + inset,
+        y_pos as f32 + inset,
+        width as f32 - border_width,
+        height as f32 - border_width,
+        inner,
     );
+    stroke_path(
+        img,
+        &path,
+        Rgba::from(border_color),
+        &Stroke::new(border_width),
+    );
+}
+
+/// Draw a pill: a rectangle with fully rounded ends, as used for tags and buttons.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `background_color` - Rgb color of the pill.
+/// * `width` - u32 - Desired width of the pill.
+/// * `height` - u32 - Desired height of the pill.
+/// * `x_pos` - X-coordinate of top corner of the pill on `img`
+/// * `y_pos` - y-coordinate of top corner of the pill on `img`
+pub fn draw_pill(
+    img: &mut DynamicImage,
+    background_color: &Rgb,
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+) {
+    let radius = width.min(height) as f32 / 2.0;
+    draw_rounded_rect(img, background_color, width, height, x_pos, y_pos, radius);
+}
+
+/// Draw a rectangle with rounded corners, filled with a preset gradient.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `width` - u32 - Desired width of rectangle.
+/// * `height` - u32 - Desired height of rectangle.
+/// * `x_pos` - X-coordinate of top corner of rectangle on `img`
+/// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+/// * `radii` - Radius of the corners, or a CornerRadii with a radius for each corner.
+/// * `preset_name` - Name of a gradient in the shared [`palettes`](crate::palettes) registry.
+pub fn draw_rounded_gradient_rect(
+    img: &mut DynamicImage,
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+    radii: impl Into<CornerRadii>,
+    preset_name: &str,
+) -> Result<()> {
+    let gradient = palettes::gradient(preset_name)?;
+    let path = rounded_rect_path(width, height, x_pos, y_pos, radii);
+    draw_gradient_shape(img, &path, &gradient);
+    Ok(())
+}
+
+/// Draw an image clipped to a rectangle with rounded corners.
+///
+/// The image is scaled to cover the rectangle and centred in it, and anything outside the
+/// rounded corners is cut off.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `source` - The image to draw.
+/// * `width` - u32 - Desired width of rectangle.
+/// * `height` - u32 - Desired height of rectangle.
+/// * `x_pos` - X-coordinate of top corner of rectangle on `img`
+/// * `y_pos` - y-coordinate of top corner of rectangle on `img`
+/// * `radii` - Radius of the corners, or a CornerRadii with a radius for each corner.
+pub fn draw_rounded_image(
+    img: &mut DynamicImage,
+    source: &DynamicImage,
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+    radii: impl Into<CornerRadii>,
+) {
+    let path = rounded_rect_path(width, height, x_pos, y_pos, radii);
+    fill_path_with(
+        img,
+        &path,
+        &Fill::Image(source.to_rgba8()),
+        FillRule::NonZero,
+    );
+}
+
+fn rounded_rect_path(
+    width: u32,
+    height: u32,
+    x_pos: i32,
+    y_pos: i32,
+    radii: impl Into<CornerRadii>,
+) -> Path {
+    Path::new().rounded_rect(
+        x_pos as f32,
+        y_pos as f32,
+        width as f32,
+        height as f32,
+        radii,
+    )
 }
 
 /// Draw a solid rectangle with a given background colour.
@@ -199,7 +389,14 @@ pub fn draw_rect_text(
 pub fn draw_gradient_rect(img: &mut DynamicImage, height: u32, width: u32, x_pos: u32, y_pos: u32) {
     let rect = create_gradient(width, height);
 
-    image::imageops::overlay(img, &rect, x_pos, y_pos);
+    composite(
+        img,
+        &rect,
+        x_pos as i32,
+        y_pos as i32,
+        BlendMode::Normal,
+        1.0,
+    );
 }
 
 /// Preset: Draw a gradient rectangle filled with a gradient.
@@ -210,7 +407,8 @@ pub fn draw_gradient_rect(img: &mut DynamicImage, height: u32, width: u32, x_pos
 /// * `height` - u32 - Desired height of gradient rectangle.
 /// * `x_pos` - X-coordinate of top corner of rectangle on `img`
 /// * `y_pos` - y-coordinate of top corner of rectangle on `img`
-/// * `preset_name` - Name of the preset. Examples include "lemongrass", "pink_blue", "pastel_pink", "pastel_mauve"
+/// * `preset_name` - Name of a gradient in the shared [`palettes`](crate::palettes) registry.
+///   Examples include "lemongrass", "pinkblue", "pink_pastel" and "mauve_pastel".
 pub fn draw_preset_rect_gradient(
     img: &mut DynamicImage,
     width: u32,
@@ -218,10 +416,18 @@ pub fn draw_preset_rect_gradient(
     x_pos: u32,
     y_pos: u32,
     preset_name: &str,
-) {
-    let rect = create_gradient_preset(width, height, preset_name);
+) -> Result<()> {
+    let rect = create_gradient_preset(width, height, preset_name)?;
 
-    image::imageops::overlay(img, &rect, x_pos, y_pos);
+    composite(
+        img,
+        &rect,
+        x_pos as i32,
+        y_pos as i32,
+        BlendMode::Normal,
+        1.0,
+    );
+    Ok(())
 }
 
 /// Draw two rectangles stacked on each other, for added depth.
@@ -243,26 +449,20 @@ pub fn draw_stacked_rect(
     x_pos: i32,
     y_pos: i32,
 ) {
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos, y_pos).of_size(width, height),
-        Rgba([
-            background_color1.r,
-            background_color1.g,
-            background_color1.b,
-            255u8,
-        ]),
+        Rgba::from(background_color1),
+        BlendMode::Normal,
+        1.0,
     );
 
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos + 10, y_pos + 10).of_size(width, height),
-        Rgba([
-            background_color2.r,
-            background_color2.g,
-            background_color2.b,
-            255u8,
-        ]),
+        Rgba::from(background_color2),
+        BlendMode::Normal,
+        1.0,
     );
 }
 
@@ -284,16 +484,14 @@ pub fn draw_stacked_borders(
     mut y_pos: i32,
 ) {
     for _ in 0..3 {
-        draw_hollow_rect_mut(
-            img,
-            Rect::at(x_pos, y_pos).of_size(width, height),
-            Rgba([
-                background_color.r,
-                background_color.g,
-                background_color.b,
-                255u8,
-            ]),
+        // Stroke along the centres of the edge pixels, so the border stays crisp.
+        let path = Path::new().rect(
+            x_pos as f32 + 0.5,
+            y_pos as f32 + 0.5,
+            width as f32 - 1.0,
+            height as f32 - 1.0,
         );
+        stroke_path(img, &path, Rgba::from(background_color), &Stroke::new(1.0));
 
         x_pos -= 40;
         y_pos += 40;
@@ -319,15 +517,12 @@ pub fn draw_inline_border_rect(
     x_pos: i32,
     y_pos: i32,
 ) {
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos, y_pos).of_size(width, height),
-        Rgba([
-            background_color.r,
-            background_color.g,
-            background_color.b,
-            255u8,
-        ]),
+        Rgba::from(background_color),
+        BlendMode::Normal,
+        1.0,
     );
 
     // Draw border
@@ -336,15 +531,12 @@ pub fn draw_inline_border_rect(
     let horizontal_width = width - (2 * inset) as u32;
     let _vertical_height = height - (2 * inset) as u32;
 
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos + inset, y_pos + inset).of_size(horizontal_width, 10),
-        Rgba([
-            background_color2.r,
-            background_color2.g,
-            background_color2.b,
-            255u8,
-        ]),
+        Rgba::from(background_color2),
+        BlendMode::Normal,
+        1.0,
     );
 
     // draw_filled_rect_mut(img,
@@ -352,15 +544,12 @@ pub fn draw_inline_border_rect(
     //     Rgba([background_color2.r, background_color2.g,
     //     background_color2.b, 255u8]));
 
-    draw_filled_rect_mut(
+    fill_rect(
         img,
         Rect::at(x_pos + inset, 550).of_size(horizontal_width, 10),
-        Rgba([
-            background_color2.r,
-            background_color2.g,
-            background_color2.b,
-            255u8,
-        ]),
+        Rgba::from(background_color2),
+        BlendMode::Normal,
+        1.0,
     );
 
     // draw_filled_rect_mut(img,
@@ -379,35 +568,7 @@ pub fn draw_inline_border_rect(
 /// * `width` - u32 - Desired width of gradient.
 /// * `height` - u32 - Desired height of gradient.
 pub fn create_gradient(width: u32, height: u32) -> DynamicImage {
-    let mut image = RgbaImage::new(width, height);
-
-    // Create a gradient.
-    let grad1 = Gradient::new(vec![
-        LinSrgba::new(1.0, 0.1, 0.1, 1.0),
-        LinSrgba::new(0.1, 0.1, 1.0, 1.0),
-        LinSrgba::new(0.1, 1.0, 0.1, 1.0),
-    ]);
-
-    let _grad3 = Gradient::new(vec![
-        Lch::from_color(LinSrgba::new(1.0, 0.1, 0.1, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 0.1, 1.0, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 1.0, 0.1, 1.0)),
-    ]);
-
-    for (i, c1) in grad1.take(width as usize).enumerate() {
-        let c1 = Srgba::from_linear(c1).into_format().into_raw();
-        {
-            let mut sub_image = image.sub_image(i as u32, 0, 1, height);
-            let (width, height) = GenericImageView::dimensions(&sub_image);
-            for x in 0..width {
-                for y in 0..height {
-                    sub_image.put_pixel(x, y, image::Rgba(c1));
-                }
-            }
-        }
-    }
-    let rgba_img = image::DynamicImage::ImageRgba8(image);
-    return rgba_img;
+    DynamicImage::ImageRgba8(default_gradient().render(width, height))
 }
 
 /// Apply a preset gradient by passing in a name.
@@ -415,47 +576,37 @@ pub fn create_gradient(width: u32, height: u32) -> DynamicImage {
 /// ### Arguments
 /// * `width` - u32 - Desired width of rectangle.
 /// * `height` - u32 - Desired height of rectangle.
-/// * `name` - The preset to be used. Presets available include: pinkblue, lemongrass
-pub fn create_gradient_preset(width: u32, height: u32, name: &str) -> DynamicImage {
-    let mut image = RgbaImage::new(width, height);
-
-    let gradient = match name {
-        "pinkblue" => Gradient::new(vec![
-            LinSrgba::new(0.2039, 0.5803, 0.90196, 1.0),
-            LinSrgba::new(0.92549, 0.431372549, 0.6784313, 1.0),
-        ]),
-        "lemongrass" => Gradient::new(vec![
-            LinSrgba::new(0.2039, 0.5803, 0.90196, 1.0),
-            LinSrgba::new(0.40392156, 0.69803921, 0.43529, 1.0),
-        ]),
-        "pink_pastel" => Gradient::new(vec![
-            LinSrgba::new(0.93725, 0.19607, 0.85098, 1.0),
-            LinSrgba::new(0.537254, 1.0, 0.9921568, 1.0),
-        ]),
-        "mauve_pastel" => Gradient::new(vec![
-            LinSrgba::new(0.498039, 0.498039, 0.835294, 1.0),
-            LinSrgba::new(0.537254, 0.6588235, 0.905882, 1.0),
-        ]),
-        _ => Gradient::new(vec![
-            LinSrgba::new(0.2039, 0.5803, 0.90196, 1.0),
-            LinSrgba::new(0.52549, 0.69803921, 0.43529, 1.0),
-        ]),
-    };
+/// * `name` - Name of a gradient in the shared [`palettes`](crate::palettes) registry, such as
+///   "pinkblue" or "lemongrass". Returns an error if there is no gradient with that name.
+pub fn create_gradient_preset(width: u32, height: u32, name: &str) -> Result<DynamicImage> {
+    let gradient = palettes::gradient(name)?;
+    Ok(DynamicImage::ImageRgba8(gradient.render(width, height)))
+}
 
-    for (i, c1) in gradient.take(width as usize).enumerate() {
-        let c1 = Srgba::from_linear(c1).into_format().into_raw();
-        {
-            let mut sub_image = image.sub_image(i as u32, 0, 1, height);
-            let (width, height) = GenericImageView::dimensions(&sub_image);
-            for x in 0..width {
-                for y in 0..height {
-                    sub_image.put_pixel(x, y, image::Rgba(c1));
-                }
-            }
-        }
-    }
-    let rgba_img = image::DynamicImage::ImageRgba8(image);
-    return rgba_img;
+/// Draw any path filled with a gradient.
+///
+/// ### Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `path` - The shape to fill.
+/// * `gradient` - The gradient, drawn across the bounding box of the shape.
+pub fn draw_gradient_shape(img: &mut DynamicImage, path: &Path, gradient: &Gradient) {
+    fill_path_with(
+        img,
+        path,
+        &Fill::Gradient(gradient.clone()),
+        FillRule::NonZero,
+    );
+}
+
+// The red, blue and green gradient drawn by `create_gradient`.
+fn default_gradient() -> Gradient {
+    Gradient::linear(0.0)
+        .colors(&[
+            Rgba([255, 89, 89, 255]),
+            Rgba([89, 89, 255, 255]),
+            Rgba([89, 255, 89, 255]),
+        ])
+        .interpolation(Interpolation::Linear)
 }
 
 // #[wasm_bindgen]
@@ -467,15 +618,6 @@ pub fn create_gradient_preset(width: u32, height: u32, name: &str) -> DynamicIma
 //                         background_color.b, 255u8]));
 // }
 
-// GRADIENT COLORS
-// #67b26f → #4ca2cd: 103, 178, 111 -> 76 162 205
-// #ee0979 →  #ff6a00: 238, 9, 121 -> 255, 106, 0
-
-// Telegram:  #1c92d2 → #f2fcfe
-// Digital Water:  #74ebd5 → #acb6e5
-// Hydrogen:  #667db6 →  #0082c8 →  #0082c8 →  #667db6
-// Blue Coral:  #36d1dc →  #5b86e5
-
 /// Triangle struct, which represents the color and co-ordinates
 /// of a Triangle.
 #[wasm_bindgen]
@@ -538,3 +680,84 @@ impl Triangle {
         return self.y2;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    const WHITE: Rgb = Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    fn painted(image: &DynamicImage) -> usize {
+        image.pixels().filter(|(_, _, pixel)| pixel[3] > 0).count()
+    }
+
+    #[test]
+    fn oversized_radii_give_a_pill() {
+        let mut huge = DynamicImage::new_rgba8(100, 40);
+        draw_rounded_rect(&mut huge, &WHITE, 100, 40, 0, 0, 1000.0);
+        let mut pill = DynamicImage::new_rgba8(100, 40);
+        draw_pill(&mut pill, &WHITE, 100, 40, 0, 0);
+        assert_eq!(huge.to_rgba8(), pill.to_rgba8());
+        // The corners are cut off, and the middle of each side is filled.
+        assert_eq!(pill.get_pixel(0, 0)[3], 0);
+        assert_eq!(pill.get_pixel(50, 0)[3], 255);
+        assert_eq!(pill.get_pixel(2, 20)[3], 255);
+    }
+
+    #[test]
+    fn negative_radii_give_square_corners() {
+        let mut image = DynamicImage::new_rgba8(20, 20);
+        draw_rounded_rect(
+            &mut image,
+            &WHITE,
+            20,
+            20,
+            0,
+            0,
+            CornerRadii::new(-5.0, 0.0, 0.0, 0.0),
+        );
+        assert_eq!(painted(&image), 400);
+        assert!(CornerRadii::all(-1.0).is_zero());
+    }
+
+    #[test]
+    fn empty_rects_draw_nothing() {
+        let mut image = DynamicImage::new_rgba8(20, 20);
+        draw_rounded_rect(&mut image, &WHITE, 0, 0, 5, 5, 4.0);
+        draw_rounded_rect(&mut image, &WHITE, 10, 0, 5, 5, 4.0);
+        draw_pill(&mut image, &WHITE, 0, 10, 5, 5);
+        draw_rounded_rect_border(&mut image, &WHITE, 2.0, 0, 0, 5, 5, 4.0);
+        draw_rounded_image(&mut image, &DynamicImage::new_rgba8(4, 4), 0, 0, 5, 5, 2.0);
+        assert_eq!(painted(&image), 0);
+    }
+
+    #[test]
+    fn wide_borders_stay_inside_the_rect() {
+        let mut image = DynamicImage::new_rgba8(20, 20);
+        draw_rounded_rect_border(&mut image, &WHITE, 30.0, 10, 10, 5, 5, 0.0);
+        assert_eq!(painted(&image), 100);
+        assert!(image
+            .pixels()
+            .filter(|(_, _, pixel)| pixel[3] > 0)
+            .all(|(x, y, _)| (5..15).contains(&x) && (5..15).contains(&y)));
+    }
+
+    #[test]
+    fn rounded_images_handle_empty_sources() {
+        let mut image = DynamicImage::new_rgba8(20, 20);
+        draw_rounded_image(
+            &mut image,
+            &DynamicImage::new_rgba8(0, 0),
+            10,
+            10,
+            0,
+            0,
+            3.0,
+        );
+    }
+}
@@ -0,0 +1,772 @@
+//! Draw emoji and other colour glyphs in text.
+//!
+//! Glyphs are drawn from their outlines in a single colour, so emoji would otherwise come out
+//! as empty boxes. An [`EmojiSet`] holds colour images for emoji, keyed by the sequence of
+//! characters each emoji is written with. Images can be added one at a time, loaded from a
+//! directory named after their code points, as the Twemoji and Noto emoji sets are, or taken
+//! from a colour font with `COLR`/`CPAL` layers or `CBDT`/`sbix` bitmaps.
+//!
+//! Once a set is given to a registry with [`FontRegistry::set_emoji`], each emoji in text drawn
+//! with any of the registry's fonts is drawn from the set, scaled to the size of the text:
+//!
+#![cfg_attr(feature = "embedded-fonts", doc = "```")]
+#![cfg_attr(not(feature = "embedded-fonts"), doc = "```ignore")]
+//! use gdl::emoji::EmojiSet;
+//! use gdl::fonts::{FontDescriptor, FontRegistry};
+//! use gdl::text::draw_text;
+//! use gdl::Rgb;
+//! use image::{Rgba, RgbaImage};
+//!
+//! let mut emoji = EmojiSet::new();
+//! emoji.insert("🚀", RgbaImage::from_pixel(72, 72, Rgba([255, 87, 34, 255])));
+//! let mut fonts = FontRegistry::embedded();
+//! fonts.set_emoji(emoji);
+//!
+//! let mut image = gdl::new_with_background(600, 120, &Rgb { r: 255, g: 255, b: 255 });
+//! let black = Rgb { r: 0, g: 0, b: 0 };
+//! draw_text(&mut image, &fonts, "Launch day 🚀", 10, 30, &FontDescriptor::new("Roboto"), 60.0, &black)?;
+//! # Ok::<(), gdl::Error>(())
+//! ```
+//!
+//! [`FontRegistry::set_emoji`]: crate::fonts::FontRegistry::set_emoji
+
+use crate::fonts::FontFace;
+use crate::helpers::blend_over;
+use crate::shaping::{ShapedGlyph, ShapedText};
+use crate::{Error, Result};
+use image::imageops::{resize, FilterType};
+use image::{GenericImage, GrayImage, ImageFormat, Rgba, RgbaImage};
+use rusttype::{point, Font, GlyphId, Scale};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::path::Path;
+use std::str::CharIndices;
+use std::sync::{Arc, Mutex, PoisonError};
+
+const TEXT_SELECTOR: char = '\u{FE0E}';
+const EMOJI_SELECTOR: char = '\u{FE0F}';
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+const COMBINING_KEYCAP: char = '\u{20E3}';
+
+// Emoji are drawn this many times the font size high, centred on the line, with the rest of
+// the font size left as space either side of them.
+const EMOJI_SIZE: f32 = 0.9;
+// Height, in pixels, glyphs made of coloured layers are rendered at before they are scaled.
+const LAYERED_GLYPH_SIZE: f32 = 128.0;
+
+/// A set of colour images drawn for emoji.
+///
+/// Images added to the set are used in preference to those from its colour font, so a few
+/// emoji from a font can be replaced with custom artwork.
+#[derive(Default)]
+pub struct EmojiSet {
+    // Images, keyed by their emoji without any emoji selectors.
+    images: HashMap<String, Arc<RgbaImage>>,
+    font: Option<ColorFont>,
+    // Emoji already rendered from the font, or `None` for those it doesn't have.
+    rendered: Mutex<HashMap<String, Option<Arc<RgbaImage>>>>,
+}
+
+impl EmojiSet {
+    /// Create an empty set.
+    pub fn new() -> EmojiSet {
+        EmojiSet::default()
+    }
+
+    /// Create a set containing every image in a directory.
+    ///
+    /// See [`EmojiSet::load_dir`].
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<EmojiSet> {
+        let mut emoji = EmojiSet::new();
+        emoji.load_dir(dir)?;
+        Ok(emoji)
+    }
+
+    /// Create a set drawing emoji from a colour font file.
+    ///
+    /// See [`EmojiSet::load_font_bytes`].
+    pub fn from_font_file<P: AsRef<Path>>(path: P) -> Result<EmojiSet> {
+        let mut emoji = EmojiSet::new();
+        emoji.load_font_file(path)?;
+        Ok(emoji)
+    }
+
+    /// Add the image drawn for an emoji, replacing any it already has.
+    ///
+    /// ### Arguments
+    /// * `sequence` - The emoji, such as "🚀", or "👩‍💻" for a sequence joined with zero width
+    ///   joiners.
+    /// * `image` - Image drawn for the emoji. It is scaled to the size of the text.
+    pub fn insert(&mut self, sequence: &str, image: RgbaImage) {
+        self.images.insert(image_key(sequence), Arc::new(image));
+    }
+
+    /// Add every image in a directory, each named after the code points of its emoji in hex.
+    ///
+    /// Code points can be separated with `-` or `_`, and an `emoji_u` prefix is ignored, so the
+    /// image for 👩‍💻 can be named `1f469-200d-1f4bb.png`, as in Twemoji, or
+    /// `emoji_u1f469_200d_1f4bb.png`, as in Noto Emoji. Other files are skipped.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let sequence = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(parse_code_points);
+            if let (Some(sequence), Ok(_)) = (sequence, ImageFormat::from_path(&path)) {
+                let image = image::open(&path)?.to_rgba8();
+                self.insert(&sequence, image);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw emoji from a colour font, given its raw bytes.
+    ///
+    /// Fonts whose glyphs are made of coloured layers (`COLR` version 0 and `CPAL` tables),
+    /// such as Twemoji Mozilla, and fonts with bitmap glyphs (`CBDT` or `sbix` tables), such
+    /// as Noto Color Emoji, are supported. The first face of a collection is used.
+    ///
+    /// Returns an error if the font can't be parsed, or has no colour glyphs.
+    pub fn load_font_bytes(&mut self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        let font = ColorFont::new(name, bytes)?;
+        self.font = Some(font);
+        self.rendered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        Ok(())
+    }
+
+    /// Draw emoji from a colour font file.
+    ///
+    /// See [`EmojiSet::load_font_bytes`].
+    pub fn load_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let bytes = fs::read(path).map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::FontNotFound(name.clone()),
+            _ => Error::Io(err),
+        })?;
+        self.load_font_bytes(&name, bytes)
+    }
+
+    /// Get the image drawn for an emoji, if the set has one.
+    ///
+    /// Emoji selectors (U+FE0F) are ignored when matching images, as image sets often leave
+    /// them out of their names.
+    pub fn get(&self, sequence: &str) -> Option<Arc<RgbaImage>> {
+        if let Some(image) = self.images.get(&image_key(sequence)) {
+            return Some(Arc::clone(image));
+        }
+        let font = self.font.as_ref()?;
+        self.rendered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(sequence.to_string())
+            .or_insert_with(|| font.render(sequence).map(Arc::new))
+            .clone()
+    }
+
+    /// The number of images added to the set, not counting those in its font.
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Whether the set has no images and no font.
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty() && self.font.is_none()
+    }
+}
+
+// A font with colour glyphs.
+struct ColorFont {
+    data: Vec<u8>,
+    font: Font<'static>,
+    // The coloured layers of each glyph made of layers, from the bottom up.
+    layers: HashMap<u16, Vec<(u16, Rgba<u8>)>>,
+}
+
+impl ColorFont {
+    fn new(name: &str, data: Vec<u8>) -> Result<ColorFont> {
+        let parse_error = || Error::FontParse(name.to_string());
+        let face = ttf_parser::Face::from_slice(&data, 0).map_err(|_| parse_error())?;
+        let layers = read_layers(&face);
+        let tables = face.tables();
+        if layers.is_empty() && tables.cbdt.is_none() && tables.sbix.is_none() {
+            return Err(Error::NoColorGlyphs(name.to_string()));
+        }
+        let font = Font::try_from_vec(data.clone()).ok_or_else(parse_error)?;
+        Ok(ColorFont { data, font, layers })
+    }
+
+    // Render an emoji, if the font has a single glyph for it.
+    fn render(&self, sequence: &str) -> Option<RgbaImage> {
+        let glyph = self.glyph(sequence)?;
+        if let Some(layers) = self.layers.get(&glyph) {
+            return Some(self.render_layers(glyph, layers));
+        }
+        let face = ttf_parser::Face::from_slice(&self.data, 0).ok()?;
+        let raster = face.glyph_raster_image(ttf_parser::GlyphId(glyph), u16::MAX)?;
+        image::load_from_memory_with_format(raster.data, ImageFormat::Png)
+            .ok()
+            .map(|image| image.to_rgba8())
+    }
+
+    // The glyph drawn for an emoji. Sequences of several characters are found by shaping them,
+    // which substitutes the font's ligature for the sequence.
+    fn glyph(&self, sequence: &str) -> Option<u16> {
+        let shaper = rustybuzz::Face::from_slice(&self.data, 0)?;
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(sequence);
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(&shaper, &[], buffer);
+        // Selectors and joiners left over from shaping take up no space.
+        let glyphs: Vec<u16> = output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .filter(|(_, position)| position.x_advance != 0)
+            .map(|(info, _)| info.glyph_id as u16)
+            .collect();
+        match glyphs[..] {
+            [glyph] if glyph != 0 => Some(glyph),
+            _ => None,
+        }
+    }
+
+    fn render_layers(&self, glyph: u16, layers: &[(u16, Rgba<u8>)]) -> RgbaImage {
+        let scale = Scale::uniform(LAYERED_GLYPH_SIZE);
+        let v_metrics = self.font.v_metrics(scale);
+        let advance = self
+            .font
+            .glyph(GlyphId(glyph))
+            .scaled(scale)
+            .h_metrics()
+            .advance_width;
+        let mut image = RgbaImage::new(
+            advance.ceil().max(1.0) as u32,
+            (v_metrics.ascent - v_metrics.descent).ceil() as u32,
+        );
+        let (width, height) = image.dimensions();
+        for &(layer, color) in layers {
+            let positioned = self
+                .font
+                .glyph(GlyphId(layer))
+                .scaled(scale)
+                .positioned(point(0.0, v_metrics.ascent));
+            if let Some(bb) = positioned.pixel_bounding_box() {
+                positioned.draw(|gx, gy, coverage| {
+                    let image_x = gx as i32 + bb.min.x;
+                    let image_y = gy as i32 + bb.min.y;
+                    if image_x >= 0
+                        && image_x < width as i32
+                        && image_y >= 0
+                        && image_y < height as i32
+                    {
+                        let pixel = image.get_pixel_mut(image_x as u32, image_y as u32);
+                        let alpha = coverage * color[3] as f32 / 255.0;
+                        *pixel = blend_over(*pixel, color, alpha);
+                    }
+                });
+            }
+        }
+        image
+    }
+}
+
+// Read the layers of each coloured glyph from the `COLR` table, with their colours from the
+// first palette in the `CPAL` table.
+fn read_layers(face: &ttf_parser::Face) -> HashMap<u16, Vec<(u16, Rgba<u8>)>> {
+    let mut layers = HashMap::new();
+    let (colr, cpal) = match (
+        face.table_data(ttf_parser::Tag::from_bytes(b"COLR")),
+        face.table_data(ttf_parser::Tag::from_bytes(b"CPAL")),
+    ) {
+        (Some(colr), Some(cpal)) => (colr, cpal),
+        _ => return layers,
+    };
+
+    let palette = |entry: u16| -> Option<Rgba<u8>> {
+        let entries = read_u16(cpal, 2)?;
+        let records = read_u32(cpal, 8)? as usize;
+        let first = read_u16(cpal, 12)?;
+        if entry >= entries {
+            return None;
+        }
+        let offset = records + (first as usize + entry as usize) * 4;
+        let bgra = cpal.get(offset..offset + 4)?;
+        Some(Rgba([bgra[2], bgra[1], bgra[0], bgra[3]]))
+    };
+
+    let (base_count, base_offset, layer_offset) =
+        match (read_u16(colr, 2), read_u32(colr, 4), read_u32(colr, 8)) {
+            (Some(count), Some(base), Some(layer)) => (count, base as usize, layer as usize),
+            _ => return layers,
+        };
+    for base in 0..base_count as usize {
+        let record = base_offset + base * 6;
+        let (glyph, first, count) = match (
+            read_u16(colr, record),
+            read_u16(colr, record + 2),
+            read_u16(colr, record + 4),
+        ) {
+            (Some(glyph), Some(first), Some(count)) => (glyph, first as usize, count as usize),
+            _ => break,
+        };
+        let glyph_layers: Vec<(u16, Rgba<u8>)> = (first..first + count)
+            .filter_map(|layer| {
+                let record = layer_offset + layer * 4;
+                let layer_glyph = read_u16(colr, record)?;
+                // Palette index 0xFFFF is the text colour, which emoji are drawn without.
+                let color = palette(read_u16(colr, record + 2)?).unwrap_or(Rgba([0, 0, 0, 255]));
+                Some((layer_glyph, color))
+            })
+            .collect();
+        layers.insert(glyph, glyph_layers);
+    }
+    layers
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Replace the glyphs of each emoji the face's emoji set has with its image.
+pub(crate) fn substitute_emoji(face: &FontFace, text: &str, shaped: &mut ShapedText) {
+    let emoji = match face.emoji() {
+        Some(emoji) => emoji,
+        None => return,
+    };
+    let found = find_emoji(face, emoji, text);
+    if found.is_empty() {
+        return;
+    }
+
+    let font_size = shaped.scale.y;
+    let mut glyphs = Vec::with_capacity(shaped.glyphs.len());
+    let mut shift = 0.0;
+    let mut current = None;
+    for glyph in shaped.glyphs.drain(..) {
+        let index = found
+            .iter()
+            .position(|(range, _)| range.contains(&glyph.cluster));
+        match index {
+            // Drop the rest of the glyphs the emoji was shaped into.
+            Some(index) if current == Some(index) => shift -= glyph.advance,
+            Some(index) => {
+                let (range, image) = &found[index];
+                let advance = font_size * (EMOJI_SIZE * aspect_ratio(image) + 1.0 - EMOJI_SIZE);
+                shaped.images.push(Arc::clone(image));
+                glyphs.push(ShapedGlyph {
+                    id: 0,
+                    cluster: range.start,
+                    x: glyph.x + shift,
+                    y: 0.0,
+                    advance,
+                    image: Some(shaped.images.len() - 1),
+                });
+                shift += advance - glyph.advance;
+            }
+            None => glyphs.push(ShapedGlyph {
+                x: glyph.x + shift,
+                ..glyph
+            }),
+        }
+        current = index;
+    }
+    shaped.glyphs = glyphs;
+    shaped.width += shift;
+}
+
+// The emoji in a line of text that are drawn from an emoji set, with their images.
+fn find_emoji(
+    face: &FontFace,
+    emoji: &EmojiSet,
+    text: &str,
+) -> Vec<(Range<usize>, Arc<RgbaImage>)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(c) = text[offset..].chars().next() {
+        let sequence = emoji_len(&text[offset..]).map(|len| &text[offset..offset + len]);
+        if let Some(sequence) = sequence.filter(|sequence| prefers_emoji(face, sequence)) {
+            // If the set doesn't have a sequence, use the first emoji in it, leaving the rest to
+            // be found on their own, and then that emoji without its skin tone or selectors.
+            let first = sequence.split(ZERO_WIDTH_JOINER).next().unwrap_or(sequence);
+            let mut candidates = vec![(sequence, sequence.len()), (first, first.len())];
+            if is_pictographic(c) && !is_regional_indicator(c) {
+                candidates.push((&first[..c.len_utf8()], first.len()));
+            }
+            let image = candidates
+                .into_iter()
+                .find_map(|(candidate, len)| emoji.get(candidate).map(|image| (len, image)));
+            if let Some((len, image)) = image {
+                found.push((offset..offset + len, image));
+                offset += len;
+                continue;
+            }
+        }
+        offset += c.len_utf8();
+    }
+    found
+}
+
+// Whether an emoji is drawn from the emoji set rather than the text's font. Sequences, emoji
+// outside the Basic Multilingual Plane and emoji with an emoji selector always are, but older
+// symbols, which are usually drawn as text, are only when the font has no glyph for them.
+fn prefers_emoji(face: &FontFace, sequence: &str) -> bool {
+    let mut chars = sequence.chars();
+    match (chars.next(), chars.next()) {
+        (Some(first), None) => first as u32 >= 0x1F000 || face.font().glyph(first).id().0 == 0,
+        _ => true,
+    }
+}
+
+// Byte length of the emoji at the start of some text, if it starts with one.
+fn emoji_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next()?;
+    let end = |chars: &mut Peekable<CharIndices>| chars.peek().map_or(text.len(), |&(i, _)| i);
+
+    // Keycaps: a digit, `#` or `*`, then the combining keycap.
+    if first.is_ascii_digit() || first == '#' || first == '*' {
+        chars.next_if(|&(_, c)| c == EMOJI_SELECTOR);
+        chars.next_if(|&(_, c)| c == COMBINING_KEYCAP)?;
+        return Some(end(&mut chars));
+    }
+    // Flags: a pair of regional indicators.
+    if is_regional_indicator(first) {
+        chars.next_if(|&(_, c)| is_regional_indicator(c))?;
+        return Some(end(&mut chars));
+    }
+    if !is_pictographic(first) {
+        return None;
+    }
+
+    loop {
+        // An emoji asked to be drawn as text is left to the font.
+        if chars.next_if(|&(_, c)| c == TEXT_SELECTOR).is_some() {
+            return None;
+        }
+        // Selectors, skin tones and the tags of subdivision flags modify the emoji before them.
+        while chars
+            .next_if(|&(_, c)| c == EMOJI_SELECTOR || is_skin_tone(c) || is_tag(c))
+            .is_some()
+        {}
+        // Further emoji can be joined on with zero width joiners.
+        let mut joined = chars.clone();
+        match (joined.next(), joined.next()) {
+            (Some((_, ZERO_WIDTH_JOINER)), Some((_, c))) if is_pictographic(c) => chars = joined,
+            _ => return Some(end(&mut chars)),
+        }
+    }
+}
+
+// Characters that are emoji, or are drawn as emoji when followed by an emoji selector.
+fn is_pictographic(c: char) -> bool {
+    matches!(c as u32,
+        0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x2194..=0x21AA
+        | 0x231A..=0x23FF | 0x24C2 | 0x25AA..=0x25FE | 0x2600..=0x27BF | 0x2934 | 0x2935
+        | 0x2B05..=0x2B55 | 0x3030 | 0x303D | 0x3297 | 0x3299 | 0x1F000..=0x1FAFF)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+fn is_skin_tone(c: char) -> bool {
+    matches!(c as u32, 0x1F3FB..=0x1F3FF)
+}
+
+fn is_tag(c: char) -> bool {
+    matches!(c as u32, 0xE0020..=0xE007F)
+}
+
+// Parse a file name such as `1f469-200d-1f4bb` or `emoji_u1f469_200d_1f4bb` into its emoji.
+fn parse_code_points(name: &str) -> Option<String> {
+    let name = name.strip_prefix("emoji_u").unwrap_or(name);
+    name.split(['-', '_'])
+        .map(|hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+        .collect()
+}
+
+// Images are found without their emoji selectors, which image sets often leave out.
+fn image_key(sequence: &str) -> String {
+    sequence.chars().filter(|&c| c != EMOJI_SELECTOR).collect()
+}
+
+fn aspect_ratio(image: &RgbaImage) -> f32 {
+    image.width() as f32 / image.height().max(1) as f32
+}
+
+// The box an emoji glyph is drawn in, as its left, top, width and height relative to the start
+// of the text and the top of the line.
+pub(crate) fn emoji_box(shaped: &ShapedText, glyph: &ShapedGlyph) -> Option<(f32, f32, f32, f32)> {
+    let image = shaped.images.get(glyph.image?)?;
+    let height = shaped.scale.y * EMOJI_SIZE;
+    let margin = (shaped.scale.y - height) / 2.0;
+    Some((
+        glyph.x + margin,
+        glyph.y + margin,
+        height * aspect_ratio(image),
+        height,
+    ))
+}
+
+/// Draw the emoji in shaped text.
+///
+/// `transform` maps points relative to the top-left of the text onto the image, as
+/// `[a, b, c, d, e, f]` for `x' = a·x + b·y + e` and `y' = c·x + d·y + f`, so text can be
+/// drawn rotated. Emoji are drawn in their own colours, at the given opacity.
+pub(crate) fn draw_shaped_emoji<I: GenericImage<Pixel = Rgba<u8>>>(
+    image: &mut I,
+    shaped: &ShapedText,
+    transform: [f32; 6],
+    opacity: f32,
+) {
+    for glyph in &shaped.glyphs {
+        draw_emoji_glyph(image, shaped, glyph, transform, opacity);
+    }
+}
+
+// Draw a single glyph of shaped text, if it is an emoji. See `draw_shaped_emoji`.
+pub(crate) fn draw_emoji_glyph<I: GenericImage<Pixel = Rgba<u8>>>(
+    image: &mut I,
+    shaped: &ShapedText,
+    glyph: &ShapedGlyph,
+    transform: [f32; 6],
+    opacity: f32,
+) {
+    if let (Some(index), Some(bounds)) = (glyph.image, emoji_box(shaped, glyph)) {
+        draw_emoji(image, &shaped.images[index], bounds, transform, opacity);
+    }
+}
+
+fn draw_emoji<I: GenericImage<Pixel = Rgba<u8>>>(
+    image: &mut I,
+    emoji: &RgbaImage,
+    (left, top, width, height): (f32, f32, f32, f32),
+    t: [f32; 6],
+    opacity: f32,
+) {
+    let determinant = t[0] * t[3] - t[1] * t[2];
+    if determinant == 0.0 || width <= 0.0 || height <= 0.0 {
+        return;
+    }
+    // Scale the emoji to the size it is drawn at first, so it is sampled smoothly.
+    let scale = determinant.abs().sqrt();
+    let scaled = resize(
+        emoji,
+        (width * scale).ceil().max(1.0) as u32,
+        (height * scale).ceil().max(1.0) as u32,
+        FilterType::Triangle,
+    );
+    let scale_x = scaled.width() as f32 / width;
+    let scale_y = scaled.height() as f32 / height;
+
+    // Only visit the pixels the emoji's corners enclose.
+    let map = |x: f32, y: f32| (t[0] * x + t[1] * y + t[4], t[2] * x + t[3] * y + t[5]);
+    let corners = [
+        map(left, top),
+        map(left + width, top),
+        map(left, top + height),
+        map(left + width, top + height),
+    ];
+    let (image_width, image_height) = image.dimensions();
+    let min_x = corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f32::MAX, f32::min)
+        .floor()
+        .max(0.0) as u32;
+    let min_y = corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f32::MAX, f32::min)
+        .floor()
+        .max(0.0) as u32;
+    let max_x = (corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f32::MIN, f32::max)
+        .ceil()
+        .max(0.0) as u32)
+        .min(image_width);
+    let max_y = (corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f32::MIN, f32::max)
+        .ceil()
+        .max(0.0) as u32)
+        .min(image_height);
+
+    for image_y in min_y..max_y {
+        for image_x in min_x..max_x {
+            // Map the centre of the pixel back onto the emoji.
+            let dx = image_x as f32 + 0.5 - t[4];
+            let dy = image_y as f32 + 0.5 - t[5];
+            let x = (t[3] * dx - t[1] * dy) / determinant - left;
+            let y = (t[0] * dy - t[2] * dx) / determinant - top;
+            let color = sample(&scaled, x * scale_x - 0.5, y * scale_y - 0.5);
+            let alpha = color[3] as f32 / 255.0 * opacity;
+            if alpha > 0.0 {
+                let pixel = image.get_pixel(image_x, image_y);
+                image.put_pixel(image_x, image_y, blend_over(pixel, color, alpha));
+            }
+        }
+    }
+}
+
+// Sample an image between pixels, treating everything outside it as transparent.
+pub(crate) fn sample(image: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let mut sum = [0.0; 4];
+    for &(dx, dy, weight) in &[
+        (0, 0, (1.0 - fx) * (1.0 - fy)),
+        (1, 0, fx * (1.0 - fy)),
+        (0, 1, (1.0 - fx) * fy),
+        (1, 1, fx * fy),
+    ] {
+        let (sx, sy) = (x0 as i32 + dx, y0 as i32 + dy);
+        if sx < 0 || sy < 0 || sx >= image.width() as i32 || sy >= image.height() as i32 {
+            continue;
+        }
+        let pixel = image.get_pixel(sx as u32, sy as u32);
+        // Weight colours by their alpha, so transparent pixels don't darken the edges.
+        let alpha = pixel[3] as f32 * weight;
+        for (channel, total) in sum.iter_mut().take(3).enumerate() {
+            *total += pixel[channel] as f32 * alpha;
+        }
+        sum[3] += alpha;
+    }
+    if sum[3] <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    Rgba([
+        (sum[0] / sum[3]).round() as u8,
+        (sum[1] / sum[3]).round() as u8,
+        (sum[2] / sum[3]).round() as u8,
+        sum[3].round().min(255.0) as u8,
+    ])
+}
+
+// Emoji in styled text, drawn apart from the glyphs so the style's effects can be drawn around
+// them while their own colours replace its fill.
+pub(crate) struct EmojiLayer {
+    image: RgbaImage,
+}
+
+impl EmojiLayer {
+    pub(crate) fn new(width: u32, height: u32) -> EmojiLayer {
+        EmojiLayer {
+            image: RgbaImage::new(width, height),
+        }
+    }
+
+    // Draw the emoji in shaped text, with the top-left of the text at the given point.
+    pub(crate) fn draw(&mut self, shaped: &ShapedText, x: f32, y: f32) {
+        draw_shaped_emoji(&mut self.image, shaped, [1.0, 0.0, 0.0, 1.0, x, y], 1.0);
+    }
+
+    // Draw a single glyph of shaped text, if it is an emoji, mapped by a transform from the
+    // top-left of the text.
+    pub(crate) fn draw_glyph(
+        &mut self,
+        shaped: &ShapedText,
+        glyph: &ShapedGlyph,
+        transform: [f32; 6],
+    ) {
+        draw_emoji_glyph(&mut self.image, shaped, glyph, transform, 1.0);
+    }
+
+    // Add the emoji's coverage to the text's mask.
+    pub(crate) fn add_to_mask(&self, mask: &mut GrayImage) {
+        for (pixel, emoji) in mask.pixels_mut().zip(self.image.pixels()) {
+            pixel[0] = pixel[0].max(emoji[3]);
+        }
+    }
+
+    // The colour of the emoji at a pixel, if one covers it.
+    pub(crate) fn color_at(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let pixel = self.image.get_pixel(x, y);
+        if pixel[3] == 0 {
+            return None;
+        }
+        Some(Rgba([pixel[0], pixel[1], pixel[2], 255]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_sets_have_no_emoji() {
+        let emoji = EmojiSet::new();
+        assert!(emoji.is_empty());
+        assert_eq!(emoji.len(), 0);
+        assert!(emoji.get("🚀").is_none());
+        assert!(emoji.get("").is_none());
+    }
+
+    #[test]
+    fn images_are_found_without_emoji_selectors() {
+        let mut emoji = EmojiSet::new();
+        emoji.insert("❤\u{FE0F}", RgbaImage::new(2, 2));
+        assert!(emoji.get("❤").is_some());
+        assert!(emoji.get("❤\u{FE0F}").is_some());
+        assert!(!emoji.is_empty());
+    }
+
+    #[test]
+    fn invalid_fonts_are_rejected() {
+        let mut emoji = EmojiSet::new();
+        assert!(emoji.load_font_bytes("empty", Vec::new()).is_err());
+        assert!(emoji.load_font_bytes("junk", vec![0; 64]).is_err());
+        assert!(emoji.is_empty());
+        assert!(EmojiSet::from_font_file("no/such/font.ttf").is_err());
+    }
+
+    #[test]
+    fn emoji_sequences_are_measured_whole() {
+        assert_eq!(emoji_len(""), None);
+        assert_eq!(emoji_len("abc"), None);
+        assert_eq!(emoji_len("1"), None);
+        assert_eq!(emoji_len("1\u{FE0F}\u{20E3}x"), Some(7));
+        assert_eq!(emoji_len("🇸🇪!"), Some(8));
+        assert_eq!(emoji_len("🇸"), None);
+        assert_eq!(emoji_len("👩‍💻 "), Some("👩‍💻".len()));
+        assert_eq!(emoji_len("👍🏽"), Some(8));
+        assert_eq!(emoji_len("☺\u{FE0E}"), None);
+    }
+
+    #[test]
+    fn file_names_are_parsed_into_emoji() {
+        assert_eq!(parse_code_points("1f469-200d-1f4bb").as_deref(), Some("👩‍💻"));
+        assert_eq!(parse_code_points("emoji_u1f680").as_deref(), Some("🚀"));
+        assert_eq!(parse_code_points("readme"), None);
+        assert_eq!(parse_code_points(""), None);
+    }
+
+    #[test]
+    fn sampling_outside_an_image_is_transparent() {
+        let image = RgbaImage::from_pixel(2, 2, Rgba([10, 20, 30, 255]));
+        assert_eq!(sample(&image, 0.0, 0.0), Rgba([10, 20, 30, 255]));
+        assert_eq!(sample(&image, -5.0, 1.0), Rgba([0, 0, 0, 0]));
+        assert_eq!(sample(&RgbaImage::new(0, 0), 0.0, 0.0), Rgba([0, 0, 0, 0]));
+        assert_eq!(aspect_ratio(&RgbaImage::new(0, 0)), 0.0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Error type returned by the fallible GDL APIs.
+
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+/// Result type used throughout GDL.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors that can occur while creating graphics.
+#[derive(Debug)]
+pub enum Error {
+    /// No font with the given name could be found.
+    FontNotFound(String),
+    /// The font's data could not be parsed.
+    FontParse(String),
+    /// The font is in a format that cannot be loaded directly, such as WOFF or WOFF2.
+    UnsupportedFontFormat(String),
+    /// The font has no colour glyphs that can be drawn as emoji.
+    NoColorGlyphs(String),
+    /// An image could not be decoded or encoded.
+    Image(image::ImageError),
+    /// A file could not be read or written.
+    Io(std::io::Error),
+    /// A base64 string could not be decoded.
+    Base64(base64::DecodeError),
+    /// A raw pixel buffer's length does not match the dimensions given for it.
+    BufferSizeMismatch { expected: usize, actual: usize },
+    /// A chart has no data to plot.
+    EmptyChartData,
+    /// No preset with the given name exists.
+    UnknownPreset(String),
+    /// A gradient or palette preset could not be loaded, or a preset was given invalid options.
+    InvalidPreset(String),
+    /// A string is not a valid CSS colour.
+    InvalidColor(String),
+    /// A template could not be loaded or rendered, with every problem found in it.
+    InvalidTemplate(String),
+    /// A template was rendered without values for some of its placeholders.
+    MissingVariables(Vec<String>),
+    /// A font size range to fit text with isn't made of finite, positive sizes.
+    InvalidFontSize { min_size: f32, max_size: f32 },
+    /// An element does not fit within the image it is being placed on.
+    OutOfBounds {
+        x: i64,
+        y: i64,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FontNotFound(name) => write!(f, "font not found: {}", name),
+            Error::FontParse(name) => write!(f, "unable to parse font: {}", name),
+            Error::UnsupportedFontFormat(name) => write!(
+                f,
+                "unsupported font format: {} (decode WOFF/WOFF2 fonts to TTF or OTF first)",
+                name
+            ),
+            Error::NoColorGlyphs(name) => write!(f, "font has no colour glyphs: {}", name),
+            Error::Image(err) => write!(f, "image error: {}", err),
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Base64(err) => write!(f, "base64 error: {}", err),
+            Error::BufferSizeMismatch { expected, actual } => write!(
+                f,
+                "pixel buffer has {} bytes, expected {}",
+                actual, expected
+            ),
+            Error::EmptyChartData => write!(f, "chart has no data"),
+            Error::UnknownPreset(name) => write!(f, "unknown preset: {}", name),
+            Error::InvalidPreset(reason) => write!(f, "invalid preset: {}", reason),
+            Error::InvalidColor(color) => write!(f, "invalid colour: {}", color),
+            Error::InvalidTemplate(reason) => write!(f, "invalid template: {}", reason),
+            Error::MissingVariables(names) => {
+                write!(f, "missing template variables: {}", names.join(", "))
+            }
+            Error::InvalidFontSize { min_size, max_size } => {
+                write!(f, "invalid font size range: {} to {}", min_size, max_size)
+            }
+            Error::OutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "element of size {}x{} at ({}, {}) is out of bounds",
+                width, height, x, y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Image(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::Base64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Error {
+        Error::Image(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(err: base64::DecodeError) -> Error {
+        Error::Base64(err)
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(err: Error) -> JsValue {
+        JsValue::from_str(&err.to_string())
+    }
+}
@@ -0,0 +1,415 @@
+//! Extract the dominant colours of a photo, to colour the text and accents drawn over it.
+//!
+//! Colours are grouped in Oklab, where the distance between two colours follows how different
+//! they look, so a photo's colours are split the way the eye would split them. Each extracted
+//! colour comes with the share of the photo's pixels it stands for.
+//!
+//! ```
+//! use gdl::extract::extract_palette;
+//! use image::{DynamicImage, Rgba, RgbaImage};
+//!
+//! let photo = DynamicImage::ImageRgba8(RgbaImage::from_fn(90, 60, |x, _| {
+//!     if x < 60 {
+//!         Rgba([20, 60, 120, 255])
+//!     } else {
+//!         Rgba([240, 200, 40, 255])
+//!     }
+//! }));
+//! let palette = extract_palette(&photo, 2);
+//! assert_eq!(palette.colors[0].color, gdl::Rgb { r: 20, g: 60, b: 120 });
+//! assert!((palette.colors[0].share - 2.0 / 3.0).abs() < 0.01);
+//! assert_eq!(palette.text_color, gdl::Rgb { r: 255, g: 255, b: 255 });
+//! ```
+
+use crate::contrast::readable_text_color;
+use crate::gradient::{linear_to_oklab, srgb_to_linear};
+use crate::palettes;
+use crate::{ColorScheme, Rgb};
+use image::{DynamicImage, GenericImageView, Rgba};
+use std::collections::HashMap;
+
+// Photos are scaled down to fit in a square this size before their colours are counted.
+const SAMPLE_SIZE: u32 = 256;
+
+// Maximum number of times k-means moves its cluster centres.
+const MAX_ITERATIONS: usize = 16;
+
+// Colours with less Oklab chroma than this look grey, and aren't picked as accents.
+const MIN_ACCENT_CHROMA: f32 = 0.04;
+
+/// How the colours of an image are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ExtractionMethod {
+    /// Start from median cut, then move each colour to the centre of the pixels nearest to
+    /// it until they settle. Slower, but follows the photo's colours more closely.
+    #[default]
+    KMeans,
+    /// Repeatedly split the group of colours with the most variation in half, across its
+    /// widest side.
+    MedianCut,
+}
+
+/// One of the dominant colours of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DominantColor {
+    /// The average colour of the pixels in the group.
+    pub color: Rgb,
+    /// The fraction of the image's opaque pixels in the group, from 0 to 1.
+    pub share: f32,
+}
+
+/// The dominant colours of an image, with colours suggested for drawing over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedPalette {
+    /// The dominant colours, from the largest share to the smallest.
+    pub colors: Vec<DominantColor>,
+    /// Black or white, whichever is easier to read on the most dominant colour.
+    pub text_color: Rgb,
+    /// The most vivid dominant colour that stands out from the most dominant colour, or its
+    /// complement if the image has no vivid colours.
+    pub accent_color: Rgb,
+}
+
+impl ExtractedPalette {
+    /// A colour scheme generated from the accent colour, for picking further colours that
+    /// go with the image.
+    pub fn scheme(&self) -> ColorScheme {
+        ColorScheme::new(self.accent_color)
+    }
+
+    /// Add the dominant colours to the shared [`palettes`](crate::palettes) registry under
+    /// the given name, so that they can be used wherever a palette name is taken.
+    pub fn register(&self, name: &str) {
+        let colors = self
+            .colors
+            .iter()
+            .map(|dominant| Rgba::from(dominant.color))
+            .collect();
+        palettes::registry_mut().register_palette(name, colors);
+    }
+}
+
+/// Extract the dominant colours of an image with k-means clustering.
+///
+/// ### Arguments
+/// * `img` - The image, such as a photo. Transparent pixels are ignored.
+/// * `count` - The maximum number of colours to extract. Fewer are returned if the image
+///   has fewer distinct colours.
+pub fn extract_palette(img: &DynamicImage, count: usize) -> ExtractedPalette {
+    extract_palette_with(img, count, ExtractionMethod::KMeans)
+}
+
+/// Extract the dominant colours of an image with the given method.
+///
+/// ### Arguments
+/// * `img` - The image, such as a photo. Transparent pixels are ignored.
+/// * `count` - The maximum number of colours to extract.
+/// * `method` - How the colours are grouped.
+pub fn extract_palette_with(
+    img: &DynamicImage,
+    count: usize,
+    method: ExtractionMethod,
+) -> ExtractedPalette {
+    let bins = color_bins(img);
+    let mut clusters = median_cut(&bins, count);
+    // No colours were asked for, or the image has no opaque pixels to take them from.
+    if clusters.is_empty() {
+        return palette_from(Vec::new());
+    }
+    if method == ExtractionMethod::KMeans {
+        clusters = k_means(&bins, clusters);
+    }
+
+    let total: f32 = clusters.iter().map(|cluster| cluster.weight).sum();
+    let mut colors: Vec<DominantColor> = clusters
+        .iter()
+        .filter(|cluster| cluster.weight > 0.0)
+        .map(|cluster| DominantColor {
+            color: crate::Rgba::from_oklab(cluster.lab[0], cluster.lab[1], cluster.lab[2]).into(),
+            share: cluster.weight / total,
+        })
+        .collect();
+    colors.sort_by(|a, b| b.share.total_cmp(&a.share));
+    palette_from(colors)
+}
+
+// Suggest text and accent colours for dominant colours, sorted from the largest share. An
+// image without colours is treated as white.
+fn palette_from(colors: Vec<DominantColor>) -> ExtractedPalette {
+    let background = colors
+        .first()
+        .map(|dominant| dominant.color)
+        .unwrap_or(Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        });
+    ExtractedPalette {
+        text_color: readable_text_color(&background),
+        accent_color: accent_color(&colors, background),
+        colors,
+    }
+}
+
+// A group of similar colours: their count, and their mean colour in Oklab.
+#[derive(Clone, Copy)]
+struct Cluster {
+    lab: [f32; 3],
+    weight: f32,
+}
+
+// Count the image's opaque colours, with each channel rounded to 5 bits so that near
+// duplicates are counted together.
+fn color_bins(img: &DynamicImage) -> Vec<Cluster> {
+    let (width, height) = img.dimensions();
+    let sample = if width > SAMPLE_SIZE || height > SAMPLE_SIZE {
+        img.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+    } else {
+        img.clone()
+    };
+
+    let mut sums: HashMap<[u8; 3], ([f32; 3], f32)> = HashMap::new();
+    for (_, _, pixel) in sample.pixels() {
+        if pixel[3] < 128 {
+            continue;
+        }
+        let key = [pixel[0] >> 3, pixel[1] >> 3, pixel[2] >> 3];
+        let (sum, weight) = sums.entry(key).or_insert(([0.0; 3], 0.0));
+        for channel in 0..3 {
+            sum[channel] += srgb_to_linear(pixel[channel] as f32 / 255.0);
+        }
+        *weight += 1.0;
+    }
+
+    let mut bins: Vec<Cluster> = sums
+        .values()
+        .map(|(sum, weight)| Cluster {
+            lab: linear_to_oklab(sum.map(|channel| channel / weight)),
+            weight: *weight,
+        })
+        .collect();
+    // Sort so that the result doesn't depend on the hash map's order.
+    bins.sort_by(|a, b| {
+        a.lab
+            .partial_cmp(&b.lab)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    bins
+}
+
+// Split the bins into at most `count` groups, always splitting the group with the largest
+// squared error at the weighted median of its widest axis.
+fn median_cut(bins: &[Cluster], count: usize) -> Vec<Cluster> {
+    if bins.is_empty() || count == 0 {
+        return Vec::new();
+    }
+    let mut boxes: Vec<Vec<Cluster>> = vec![bins.to_vec()];
+    while boxes.len() < count {
+        let splittable = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .max_by(|(_, a), (_, b)| squared_error(a).total_cmp(&squared_error(b)));
+        let index = match splittable {
+            Some((index, _)) => index,
+            None => break,
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        let axis = (0..3)
+            .max_by(|&a, &b| axis_range(&colors, a).total_cmp(&axis_range(&colors, b)))
+            .unwrap_or(0);
+        colors.sort_by(|a, b| a.lab[axis].total_cmp(&b.lab[axis]));
+
+        let half = colors.iter().map(|bin| bin.weight).sum::<f32>() / 2.0;
+        let mut cumulative = 0.0;
+        let mut split = 1;
+        for (i, bin) in colors.iter().enumerate() {
+            cumulative += bin.weight;
+            if cumulative >= half {
+                split = (i + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|colors| mean(colors)).collect()
+}
+
+// Move each cluster to the mean of the bins nearest to it, until no bin changes cluster.
+fn k_means(bins: &[Cluster], mut clusters: Vec<Cluster>) -> Vec<Cluster> {
+    if clusters.is_empty() {
+        return clusters;
+    }
+    let mut assignments = vec![usize::MAX; bins.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (bin, assignment) in bins.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest(&clusters, bin.lab);
+            if nearest != *assignment {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let mut members: Vec<Vec<Cluster>> = vec![Vec::new(); clusters.len()];
+        for (bin, &assignment) in bins.iter().zip(assignments.iter()) {
+            members[assignment].push(*bin);
+        }
+        clusters = members.iter().map(|colors| mean(colors)).collect();
+    }
+    clusters
+}
+
+fn nearest(clusters: &[Cluster], lab: [f32; 3]) -> usize {
+    clusters
+        .iter()
+        .enumerate()
+        .filter(|(_, cluster)| cluster.weight > 0.0)
+        .min_by(|(_, a), (_, b)| distance(a.lab, lab).total_cmp(&distance(b.lab, lab)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn mean(colors: &[Cluster]) -> Cluster {
+    let weight: f32 = colors.iter().map(|bin| bin.weight).sum();
+    if weight <= 0.0 {
+        return Cluster {
+            lab: [0.0; 3],
+            weight: 0.0,
+        };
+    }
+    let lab = [0, 1, 2].map(|axis| {
+        colors
+            .iter()
+            .map(|bin| bin.lab[axis] * bin.weight)
+            .sum::<f32>()
+            / weight
+    });
+    Cluster { lab, weight }
+}
+
+fn squared_error(colors: &[Cluster]) -> f32 {
+    let centre = mean(colors).lab;
+    colors
+        .iter()
+        .map(|bin| distance(bin.lab, centre) * bin.weight)
+        .sum()
+}
+
+fn axis_range(colors: &[Cluster], axis: usize) -> f32 {
+    let values = colors.iter().map(|bin| bin.lab[axis]);
+    let max = values.clone().fold(f32::MIN, f32::max);
+    let min = values.fold(f32::MAX, f32::min);
+    max - min
+}
+
+// Squared distance between two Oklab colours.
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+fn chroma(lab: [f32; 3]) -> f32 {
+    lab[1].hypot(lab[2])
+}
+
+// The colour that is both vivid and far from the background, weighing colours that cover
+// more of the image slightly higher.
+fn accent_color(colors: &[DominantColor], background: Rgb) -> Rgb {
+    let background_lab = crate::Rgba::from(background).to_oklab();
+    colors
+        .iter()
+        .skip(1)
+        .map(|dominant| (dominant, crate::Rgba::from(dominant.color).to_oklab()))
+        .filter(|(_, lab)| chroma(*lab) >= MIN_ACCENT_CHROMA)
+        .map(|(dominant, lab)| {
+            let score =
+                chroma(lab) * distance(lab, background_lab).sqrt() * (0.5 + dominant.share).sqrt();
+            (dominant.color, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(color, _)| color)
+        .unwrap_or_else(|| ColorScheme::new(background).complementary()[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn halves(left: Rgba<u8>, right: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 20, |x, _| {
+            if x < 30 {
+                left
+            } else {
+                right
+            }
+        }))
+    }
+
+    fn bin(lab: [f32; 3], weight: f32) -> Cluster {
+        Cluster { lab, weight }
+    }
+
+    #[test]
+    fn zero_colours_gives_an_empty_palette() {
+        let img = halves(Rgba([200, 30, 30, 255]), Rgba([30, 30, 200, 255]));
+        for &method in &[ExtractionMethod::KMeans, ExtractionMethod::MedianCut] {
+            let palette = extract_palette_with(&img, 0, method);
+            assert!(palette.colors.is_empty());
+            assert_eq!(palette.text_color, Rgb::new(0, 0, 0));
+        }
+    }
+
+    #[test]
+    fn transparent_image_gives_an_empty_palette() {
+        let img = halves(Rgba([200, 30, 30, 0]), Rgba([30, 30, 200, 100]));
+        assert!(extract_palette(&img, 5).colors.is_empty());
+        assert!(extract_palette(&DynamicImage::new_rgba8(0, 0), 5)
+            .colors
+            .is_empty());
+    }
+
+    #[test]
+    fn fewer_colours_than_asked_for() {
+        let img = halves(Rgba([200, 30, 30, 255]), Rgba([30, 30, 200, 255]));
+        for &method in &[ExtractionMethod::KMeans, ExtractionMethod::MedianCut] {
+            let palette = extract_palette_with(&img, 8, method);
+            assert_eq!(palette.colors.len(), 2);
+            assert_eq!(palette.colors[0].color, Rgb::new(200, 30, 30));
+            assert!((palette.colors[0].share - 0.75).abs() < 1e-3);
+            let shares: f32 = palette.colors.iter().map(|dominant| dominant.share).sum();
+            assert!((shares - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn k_means_moves_clusters_to_their_members() {
+        let bins = [
+            bin([0.0, 0.0, 0.0], 1.0),
+            bin([0.1, 0.0, 0.0], 1.0),
+            bin([1.0, 0.0, 0.0], 2.0),
+        ];
+        let clusters = k_means(
+            &bins,
+            vec![bin([0.4, 0.0, 0.0], 1.0), bin([0.9, 0.0, 0.0], 1.0)],
+        );
+        assert!((clusters[0].lab[0] - 0.05).abs() < 1e-6);
+        assert_eq!(clusters[0].weight, 2.0);
+        assert_eq!(clusters[1].lab[0], 1.0);
+        assert_eq!(clusters[1].weight, 2.0);
+    }
+
+    #[test]
+    fn k_means_without_clusters_or_bins() {
+        assert!(k_means(&[bin([0.5, 0.0, 0.0], 1.0)], Vec::new()).is_empty());
+        let clusters = k_means(&[], vec![bin([0.5, 0.0, 0.0], 1.0)]);
+        assert_eq!(clusters.len(), 1);
+        assert!(median_cut(&[], 3).is_empty());
+    }
+}
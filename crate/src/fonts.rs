@@ -0,0 +1,636 @@
+//! Load fonts once and share them between drawing functions.
+//!
+//! Fonts are looked up by family, weight and style using a [`FontDescriptor`], in the same way
+//! CSS matches fonts: the closest style is chosen first, then the closest weight, and if the
+//! family is missing the descriptor's and the registry's fallback families are tried in order.
+//!
+//! Emoji are drawn from a separate [`EmojiSet`], set with [`FontRegistry::set_emoji`], which
+//! every face in the registry falls back to for the emoji in its text.
+
+use crate::emoji::EmojiSet;
+use crate::{Error, Result};
+use rusttype::Font;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Common font weights, as used in CSS and the OpenType `OS/2` table.
+pub mod weight {
+    pub const THIN: u16 = 100;
+    pub const EXTRA_LIGHT: u16 = 200;
+    pub const LIGHT: u16 = 300;
+    pub const REGULAR: u16 = 400;
+    pub const MEDIUM: u16 = 500;
+    pub const SEMI_BOLD: u16 = 600;
+    pub const BOLD: u16 = 700;
+    pub const EXTRA_BOLD: u16 = 800;
+    pub const BLACK: u16 = 900;
+}
+
+/// The slant of a font face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+/// Describes the font to draw text with: a family, a weight between 1 and 1000, a style and
+/// the families to fall back to if the family isn't available.
+///
+/// ```
+/// use gdl::fonts::{FontDescriptor, FontStyle};
+///
+/// let font = FontDescriptor::new("Roboto")
+///     .weight(700)
+///     .style(FontStyle::Italic)
+///     .fallback("Lato");
+/// ```
+///
+/// A descriptor can also be created from a name such as "Roboto-Bold" or "Lato-LightItalic",
+/// where the part after the last hyphen is read as the weight and style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontDescriptor {
+    pub family: String,
+    pub weight: u16,
+    pub style: FontStyle,
+    pub fallbacks: Vec<String>,
+}
+
+impl FontDescriptor {
+    /// Describe the regular, upright face of a font family.
+    pub fn new(family: &str) -> FontDescriptor {
+        FontDescriptor {
+            family: family.to_string(),
+            weight: weight::REGULAR,
+            style: FontStyle::Normal,
+            fallbacks: Vec::new(),
+        }
+    }
+
+    /// Set the weight, eg: 400 for regular, 700 for bold.
+    pub fn weight(mut self, weight: u16) -> FontDescriptor {
+        self.weight = weight;
+        self
+    }
+
+    /// Set the style.
+    pub fn style(mut self, style: FontStyle) -> FontDescriptor {
+        self.style = style;
+        self
+    }
+
+    /// Shorthand for `.weight(700)`.
+    pub fn bold(self) -> FontDescriptor {
+        self.weight(weight::BOLD)
+    }
+
+    /// Shorthand for `.style(FontStyle::Italic)`.
+    pub fn italic(self) -> FontDescriptor {
+        self.style(FontStyle::Italic)
+    }
+
+    /// Add a family to try if none of the previous families are available.
+    pub fn fallback(mut self, family: &str) -> FontDescriptor {
+        self.fallbacks.push(family.to_string());
+        self
+    }
+}
+
+impl From<&str> for FontDescriptor {
+    fn from(name: &str) -> FontDescriptor {
+        let (family, weight, style) = parse_font_name(name);
+        FontDescriptor::new(family).weight(weight).style(style)
+    }
+}
+
+impl fmt::Display for FontDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {:?}", self.family, self.weight, self.style)
+    }
+}
+
+/// A single loaded font face, along with the metadata used to match it.
+pub struct FontFace {
+    /// The name the face was registered under, usually its file name without the extension.
+    pub name: String,
+    /// The family, read from the font's name table.
+    pub family: String,
+    pub weight: u16,
+    pub style: FontStyle,
+    // Parsed from its own copy of `data`, which rusttype owns.
+    font: Font<'static>,
+    // Normalised names this face can be found by.
+    aliases: Vec<String>,
+    // The raw font data, shared by every face of a collection, and the index of the face
+    // within it.
+    data: Arc<Vec<u8>>,
+    index: u32,
+    // The registry's emoji, drawn in place of the face's own glyphs for emoji.
+    emoji: Option<Arc<EmojiSet>>,
+}
+
+impl FontFace {
+    /// The parsed font, for drawing and measuring glyphs.
+    pub fn font(&self) -> &Font<'_> {
+        &self.font
+    }
+
+    /// Parse the face's tables for shaping with rustybuzz.
+    pub(crate) fn shaper(&self) -> Option<rustybuzz::Face<'_>> {
+        rustybuzz::Face::from_slice(&self.data, self.index)
+    }
+
+    /// The emoji this face falls back to, if the registry has any.
+    pub fn emoji(&self) -> Option<&EmojiSet> {
+        self.emoji.as_deref()
+    }
+}
+
+impl fmt::Debug for FontFace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontFace")
+            .field("name", &self.name)
+            .field("family", &self.family)
+            .field("weight", &self.weight)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+/// A set of parsed font faces, looked up by family, weight and style.
+///
+/// Each font is read and parsed once, when it is added to the registry, and can then
+/// be used by any text, preset, collage or diagram function that takes a `&FontRegistry`.
+///
+/// TrueType (`.ttf`) and OpenType (`.otf`) fonts are supported, as are collections
+/// (`.ttc`, `.otc`), where every face in the collection is added. WOFF and WOFF2 fonts
+/// must be decoded to TrueType or OpenType before they are loaded.
+#[derive(Default)]
+pub struct FontRegistry {
+    faces: Vec<FontFace>,
+    fallbacks: Vec<String>,
+    emoji: Option<Arc<EmojiSet>>,
+}
+
+impl FontRegistry {
+    /// Create an empty registry.
+    pub fn new() -> FontRegistry {
+        FontRegistry {
+            faces: Vec::new(),
+            fallbacks: Vec::new(),
+            emoji: None,
+        }
+    }
+
+    /// Create a registry containing the fonts bundled with GDL, which are embedded in the binary
+    /// at compile time. Requires the `embedded-fonts` feature.
+    ///
+    /// Families available include Bebas Kai, Roboto (thin to black), Lato (regular and bold),
+    /// Oswald, Montserrat, Mr Dafoe and Norwester. No fallback families are set, so looking up
+    /// a family that isn't bundled is an error; call [`FontRegistry::set_fallbacks`] to fall
+    /// back to one of these instead.
+    #[cfg(feature = "embedded-fonts")]
+    pub fn embedded() -> FontRegistry {
+        let mut registry = FontRegistry::new();
+        for (name, bytes) in EMBEDDED_FONTS {
+            // The bundled fonts are known to be valid, so this cannot fail.
+            let _ = registry.load_bytes(name, bytes.to_vec());
+        }
+        registry
+    }
+
+    /// Create a registry containing every font in a directory.
+    ///
+    /// See [`FontRegistry::load_dir`].
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<FontRegistry> {
+        let mut registry = FontRegistry::new();
+        registry.load_dir(dir)?;
+        Ok(registry)
+    }
+
+    /// Set the families tried, in order, when a descriptor's own families aren't available.
+    pub fn set_fallbacks(&mut self, families: &[&str]) {
+        self.fallbacks = families.iter().map(|family| family.to_string()).collect();
+    }
+
+    /// Set the emoji drawn in text, in place of glyphs from the text's own font.
+    ///
+    /// Emoji outside the Basic Multilingual Plane, such as 🚀, and any emoji followed by the
+    /// emoji variation selector U+FE0F, are always taken from the set when it has them. Older
+    /// symbols such as ☀ or © are only taken from the set when the font has no glyph for them.
+    pub fn set_emoji(&mut self, emoji: EmojiSet) {
+        let emoji = Arc::new(emoji);
+        for face in &mut self.faces {
+            face.emoji = Some(Arc::clone(&emoji));
+        }
+        self.emoji = Some(emoji);
+    }
+
+    /// The emoji drawn in text, if any have been set.
+    pub fn emoji(&self) -> Option<&EmojiSet> {
+        self.emoji.as_deref()
+    }
+
+    /// Add a font from its raw bytes, under the given name.
+    ///
+    /// The bytes can contain a TrueType or OpenType font, or a font collection. Each face of
+    /// a collection after the first is named after its full name in the font. An existing
+    /// face with the same name is replaced.
+    pub fn load_bytes(&mut self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        if is_woff(&bytes) {
+            return Err(Error::UnsupportedFontFormat(name.to_string()));
+        }
+        let count = ttf_parser::fonts_in_collection(&bytes).unwrap_or(1);
+        let data = Arc::new(bytes);
+        for index in 0..count {
+            let face = ttf_parser::Face::from_slice(&data, index)
+                .map_err(|_| Error::FontParse(name.to_string()))?;
+            let family = face_family(&face).unwrap_or_else(|| parse_font_name(name).0.to_string());
+            let face_name = match face_full_name(&face) {
+                Some(full_name) if index > 0 => full_name,
+                _ => name.to_string(),
+            };
+            let weight = face.weight().to_number();
+            let style = if face.is_italic() {
+                FontStyle::Italic
+            } else if face.is_oblique() {
+                FontStyle::Oblique
+            } else {
+                FontStyle::Normal
+            };
+            let aliases = vec![
+                normalise(&family),
+                normalise(&face_name),
+                normalise(parse_font_name(&face_name).0),
+            ];
+
+            let font = Font::try_from_vec_and_index(data.to_vec(), index)
+                .ok_or_else(|| Error::FontParse(name.to_string()))?;
+
+            self.faces.retain(|existing| existing.name != face_name);
+            self.faces.push(FontFace {
+                name: face_name,
+                family,
+                weight,
+                style,
+                font,
+                aliases,
+                data: Arc::clone(&data),
+                index,
+                emoji: self.emoji.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Add a font from a file. The font is named after the file, without its extension,
+    /// so `fonts/Robotika.ttf` is registered as "Robotika".
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let name = font_name(path);
+        let bytes = fs::read(path).map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::FontNotFound(name.clone()),
+            _ => Error::Io(err),
+        })?;
+        self.load_bytes(&name, bytes)
+    }
+
+    /// Add every font (`*.ttf`, `*.otf`, `*.ttc` and `*.otc`) found in a directory,
+    /// each named after its file.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if is_font_file(&path) {
+                self.load_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the face that best matches a descriptor.
+    ///
+    /// The descriptor's family is tried first, then its fallbacks, then the registry's
+    /// fallbacks. Within a family, the face with the closest style is chosen, and among
+    /// those, the closest weight, following the CSS font matching rules.
+    pub fn get(&self, descriptor: &FontDescriptor) -> Result<&Font<'_>> {
+        self.face(descriptor).map(FontFace::font)
+    }
+
+    /// Get the face that best matches a descriptor, along with its metadata.
+    ///
+    /// See [`FontRegistry::get`].
+    pub fn face(&self, descriptor: &FontDescriptor) -> Result<&FontFace> {
+        let families = std::iter::once(&descriptor.family)
+            .chain(descriptor.fallbacks.iter())
+            .chain(self.fallbacks.iter());
+
+        for family in families {
+            let family = normalise(family);
+            let best = self
+                .faces
+                .iter()
+                .filter(|face| face.aliases.contains(&family))
+                .min_by_key(|face| {
+                    (
+                        style_distance(descriptor.style, face.style),
+                        weight_distance(descriptor.weight, face.weight),
+                    )
+                });
+            if let Some(face) = best {
+                return Ok(face);
+            }
+        }
+        Err(Error::FontNotFound(descriptor.family.clone()))
+    }
+
+    /// Check whether a face or family with the given name has been loaded.
+    pub fn contains(&self, name: &str) -> bool {
+        let name = normalise(name);
+        self.faces.iter().any(|face| face.aliases.contains(&name))
+    }
+
+    /// Names of all faces in the registry, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.faces.iter().map(|face| face.name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Families of all faces in the registry, in alphabetical order.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = self.faces.iter().map(|face| face.family.as_str()).collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+}
+
+#[cfg(feature = "embedded-fonts")]
+thread_local! {
+    static EMBEDDED_REGISTRY: FontRegistry = FontRegistry::embedded();
+}
+
+// Call a function with a registry of the embedded fonts, which are only parsed once per thread.
+// Used by the functions exported to JavaScript, which can't be passed a registry.
+#[cfg(feature = "embedded-fonts")]
+pub(crate) fn with_embedded_fonts<R>(f: impl FnOnce(&FontRegistry) -> R) -> R {
+    EMBEDDED_REGISTRY.with(f)
+}
+
+// Name a font after its file stem.
+fn font_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_font_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ["ttf", "otf", "ttc", "otc"]
+            .iter()
+            .any(|font_ext| ext.eq_ignore_ascii_case(font_ext)),
+        None => false,
+    }
+}
+
+fn is_woff(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"wOFF") || bytes.starts_with(b"wOF2")
+}
+
+// Prefer the typographic family (name ID 16), which groups weights such as
+// "Roboto Light" under "Roboto", over the legacy family (name ID 1).
+fn face_family(face: &ttf_parser::Face) -> Option<String> {
+    face_name_entry(face, 16).or_else(|| face_name_entry(face, 1))
+}
+
+fn face_full_name(face: &ttf_parser::Face) -> Option<String> {
+    face_name_entry(face, 4)
+}
+
+fn face_name_entry(face: &ttf_parser::Face, name_id: u16) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == name_id)
+        .find_map(|name| name.to_string())
+}
+
+// Lowercase a name and drop spaces, hyphens and underscores, so "Bebas Kai",
+// "BebasKai" and "bebas-kai" all match.
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// Split a name such as "Roboto-BoldItalic" into its family, weight and style.
+// Names without a recognised suffix are treated as a regular, upright family.
+fn parse_font_name(name: &str) -> (&str, u16, FontStyle) {
+    let (family, suffix) = match name.rfind('-') {
+        Some(i) => (&name[..i], &name[i + 1..]),
+        None => return (name, weight::REGULAR, FontStyle::Normal),
+    };
+    let suffix = suffix.to_lowercase();
+
+    let (weight_name, style) = if let Some(weight_name) = suffix.strip_suffix("italic") {
+        (weight_name, FontStyle::Italic)
+    } else if let Some(weight_name) = suffix.strip_suffix("oblique") {
+        (weight_name, FontStyle::Oblique)
+    } else {
+        (suffix.as_str(), FontStyle::Normal)
+    };
+
+    let weight = match weight_name {
+        "thin" | "hairline" => weight::THIN,
+        "extralight" | "ultralight" => weight::EXTRA_LIGHT,
+        "light" => weight::LIGHT,
+        "" | "regular" | "normal" | "book" => weight::REGULAR,
+        "medium" => weight::MEDIUM,
+        "semibold" | "demibold" => weight::SEMI_BOLD,
+        "bold" => weight::BOLD,
+        "extrabold" | "ultrabold" => weight::EXTRA_BOLD,
+        "black" | "heavy" => weight::BLACK,
+        _ => return (name, weight::REGULAR, FontStyle::Normal),
+    };
+    (family, weight, style)
+}
+
+fn style_distance(wanted: FontStyle, actual: FontStyle) -> u8 {
+    use FontStyle::*;
+    match (wanted, actual) {
+        (Normal, Normal) | (Italic, Italic) | (Oblique, Oblique) => 0,
+        (Italic, Oblique) | (Oblique, Italic) | (Normal, Oblique) => 1,
+        _ => 2,
+    }
+}
+
+// CSS weight matching: for 400-500, heavier weights up to 500 are tried first, then
+// lighter, then heavier; below 400 lighter weights are preferred, above 500 heavier ones.
+fn weight_distance(wanted: u16, actual: u16) -> u32 {
+    let (wanted, actual) = (u32::from(wanted), u32::from(actual));
+    if actual == wanted {
+        return 0;
+    }
+    if (400..=500).contains(&wanted) {
+        if actual > wanted && actual <= 500 {
+            actual - wanted
+        } else if actual < wanted {
+            1000 + wanted - actual
+        } else {
+            2000 + actual - wanted
+        }
+    } else if wanted < 400 {
+        if actual < wanted {
+            wanted - actual
+        } else {
+            1000 + actual - wanted
+        }
+    } else if actual > wanted {
+        actual - wanted
+    } else {
+        1000 + wanted - actual
+    }
+}
+
+#[cfg(feature = "embedded-fonts")]
+const EMBEDDED_FONTS: &[(&str, &[u8])] = &[
+    ("BebasKai", include_bytes!("../fonts/BebasKai.ttf")),
+    ("Lato-Bold", include_bytes!("../fonts/Lato-Bold.ttf")),
+    ("Lato-Regular", include_bytes!("../fonts/Lato-Regular.ttf")),
+    (
+        "Montserrat-Regular",
+        include_bytes!("../fonts/Montserrat-Regular.ttf"),
+    ),
+    (
+        "MrDafoe-Regular",
+        include_bytes!("../fonts/MrDafoe-Regular.ttf"),
+    ),
+    ("norwester", include_bytes!("../fonts/norwester.ttf")),
+    (
+        "Oswald-Regular",
+        include_bytes!("../fonts/Oswald-Regular.ttf"),
+    ),
+    ("Roboto-Black", include_bytes!("../fonts/Roboto-Black.ttf")),
+    ("Roboto-Bold", include_bytes!("../fonts/Roboto-Bold.ttf")),
+    ("Roboto-Light", include_bytes!("../fonts/Roboto-Light.ttf")),
+    (
+        "Roboto-Regular",
+        include_bytes!("../fonts/Roboto-Regular.ttf"),
+    ),
+    ("Roboto-Thin", include_bytes!("../fonts/Roboto-Thin.ttf")),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        let mut registry = FontRegistry::new();
+        assert!(matches!(
+            registry.load_bytes("garbage", vec![0; 16]),
+            Err(Error::FontParse(_))
+        ));
+        assert!(matches!(
+            registry.load_bytes("web", b"wOFF\0\0\0\0".to_vec()),
+            Err(Error::UnsupportedFontFormat(_))
+        ));
+        assert!(registry.names().is_empty());
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn faces_can_be_used_after_the_registry_moves() {
+        let mut registry = FontRegistry::new();
+        let (name, bytes) = EMBEDDED_FONTS[0];
+        registry.load_bytes(name, bytes.to_vec()).unwrap();
+        let registry = Box::new(registry);
+        let face = registry.face(&FontDescriptor::new(name)).unwrap();
+        assert_ne!(face.font().glyph('A').id().0, 0);
+        assert!(face.shaper().is_some());
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn replacing_a_face_keeps_the_others() {
+        let mut registry = FontRegistry::embedded();
+        let count = registry.names().len();
+        let (name, bytes) = EMBEDDED_FONTS[1];
+        registry.load_bytes(name, bytes.to_vec()).unwrap();
+        assert_eq!(registry.names().len(), count);
+        assert_ne!(registry.get(&"Roboto".into()).unwrap().glyph('g').id().0, 0);
+    }
+
+    #[test]
+    fn empty_registry_finds_nothing() {
+        let registry = FontRegistry::new();
+        assert!(registry.names().is_empty());
+        assert!(!registry.contains("Roboto"));
+        assert!(matches!(
+            registry.face(&FontDescriptor::new("Roboto")),
+            Err(Error::FontNotFound(_))
+        ));
+        assert!(FontRegistry::new().load_dir("no/such/directory").is_err());
+    }
+
+    #[test]
+    fn font_names_give_weight_and_style() {
+        assert_eq!(
+            parse_font_name("Roboto-Bold"),
+            ("Roboto", 700, FontStyle::Normal)
+        );
+        assert_eq!(
+            parse_font_name("Lato-LightItalic"),
+            ("Lato", 300, FontStyle::Italic)
+        );
+        assert_eq!(
+            parse_font_name("Oswald"),
+            ("Oswald", 400, FontStyle::Normal)
+        );
+        assert_eq!(
+            parse_font_name("Mr-Dafoe"),
+            ("Mr-Dafoe", 400, FontStyle::Normal)
+        );
+        assert_eq!(parse_font_name("-"), ("", 400, FontStyle::Normal));
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn embedded_registry_matches_the_nearest_face() {
+        let mut registry = FontRegistry::embedded();
+        let face = |registry: &FontRegistry, descriptor: FontDescriptor| {
+            registry.face(&descriptor).unwrap().name.clone()
+        };
+        assert_eq!(
+            face(&registry, FontDescriptor::new("Roboto").weight(650)),
+            "Roboto-Bold"
+        );
+        assert_eq!(
+            face(&registry, FontDescriptor::new("Roboto").weight(50)),
+            "Roboto-Thin"
+        );
+        assert_eq!(
+            face(&registry, FontDescriptor::new("Lato").italic()),
+            "Lato-Regular"
+        );
+        assert_eq!(face(&registry, "Roboto-Light".into()), "Roboto-Light");
+        // Unknown families fall back to the descriptor's fallbacks, then to the registry's.
+        assert_eq!(
+            face(&registry, FontDescriptor::new("Nope").fallback("Oswald")),
+            "Oswald-Regular"
+        );
+        assert!(matches!(
+            registry.face(&FontDescriptor::new("Nope")),
+            Err(Error::FontNotFound(_))
+        ));
+        registry.set_fallbacks(&["Roboto"]);
+        assert_eq!(
+            face(&registry, FontDescriptor::new("Nope")),
+            "Roboto-Regular"
+        );
+        assert!(registry.families().contains(&"Bebas Kai"));
+    }
+}
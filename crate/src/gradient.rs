@@ -0,0 +1,443 @@
+//! Gradients: linear at any angle, radial and conic, with any number of colour stops.
+//!
+//! A [`Gradient`] is drawn across a box, such as a background, the bounding box of a shape or
+//! the box around some text, so the same gradient can fill things of any size. Stops are placed
+//! at offsets from 0 to 1 along the gradient, with colours that may be translucent, and the
+//! colours between them are mixed in a choice of colour space. Colours can be given as a
+//! [`crate::Rgba`] or an `image::Rgba<u8>`.
+//!
+//! ```
+//! use gdl::gradient::{Gradient, Interpolation};
+//! use image::Rgba;
+//!
+//! let sunset = Gradient::linear(135.0)
+//!     .stop(0.0, Rgba([255, 94, 98, 255]))
+//!     .stop(0.6, "#ff9966".parse::<gdl::Rgba>().unwrap())
+//!     .stop(1.0, Rgba([255, 200, 120, 0]))
+//!     .interpolation(Interpolation::Oklab);
+//! let background = sunset.render(1080, 1080);
+//!
+//! let spotlight = Gradient::radial(0.5, 0.3, 0.8).colors(&[
+//!     Rgba([255, 255, 255, 200]),
+//!     Rgba([255, 255, 255, 0]),
+//! ]);
+//! assert_eq!(spotlight.color_at(0.0), Rgba([255, 255, 255, 200]));
+//! ```
+
+use image::{Rgba, RgbaImage};
+
+// Number of colours worked out along a gradient before drawing it.
+const LOOKUP_SIZE: usize = 1024;
+
+/// The shape of a gradient, and where it sits in the box it is drawn across.
+///
+/// Angles are in degrees, clockwise from the positive x-axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientShape {
+    /// Colours change along a line through the middle of the box at `angle`, so 0 runs from
+    /// left to right and 90 from top to bottom. As in CSS, the line is just long enough for the
+    /// first and last colours to reach the corners.
+    Linear { angle: f32 },
+    /// Colours change outwards from a centre, given as a fraction of the box's width and height.
+    /// A `radius` of 1 reaches the corner furthest from the centre.
+    Radial { center: (f32, f32), radius: f32 },
+    /// Colours change around a centre, given as a fraction of the box's width and height,
+    /// clockwise from `angle`.
+    Conic { center: (f32, f32), angle: f32 },
+}
+
+/// The colour space colours between stops are mixed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Mix the sRGB values directly, as browsers do by default.
+    #[default]
+    Srgb,
+    /// Mix linear light, which keeps mixes of bright colours bright.
+    Linear,
+    /// Mix lightness, chroma and hue, going the short way round the hue circle, which keeps
+    /// colours saturated.
+    Lch,
+    /// Mix in the Oklab perceptual colour space, which gives smooth changes with no grey or
+    /// overly dark middle.
+    Oklab,
+}
+
+/// A colour at a position along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0 at its start to 1 at its end.
+    pub offset: f32,
+    pub color: Rgba<u8>,
+}
+
+/// A gradient, which can be rendered into an image or used to fill shapes and text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    pub interpolation: Interpolation,
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    /// A gradient of the given shape, with no stops.
+    pub fn new(shape: GradientShape) -> Gradient {
+        Gradient {
+            shape,
+            interpolation: Interpolation::default(),
+            stops: Vec::new(),
+        }
+    }
+
+    /// A linear gradient at an angle, in degrees clockwise from left-to-right.
+    pub fn linear(angle: f32) -> Gradient {
+        Gradient::new(GradientShape::Linear { angle })
+    }
+
+    /// A radial gradient. The centre is a fraction of the box's width and height, and a radius
+    /// of 1 reaches the corner furthest from it.
+    pub fn radial(center_x: f32, center_y: f32, radius: f32) -> Gradient {
+        Gradient::new(GradientShape::Radial {
+            center: (center_x, center_y),
+            radius,
+        })
+    }
+
+    /// A conic gradient around a centre, given as a fraction of the box's width and height,
+    /// starting at an angle in degrees clockwise from the positive x-axis.
+    pub fn conic(center_x: f32, center_y: f32, angle: f32) -> Gradient {
+        Gradient::new(GradientShape::Conic {
+            center: (center_x, center_y),
+            angle,
+        })
+    }
+
+    /// Add a colour stop at an offset along the gradient, from 0 to 1. Stops at the same offset
+    /// give a hard edge between colours.
+    pub fn stop(mut self, offset: f32, color: impl Into<crate::Rgba>) -> Gradient {
+        let color = color.into().into();
+        let index = self.stops.partition_point(|stop| stop.offset <= offset);
+        self.stops.insert(index, GradientStop { offset, color });
+        self
+    }
+
+    /// Replace the stops with colours spaced evenly from the start of the gradient to its end.
+    pub fn colors<C: Into<crate::Rgba> + Copy>(mut self, colors: &[C]) -> Gradient {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        self.stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| GradientStop {
+                offset: i as f32 / last,
+                color: color.into().into(),
+            })
+            .collect();
+        self
+    }
+
+    /// Set the colour space colours are mixed in.
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Gradient {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// The colour stops, in order of offset.
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// The colour at a position along the gradient, from 0 at its start to 1 at its end.
+    /// Positions before the first stop or after the last take that stop's colour.
+    pub fn color_at(&self, position: f32) -> Rgba<u8> {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Rgba([0, 0, 0, 0]),
+        };
+        if position.is_nan() || position <= first.offset {
+            return first.color;
+        }
+        if position >= last.offset {
+            return last.color;
+        }
+        let end = self
+            .stops
+            .partition_point(|stop| stop.offset <= position)
+            .min(self.stops.len() - 1);
+        let (from, to) = (self.stops[end - 1], self.stops[end]);
+        let span = to.offset - from.offset;
+        if span <= 0.0 {
+            return to.color;
+        }
+        mix(
+            from.color,
+            to.color,
+            (position - from.offset) / span,
+            self.interpolation,
+        )
+    }
+
+    /// The position along the gradient of a point in a box of the given size.
+    pub fn position_at(&self, x: f32, y: f32, width: f32, height: f32) -> f32 {
+        match self.shape {
+            GradientShape::Linear { angle } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let length = (width * cos).abs() + (height * sin).abs();
+                if length <= 0.0 {
+                    return 0.0;
+                }
+                let along = (x - width / 2.0) * cos + (y - height / 2.0) * sin;
+                along / length + 0.5
+            }
+            GradientShape::Radial { center, radius } => {
+                let (cx, cy) = (center.0 * width, center.1 * height);
+                let furthest = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+                    .iter()
+                    .map(|&(corner_x, corner_y)| distance(cx, cy, corner_x, corner_y))
+                    .fold(0.0f32, f32::max);
+                let radius = radius * furthest;
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+                distance(cx, cy, x, y) / radius
+            }
+            GradientShape::Conic { center, angle } => {
+                let (dx, dy) = (x - center.0 * width, y - center.1 * height);
+                (dy.atan2(dx).to_degrees() - angle).rem_euclid(360.0) / 360.0
+            }
+        }
+    }
+
+    /// Draw the gradient across a new image.
+    pub fn render(&self, width: u32, height: u32) -> RgbaImage {
+        let sampler = self.sampler(width, height);
+        RgbaImage::from_fn(width, height, |x, y| sampler.sample(x, y))
+    }
+
+    // Prepare to draw the gradient across a box of the given size, working out its colours
+    // once rather than at every pixel.
+    pub(crate) fn sampler(&self, width: u32, height: u32) -> GradientSampler<'_> {
+        let lookup = (0..LOOKUP_SIZE)
+            .map(|i| self.color_at(i as f32 / (LOOKUP_SIZE - 1) as f32))
+            .collect();
+        GradientSampler {
+            gradient: self,
+            lookup,
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+}
+
+// A gradient ready to be drawn across a box.
+pub(crate) struct GradientSampler<'a> {
+    gradient: &'a Gradient,
+    lookup: Vec<Rgba<u8>>,
+    width: f32,
+    height: f32,
+}
+
+impl GradientSampler<'_> {
+    // The colour of the pixel at a point in the box.
+    pub(crate) fn sample(&self, x: u32, y: u32) -> Rgba<u8> {
+        let position =
+            self.gradient
+                .position_at(x as f32 + 0.5, y as f32 + 0.5, self.width, self.height);
+        let index = (position.clamp(0.0, 1.0) * (LOOKUP_SIZE - 1) as f32).round() as usize;
+        self.lookup[index]
+    }
+}
+
+fn distance(x0: f32, y0: f32, x1: f32, y1: f32) -> f32 {
+    ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt()
+}
+
+// Mix two colours, `t` of the way from `from` to `to`, in a colour space.
+pub(crate) fn mix(from: Rgba<u8>, to: Rgba<u8>, t: f32, interpolation: Interpolation) -> Rgba<u8> {
+    let alpha = from[3] as f32 / 255.0 * (1.0 - t) + to[3] as f32 / 255.0 * t;
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let color = if interpolation == Interpolation::Lch {
+        let (a, b) = (
+            crate::Rgba::from(from).to_lch(),
+            crate::Rgba::from(to).to_lch(),
+        );
+        // A grey has no hue of its own, so take the other colour's.
+        let (hue_a, hue_b) = match (a[1] < 1e-3, b[1] < 1e-3) {
+            (true, false) => (b[2], b[2]),
+            (false, true) => (a[2], a[2]),
+            _ => (a[2], a[2] + (b[2] - a[2] + 180.0).rem_euclid(360.0) - 180.0),
+        };
+        let mixed = crate::Rgba::from_lch(
+            a[0] + (b[0] - a[0]) * t,
+            a[1] + (b[1] - a[1]) * t,
+            hue_a + (hue_b - hue_a) * t,
+        );
+        [mixed.r, mixed.g, mixed.b].map(|channel| channel as f32 / 255.0)
+    } else {
+        // Colours are premultiplied by their alpha, so a transparent stop doesn't tint the mix.
+        let convert = |color: Rgba<u8>| {
+            let weight = color[3] as f32 / 255.0;
+            let c = to_space(color, interpolation);
+            [c[0] * weight, c[1] * weight, c[2] * weight]
+        };
+        let (a, b) = (convert(from), convert(to));
+        let mixed = [0, 1, 2].map(|i| (a[i] * (1.0 - t) + b[i] * t) / alpha);
+        from_space(mixed, interpolation)
+    };
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        channel(alpha),
+    ])
+}
+
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.max(0.0).powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Convert an sRGB colour to the components of a colour space that can be mixed directly.
+fn to_space(color: Rgba<u8>, interpolation: Interpolation) -> [f32; 3] {
+    let srgb = [0, 1, 2].map(|i| color[i] as f32 / 255.0);
+    match interpolation {
+        Interpolation::Srgb | Interpolation::Lch => srgb,
+        Interpolation::Linear => srgb.map(srgb_to_linear),
+        Interpolation::Oklab => linear_to_oklab(srgb.map(srgb_to_linear)),
+    }
+}
+
+// Convert components of a colour space back to sRGB, from 0 to 1.
+fn from_space(components: [f32; 3], interpolation: Interpolation) -> [f32; 3] {
+    match interpolation {
+        Interpolation::Srgb | Interpolation::Lch => components,
+        Interpolation::Linear => components.map(linear_to_srgb),
+        Interpolation::Oklab => oklab_to_linear(components).map(linear_to_srgb),
+    }
+}
+
+// Björn Ottosson's conversions between linear sRGB and Oklab.
+pub(crate) fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+pub(crate) fn oklab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    #[test]
+    fn gradient_without_stops_is_transparent() {
+        let gradient = Gradient::linear(0.0);
+        assert_eq!(gradient.color_at(0.5), Rgba([0, 0, 0, 0]));
+        assert_eq!(
+            Gradient::linear(0.0).colors::<Rgba<u8>>(&[]).color_at(0.0),
+            Rgba([0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn single_stop_is_used_everywhere() {
+        let gradient = Gradient::linear(0.0).colors(&[RED]);
+        for &position in &[-1.0, 0.0, 0.5, 1.0, 2.0, f32::NAN] {
+            assert_eq!(gradient.color_at(position), RED);
+        }
+    }
+
+    #[test]
+    fn positions_outside_the_stops_take_the_end_colours() {
+        let gradient = Gradient::linear(0.0).stop(0.25, RED).stop(0.75, BLUE);
+        assert_eq!(gradient.color_at(0.0), RED);
+        assert_eq!(gradient.color_at(f32::NAN), RED);
+        assert_eq!(gradient.color_at(1.0), BLUE);
+        assert_eq!(gradient.color_at(0.5), Rgba([128, 0, 128, 255]));
+    }
+
+    #[test]
+    fn stops_at_the_same_offset_give_a_hard_edge() {
+        let gradient = Gradient::linear(0.0)
+            .stop(0.0, RED)
+            .stop(0.5, RED)
+            .stop(0.5, BLUE)
+            .stop(1.0, BLUE);
+        assert_eq!(gradient.color_at(0.49), RED);
+        assert_eq!(gradient.color_at(0.5), BLUE);
+    }
+
+    #[test]
+    fn stops_are_kept_in_order() {
+        let gradient = Gradient::linear(0.0).stop(1.0, BLUE).stop(0.0, RED);
+        let offsets: Vec<f32> = gradient.stops().iter().map(|stop| stop.offset).collect();
+        assert_eq!(offsets, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn transparent_stops_dont_tint_the_mix() {
+        let gradient = Gradient::linear(0.0).colors(&[RED, Rgba([0, 0, 255, 0])]);
+        assert_eq!(gradient.color_at(0.5), Rgba([255, 0, 0, 128]));
+        let clear = Gradient::linear(0.0).colors(&[Rgba([0, 0, 0, 0]); 2]);
+        assert_eq!(clear.color_at(0.5), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn every_colour_space_keeps_the_end_colours() {
+        for &interpolation in &[
+            Interpolation::Srgb,
+            Interpolation::Linear,
+            Interpolation::Lch,
+            Interpolation::Oklab,
+        ] {
+            let gradient = Gradient::linear(0.0)
+                .colors(&[RED, BLUE])
+                .interpolation(interpolation);
+            assert_eq!(gradient.color_at(0.0), RED);
+            assert_eq!(gradient.color_at(1.0), BLUE);
+            let middle = gradient.color_at(0.5);
+            assert!(middle[0] > 0 && middle[2] > 0, "{:?}", interpolation);
+        }
+    }
+
+    #[test]
+    fn degenerate_shapes_dont_panic() {
+        let radial = Gradient::radial(0.5, 0.5, 0.0).colors(&[RED, BLUE]);
+        assert_eq!(radial.position_at(1.0, 1.0, 2.0, 2.0), 1.0);
+        assert_eq!(radial.render(4, 4).get_pixel(0, 0), &BLUE);
+
+        let linear = Gradient::linear(45.0).colors(&[RED, BLUE]);
+        assert_eq!(linear.position_at(0.0, 0.0, 0.0, 0.0), 0.0);
+        assert_eq!(linear.render(0, 0).dimensions(), (0, 0));
+        assert_eq!(linear.render(1, 1).dimensions(), (1, 1));
+    }
+}
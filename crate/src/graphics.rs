@@ -1,3 +1,4 @@
+use crate::compositing::{composite, BlendMode};
 use crate::{helpers, PhotonImage, Rgb};
 use image::ImageBuffer;
 use wasm_bindgen::prelude::*;
@@ -38,7 +39,14 @@ pub fn draw_photonimage(
     let mut dyn_container_img = helpers::dyn_image_from_raw(&container_img);
     let dyn_photonimg = helpers::dyn_image_from_raw(&photon_img);
 
-    image::imageops::overlay(&mut dyn_container_img, &dyn_photonimg, x_pos, y_pos);
+    composite(
+        &mut dyn_container_img,
+        &dyn_photonimg,
+        x_pos as i32,
+        y_pos as i32,
+        BlendMode::Normal,
+        1.0,
+    );
     let raw_pixels = dyn_container_img.raw_pixels();
     container_img.raw_pixels = raw_pixels;
 }
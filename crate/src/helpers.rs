@@ -1,8 +1,9 @@
 //! Helpers functions for image processing, writing images, etc.,
 
-use crate::{PhotonImage, Rgb};
-use base64::{decode, encode};
-use image::{DynamicImage, GenericImageView, ImageBuffer};
+use crate::compositing::{blend_pixel, BlendMode};
+use crate::{Error, PhotonImage, Result, Rgb};
+use base64::decode;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
 use wasm_bindgen::prelude::*;
 
 // Gets the square distance between two colours
@@ -12,17 +13,24 @@ pub fn square_distance(color1: Rgb, color2: Rgb) -> i32 {
     return i32::pow(r1 - r2, 2) + i32::pow(g1 - g2, 2) + i32::pow(b1 - b2, 2);
 }
 
-pub fn open_image(img_path: &'static str) -> DynamicImage {
-    let img = image::open(img_path).unwrap();
-    return img;
+// Blend a colour over a pixel, which may itself be translucent. `alpha` is the opacity the
+// colour is drawn with; the colour's own alpha channel is ignored.
+pub(crate) fn blend_over(pixel: Rgba<u8>, color: Rgba<u8>, alpha: f32) -> Rgba<u8> {
+    let color = Rgba([color[0], color[1], color[2], 255]);
+    blend_pixel(pixel, color, BlendMode::Normal, alpha)
 }
 
-pub fn open_dyn_image(img_path: &'static str) -> DynamicImage {
-    let img = image::open(img_path).unwrap();
-    return img;
+pub fn open_image(img_path: &str) -> Result<DynamicImage> {
+    let img = image::open(img_path)?;
+    Ok(img)
 }
 
-pub fn save_dyn_image(img: DynamicImage, filtered_img_path: &str) {
+pub fn open_dyn_image(img_path: &str) -> Result<DynamicImage> {
+    let img = image::open(img_path)?;
+    Ok(img)
+}
+
+pub fn save_dyn_image(img: DynamicImage, filtered_img_path: &str) -> Result<()> {
     // let raw_pixels = img.raw_pixels;
     // let width = img.width;
     // let height = img.height;
@@ -30,11 +38,13 @@ pub fn save_dyn_image(img: DynamicImage, filtered_img_path: &str) {
     // let img_buffer = ImageBuffer::from_vec(width, height, raw_pixels).unwrap();
     // let dynimage = image::ImageRgba8(img_buffer);
 
-    img.save(filtered_img_path).unwrap();
+    img.save(filtered_img_path)?;
+    Ok(())
 }
 
-pub fn save_image(img: DynamicImage, filtered_img_path: &str) {
-    img.save(filtered_img_path).unwrap();
+pub fn save_image(img: DynamicImage, filtered_img_path: &str) -> Result<()> {
+    img.save(filtered_img_path)?;
+    Ok(())
 }
 
 pub fn get_pixels(img: DynamicImage) -> Vec<u8> {
@@ -43,26 +53,35 @@ pub fn get_pixels(img: DynamicImage) -> Vec<u8> {
     raw_pixels
 }
 
-pub fn dyn_image_from_raw(photon_image: &PhotonImage) -> DynamicImage {
+pub fn dyn_image_from_raw(photon_image: &PhotonImage) -> Result<DynamicImage> {
     // convert a vec of raw pixels (as u8s) to a DynamicImage type
-    let _len_vec = photon_image.raw_pixels.len() as u128;
     let raw_pixels = &photon_image.raw_pixels;
+    let expected = photon_image.width as usize * photon_image.height as usize * 4;
     let img_buffer =
-        ImageBuffer::from_vec(photon_image.width, photon_image.height, raw_pixels.to_vec())
-            .unwrap();
+        ImageBuffer::from_vec(photon_image.width, photon_image.height, raw_pixels.to_vec()).ok_or(
+            Error::BufferSizeMismatch {
+                expected,
+                actual: raw_pixels.len(),
+            },
+        )?;
     let dynimage = image::DynamicImage::ImageRgba8(img_buffer);
-    dynimage
+    Ok(dynimage)
 }
 
-#[wasm_bindgen]
-pub fn base64_to_image(base64: &str) -> Vec<u8> {
-    let a = "hello world";
-    let b = "aGVsbG8gd29ybGQ=";
+/// Decode base64 into the bytes of an image file.
+///
+/// Returns an error if the string isn't valid base64.
+pub fn base64_to_image(base64: &str) -> Result<Vec<u8>> {
+    let res = decode(base64)?;
 
-    assert_eq!(encode(a), b);
-    let res = decode(base64).unwrap();
+    Ok(res)
+}
 
-    return res;
+/// Decode base64 into the bytes of an image file, for use from JavaScript. See
+/// [`base64_to_image`].
+#[wasm_bindgen(js_name = base64_to_image)]
+pub fn base64_to_image_web(base64: &str) -> std::result::Result<Vec<u8>, JsValue> {
+    Ok(base64_to_image(base64)?)
 }
 
 pub fn dyn_to_photonimg(dynimage: &DynamicImage) -> PhotonImage {
@@ -72,3 +91,17 @@ pub fn dyn_to_photonimg(dynimage: &DynamicImage) -> PhotonImage {
         height: dynimage.height(),
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_base64_is_an_error() {
+        assert_eq!(base64_to_image("aGVsbG8=").unwrap(), b"hello");
+        assert!(matches!(
+            base64_to_image("not base64!"),
+            Err(Error::Base64(_))
+        ));
+    }
+}
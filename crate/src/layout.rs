@@ -0,0 +1,966 @@
+//! Lay out paragraphs of text into lines, using the real advances of shaped glyphs.
+//!
+//! Text is wrapped to a maximum width at spaces, and optionally within words using a
+//! [`Hyphenator`] or soft hyphens (U+00AD). The resulting [`LineBox`]es can be drawn with
+//! [`draw_paragraph`], or used to place other elements around the text.
+
+use crate::emoji::draw_shaped_emoji;
+use crate::fonts::FontFace;
+use crate::helpers::blend_over;
+use crate::shaping::{draw_shaped_text, shape_text, ShapedText, TextDirection};
+use crate::{Error, Result};
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use rusttype::{GlyphId, OutlineBuilder, Scale};
+use std::ops::Range;
+use std::rc::Rc;
+
+const SOFT_HYPHEN: char = '\u{ad}';
+
+/// How lines are aligned within a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    /// Align to the edge text starts from: the left for left-to-right text, and the right
+    /// for right-to-left text.
+    #[default]
+    Start,
+    /// Align to the edge text ends at.
+    End,
+    Left,
+    Right,
+    Centre,
+    /// Stretch the spaces in each line so it fills the paragraph's width. The last line of
+    /// each paragraph is aligned to the start.
+    Justify,
+}
+
+impl TextAlign {
+    /// Find an alignment by name, such as "start" or "centre". The spelling "center" is
+    /// accepted too.
+    pub fn from_name(name: &str) -> Option<TextAlign> {
+        match name.trim().to_lowercase().as_str() {
+            "start" => Some(TextAlign::Start),
+            "end" => Some(TextAlign::End),
+            "left" => Some(TextAlign::Left),
+            "right" => Some(TextAlign::Right),
+            "centre" | "center" => Some(TextAlign::Centre),
+            "justify" => Some(TextAlign::Justify),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the places a word can be broken across two lines with a hyphen.
+///
+/// Any `Fn(&str) -> Vec<usize>` can be used as a hyphenator.
+pub trait Hyphenator {
+    /// Byte offsets within `word` where it may be split. A hyphen is added to the end
+    /// of the part before the split.
+    fn hyphenate(&self, word: &str) -> Vec<usize>;
+}
+
+impl<F: Fn(&str) -> Vec<usize>> Hyphenator for F {
+    fn hyphenate(&self, word: &str) -> Vec<usize> {
+        self(word)
+    }
+}
+
+/// The point of a paragraph that is placed at the given coordinates when it is drawn rotated.
+/// The paragraph is rotated around this point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAnchor {
+    /// The top-left corner of the paragraph.
+    #[default]
+    TopLeft,
+    /// The centre of the paragraph.
+    Centre,
+    /// The left end of the first line's baseline.
+    Baseline,
+}
+
+/// Options for laying out a paragraph.
+///
+/// ```
+/// use gdl::layout::{ParagraphOptions, TextAlign};
+///
+/// let options = ParagraphOptions::new()
+///     .max_width(600.0)
+///     .line_height(1.4)
+///     .letter_spacing(2.0)
+///     .align(TextAlign::Centre);
+/// ```
+#[derive(Clone)]
+pub struct ParagraphOptions {
+    /// Width lines are wrapped to, in pixels. Lines are never wrapped if this is `None`.
+    pub max_width: Option<f32>,
+    /// Distance between the tops of consecutive lines, as a multiple of the font size.
+    pub line_height: f32,
+    /// Extra space added after each glyph, in pixels. Can be negative.
+    pub letter_spacing: f32,
+    pub align: TextAlign,
+    pub direction: TextDirection,
+    /// Used to break words that don't fit on a line.
+    pub hyphenator: Option<Rc<dyn Hyphenator>>,
+}
+
+impl Default for ParagraphOptions {
+    fn default() -> ParagraphOptions {
+        ParagraphOptions {
+            max_width: None,
+            line_height: 1.2,
+            letter_spacing: 0.0,
+            align: TextAlign::Start,
+            direction: TextDirection::Auto,
+            hyphenator: None,
+        }
+    }
+}
+
+impl ParagraphOptions {
+    /// Options for unwrapped, start-aligned text with a line height of 1.2.
+    pub fn new() -> ParagraphOptions {
+        ParagraphOptions::default()
+    }
+
+    /// Set the width lines are wrapped to, in pixels.
+    pub fn max_width(mut self, max_width: f32) -> ParagraphOptions {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Set the line height, as a multiple of the font size.
+    pub fn line_height(mut self, line_height: f32) -> ParagraphOptions {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Set the extra space added after each glyph, in pixels.
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> ParagraphOptions {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Set how lines are aligned.
+    pub fn align(mut self, align: TextAlign) -> ParagraphOptions {
+        self.align = align;
+        self
+    }
+
+    /// Set the base direction of the text.
+    pub fn direction(mut self, direction: TextDirection) -> ParagraphOptions {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the hyphenator used to break words that don't fit on a line.
+    pub fn hyphenator<H: Hyphenator + 'static>(mut self, hyphenator: H) -> ParagraphOptions {
+        self.hyphenator = Some(Rc::new(hyphenator));
+        self
+    }
+}
+
+/// A single laid out line of a paragraph.
+///
+/// Positions are in pixels, relative to the top-left corner of the paragraph.
+#[derive(Debug, Clone)]
+pub struct LineBox {
+    /// The text drawn on the line, including a hyphen if the line ends in a broken word.
+    pub text: String,
+    /// Byte range of the line within the paragraph's text.
+    pub range: Range<usize>,
+    /// Whether the line ends with a word broken by hyphenation.
+    pub hyphenated: bool,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Y-coordinate of the line's baseline.
+    pub baseline: f32,
+    /// The line's glyphs, with letter-spacing and justification applied.
+    pub shaped: ShapedText,
+}
+
+/// A paragraph of text laid out into lines.
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    pub lines: Vec<LineBox>,
+    /// Width of the paragraph: the maximum width if one was given, otherwise the widest line.
+    pub width: f32,
+    pub height: f32,
+    /// The resolved direction of the paragraph.
+    pub direction: TextDirection,
+}
+
+impl Paragraph {
+    /// Width of the widest line, which can be wider than the paragraph if a word overflows.
+    pub fn widest_line(&self) -> f32 {
+        self.lines.iter().map(|line| line.width).fold(0.0, f32::max)
+    }
+}
+
+/// Text laid out at the largest size that fits a box.
+///
+/// `P` is the laid out text: a [`Paragraph`], or a [`crate::rich_text::RichParagraph`].
+#[derive(Debug, Clone)]
+pub struct FittedText<P = Paragraph> {
+    /// The font size chosen.
+    pub font_size: f32,
+    pub paragraph: P,
+    /// How far the text extends past the right of the box, in pixels.
+    pub overflow_x: f32,
+    /// How far the text extends past the bottom of the box, in pixels.
+    pub overflow_y: f32,
+}
+
+impl<P> FittedText<P> {
+    /// Whether the text doesn't fit the box, even at the minimum font size.
+    pub fn overflows(&self) -> bool {
+        self.overflow_x > 0.0 || self.overflow_y > 0.0
+    }
+}
+
+/// Lay out text into lines.
+///
+/// Lines are broken at newlines, and wherever the next word would make the line wider than
+/// `options.max_width`. A word that doesn't fit on a line by itself is hyphenated if
+/// possible, and otherwise left to overflow.
+///
+/// ### Arguments
+/// * `face` - The font face to lay out with.
+/// * `text` - Text to lay out.
+/// * `font_size` - Height of the font in pixels.
+/// * `options` - Wrapping, spacing and alignment options.
+pub fn layout_paragraph(
+    face: &FontFace,
+    text: &str,
+    font_size: f32,
+    options: &ParagraphOptions,
+) -> Paragraph {
+    let direction = options.direction.resolve(text);
+    let mut breaker = LineBreaker {
+        face,
+        text,
+        font_size,
+        options,
+        direction,
+        lines: Vec::new(),
+    };
+
+    let mut start = 0;
+    for hard_line in text.split('\n') {
+        breaker.break_line(start, start + hard_line.len());
+        start += hard_line.len() + 1;
+    }
+
+    let lines = breaker.lines;
+    let line_height = options.line_height * font_size;
+    let width = options.max_width.unwrap_or_else(|| {
+        lines
+            .iter()
+            .map(|(_, _, shaped, _)| shaped.width)
+            .fold(0.0, f32::max)
+    });
+
+    let line_count = lines.len();
+    let mut line_boxes = Vec::with_capacity(line_count);
+    for (i, (range, hyphenated, mut shaped, ends_paragraph)) in lines.into_iter().enumerate() {
+        let line_text = line_text(text, &range, hyphenated);
+        let align = match (options.align, ends_paragraph || i + 1 == line_count) {
+            (TextAlign::Justify, true) => TextAlign::Start,
+            (align, _) => align,
+        };
+        if align == TextAlign::Justify {
+            justify(&mut shaped, &line_text, width);
+        }
+
+        let x = match (align, direction) {
+            (TextAlign::Left, _)
+            | (TextAlign::Start, TextDirection::LeftToRight)
+            | (TextAlign::End, TextDirection::RightToLeft)
+            | (TextAlign::Justify, _) => 0.0,
+            (TextAlign::Centre, _) => (width - shaped.width) / 2.0,
+            _ => width - shaped.width,
+        };
+        let y = i as f32 * line_height;
+        // Share any extra leading equally above and below the glyphs.
+        let baseline = y + (line_height - font_size) / 2.0 + shaped.ascent;
+
+        line_boxes.push(LineBox {
+            text: line_text,
+            range,
+            hyphenated,
+            x,
+            y,
+            width: shaped.width,
+            height: line_height,
+            baseline,
+            shaped,
+        });
+    }
+
+    Paragraph {
+        lines: line_boxes,
+        width,
+        height: line_count as f32 * line_height,
+        direction,
+    }
+}
+
+/// Find the largest font size at which text, wrapped to `width`, fits within `height`.
+///
+/// The size is found by a binary search between `min_size` and `max_size`, to within half a
+/// pixel. If the text doesn't fit at `min_size`, it is laid out at `min_size` and the amount it
+/// overflows the box by is reported. `options.max_width` is replaced by `width`.
+///
+/// ### Arguments
+/// * `face` - The font face to lay out with.
+/// * `text` - Text to lay out.
+/// * `width` - Width of the box, in pixels.
+/// * `height` - Height of the box, in pixels.
+/// * `min_size` - Smallest font size that can be used.
+/// * `max_size` - Largest font size that can be used.
+/// * `options` - Spacing and alignment options.
+///
+/// Returns [`Error::InvalidFontSize`] if either size isn't a finite, positive number.
+pub fn fit_paragraph(
+    face: &FontFace,
+    text: &str,
+    width: f32,
+    height: f32,
+    min_size: f32,
+    max_size: f32,
+    options: &ParagraphOptions,
+) -> Result<FittedText> {
+    let options = options.clone().max_width(width);
+    fit_font_size(width, height, min_size, max_size, |font_size| {
+        let paragraph = layout_paragraph(face, text, font_size, &options);
+        let (widest, height) = (paragraph.widest_line(), paragraph.height);
+        (paragraph, widest, height)
+    })
+}
+
+// Binary search for the largest font size at which text fits in a box. `layout` lays the text
+// out at a size, returning it along with its width and height.
+pub(crate) fn fit_font_size<P, F: Fn(f32) -> (P, f32, f32)>(
+    width: f32,
+    height: f32,
+    min_size: f32,
+    max_size: f32,
+    layout: F,
+) -> Result<FittedText<P>> {
+    let valid = |size: f32| size.is_finite() && size > 0.0;
+    if !valid(min_size) || !valid(max_size) {
+        return Err(Error::InvalidFontSize { min_size, max_size });
+    }
+
+    let fit = |font_size: f32| {
+        let (paragraph, text_width, text_height) = layout(font_size);
+        FittedText {
+            font_size,
+            overflow_x: (text_width - width).max(0.0),
+            overflow_y: (text_height - height).max(0.0),
+            paragraph,
+        }
+    };
+
+    let largest = fit(max_size);
+    if !largest.overflows() || max_size <= min_size {
+        return Ok(largest);
+    }
+    let mut best = fit(min_size);
+    if best.overflows() {
+        return Ok(best);
+    }
+
+    let (mut low, mut high) = (min_size, max_size);
+    while high - low > 0.5 {
+        let mid = (low + high) / 2.0;
+        let fitted = fit(mid);
+        if fitted.overflows() {
+            high = mid;
+        } else {
+            low = mid;
+            best = fitted;
+        }
+    }
+    Ok(best)
+}
+
+/// Draw a laid out paragraph onto an image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `face` - The font face the paragraph was laid out with.
+/// * `paragraph` - The paragraph.
+/// * `x` - X-coordinate of the left of the paragraph.
+/// * `y` - Y-coordinate of the top of the paragraph.
+/// * `color` - Text colour.
+pub fn draw_paragraph(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    paragraph: &Paragraph,
+    x: i32,
+    y: i32,
+    color: Rgba<u8>,
+) {
+    for line in &paragraph.lines {
+        draw_shaped_text(
+            image,
+            face,
+            &line.shaped,
+            x + line.x.round() as i32,
+            y + (line.baseline - line.shaped.ascent).round() as i32,
+            color,
+        );
+    }
+}
+
+/// Draw a laid out paragraph onto an image, rotated by any angle.
+///
+/// The glyph outlines are rotated and anti-aliased directly onto the image, so nothing is
+/// cropped and the pixels underneath show through where the colour is translucent.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `face` - The font face the paragraph was laid out with.
+/// * `paragraph` - The laid out paragraph.
+/// * `x` - X-coordinate the anchor is placed at.
+/// * `y` - Y-coordinate the anchor is placed at.
+/// * `angle` - Clockwise rotation, in degrees.
+/// * `anchor` - The point of the paragraph placed at `(x, y)`, which it is rotated around.
+/// * `color` - Text colour. Its alpha channel sets the opacity of the text.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_paragraph_rotated(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    paragraph: &Paragraph,
+    x: f32,
+    y: f32,
+    angle: f32,
+    anchor: TextAnchor,
+    color: Rgba<u8>,
+) {
+    let (anchor_x, anchor_y) = match anchor {
+        TextAnchor::TopLeft => (0.0, 0.0),
+        TextAnchor::Centre => (
+            paragraph.width.max(paragraph.widest_line()) / 2.0,
+            paragraph.height / 2.0,
+        ),
+        TextAnchor::Baseline => paragraph
+            .lines
+            .first()
+            .map_or((0.0, 0.0), |line| (line.x, line.baseline)),
+    };
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // Maps a point relative to the paragraph onto the image.
+    let transform = |offset_x: f32, offset_y: f32| {
+        [
+            cos,
+            -sin,
+            sin,
+            cos,
+            x + offset_x * cos - offset_y * sin,
+            y + offset_x * sin + offset_y * cos,
+        ]
+    };
+
+    for line in &paragraph.lines {
+        let top = line.baseline - line.shaped.ascent;
+        let opacity = color[3] as f32 / 255.0;
+        draw_shaped_emoji(
+            image,
+            &line.shaped,
+            transform(line.x - anchor_x, top - anchor_y),
+            opacity,
+        );
+    }
+
+    let glyphs = paragraph.lines.iter().flat_map(|line| {
+        line.shaped
+            .glyphs
+            .iter()
+            .filter(|glyph| glyph.image.is_none())
+            .map(move |glyph| {
+                let glyph_x = line.x + glyph.x - anchor_x;
+                let glyph_y = line.baseline + glyph.y - anchor_y;
+                (
+                    GlyphId(glyph.id),
+                    line.shaped.scale,
+                    transform(glyph_x, glyph_y),
+                )
+            })
+    });
+    draw_glyphs_transformed(image, face, glyphs, color);
+}
+
+// Draw glyphs, each mapped onto the image by its own affine transform, given as
+// `[a, b, c, d, e, f]` for `x' = a·x + b·y + e` and `y' = c·x + d·y + f` from a point
+// relative to the glyph's origin on the baseline. The outlines are anti-aliased together, so
+// glyphs that touch don't leave seams.
+pub(crate) fn draw_glyphs_transformed<G: IntoIterator<Item = (GlyphId, Scale, [f32; 6])>>(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    glyphs: G,
+    color: Rgba<u8>,
+) {
+    let mut outline = TransformedOutline {
+        segments: Vec::new(),
+        start: point(0.0, 0.0),
+        last: point(0.0, 0.0),
+        transform: [0.0; 6],
+    };
+    for (id, scale, transform) in glyphs {
+        outline.transform = transform;
+        face.font()
+            .glyph(id)
+            .scaled(scale)
+            .build_outline(&mut outline);
+    }
+    if outline.segments.is_empty() {
+        return;
+    }
+
+    // Rasterize only the part of the image covered by the text.
+    let (width, height) = image.dimensions();
+    let points = outline.segments.iter().flat_map(|segment| match segment {
+        Segment::Line(p0, p1) => vec![*p0, *p1],
+        Segment::Quad(p0, p1, p2) => vec![*p0, *p1, *p2],
+        Segment::Cubic(p0, p1, p2, p3) => vec![*p0, *p1, *p2, *p3],
+    });
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for p in points {
+        min_x = min_x.min(p.x);
+        min_y = min_y.min(p.y);
+        max_x = max_x.max(p.x);
+        max_y = max_y.max(p.y);
+    }
+    let min_x = min_x.floor().max(0.0);
+    let min_y = min_y.floor().max(0.0);
+    let max_x = max_x.ceil().min(width as f32);
+    let max_y = max_y.ceil().min(height as f32);
+    if min_x >= max_x || min_y >= max_y {
+        return;
+    }
+
+    // Segments outside the rasterizer are clamped to its edges, which keeps the winding
+    // of the visible part correct.
+    let clamp = |p: Point| {
+        point(
+            (p.x - min_x).clamp(0.0, max_x - min_x),
+            (p.y - min_y).clamp(0.0, max_y - min_y),
+        )
+    };
+    let mut rasterizer = Rasterizer::new((max_x - min_x) as usize, (max_y - min_y) as usize);
+    for segment in &outline.segments {
+        match *segment {
+            Segment::Line(p0, p1) => rasterizer.draw_line(clamp(p0), clamp(p1)),
+            Segment::Quad(p0, p1, p2) => rasterizer.draw_quad(clamp(p0), clamp(p1), clamp(p2)),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                rasterizer.draw_cubic(clamp(p0), clamp(p1), clamp(p2), clamp(p3))
+            }
+        }
+    }
+
+    let opacity = color[3] as f32 / 255.0;
+    rasterizer.for_each_pixel_2d(|px, py, coverage| {
+        let alpha = coverage.min(1.0) * opacity;
+        if alpha <= 0.0 {
+            return;
+        }
+        let (image_x, image_y) = (px + min_x as u32, py + min_y as u32);
+        let pixel = image.get_pixel(image_x, image_y);
+        image.put_pixel(image_x, image_y, blend_over(pixel, color, alpha));
+    });
+}
+
+enum Segment {
+    Line(Point, Point),
+    Quad(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+// Collects a glyph's outline as segments, mapped through an affine transform.
+struct TransformedOutline {
+    segments: Vec<Segment>,
+    start: Point,
+    last: Point,
+    // [a, b, c, d, e, f] maps (x, y) to (a * x + b * y + e, c * x + d * y + f).
+    transform: [f32; 6],
+}
+
+impl TransformedOutline {
+    fn map(&self, x: f32, y: f32) -> Point {
+        let [a, b, c, d, e, f] = self.transform;
+        point(a * x + b * y + e, c * x + d * y + f)
+    }
+}
+
+impl OutlineBuilder for TransformedOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.map(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.map(x, y);
+        self.segments.push(Segment::Line(self.last, p));
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p1, p) = (self.map(x1, y1), self.map(x, y));
+        self.segments.push(Segment::Quad(self.last, p1, p));
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1, p2, p) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.segments.push(Segment::Cubic(self.last, p1, p2, p));
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.segments.push(Segment::Line(self.last, self.start));
+        }
+        self.last = self.start;
+    }
+}
+
+// A line's byte range, whether it ends in a hyphen, its shaped glyphs, and whether it is
+// the last line before a newline.
+type BrokenLine = (Range<usize>, bool, ShapedText, bool);
+
+struct LineBreaker<'a> {
+    face: &'a FontFace,
+    text: &'a str,
+    font_size: f32,
+    options: &'a ParagraphOptions,
+    direction: TextDirection,
+    lines: Vec<BrokenLine>,
+}
+
+impl<'a> LineBreaker<'a> {
+    // Break the text between `start` and `end`, which contains no newlines, into lines.
+    fn break_line(&mut self, start: usize, end: usize) {
+        let max_width = match self.options.max_width {
+            Some(max_width) => max_width,
+            None => {
+                let shaped = self.shape(start..end, false);
+                self.lines.push((start..end, false, shaped, true));
+                return;
+            }
+        };
+
+        let words = word_ranges(self.text, start, end);
+        if words.is_empty() {
+            let shaped = self.shape(start..start, false);
+            self.lines.push((start..start, false, shaped, true));
+            return;
+        }
+
+        // Words are shaped once each, and a line's width is the sum of its words and the
+        // spaces between them. Lines are only shaped as a whole once they are complete.
+        let spacing = self.options.letter_spacing;
+        let mut line_start = None;
+        let mut line_end = start;
+        let mut line_width = 0.0;
+        for word in words {
+            let mut word_start = word.start;
+            let mut word_width = self.width(word.clone());
+            loop {
+                let width = match line_start {
+                    Some(_) => {
+                        let gap = self.width(line_end..word_start);
+                        line_width + gap + word_width + 2.0 * spacing
+                    }
+                    None => word_width,
+                };
+                if width <= max_width {
+                    line_start = Some(line_start.unwrap_or(word_start));
+                    line_end = word.end;
+                    line_width = width;
+                    break;
+                }
+
+                // Fit as much of the word as possible onto the line with a hyphen.
+                let from = line_start.unwrap_or(word_start);
+                if let Some((split, shaped)) = self.hyphenate(from, word_start..word.end, max_width)
+                {
+                    self.lines.push((from..split, true, shaped, false));
+                    line_start = None;
+                    word_start = split;
+                    word_width = self.width(split..word.end);
+                    continue;
+                }
+
+                match line_start {
+                    // Move the word onto the next line.
+                    Some(line_start_offset) => {
+                        let range = line_start_offset..line_end;
+                        let shaped = self.shape(range.clone(), false);
+                        self.lines.push((range, false, shaped, false));
+                        line_start = None;
+                    }
+                    // The word doesn't fit on a line by itself, so let it overflow.
+                    None => {
+                        line_start = Some(word_start);
+                        line_end = word.end;
+                        line_width = word_width;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(line_start) = line_start {
+            let shaped = self.shape(line_start..line_end, false);
+            self.lines.push((line_start..line_end, false, shaped, true));
+        } else if let Some(last) = self.lines.last_mut() {
+            last.3 = true;
+        }
+    }
+
+    // Find the longest part of a word which fits on the line starting at `line_start` when
+    // followed by a hyphen, returning the offset the word is split at.
+    fn hyphenate(
+        &self,
+        line_start: usize,
+        word: Range<usize>,
+        max_width: f32,
+    ) -> Option<(usize, ShapedText)> {
+        let word_text = &self.text[word.clone()];
+        let mut splits: Vec<usize> = word_text
+            .char_indices()
+            .filter(|&(_, c)| c == SOFT_HYPHEN)
+            .map(|(i, _)| i)
+            .collect();
+        if let Some(hyphenator) = &self.options.hyphenator {
+            splits.extend(hyphenator.hyphenate(word_text));
+        }
+        splits.sort_unstable();
+        splits.dedup();
+
+        splits
+            .into_iter()
+            .rev()
+            .filter(|&split| split > 0 && split < word_text.len())
+            .filter(|&split| word_text.is_char_boundary(split))
+            .map(|split| word.start + split)
+            .find_map(|split| {
+                let shaped = self.shape(line_start..split, true);
+                if shaped.width <= max_width {
+                    Some((split, shaped))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn width(&self, range: Range<usize>) -> f32 {
+        self.shape(range, false).width
+    }
+
+    fn shape(&self, range: Range<usize>, hyphenated: bool) -> ShapedText {
+        let text = line_text(self.text, &range, hyphenated);
+        let mut shaped = shape_text(self.face, &text, self.font_size, self.direction);
+        apply_letter_spacing(&mut shaped, self.options.letter_spacing);
+        shaped
+    }
+}
+
+// The text drawn for a line, with soft hyphens removed and a hyphen added if the line
+// ends in a broken word.
+fn line_text(text: &str, range: &Range<usize>, hyphenated: bool) -> String {
+    let mut line: String = text[range.clone()]
+        .chars()
+        .filter(|&c| c != SOFT_HYPHEN)
+        .collect();
+    if hyphenated {
+        line.push('-');
+    }
+    line
+}
+
+// Byte ranges of the words between `start` and `end`.
+fn word_ranges(text: &str, start: usize, end: usize) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (i, c) in text[start..end].char_indices() {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(ws)) => {
+                words.push(start + ws..start + i);
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(ws) = word_start {
+        words.push(start + ws..end);
+    }
+    words
+}
+
+// Add space after every glyph with an advance, leaving marks on their base glyphs.
+pub(crate) fn apply_letter_spacing(shaped: &mut ShapedText, letter_spacing: f32) {
+    if letter_spacing == 0.0 || shaped.glyphs.is_empty() {
+        return;
+    }
+    let mut offset = 0.0;
+    let mut spaced = 0;
+    for glyph in &mut shaped.glyphs {
+        glyph.x += offset;
+        if glyph.advance > 0.0 {
+            offset += letter_spacing;
+            spaced += 1;
+        }
+    }
+    // No spacing is added after the last glyph.
+    if spaced > 0 {
+        shaped.width += letter_spacing * (spaced - 1) as f32;
+    }
+}
+
+// Widen the spaces in a line so it is `width` wide.
+fn justify(shaped: &mut ShapedText, line_text: &str, width: f32) {
+    let is_space = |cluster: usize| line_text[cluster..].starts_with(char::is_whitespace);
+    let spaces = shaped
+        .glyphs
+        .iter()
+        .filter(|glyph| is_space(glyph.cluster))
+        .count();
+    if spaces == 0 || shaped.width >= width {
+        return;
+    }
+
+    let extra = (width - shaped.width) / spaces as f32;
+    let mut offset = 0.0;
+    for glyph in &mut shaped.glyphs {
+        glyph.x += offset;
+        if is_space(glyph.cluster) {
+            offset += extra;
+        }
+    }
+    shaped.width = width;
+}
+
+#[cfg(all(test, feature = "embedded-fonts"))]
+mod tests {
+    use super::*;
+    use crate::fonts::{FontDescriptor, FontRegistry};
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog and keeps on running";
+
+    fn lay_out(text: &str, options: &ParagraphOptions) -> Paragraph {
+        let fonts = FontRegistry::embedded();
+        let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+        layout_paragraph(face, text, 24.0, options)
+    }
+
+    #[test]
+    fn empty_text_has_one_empty_line() {
+        let paragraph = lay_out("", &ParagraphOptions::new().max_width(100.0));
+        assert_eq!(paragraph.lines.len(), 1);
+        assert_eq!(paragraph.lines[0].text, "");
+        assert_eq!(paragraph.widest_line(), 0.0);
+    }
+
+    #[test]
+    fn wrapped_lines_fit_the_width() {
+        for &spacing in &[0.0, 3.0] {
+            let options = ParagraphOptions::new()
+                .max_width(150.0)
+                .letter_spacing(spacing);
+            let paragraph = lay_out(TEXT, &options);
+            assert!(paragraph.lines.len() > 2);
+            for line in &paragraph.lines {
+                assert!(
+                    line.width <= 150.5,
+                    "{:?} is {}px wide",
+                    line.text,
+                    line.width
+                );
+            }
+            let words: Vec<&str> = paragraph
+                .lines
+                .iter()
+                .flat_map(|line| line.text.split_whitespace())
+                .collect();
+            assert_eq!(words, TEXT.split_whitespace().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn lines_are_filled_greedily() {
+        let paragraph = lay_out(TEXT, &ParagraphOptions::new().max_width(150.0));
+        for pair in paragraph.lines.windows(2) {
+            let joined = format!(
+                "{} {}",
+                pair[0].text,
+                pair[1].text.split_whitespace().next().unwrap()
+            );
+            let wider = lay_out(&joined, &ParagraphOptions::new());
+            assert!(wider.widest_line() > 150.0, "{:?} would have fit", joined);
+        }
+    }
+
+    #[test]
+    fn long_words_overflow_or_hyphenate() {
+        let options = ParagraphOptions::new().max_width(40.0);
+        let paragraph = lay_out("Extraordinarily", &options);
+        assert_eq!(paragraph.lines.len(), 1);
+        assert!(paragraph.widest_line() > 40.0);
+
+        let paragraph = lay_out("Extra\u{ad}ordinarily", &options.max_width(80.0));
+        assert_eq!(paragraph.lines.len(), 2);
+        assert!(paragraph.lines[0].hyphenated);
+        assert_eq!(paragraph.lines[0].text, "Extra-");
+    }
+
+    #[test]
+    fn newlines_always_break() {
+        let paragraph = lay_out("a\n\nb", &ParagraphOptions::new().max_width(500.0));
+        let lines: Vec<&str> = paragraph
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(lines, ["a", "", "b"]);
+    }
+
+    #[test]
+    fn baseline_anchor_is_the_start_of_the_first_line() {
+        let fonts = FontRegistry::embedded();
+        let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+        let options = ParagraphOptions::new()
+            .max_width(200.0)
+            .align(TextAlign::Right);
+        let paragraph = layout_paragraph(face, "Hi", 40.0, &options);
+        let line = &paragraph.lines[0];
+        assert!(line.x > 100.0);
+
+        let mut image = DynamicImage::new_rgba8(300, 100);
+        let color = Rgba([255, 255, 255, 255]);
+        draw_paragraph_rotated(
+            &mut image,
+            face,
+            &paragraph,
+            50.0,
+            60.0,
+            0.0,
+            TextAnchor::Baseline,
+            color,
+        );
+        let image = image.to_rgba8();
+        let ink: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[3] > 128)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let left = ink.iter().map(|&(x, _)| x).min().unwrap();
+        let bottom = ink.iter().map(|&(_, y)| y).max().unwrap();
+        assert!((50..60).contains(&left), "ink starts at {}", left);
+        assert!((57..62).contains(&bottom), "ink ends at {}", bottom);
+    }
+}
@@ -7,10 +7,7 @@
 //! To view a full demo of templates available, visit the [official website](https://silvia-odwyer.github.io/gdl).
 //!
 
-use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, RgbaImage};
-use palette::encoding::pixel::Pixel;
-use palette::rgb::LinSrgba;
-use palette::{FromColor, Gradient, Hue, IntoColor, Lch, Srgb, Srgba};
+use image::{DynamicImage, ImageBuffer};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::Clamped;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
@@ -66,12 +63,7 @@ impl PhotonImage {
 
     pub fn new_with_background(width: u32, height: u32, background_color: &Rgb) -> PhotonImage {
         // create a pixel
-        let pixel = image::Rgba([
-            background_color.r,
-            background_color.g,
-            background_color.b,
-            255,
-        ]);
+        let pixel = image::Rgba::from(background_color);
         let image_buffer = ImageBuffer::from_pixel(width, height, pixel);
         let rgba_img = image::DynamicImage::ImageRgba8(image_buffer);
 
@@ -94,36 +86,7 @@ impl PhotonImage {
     // }
 
     pub fn new_with_gradient(width: u32, height: u32) -> PhotonImage {
-        // create a pixel
-        let mut image = RgbaImage::new(width, height);
-        let grad1 = Gradient::new(vec![
-            LinSrgba::new(1.0, 0.1, 0.1, 1.0),
-            LinSrgba::new(0.1, 0.1, 1.0, 1.0),
-            LinSrgba::new(0.1, 1.0, 0.1, 1.0),
-        ]);
-
-        //The same colors and offsets as in grad1, but in a color space where the hue
-        // is a component
-        let _grad3 = Gradient::new(vec![
-            Lch::from_color(LinSrgba::new(1.0, 0.1, 0.1, 1.0)),
-            Lch::from_color(LinSrgba::new(0.1, 0.1, 1.0, 1.0)),
-            Lch::from_color(LinSrgba::new(0.1, 1.0, 0.1, 1.0)),
-        ]);
-
-        for (i, c1) in grad1.take(width as usize).enumerate() {
-            let c1 = Srgba::from_linear(c1).into_format().into_raw();
-            {
-                let mut sub_image = image.sub_image(i as u32, 0, 1, height);
-                let (width, height) = sub_image.dimensions();
-                for x in 0..width {
-                    for y in 0..height {
-                        sub_image.put_pixel(x, y, image::Rgba(c1));
-                    }
-                }
-            }
-        }
-        let rgba_img = image::DynamicImage::ImageRgba8(image);
-        let raw_pixels = rgba_img.to_bytes();
+        let raw_pixels = elements::create_gradient(width, height).to_bytes();
         return PhotonImage {
             raw_pixels: raw_pixels,
             width: width,
@@ -131,6 +94,17 @@ impl PhotonImage {
         };
     }
 
+    /// Create an image filled with a gradient preset, for use from JavaScript. See
+    /// [`PhotonImage::new_with_gradient_preset`].
+    #[wasm_bindgen(js_name = new_with_gradient_preset)]
+    pub fn new_with_gradient_preset_web(
+        width: u32,
+        height: u32,
+        name: &str,
+    ) -> std::result::Result<PhotonImage, JsValue> {
+        Ok(PhotonImage::new_with_gradient_preset(width, height, name)?)
+    }
+
     /// Create a new social media graphic.
     /// Available types include: linkedin_banner, pinterest, fb_ad, fb_post, instagram_post,
     /// twitter_header, twitter_post.
@@ -158,40 +132,19 @@ impl PhotonImage {
     }
 }
 
-/// Generate color schemes from a single input color.
-#[wasm_bindgen]
-#[derive(Debug)]
-pub struct ColorScheme {
-    main_color: Rgb,
-    analogous_swatch: Vec<Lch>,
-}
-
-#[wasm_bindgen]
-impl ColorScheme {
-    pub fn new(main_color: Rgb) -> ColorScheme {
-        let distance = 120.0;
-
-        let primary: Lch = Srgb::new(main_color.r, main_color.g, main_color.b)
-            .into_format::<f32>()
-            .into_linear()
-            .into_color();
-
-        let an_shift = 180.0 - (distance / 2.0);
-
-        let analogous_swatch = vec![primary.shift_hue(an_shift), primary.shift_hue(-an_shift)];
-
-        // gen secondary swatch
-
-        // gen rectangular swatch
-        return ColorScheme {
-            main_color: main_color,
-            analogous_swatch: analogous_swatch,
-        };
+impl PhotonImage {
+    /// Create an image filled with a gradient from the shared preset registry, such as
+    /// "pinkblue" or "flames".
+    ///
+    /// Returns [`Error::UnknownPreset`] if there is no gradient with that name.
+    pub fn new_with_gradient_preset(width: u32, height: u32, name: &str) -> Result<PhotonImage> {
+        let raw_pixels = elements::create_gradient_preset(width, height, name)?.to_bytes();
+        Ok(PhotonImage {
+            raw_pixels,
+            width,
+            height,
+        })
     }
-
-    /// Create a swatch image in PNG format of the colour swatches generated
-    /// from the main colour.
-    pub fn create_swatch_image() {}
 }
 
 /// Provides the image's height, width, and contains the image's raw pixels.
@@ -217,7 +170,7 @@ impl Font {
 
 /// Rgb color type.
 #[wasm_bindgen]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rgb {
     pub r: u8,
     pub g: u8,
@@ -227,8 +180,29 @@ pub struct Rgb {
 #[wasm_bindgen]
 impl Rgb {
     /// Create a new Rgb color.
-    pub fn new(&mut self, r: u8, g: u8, b: u8) -> Rgb {
-        return Rgb { r: r, g: g, b: b };
+    #[wasm_bindgen(constructor)]
+    pub fn new(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb { r, g, b }
+    }
+
+    /// Parse a CSS colour, for use from JavaScript. See [`Rgb::parse`].
+    #[wasm_bindgen(js_name = parse)]
+    pub fn parse_web(css: &str) -> std::result::Result<Rgb, JsValue> {
+        Ok(Rgb::parse(css)?)
+    }
+
+    /// The colour as a "#rrggbb" hex code.
+    pub fn to_hex(&self) -> String {
+        Rgba::from(*self).to_hex()
+    }
+}
+
+impl Rgb {
+    /// Parse a CSS colour, such as "#f12711" or "tomato", ignoring its alpha.
+    ///
+    /// Returns [`Error::InvalidColor`] if the string isn't a colour.
+    pub fn parse(css: &str) -> Result<Rgb> {
+        Ok(Rgba::parse(css)?.into())
     }
 }
 
@@ -240,7 +214,7 @@ pub fn draw_rect_web(ctx: CanvasRenderingContext2d) {
 
 // Called by the JS entry point to ensure that everything is working as expected
 #[wasm_bindgen]
-pub fn run() -> Result<(), JsValue> {
+pub fn run() -> std::result::Result<(), JsValue> {
     set_panic_hook();
 
     let window = web_sys::window().expect("No Window found, should have a Window");
@@ -264,26 +238,21 @@ pub fn run() -> Result<(), JsValue> {
 pub fn put_image_data(
     canvas: HtmlCanvasElement,
     ctx: CanvasRenderingContext2d,
-    mut new_image: PhotonImage,
-) {
+    new_image: PhotonImage,
+) -> std::result::Result<(), JsValue> {
     // Convert the raw pixels back to an ImageData object.
     let new_imgdata = ImageData::new_with_u8_clamped_array_and_sh(
-        Clamped(&mut new_image.raw_pixels),
+        Clamped(&new_image.raw_pixels),
         canvas.width(),
         canvas.height(),
-    );
+    )?;
 
     // Place the new imagedata onto the canvas
-    ctx.put_image_data(&new_imgdata.unwrap(), 0.0, 0.0).unwrap();
+    ctx.put_image_data(&new_imgdata, 0.0, 0.0)
 }
 
 pub fn new_with_background(width: u32, height: u32, background_color: &Rgb) -> DynamicImage {
-    let pixel = image::Rgba([
-        background_color.r,
-        background_color.g,
-        background_color.b,
-        255,
-    ]);
+    let pixel = image::Rgba::from(background_color);
     let image_buffer = ImageBuffer::from_pixel(width, height, pixel);
     let rgba_img = image::DynamicImage::ImageRgba8(image_buffer);
     return rgba_img;
@@ -293,14 +262,17 @@ pub fn new_with_background(width: u32, height: u32, background_color: &Rgb) -> D
 /// which can be used to filter or apply effects to the image
 #[wasm_bindgen]
 #[no_mangle]
-pub fn open_image(canvas: HtmlCanvasElement, ctx: CanvasRenderingContext2d) -> PhotonImage {
-    let imgdata = get_image_data(&canvas, &ctx);
+pub fn open_image(
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+) -> std::result::Result<PhotonImage, JsValue> {
+    let imgdata = get_image_data(&canvas, &ctx)?;
     let raw_pixels = to_raw_pixels(imgdata);
-    return PhotonImage {
+    Ok(PhotonImage {
         raw_pixels: raw_pixels,
         width: canvas.width(),
         height: canvas.height(),
-    };
+    })
 }
 
 /// Convert ImageData to raw pixels.
@@ -312,27 +284,23 @@ pub fn to_raw_pixels(imgdata: ImageData) -> Vec<u8> {
 
 /// Convert a PhotonImage to JS-compatible ImageData
 #[wasm_bindgen]
-pub fn to_image_data(photon_image: PhotonImage) -> ImageData {
-    let mut raw_pixels = photon_image.raw_pixels;
+pub fn to_image_data(photon_image: PhotonImage) -> std::result::Result<ImageData, JsValue> {
+    let raw_pixels = photon_image.raw_pixels;
     let width = photon_image.width;
     let height = photon_image.height;
-    let new_imgdata =
-        ImageData::new_with_u8_clamped_array_and_sh(Clamped(&mut raw_pixels), width, height)
-            .unwrap();
-
-    return new_imgdata;
+    ImageData::new_with_u8_clamped_array_and_sh(Clamped(&raw_pixels), width, height)
 }
 
 /// Get the ImageData from a 2D canvas context
 #[wasm_bindgen]
-pub fn get_image_data(canvas: &HtmlCanvasElement, ctx: &CanvasRenderingContext2d) -> ImageData {
+pub fn get_image_data(
+    canvas: &HtmlCanvasElement,
+    ctx: &CanvasRenderingContext2d,
+) -> std::result::Result<ImageData, JsValue> {
     let width = canvas.width();
     let height = canvas.height();
 
-    let data = ctx
-        .get_image_data(0.0, 0.0, width as f64, height as f64)
-        .unwrap();
-    return data;
+    ctx.get_image_data(0.0, 0.0, width as f64, height as f64)
 }
 
 fn set_panic_hook() {
@@ -342,11 +310,34 @@ fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+mod error;
+pub use color::Rgba;
+pub use color_scheme::ColorScheme;
+pub use error::{Error, Result};
+
 pub mod background;
 pub mod collage;
+pub mod color;
+pub mod color_scheme;
+pub mod compositing;
+pub mod contrast;
 pub mod diagrams;
+pub mod document;
 pub mod elements;
+pub mod emoji;
+pub mod extract;
+pub mod fonts;
+pub mod gradient;
 pub mod helpers;
+pub mod layout;
+pub mod metrics;
+pub mod palettes;
+pub mod path;
 pub mod presets;
 pub mod resize;
+pub mod rich_text;
+pub mod shaping;
+pub mod style;
+pub mod template;
 pub mod text;
+pub mod text_path;
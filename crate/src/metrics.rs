@@ -0,0 +1,217 @@
+//! Measure how large text will be when it is drawn.
+//!
+//! [`measure_text`] lays text out exactly as the drawing functions do, and reports its size in
+//! the same terms as the browser's
+//! [`TextMetrics`](https://developer.mozilla.org/en-US/docs/Web/API/TextMetrics), so designs can
+//! be positioned the same way natively and on a canvas.
+
+use crate::emoji::emoji_box;
+#[cfg(feature = "embedded-fonts")]
+use crate::fonts::with_embedded_fonts;
+use crate::fonts::{FontDescriptor, FontFace, FontRegistry};
+use crate::layout::{layout_paragraph, Paragraph, ParagraphOptions};
+use crate::Result;
+use rusttype::{GlyphId, Scale};
+use wasm_bindgen::prelude::*;
+
+/// The size of a piece of text.
+///
+/// Distances are in pixels, measured from the left edge of the text on the baseline of its
+/// first line. Bounding box distances are positive in the direction they are named after, so
+/// `actual_bounding_box_descent` is positive for glyphs that extend below the baseline.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextMetrics {
+    /// Advance width of the text: the widest line, or the maximum width if the text was wrapped.
+    pub width: f32,
+    /// Height of all the lines of the text, including line spacing.
+    pub height: f32,
+    /// Distance from the left edge to the leftmost inked pixel.
+    #[wasm_bindgen(js_name = actualBoundingBoxLeft)]
+    pub actual_bounding_box_left: f32,
+    /// Distance from the left edge to the rightmost inked pixel.
+    #[wasm_bindgen(js_name = actualBoundingBoxRight)]
+    pub actual_bounding_box_right: f32,
+    /// Distance from the baseline to the top of the highest glyph.
+    #[wasm_bindgen(js_name = actualBoundingBoxAscent)]
+    pub actual_bounding_box_ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest glyph.
+    #[wasm_bindgen(js_name = actualBoundingBoxDescent)]
+    pub actual_bounding_box_descent: f32,
+    /// The font's ascent: the distance from the baseline to the top of the line.
+    #[wasm_bindgen(js_name = fontBoundingBoxAscent)]
+    pub font_bounding_box_ascent: f32,
+    /// The font's descent: the distance from the baseline to the bottom of the line.
+    #[wasm_bindgen(js_name = fontBoundingBoxDescent)]
+    pub font_bounding_box_descent: f32,
+    /// Extra space the font recommends between the descent of one line and the ascent of the next.
+    #[wasm_bindgen(js_name = lineGap)]
+    pub line_gap: f32,
+}
+
+impl TextMetrics {
+    /// Width of the ink bounding box.
+    pub fn ink_width(&self) -> f32 {
+        self.actual_bounding_box_left + self.actual_bounding_box_right
+    }
+
+    /// Height of the ink bounding box.
+    pub fn ink_height(&self) -> f32 {
+        self.actual_bounding_box_ascent + self.actual_bounding_box_descent
+    }
+}
+
+/// Measure text as it would be drawn with the given font and layout options.
+///
+/// ### Arguments
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text to measure.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `font_size` - Height of the font in pixels.
+/// * `options` - Wrapping, spacing and alignment options, as passed to [`crate::text::draw_text_block`].
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+pub fn measure_text(
+    fonts: &FontRegistry,
+    text: &str,
+    font: &FontDescriptor,
+    font_size: f32,
+    options: &ParagraphOptions,
+) -> Result<TextMetrics> {
+    let face = fonts.face(font)?;
+    let paragraph = layout_paragraph(face, text, font_size, options);
+    Ok(measure_paragraph(face, &paragraph, font_size))
+}
+
+/// Measure a paragraph that has already been laid out.
+///
+/// ### Arguments
+/// * `face` - The font face the paragraph was laid out with.
+/// * `paragraph` - The laid out paragraph.
+/// * `font_size` - Height of the font in pixels.
+pub fn measure_paragraph(face: &FontFace, paragraph: &Paragraph, font_size: f32) -> TextMetrics {
+    let scale = Scale::uniform(font_size);
+    let v_metrics = face.font().v_metrics(scale);
+    let first_baseline = paragraph
+        .lines
+        .first()
+        .map_or(v_metrics.ascent, |line| line.baseline);
+
+    let mut ink: Option<(f32, f32, f32, f32)> = None;
+    for line in &paragraph.lines {
+        for glyph in &line.shaped.glyphs {
+            let bounds = match emoji_box(&line.shaped, glyph) {
+                Some((left, top, width, height)) => {
+                    let y = top - line.shaped.ascent;
+                    Some((left - glyph.x, y, left - glyph.x + width, y + height))
+                }
+                None => face
+                    .font()
+                    .glyph(GlyphId(glyph.id))
+                    .scaled(line.shaped.scale)
+                    .exact_bounding_box()
+                    .map(|bounds| (bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y)),
+            };
+            if let Some((left, top, right, bottom)) = bounds {
+                let x = line.x + glyph.x;
+                let y = line.baseline + glyph.y - first_baseline;
+                let (min_x, min_y) = (x + left, y + top);
+                let (max_x, max_y) = (x + right, y + bottom);
+                ink = Some(match ink {
+                    Some((x0, y0, x1, y1)) => {
+                        (x0.min(min_x), y0.min(min_y), x1.max(max_x), y1.max(max_y))
+                    }
+                    None => (min_x, min_y, max_x, max_y),
+                });
+            }
+        }
+    }
+    let (min_x, min_y, max_x, max_y) = ink.unwrap_or_default();
+
+    TextMetrics {
+        width: paragraph.width.max(paragraph.widest_line()),
+        height: paragraph.height,
+        actual_bounding_box_left: -min_x,
+        actual_bounding_box_right: max_x,
+        actual_bounding_box_ascent: -min_y,
+        actual_bounding_box_descent: max_y,
+        font_bounding_box_ascent: v_metrics.ascent,
+        font_bounding_box_descent: -v_metrics.descent,
+        line_gap: v_metrics.line_gap,
+    }
+}
+
+/// Measure text drawn with one of the embedded fonts, for use from JavaScript.
+///
+/// ### Arguments
+/// * `text` - Text to measure.
+/// * `font` - Name of the font, such as "Roboto" or "Roboto-Bold".
+/// * `font_size` - Height of the font in pixels.
+/// * `max_width` - Width to wrap the text to, if any.
+#[cfg(feature = "embedded-fonts")]
+#[wasm_bindgen]
+pub fn measure_text_web(
+    text: &str,
+    font: &str,
+    font_size: f32,
+    max_width: Option<f32>,
+) -> std::result::Result<TextMetrics, JsValue> {
+    let mut options = ParagraphOptions::new();
+    options.max_width = max_width;
+    with_embedded_fonts(|fonts| {
+        measure_text(
+            fonts,
+            text,
+            &FontDescriptor::from(font),
+            font_size,
+            &options,
+        )
+        .map_err(JsValue::from)
+    })
+}
+
+#[cfg(all(test, feature = "embedded-fonts"))]
+mod tests {
+    use super::*;
+
+    fn measure(text: &str, options: &ParagraphOptions) -> TextMetrics {
+        with_embedded_fonts(|fonts| {
+            measure_text(fonts, text, &FontDescriptor::new("Roboto"), 40.0, options).unwrap()
+        })
+    }
+
+    #[test]
+    fn empty_text_has_no_ink() {
+        let metrics = measure("", &ParagraphOptions::new());
+        assert_eq!(metrics.width, 0.0);
+        assert_eq!(metrics.ink_width(), 0.0);
+        assert_eq!(metrics.ink_height(), 0.0);
+        assert!(metrics.font_bounding_box_ascent > 0.0);
+    }
+
+    #[test]
+    fn descenders_extend_below_the_baseline() {
+        let flat = measure("ace", &ParagraphOptions::new());
+        let descending = measure("gyp", &ParagraphOptions::new());
+        assert!(flat.actual_bounding_box_descent < 1.0);
+        assert!(descending.actual_bounding_box_descent > 5.0);
+        assert!(flat.actual_bounding_box_ascent > 0.0);
+    }
+
+    #[test]
+    fn wrapped_text_reports_the_maximum_width() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let single = measure(text, &ParagraphOptions::new());
+        let wrapped = measure(text, &ParagraphOptions::new().max_width(200.0));
+        assert_eq!(wrapped.width, 200.0);
+        assert!(wrapped.height > single.height);
+        assert!(wrapped.ink_width() <= 200.0 + 1.0);
+    }
+
+    #[test]
+    fn missing_fonts_are_an_error() {
+        let fonts = FontRegistry::new();
+        let font = FontDescriptor::new("Roboto");
+        assert!(measure_text(&fonts, "text", &font, 40.0, &ParagraphOptions::new()).is_err());
+    }
+}
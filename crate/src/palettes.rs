@@ -0,0 +1,607 @@
+//! Named gradient and palette presets, shared by every function that takes a preset name.
+//!
+//! GDL comes with a set of built-in gradients, such as "pinkblue", "flames" and "ocean", which
+//! are resolved against a single registry whether they are drawn natively or on a canvas. More
+//! presets can be added in code, or loaded from a JSON or TOML file, and are then available by
+//! name everywhere. Names are matched ignoring case, spaces, hyphens and underscores, so
+//! "pink_blue", "Pink Blue" and "pinkblue" are the same preset.
+//!
+//! A preset file has a table of gradients and a table of palettes, both optional. Colours can be
+//! written any way [`Rgba`](crate::Rgba) parses them, as hex codes, CSS functions or names:
+//!
+//! ```
+//! use gdl::palettes;
+//!
+//! palettes::registry_mut()
+//!     .load_toml(
+//!         r##"
+//!         [gradients.sunset]
+//!         colors = ["#ff5e62", "#ff9966"]
+//!         angle = 45
+//!         interpolation = "oklab"
+//!
+//!         [gradients.spotlight]
+//!         shape = "radial"
+//!         center = [0.5, 0.3]
+//!         stops = [{ offset = 0.0, color = "#ffffffcc" }, { offset = 1.0, color = "#ffffff00" }]
+//!
+//!         [palettes]
+//!         brand = ["#1c92d2", "#f2fcfe", "#333"]
+//!         "##,
+//!     )
+//!     .unwrap();
+//!
+//! let registry = palettes::registry();
+//! assert!(registry.gradient("sunset").is_ok());
+//! assert_eq!(registry.palette("brand").unwrap().len(), 3);
+//! assert!(registry.gradient("no such gradient").is_err());
+//! ```
+
+use crate::gradient::{Gradient, GradientShape, Interpolation};
+use crate::{Error, Result};
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use wasm_bindgen::prelude::*;
+
+// Built-in two-colour gradients, drawn from left to right.
+const BUILTIN_GRADIENTS: [(&str, [[u8; 3]; 2], Interpolation); 18] = [
+    (
+        "pinkblue",
+        [[52, 148, 230], [236, 110, 173]],
+        Interpolation::Linear,
+    ),
+    (
+        "lemongrass",
+        [[52, 148, 230], [103, 178, 111]],
+        Interpolation::Linear,
+    ),
+    (
+        "pink_pastel",
+        [[239, 50, 217], [137, 255, 253]],
+        Interpolation::Linear,
+    ),
+    (
+        "mauve_pastel",
+        [[127, 127, 213], [137, 168, 231]],
+        Interpolation::Linear,
+    ),
+    (
+        "flames",
+        [[241, 39, 17], [245, 175, 25]],
+        Interpolation::Srgb,
+    ),
+    (
+        "pastel",
+        [[131, 96, 195], [46, 191, 145]],
+        Interpolation::Srgb,
+    ),
+    (
+        "pink",
+        [[221, 94, 137], [247, 187, 151]],
+        Interpolation::Srgb,
+    ),
+    (
+        "ocean",
+        [[76, 184, 196], [60, 211, 173]],
+        Interpolation::Srgb,
+    ),
+    (
+        "aquamarine",
+        [[26, 41, 128], [38, 208, 206]],
+        Interpolation::Srgb,
+    ),
+    (
+        "rosewater",
+        [[229, 93, 135], [95, 195, 228]],
+        Interpolation::Srgb,
+    ),
+    (
+        "zigzag",
+        [[60, 165, 92], [181, 172, 73]],
+        Interpolation::Srgb,
+    ),
+    (
+        "seaweed",
+        [[52, 143, 80], [86, 180, 211]],
+        Interpolation::Srgb,
+    ),
+    (
+        "tropics",
+        [[22, 160, 133], [244, 208, 63]],
+        Interpolation::Srgb,
+    ),
+    (
+        "green_blue",
+        [[103, 178, 111], [76, 162, 205]],
+        Interpolation::Srgb,
+    ),
+    (
+        "ibiza_sunset",
+        [[238, 9, 121], [255, 106, 0]],
+        Interpolation::Srgb,
+    ),
+    (
+        "telegram",
+        [[28, 146, 210], [242, 252, 254]],
+        Interpolation::Srgb,
+    ),
+    (
+        "digital_water",
+        [[116, 235, 213], [172, 182, 229]],
+        Interpolation::Srgb,
+    ),
+    (
+        "blue_coral",
+        [[54, 209, 220], [91, 134, 229]],
+        Interpolation::Srgb,
+    ),
+];
+
+/// A set of named gradients and colour palettes.
+///
+/// Most code uses the shared registry returned by [`registry`] and [`registry_mut`], which
+/// every function that takes a preset name resolves against, but a registry can also be
+/// created and used on its own.
+#[derive(Debug, Clone, Default)]
+pub struct PaletteRegistry {
+    gradients: BTreeMap<String, (String, Gradient)>,
+    palettes: BTreeMap<String, (String, Vec<Rgba<u8>>)>,
+}
+
+impl PaletteRegistry {
+    /// Create an empty registry.
+    pub fn new() -> PaletteRegistry {
+        PaletteRegistry {
+            gradients: BTreeMap::new(),
+            palettes: BTreeMap::new(),
+        }
+    }
+
+    /// Create a registry containing the gradients bundled with GDL.
+    pub fn builtin() -> PaletteRegistry {
+        let mut registry = PaletteRegistry::new();
+        for (name, [start, end], interpolation) in BUILTIN_GRADIENTS.iter() {
+            let gradient = Gradient::linear(0.0)
+                .colors(&[
+                    Rgba([start[0], start[1], start[2], 255]),
+                    Rgba([end[0], end[1], end[2], 255]),
+                ])
+                .interpolation(*interpolation);
+            registry.register_gradient(name, gradient);
+        }
+        // #667db6 → #0082c8 → #0082c8 → #667db6
+        let hydrogen = Gradient::linear(0.0).colors(&[
+            Rgba([102, 125, 182, 255]),
+            Rgba([0, 130, 200, 255]),
+            Rgba([0, 130, 200, 255]),
+            Rgba([102, 125, 182, 255]),
+        ]);
+        registry.register_gradient("hydrogen", hydrogen);
+        registry
+    }
+
+    /// Add a gradient under the given name, replacing any gradient with the same name.
+    pub fn register_gradient(&mut self, name: &str, gradient: Gradient) {
+        self.gradients
+            .insert(normalise(name), (name.to_string(), gradient));
+    }
+
+    /// Add a palette under the given name, replacing any palette with the same name.
+    pub fn register_palette(&mut self, name: &str, colors: Vec<Rgba<u8>>) {
+        self.palettes
+            .insert(normalise(name), (name.to_string(), colors));
+    }
+
+    /// Get the gradient with the given name.
+    ///
+    /// Returns [`Error::UnknownPreset`] if there is no gradient with that name.
+    pub fn gradient(&self, name: &str) -> Result<Gradient> {
+        self.gradients
+            .get(&normalise(name))
+            .map(|(_, gradient)| gradient.clone())
+            .ok_or_else(|| Error::UnknownPreset(name.to_string()))
+    }
+
+    /// Get the colours of the palette with the given name.
+    ///
+    /// A gradient's colours can also be used as a palette, so if there is no palette with
+    /// the name, the colours of the gradient with that name are returned instead.
+    /// Returns [`Error::UnknownPreset`] if there is neither.
+    pub fn palette(&self, name: &str) -> Result<Vec<Rgba<u8>>> {
+        let key = normalise(name);
+        if let Some((_, colors)) = self.palettes.get(&key) {
+            return Ok(colors.clone());
+        }
+        self.gradients
+            .get(&key)
+            .map(|(_, gradient)| gradient.stops().iter().map(|stop| stop.color).collect())
+            .ok_or_else(|| Error::UnknownPreset(name.to_string()))
+    }
+
+    /// Check whether a gradient with the given name exists.
+    pub fn contains_gradient(&self, name: &str) -> bool {
+        self.gradients.contains_key(&normalise(name))
+    }
+
+    /// Check whether a palette with the given name exists.
+    pub fn contains_palette(&self, name: &str) -> bool {
+        self.palettes.contains_key(&normalise(name))
+    }
+
+    /// Names of all gradients in the registry, in alphabetical order.
+    pub fn gradient_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .gradients
+            .values()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Names of all palettes in the registry, in alphabetical order.
+    pub fn palette_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .palettes
+            .values()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Add the gradients and palettes in a JSON document.
+    ///
+    /// Nothing is added if any preset in the document is invalid.
+    pub fn load_json(&mut self, json: &str) -> Result<()> {
+        let file: PresetFile =
+            serde_json::from_str(json).map_err(|err| Error::InvalidPreset(err.to_string()))?;
+        self.load(file)
+    }
+
+    /// Add the gradients and palettes in a TOML document.
+    ///
+    /// Nothing is added if any preset in the document is invalid.
+    pub fn load_toml(&mut self, toml: &str) -> Result<()> {
+        let file: PresetFile =
+            toml::from_str(toml).map_err(|err| Error::InvalidPreset(err.to_string()))?;
+        self.load(file)
+    }
+
+    /// Add the gradients and palettes in a `.json` or `.toml` file.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => self.load_toml(&contents),
+            _ => self.load_json(&contents),
+        }
+    }
+
+    fn load(&mut self, file: PresetFile) -> Result<()> {
+        let gradients = file
+            .gradients
+            .into_iter()
+            .map(|(name, spec)| match spec.build() {
+                Ok(gradient) => Ok((name, gradient)),
+                Err(reason) => Err(invalid(&name, &reason)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let palettes = file
+            .palettes
+            .into_iter()
+            .map(|(name, colors)| {
+                colors
+                    .iter()
+                    .map(|color| parse_color(color).map_err(|reason| invalid(&name, &reason)))
+                    .collect::<Result<Vec<_>>>()
+                    .map(|colors| (name, colors))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (name, gradient) in gradients {
+            self.register_gradient(&name, gradient);
+        }
+        for (name, colors) in palettes {
+            self.register_palette(&name, colors);
+        }
+        Ok(())
+    }
+}
+
+/// The registry shared by every function that takes a gradient or palette name.
+///
+/// It starts out containing the built-in presets.
+pub fn registry() -> RwLockReadGuard<'static, PaletteRegistry> {
+    shared()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The shared registry, for adding presets to it.
+pub fn registry_mut() -> RwLockWriteGuard<'static, PaletteRegistry> {
+    shared()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Get a gradient from the shared registry.
+pub fn gradient(name: &str) -> Result<Gradient> {
+    registry().gradient(name)
+}
+
+/// Get a palette from the shared registry.
+pub fn palette(name: &str) -> Result<Vec<Rgba<u8>>> {
+    registry().palette(name)
+}
+
+/// Names of the gradients in the shared registry, in alphabetical order.
+#[wasm_bindgen]
+pub fn gradient_preset_names() -> Vec<String> {
+    registry()
+        .gradient_names()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Names of the palettes in the shared registry, in alphabetical order.
+#[wasm_bindgen]
+pub fn palette_preset_names() -> Vec<String> {
+    registry()
+        .palette_names()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Add the gradients and palettes in a JSON document to the shared registry.
+///
+/// Returns [`Error::InvalidPreset`] if the document or any preset in it is invalid.
+pub fn load_presets_json(json: &str) -> Result<()> {
+    registry_mut().load_json(json)
+}
+
+/// Add the gradients and palettes in a TOML document to the shared registry.
+///
+/// Returns [`Error::InvalidPreset`] if the document or any preset in it is invalid.
+pub fn load_presets_toml(toml: &str) -> Result<()> {
+    registry_mut().load_toml(toml)
+}
+
+/// Add the gradients and palettes in a JSON document to the shared registry, for use from
+/// JavaScript.
+#[wasm_bindgen(js_name = load_presets_json)]
+pub fn load_presets_json_web(json: &str) -> std::result::Result<(), JsValue> {
+    Ok(load_presets_json(json)?)
+}
+
+/// Add the gradients and palettes in a TOML document to the shared registry, for use from
+/// JavaScript.
+#[wasm_bindgen(js_name = load_presets_toml)]
+pub fn load_presets_toml_web(toml: &str) -> std::result::Result<(), JsValue> {
+    Ok(load_presets_toml(toml)?)
+}
+
+fn shared() -> &'static RwLock<PaletteRegistry> {
+    static REGISTRY: OnceLock<RwLock<PaletteRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(PaletteRegistry::builtin()))
+}
+
+#[derive(Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    gradients: BTreeMap<String, GradientSpec>,
+    #[serde(default)]
+    palettes: BTreeMap<String, Vec<String>>,
+}
+
+// A gradient as it is written in a preset or template file.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct GradientSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shape: Option<String>,
+    #[serde(default)]
+    angle: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    center: Option<(f32, f32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    radius: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    colors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stops: Vec<StopSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interpolation: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StopSpec {
+    offset: f32,
+    color: String,
+}
+
+impl GradientSpec {
+    // Build the gradient, or say what is wrong with it.
+    pub(crate) fn build(&self) -> std::result::Result<Gradient, String> {
+        let (center_x, center_y) = self.center.unwrap_or((0.5, 0.5));
+        let shape = match self.shape.as_deref().map(normalise).as_deref() {
+            None | Some("linear") => GradientShape::Linear { angle: self.angle },
+            Some("radial") => GradientShape::Radial {
+                center: (center_x, center_y),
+                radius: self.radius.unwrap_or(1.0),
+            },
+            Some("conic") => GradientShape::Conic {
+                center: (center_x, center_y),
+                angle: self.angle,
+            },
+            Some(other) => return Err(format!("unknown shape \"{}\"", other)),
+        };
+        let interpolation = match self.interpolation.as_deref().map(normalise).as_deref() {
+            None | Some("srgb") => Interpolation::Srgb,
+            Some("linear") | Some("linearsrgb") => Interpolation::Linear,
+            Some("lch") => Interpolation::Lch,
+            Some("oklab") => Interpolation::Oklab,
+            Some(other) => return Err(format!("unknown interpolation \"{}\"", other)),
+        };
+
+        let colors = self
+            .colors
+            .iter()
+            .map(|color| parse_color(color))
+            .collect::<std::result::Result<Vec<_>, String>>()?;
+        let mut gradient = Gradient::new(shape)
+            .colors(&colors)
+            .interpolation(interpolation);
+        for stop in &self.stops {
+            gradient = gradient.stop(stop.offset, parse_color(&stop.color)?);
+        }
+        if gradient.stops().is_empty() {
+            return Err("no colours".to_string());
+        }
+        Ok(gradient)
+    }
+}
+
+// Parse a CSS colour, such as "#f12711", "rgb(241, 39, 17)" or "tomato".
+fn parse_color(color: &str) -> std::result::Result<Rgba<u8>, String> {
+    color
+        .parse::<crate::Rgba>()
+        .map(Rgba::from)
+        .map_err(|_| format!("invalid colour \"{}\"", color))
+}
+
+fn invalid(name: &str, reason: &str) -> Error {
+    Error::InvalidPreset(format!("{}: {}", name, reason))
+}
+
+// Lowercase a name and remove spaces, hyphens and underscores.
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_matched_loosely() {
+        let registry = PaletteRegistry::builtin();
+        assert!(registry.gradient("Pink_Blue").is_ok());
+        assert!(registry.gradient("pink blue").is_ok());
+        assert_eq!(registry.palette("hydrogen").unwrap().len(), 4);
+        assert!(matches!(
+            registry.gradient(""),
+            Err(Error::UnknownPreset(_))
+        ));
+        assert!(matches!(
+            registry.palette("no such"),
+            Err(Error::UnknownPreset(_))
+        ));
+    }
+
+    #[test]
+    fn empty_registries_have_no_names() {
+        let registry = PaletteRegistry::new();
+        assert!(registry.gradient_names().is_empty());
+        assert!(registry.palette_names().is_empty());
+        assert!(registry.gradient("pinkblue").is_err());
+    }
+
+    #[test]
+    fn empty_documents_add_nothing() {
+        let mut registry = PaletteRegistry::new();
+        registry.load_json("{}").unwrap();
+        registry.load_toml("").unwrap();
+        assert!(registry.gradient_names().is_empty());
+        registry
+            .load_json(r##"{"palettes": {"none": []}}"##)
+            .unwrap();
+        assert_eq!(registry.palette("none").unwrap(), []);
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        let mut registry = PaletteRegistry::new();
+        let documents = [
+            "",
+            "42",
+            r##"{"gradients": {"a": {"colors": ["#fff"]}"##,
+            r##"{"gradients": {"a": {"colors": "#fff"}}}"##,
+            r##"{"gradients": {"a": {"angle": "wide", "colors": ["#fff"]}}}"##,
+            r##"{"gradients": {"a": {"stops": [{"offset": 0.5}]}}}"##,
+        ];
+        for json in &documents {
+            assert!(
+                matches!(registry.load_json(json), Err(Error::InvalidPreset(_))),
+                "{}",
+                json
+            );
+        }
+        assert!(registry.load_toml("[gradients.a\ncolors = 1").is_err());
+        assert!(registry.gradient_names().is_empty());
+    }
+
+    #[test]
+    fn invalid_presets_name_the_problem() {
+        let mut registry = PaletteRegistry::new();
+        let cases = [
+            (r##"{"gradients": {"a": {}}}"##, "no colours"),
+            (
+                r##"{"gradients": {"a": {"colors": ["#fff"], "shape": "star"}}}"##,
+                "star",
+            ),
+            (
+                r##"{"gradients": {"a": {"colors": ["#fff"], "interpolation": "hsl"}}}"##,
+                "hsl",
+            ),
+            (r##"{"gradients": {"a": {"colors": ["#ggg"]}}}"##, "#ggg"),
+            (
+                r##"{"palettes": {"b": ["#fff", "rgb(1, 2)"]}}"##,
+                "rgb(1, 2)",
+            ),
+        ];
+        for (json, reason) in &cases {
+            match registry.load_json(json) {
+                Err(Error::InvalidPreset(message)) => {
+                    assert!(message.contains(reason), "{}", message)
+                }
+                other => panic!("{}: {:?}", json, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn shared_registry_errors_are_returned() {
+        assert!(matches!(
+            load_presets_json("{"),
+            Err(Error::InvalidPreset(_))
+        ));
+        assert!(matches!(
+            load_presets_toml("= 1"),
+            Err(Error::InvalidPreset(_))
+        ));
+        assert!(matches!(
+            crate::PhotonImage::new_with_gradient_preset(4, 4, "no such gradient"),
+            Err(Error::UnknownPreset(_))
+        ));
+        assert!(crate::PhotonImage::new_with_gradient_preset(4, 4, "pinkblue").is_ok());
+    }
+
+    #[test]
+    fn invalid_documents_add_nothing() {
+        let mut registry = PaletteRegistry::new();
+        let json = r##"{"gradients": {"good": {"colors": ["#fff", "#000"]}},
+                       "palettes": {"bad": ["not a colour"]}}"##;
+        assert!(registry.load_json(json).is_err());
+        assert!(!registry.contains_gradient("good"));
+        assert!(registry.load_file("no/such/presets.json").is_err());
+    }
+}
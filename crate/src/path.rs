@@ -0,0 +1,1241 @@
+//! Draw shapes from vector paths, with anti-aliased edges.
+//!
+//! A [`Path`] is built from lines, quadratic and cubic Bézier curves and circular arcs, like a
+//! path on an HTML canvas or in SVG. It can be filled with the non-zero or even-odd rule, or
+//! stroked with a width, line joins, line caps and a dash pattern. Edges are anti-aliased by
+//! working out how much of each pixel the shape covers, and the shape is composited onto the
+//! image with [`crate::compositing`]. The rasterizer is plain Rust, so it works the same natively
+//! and in WebAssembly.
+//!
+//! ```
+//! use gdl::path::{fill_path, stroke_path, FillRule, LineCap, LineJoin, Path, Stroke};
+//! use gdl::Rgb;
+//! use image::Rgba;
+//!
+//! let mut image = gdl::new_with_background(300, 200, &Rgb { r: 255, g: 255, b: 255 });
+//!
+//! let star = Path::new().polygon(&[
+//!     (150.0, 20.0),
+//!     (185.0, 130.0),
+//!     (90.0, 60.0),
+//!     (210.0, 60.0),
+//!     (115.0, 130.0),
+//! ]);
+//! fill_path(&mut image, &star, Rgba([250, 190, 20, 255]), FillRule::EvenOdd);
+//!
+//! let wave = Path::new()
+//!     .move_to(20.0, 170.0)
+//!     .cubic_to(80.0, 120.0, 120.0, 220.0, 280.0, 160.0);
+//! let dashed = Stroke::new(6.0)
+//!     .cap(LineCap::Round)
+//!     .join(LineJoin::Round)
+//!     .dashes(vec![12.0, 10.0]);
+//! stroke_path(&mut image, &wave, Rgba([40, 90, 200, 255]), &dashed);
+//! ```
+
+use crate::compositing::{blend_pixel, fill_mask, BlendMode};
+use crate::gradient::Gradient;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Rgba, RgbaImage};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+// Largest distance, in pixels, a curve is allowed to stray from the straight lines it is drawn
+// with.
+const TOLERANCE: f32 = 0.1;
+
+// Number of scanlines sampled within each row of pixels. Coverage across a row is exact.
+const SUBSAMPLES: usize = 16;
+
+type Point = (f32, f32);
+
+/// One step in a [`Path`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCommand {
+    /// Start a new subpath at a point.
+    MoveTo(f32, f32),
+    /// A straight line to a point.
+    LineTo(f32, f32),
+    /// A quadratic Bézier curve to a point.
+    QuadTo { control: Point, to: Point },
+    /// A cubic Bézier curve to a point.
+    CubicTo {
+        control1: Point,
+        control2: Point,
+        to: Point,
+    },
+    /// A straight line back to the start of the subpath, closing it.
+    Close,
+}
+
+/// Which parts of a path that crosses itself are inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+    /// A point is inside if the path winds around it at all, counting clockwise turns against
+    /// anticlockwise ones.
+    #[default]
+    NonZero,
+    /// A point is inside if a line from it crosses the path an odd number of times, so
+    /// overlapping parts leave holes.
+    EvenOdd,
+}
+
+/// The shape drawn where two lines of a stroke meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// A sharp corner, cut off as a bevel if it would stick out further than the miter limit.
+    #[default]
+    Miter,
+    /// A rounded corner.
+    Round,
+    /// A corner cut off flat.
+    Bevel,
+}
+
+/// The shape drawn at the ends of an open stroke, and of each dash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// The stroke stops flat at the end point.
+    #[default]
+    Butt,
+    /// The stroke ends in a semicircle around the end point.
+    Round,
+    /// The stroke stops flat, half its width past the end point.
+    Square,
+}
+
+/// How a path is outlined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    /// Width of the line, in pixels.
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// How far a miter join may stick out, as a multiple of the stroke width, before it is
+    /// drawn as a bevel.
+    pub miter_limit: f32,
+    /// Lengths of the dashes and the gaps between them, in turn, in pixels. A list of odd length
+    /// is repeated to make it even, as in SVG. An empty list draws a solid line.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern the stroke starts, in pixels.
+    pub dash_offset: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Stroke {
+        Stroke {
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl Stroke {
+    /// A solid stroke of the given width, with miter joins and butt caps.
+    pub fn new(width: f32) -> Stroke {
+        Stroke {
+            width,
+            ..Stroke::default()
+        }
+    }
+
+    /// Set how lines are joined.
+    pub fn join(mut self, join: LineJoin) -> Stroke {
+        self.join = join;
+        self
+    }
+
+    /// Set how the ends of the stroke are drawn.
+    pub fn cap(mut self, cap: LineCap) -> Stroke {
+        self.cap = cap;
+        self
+    }
+
+    /// Set how far miter joins may stick out, as a multiple of the stroke width.
+    pub fn miter_limit(mut self, miter_limit: f32) -> Stroke {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    /// Set the lengths of the dashes and gaps, in pixels.
+    pub fn dashes(mut self, dashes: Vec<f32>) -> Stroke {
+        self.dashes = dashes;
+        self
+    }
+
+    /// Set how far into the dash pattern the stroke starts, in pixels.
+    pub fn dash_offset(mut self, dash_offset: f32) -> Stroke {
+        self.dash_offset = dash_offset;
+        self
+    }
+}
+
+/// Radii of the corners of a rounded rectangle, in pixels.
+///
+/// Radii too large for the rectangle are scaled down together, as in CSS, so a radius of half
+/// the rectangle's height or more gives a pill shape.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    /// Radii for each corner, clockwise from the top left, as in CSS.
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> CornerRadii {
+        CornerRadii {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+
+    /// The same radius for every corner.
+    pub fn all(radius: f32) -> CornerRadii {
+        CornerRadii::new(radius, radius, radius, radius)
+    }
+
+    /// Whether every corner is square.
+    pub fn is_zero(&self) -> bool {
+        self.top_left <= 0.0
+            && self.top_right <= 0.0
+            && self.bottom_right <= 0.0
+            && self.bottom_left <= 0.0
+    }
+}
+
+impl From<f32> for CornerRadii {
+    fn from(radius: f32) -> CornerRadii {
+        CornerRadii::all(radius)
+    }
+}
+
+/// What a shape is painted with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fill {
+    /// A single colour. Its alpha channel sets how much it covers the image.
+    Solid(Rgba<u8>),
+    /// A gradient, drawn across the bounding box of the shape.
+    Gradient(Gradient),
+    /// An image, scaled to cover the bounding box of the shape and centred in it. Any part of
+    /// the image outside the shape is clipped off.
+    Image(RgbaImage),
+}
+
+impl From<Gradient> for Fill {
+    fn from(gradient: Gradient) -> Fill {
+        Fill::Gradient(gradient)
+    }
+}
+
+impl From<Rgba<u8>> for Fill {
+    fn from(color: Rgba<u8>) -> Fill {
+        Fill::Solid(color)
+    }
+}
+
+/// A vector shape made of one or more subpaths.
+///
+/// Paths are built by chaining commands, starting from [`Path::new`]. Coordinates are in
+/// pixels, with y increasing downwards, and angles are in degrees, clockwise from the positive
+/// x-axis.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+    start: Option<Point>,
+    current: Option<Point>,
+}
+
+impl Path {
+    /// An empty path.
+    pub fn new() -> Path {
+        Path::default()
+    }
+
+    /// The commands the path is made of.
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    /// Whether the path has no commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Start a new subpath at a point.
+    pub fn move_to(mut self, x: f32, y: f32) -> Path {
+        self.commands.push(PathCommand::MoveTo(x, y));
+        self.start = Some((x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Add a straight line to a point. If the path has no current point, this starts a subpath
+    /// there instead.
+    pub fn line_to(mut self, x: f32, y: f32) -> Path {
+        if self.current.is_none() {
+            return self.move_to(x, y);
+        }
+        self.commands.push(PathCommand::LineTo(x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Add a quadratic Bézier curve to `(x, y)`, pulled towards the control point.
+    pub fn quad_to(mut self, cx: f32, cy: f32, x: f32, y: f32) -> Path {
+        if self.current.is_none() {
+            self = self.move_to(cx, cy);
+        }
+        self.commands.push(PathCommand::QuadTo {
+            control: (cx, cy),
+            to: (x, y),
+        });
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Add a cubic Bézier curve to `(x, y)`, leaving towards the first control point and
+    /// arriving from the second.
+    pub fn cubic_to(mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> Path {
+        if self.current.is_none() {
+            self = self.move_to(c1x, c1y);
+        }
+        self.commands.push(PathCommand::CubicTo {
+            control1: (c1x, c1y),
+            control2: (c2x, c2y),
+            to: (x, y),
+        });
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Add part of a circle, from `start_angle` to `end_angle`. It runs clockwise if `end_angle`
+    /// is the larger, and anticlockwise otherwise. A straight line joins the current point to
+    /// the start of the arc.
+    pub fn arc(mut self, cx: f32, cy: f32, radius: f32, start_angle: f32, end_angle: f32) -> Path {
+        let start = start_angle.to_radians();
+        let sweep = (end_angle - start_angle).to_radians().clamp(-TAU, TAU);
+        let point = |angle: f32| (cx + radius * angle.cos(), cy + radius * angle.sin());
+
+        let (x, y) = point(start);
+        self = self.line_to(x, y);
+        // Each piece of at most a quarter turn is drawn as a cubic curve.
+        let pieces = (sweep.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / pieces as f32;
+        let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        for i in 0..pieces {
+            let a0 = start + step * i as f32;
+            let a1 = a0 + step;
+            let (x0, y0) = point(a0);
+            let (x1, y1) = point(a1);
+            self = self.cubic_to(
+                x0 - handle * a0.sin(),
+                y0 + handle * a0.cos(),
+                x1 + handle * a1.sin(),
+                y1 - handle * a1.cos(),
+                x1,
+                y1,
+            );
+        }
+        self
+    }
+
+    /// Close the current subpath with a straight line back to its start.
+    pub fn close(mut self) -> Path {
+        if self.current.is_some() {
+            self.commands.push(PathCommand::Close);
+            self.current = self.start;
+        }
+        self
+    }
+
+    /// Add a closed rectangle as a new subpath.
+    pub fn rect(self, x: f32, y: f32, width: f32, height: f32) -> Path {
+        self.move_to(x, y)
+            .line_to(x + width, y)
+            .line_to(x + width, y + height)
+            .line_to(x, y + height)
+            .close()
+    }
+
+    /// Add a closed circle as a new subpath.
+    pub fn circle(self, cx: f32, cy: f32, radius: f32) -> Path {
+        self.ellipse(cx, cy, radius, radius)
+    }
+
+    /// Add a closed ellipse as a new subpath.
+    pub fn ellipse(mut self, cx: f32, cy: f32, rx: f32, ry: f32) -> Path {
+        // Quarter turns of a unit circle, scaled to the ellipse.
+        let k = 4.0 / 3.0 * (2f32.sqrt() - 1.0);
+        self = self.move_to(cx + rx, cy);
+        self = self.cubic_to(cx + rx, cy + k * ry, cx + k * rx, cy + ry, cx, cy + ry);
+        self = self.cubic_to(cx - k * rx, cy + ry, cx - rx, cy + k * ry, cx - rx, cy);
+        self = self.cubic_to(cx - rx, cy - k * ry, cx - k * rx, cy - ry, cx, cy - ry);
+        self = self.cubic_to(cx + k * rx, cy - ry, cx + rx, cy - k * ry, cx + rx, cy);
+        self.close()
+    }
+
+    /// Add a closed rectangle with rounded corners as a new subpath.
+    pub fn rounded_rect(
+        mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radii: impl Into<CornerRadii>,
+    ) -> Path {
+        let radii = radii.into();
+        let [top_left, top_right, bottom_right, bottom_left] = [
+            radii.top_left,
+            radii.top_right,
+            radii.bottom_right,
+            radii.bottom_left,
+        ]
+        .map(|radius| radius.max(0.0));
+        // Shrink the radii together until adjacent corners fit along each side.
+        let fit = [
+            width / (top_left + top_right),
+            height / (top_right + bottom_right),
+            width / (bottom_right + bottom_left),
+            height / (bottom_left + top_left),
+        ]
+        .iter()
+        .copied()
+        .filter(|fit| fit.is_finite())
+        .fold(1.0f32, f32::min)
+        .max(0.0);
+        let [top_left, top_right, bottom_right, bottom_left] =
+            [top_left, top_right, bottom_right, bottom_left].map(|radius| radius * fit);
+
+        let corner = |path: Path, cx: f32, cy: f32, radius: f32, start_angle: f32| {
+            if radius > 0.0 {
+                path.arc(cx, cy, radius, start_angle, start_angle + 90.0)
+            } else {
+                path.line_to(cx, cy)
+            }
+        };
+        let (right, bottom) = (x + width, y + height);
+        self = self.move_to(x + top_left, y);
+        self = corner(self, right - top_right, y + top_right, top_right, -90.0);
+        self = corner(
+            self,
+            right - bottom_right,
+            bottom - bottom_right,
+            bottom_right,
+            0.0,
+        );
+        self = corner(
+            self,
+            x + bottom_left,
+            bottom - bottom_left,
+            bottom_left,
+            90.0,
+        );
+        self = corner(self, x + top_left, y + top_left, top_left, 180.0);
+        self.close()
+    }
+
+    /// Add a closed polygon through the given points as a new subpath.
+    pub fn polygon(mut self, points: &[(f32, f32)]) -> Path {
+        if let Some(&(x, y)) = points.first() {
+            self = self.move_to(x, y);
+            for &(x, y) in &points[1..] {
+                self = self.line_to(x, y);
+            }
+            self = self.close();
+        }
+        self
+    }
+
+    /// The smallest rectangle containing the path and its control points, as
+    /// `(left, top, right, bottom)`, or `None` if the path is empty.
+    pub fn bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        let mut points = self.commands.iter().flat_map(|command| match *command {
+            PathCommand::MoveTo(x, y) | PathCommand::LineTo(x, y) => vec![(x, y)],
+            PathCommand::QuadTo { control, to } => vec![control, to],
+            PathCommand::CubicTo {
+                control1,
+                control2,
+                to,
+            } => vec![control1, control2, to],
+            PathCommand::Close => Vec::new(),
+        });
+        let (x, y) = points.next()?;
+        Some(points.fold((x, y, x, y), |(l, t, r, b), (x, y)| {
+            (l.min(x), t.min(y), r.max(x), b.max(y))
+        }))
+    }
+
+    /// Work out how much of each pixel of an image the filled path covers.
+    ///
+    /// Returns a mask the size of the image, where 255 is fully covered. It can be used to clip
+    /// or fill the shape with [`crate::compositing::fill_mask`].
+    pub fn mask(&self, rule: FillRule, width: u32, height: u32) -> GrayImage {
+        let polygons: Vec<Vec<Point>> =
+            self.flatten().into_iter().map(|line| line.points).collect();
+        expand_mask(rasterize(&polygons, rule, width, height), width, height)
+    }
+
+    /// Work out how much of each pixel of an image the stroked path covers.
+    ///
+    /// Returns a mask the size of the image, where 255 is fully covered.
+    pub fn stroke_mask(&self, stroke: &Stroke, width: u32, height: u32) -> GrayImage {
+        let polygons = stroke_polygons(&self.flatten(), stroke);
+        expand_mask(
+            rasterize(&polygons, FillRule::NonZero, width, height),
+            width,
+            height,
+        )
+    }
+
+    // Split the path into subpaths of straight lines.
+    fn flatten(&self) -> Vec<Polyline> {
+        let mut lines = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        let finish = |lines: &mut Vec<Polyline>, points: &mut Vec<Point>, closed: bool| {
+            if points.len() > 1 {
+                lines.push(Polyline {
+                    points: std::mem::take(points),
+                    closed,
+                });
+            }
+            points.clear();
+        };
+
+        for command in &self.commands {
+            let last = points.last().copied();
+            match *command {
+                PathCommand::MoveTo(x, y) => {
+                    finish(&mut lines, &mut points, false);
+                    points.push((x, y));
+                }
+                PathCommand::LineTo(x, y) => points.push((x, y)),
+                PathCommand::QuadTo { control, to } => {
+                    let from = last.unwrap_or(control);
+                    let dd = length(sub(add(from, to), scale(control, 2.0)));
+                    let pieces = curve_pieces(dd);
+                    for i in 1..=pieces {
+                        let t = i as f32 / pieces as f32;
+                        let mt = 1.0 - t;
+                        points.push(add(
+                            add(scale(from, mt * mt), scale(control, 2.0 * mt * t)),
+                            scale(to, t * t),
+                        ));
+                    }
+                }
+                PathCommand::CubicTo {
+                    control1,
+                    control2,
+                    to,
+                } => {
+                    let from = last.unwrap_or(control1);
+                    let dd1 = length(sub(add(from, control2), scale(control1, 2.0)));
+                    let dd2 = length(sub(add(control1, to), scale(control2, 2.0)));
+                    let pieces = curve_pieces(dd1.max(dd2) * 1.5);
+                    for i in 1..=pieces {
+                        let t = i as f32 / pieces as f32;
+                        let mt = 1.0 - t;
+                        points.push(add(
+                            add(
+                                scale(from, mt * mt * mt),
+                                scale(control1, 3.0 * mt * mt * t),
+                            ),
+                            add(scale(control2, 3.0 * mt * t * t), scale(to, t * t * t)),
+                        ));
+                    }
+                }
+                PathCommand::Close => {
+                    let start = points.first().copied();
+                    finish(&mut lines, &mut points, true);
+                    // Following commands carry on from the start of the closed subpath.
+                    if let Some(start) = start {
+                        points.push(start);
+                    }
+                }
+            }
+        }
+        finish(&mut lines, &mut points, false);
+        lines
+    }
+}
+
+/// Fill a path with a colour, a gradient or an image, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `path` - The shape to fill. Open subpaths are closed with a straight line.
+/// * `fill` - What the shape is painted with.
+/// * `rule` - Which parts of a path that crosses itself are filled.
+pub fn fill_path_with(image: &mut DynamicImage, path: &Path, fill: &Fill, rule: FillRule) {
+    let (width, height) = image.dimensions();
+    let polygons: Vec<Vec<Point>> = path.flatten().into_iter().map(|line| line.points).collect();
+    if let Some(mask) = rasterize(&polygons, rule, width, height) {
+        paint_mask(image, mask, path, fill);
+    }
+}
+
+/// Outline a path with a colour, a gradient or an image, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `path` - The shape to outline.
+/// * `fill` - What the outline is painted with. Gradients and images cover the bounding box of
+///   the path.
+/// * `stroke` - Width, joins, caps and dashes of the outline.
+pub fn stroke_path_with(image: &mut DynamicImage, path: &Path, fill: &Fill, stroke: &Stroke) {
+    let (width, height) = image.dimensions();
+    let polygons = stroke_polygons(&path.flatten(), stroke);
+    if let Some(mask) = rasterize(&polygons, FillRule::NonZero, width, height) {
+        paint_mask(image, mask, path, fill);
+    }
+}
+
+/// Fill a path with a colour, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `path` - The shape to fill. Open subpaths are closed with a straight line.
+/// * `color` - Fill colour. Its alpha channel sets how much it covers the image.
+/// * `rule` - Which parts of a path that crosses itself are filled.
+pub fn fill_path(image: &mut DynamicImage, path: &Path, color: Rgba<u8>, rule: FillRule) {
+    let (width, height) = image.dimensions();
+    let polygons: Vec<Vec<Point>> = path.flatten().into_iter().map(|line| line.points).collect();
+    if let Some((mask, x, y)) = rasterize(&polygons, rule, width, height) {
+        fill_mask(image, &mask, x, y, color, BlendMode::Normal, 1.0);
+    }
+}
+
+/// Outline a path with a colour, compositing it onto the image.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `path` - The shape to outline.
+/// * `color` - Stroke colour. Its alpha channel sets how much it covers the image.
+/// * `stroke` - Width, joins, caps and dashes of the outline.
+pub fn stroke_path(image: &mut DynamicImage, path: &Path, color: Rgba<u8>, stroke: &Stroke) {
+    let (width, height) = image.dimensions();
+    let polygons = stroke_polygons(&path.flatten(), stroke);
+    if let Some((mask, x, y)) = rasterize(&polygons, FillRule::NonZero, width, height) {
+        fill_mask(image, &mask, x, y, color, BlendMode::Normal, 1.0);
+    }
+}
+
+// Composite a fill onto the pixels a rasterized mask covers.
+fn paint_mask(
+    image: &mut DynamicImage,
+    (mask, x, y): (GrayImage, i32, i32),
+    path: &Path,
+    fill: &Fill,
+) {
+    if let Fill::Solid(color) = fill {
+        fill_mask(image, &mask, x, y, *color, BlendMode::Normal, 1.0);
+        return;
+    }
+    let (left, top, right, bottom) = match path.bounds() {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let (box_x, box_y) = (left.floor() as i32, top.floor() as i32);
+    let box_width = (right.ceil() as i32 - box_x).max(1) as u32;
+    let box_height = (bottom.ceil() as i32 - box_y).max(1) as u32;
+
+    match fill {
+        Fill::Solid(_) => {}
+        Fill::Gradient(gradient) => {
+            let sampler = gradient.sampler(box_width, box_height);
+            paint_covered(image, &mask, x, y, |image_x, image_y| {
+                let (bx, by) = (image_x - box_x, image_y - box_y);
+                let inside = bx >= 0 && by >= 0;
+                inside.then(|| sampler.sample(bx as u32, by as u32))
+            });
+        }
+        Fill::Image(source) => {
+            let (source_width, source_height) = source.dimensions();
+            if source_width == 0 || source_height == 0 {
+                return;
+            }
+            // Scale the image to cover the box, and centre it.
+            let (box_w, box_h) = (right - left, bottom - top);
+            let scale = (box_w / source_width as f32).max(box_h / source_height as f32);
+            let scaled_width = (source_width as f32 * scale).ceil().max(1.0) as u32;
+            let scaled_height = (source_height as f32 * scale).ceil().max(1.0) as u32;
+            let scaled =
+                imageops::resize(source, scaled_width, scaled_height, FilterType::Triangle);
+            let offset_x = (left + (box_w - scaled_width as f32) / 2.0).round() as i32;
+            let offset_y = (top + (box_h - scaled_height as f32) / 2.0).round() as i32;
+            paint_covered(image, &mask, x, y, |image_x, image_y| {
+                let (sx, sy) = (image_x - offset_x, image_y - offset_y);
+                let inside =
+                    sx >= 0 && sy >= 0 && sx < scaled_width as i32 && sy < scaled_height as i32;
+                inside.then(|| *scaled.get_pixel(sx as u32, sy as u32))
+            });
+        }
+    }
+}
+
+// Composite the colour of each pixel a mask covers onto the image, given by its position.
+fn paint_covered<F: Fn(i32, i32) -> Option<Rgba<u8>>>(
+    image: &mut DynamicImage,
+    mask: &GrayImage,
+    x: i32,
+    y: i32,
+    color_at: F,
+) {
+    for (mx, my, coverage) in mask.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+        let (image_x, image_y) = (x + mx as i32, y + my as i32);
+        if let Some(color) = color_at(image_x, image_y) {
+            let backdrop = image.get_pixel(image_x as u32, image_y as u32);
+            let alpha = coverage[0] as f32 / 255.0;
+            image.put_pixel(
+                image_x as u32,
+                image_y as u32,
+                blend_pixel(backdrop, color, BlendMode::Normal, alpha),
+            );
+        }
+    }
+}
+
+// A subpath made of straight lines.
+#[derive(Debug, Clone)]
+struct Polyline {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+// A polygon edge, stored with y0 < y1. `winding` is 1 if the edge runs downwards and -1 if it
+// runs upwards.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+// Work out how much of each pixel the polygons cover, within an image of the given size.
+// Returns a mask of the polygons' bounding box and the position of its top-left corner.
+fn rasterize(
+    polygons: &[Vec<Point>],
+    rule: FillRule,
+    width: u32,
+    height: u32,
+) -> Option<(GrayImage, i32, i32)> {
+    let mut edges = Vec::new();
+    for polygon in polygons {
+        for (i, &(x0, y0)) in polygon.iter().enumerate() {
+            let (x1, y1) = polygon[(i + 1) % polygon.len()];
+            if y0 == y1 || !(x0.is_finite() && y0.is_finite() && x1.is_finite() && y1.is_finite()) {
+                continue;
+            }
+            edges.push(if y0 < y1 {
+                Edge {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    winding: 1,
+                }
+            } else {
+                Edge {
+                    x0: x1,
+                    y0: y1,
+                    x1: x0,
+                    y1: y0,
+                    winding: -1,
+                }
+            });
+        }
+    }
+
+    let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for edge in &edges {
+        left = left.min(edge.x0.min(edge.x1));
+        right = right.max(edge.x0.max(edge.x1));
+        top = top.min(edge.y0);
+        bottom = bottom.max(edge.y1);
+    }
+    let left = (left.floor().max(0.0) as i64).min(width as i64) as u32;
+    let top = (top.floor().max(0.0) as i64).min(height as i64) as u32;
+    let right = (right.ceil().max(0.0) as i64).min(width as i64) as u32;
+    let bottom = (bottom.ceil().max(0.0) as i64).min(height as i64) as u32;
+    if edges.is_empty() || right <= left || bottom <= top {
+        return None;
+    }
+
+    let (mask_width, mask_height) = (right - left, bottom - top);
+    let mut mask = GrayImage::new(mask_width, mask_height);
+    edges.sort_by(|a, b| a.y0.total_cmp(&b.y0));
+
+    let columns = mask_width as usize;
+    // Coverage of the pixels spans start or end in, and changes in coverage between them.
+    let mut cover = vec![0.0f32; columns + 2];
+    let mut delta = vec![0.0f32; columns + 2];
+    let mut active: Vec<Edge> = Vec::new();
+    let mut next = 0;
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    let weight = 1.0 / SUBSAMPLES as f32;
+
+    for row in 0..mask_height {
+        let y = (top + row) as f32;
+        while next < edges.len() && edges[next].y0 < y + 1.0 {
+            active.push(edges[next]);
+            next += 1;
+        }
+        active.retain(|edge| edge.y1 > y);
+        if active.is_empty() {
+            continue;
+        }
+        cover.iter_mut().for_each(|c| *c = 0.0);
+        delta.iter_mut().for_each(|d| *d = 0.0);
+
+        for sample in 0..SUBSAMPLES {
+            let sample_y = y + (sample as f32 + 0.5) * weight;
+            crossings.clear();
+            for edge in &active {
+                if edge.y0 <= sample_y && sample_y < edge.y1 {
+                    let t = (sample_y - edge.y0) / (edge.y1 - edge.y0);
+                    let x = edge.x0 + t * (edge.x1 - edge.x0) - left as f32;
+                    crossings.push((x, edge.winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            let mut span_start = 0.0;
+            for &(x, direction) in &crossings {
+                let was_inside = is_inside(winding, rule);
+                winding += direction;
+                match (was_inside, is_inside(winding, rule)) {
+                    (false, true) => span_start = x,
+                    (true, false) => {
+                        add_span(&mut cover, &mut delta, span_start, x, columns, weight)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut running = 0.0;
+        for column in 0..columns {
+            running += delta[column];
+            let coverage = (cover[column] + running).clamp(0.0, 1.0);
+            mask.put_pixel(column as u32, row, Luma([(coverage * 255.0).round() as u8]));
+        }
+    }
+    Some((mask, left as i32, top as i32))
+}
+
+fn is_inside(winding: i32, rule: FillRule) -> bool {
+    match rule {
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+// Add a span of a sample scanline, from x `start` to `end`, to the row's coverage.
+fn add_span(
+    cover: &mut [f32],
+    delta: &mut [f32],
+    start: f32,
+    end: f32,
+    columns: usize,
+    weight: f32,
+) {
+    let start = start.clamp(0.0, columns as f32);
+    let end = end.clamp(0.0, columns as f32);
+    if end <= start {
+        return;
+    }
+    let first = start as usize;
+    let last = end as usize;
+    if first == last {
+        cover[first] += (end - start) * weight;
+        return;
+    }
+    cover[first] += (first as f32 + 1.0 - start) * weight;
+    delta[first + 1] += weight;
+    delta[last] -= weight;
+    cover[last] += (end - last as f32) * weight;
+}
+
+// Place a mask of a bounding box in a mask the size of the whole image.
+fn expand_mask(rasterized: Option<(GrayImage, i32, i32)>, width: u32, height: u32) -> GrayImage {
+    let mut mask = GrayImage::new(width, height);
+    if let Some((part, x, y)) = rasterized {
+        for (px, py, pixel) in part.enumerate_pixels() {
+            mask.put_pixel(x as u32 + px, y as u32 + py, *pixel);
+        }
+    }
+    mask
+}
+
+// Turn the outline of each subpath into polygons that, filled with the non-zero rule, cover
+// the stroke.
+fn stroke_polygons(lines: &[Polyline], stroke: &Stroke) -> Vec<Vec<Point>> {
+    let half = stroke.width / 2.0;
+    let mut polygons = Vec::new();
+    if half <= 0.0 || !half.is_finite() {
+        return polygons;
+    }
+    let dashed;
+    let lines = if stroke.dashes.iter().all(|dash| *dash >= 0.0)
+        && stroke.dashes.iter().sum::<f32>() > 0.0
+    {
+        dashed = dash_lines(lines, &stroke.dashes, stroke.dash_offset);
+        &dashed
+    } else {
+        lines
+    };
+
+    for line in lines {
+        let mut points = line.points.clone();
+        points.dedup_by(|a, b| length(sub(*a, *b)) < 1e-4);
+        if line.closed
+            && points.len() > 1
+            && length(sub(points[0], points[points.len() - 1])) < 1e-4
+        {
+            points.pop();
+        }
+
+        // A line of no length is drawn as a dot by round and square caps.
+        if points.len() == 1 {
+            let point = points[0];
+            match stroke.cap {
+                LineCap::Round => push_polygon(&mut polygons, circle_polygon(point, half)),
+                LineCap::Square => push_polygon(
+                    &mut polygons,
+                    vec![
+                        (point.0 - half, point.1 - half),
+                        (point.0 + half, point.1 - half),
+                        (point.0 + half, point.1 + half),
+                        (point.0 - half, point.1 + half),
+                    ],
+                ),
+                LineCap::Butt => {}
+            }
+            continue;
+        }
+
+        let closed = line.closed && points.len() > 2;
+        let count = points.len();
+        let segments = if closed { count } else { count - 1 };
+        for i in 0..segments {
+            let (from, to) = (points[i], points[(i + 1) % count]);
+            let normal = scale(normal(direction(from, to)), half);
+            push_polygon(
+                &mut polygons,
+                vec![
+                    add(from, normal),
+                    add(to, normal),
+                    sub(to, normal),
+                    sub(from, normal),
+                ],
+            );
+        }
+
+        let joins = if closed { 0..count } else { 1..count - 1 };
+        for i in joins {
+            let previous = points[(i + count - 1) % count];
+            let next = points[(i + 1) % count];
+            let incoming = direction(previous, points[i]);
+            let outgoing = direction(points[i], next);
+            if let Some(join) = join_polygon(points[i], incoming, outgoing, half, stroke) {
+                push_polygon(&mut polygons, join);
+            }
+        }
+
+        if !closed {
+            let start_direction = direction(points[1], points[0]);
+            let end_direction = direction(points[count - 2], points[count - 1]);
+            for (point, outwards) in [
+                (points[0], start_direction),
+                (points[count - 1], end_direction),
+            ] {
+                if let Some(cap) = cap_polygon(point, outwards, half, stroke.cap) {
+                    push_polygon(&mut polygons, cap);
+                }
+            }
+        }
+    }
+    polygons
+}
+
+// The polygon filling the outside of the corner where two lines of a stroke meet.
+fn join_polygon(
+    point: Point,
+    incoming: Point,
+    outgoing: Point,
+    half: f32,
+    stroke: &Stroke,
+) -> Option<Vec<Point>> {
+    let cross = incoming.0 * outgoing.1 - incoming.1 * outgoing.0;
+    let dot = incoming.0 * outgoing.0 + incoming.1 * outgoing.1;
+    // Straight on, or so nearly that the join can't be seen, as along a curve.
+    if cross.abs() < 1e-6 && dot > 0.0 {
+        return None;
+    }
+    if stroke.join == LineJoin::Round && dot < 0.9999 {
+        return Some(circle_polygon(point, half));
+    }
+    // The outside of the corner is on the side the lines turn away from.
+    let side = if cross > 0.0 { -half } else { half };
+    let (n0, n1) = (normal(incoming), normal(outgoing));
+    let outer0 = add(point, scale(n0, side));
+    let outer1 = add(point, scale(n1, side));
+
+    if stroke.join == LineJoin::Miter {
+        let sum = add(n0, n1);
+        if length(sum) > 1e-6 {
+            let middle = scale(sum, 1.0 / length(sum));
+            let ratio = 1.0 / (middle.0 * n0.0 + middle.1 * n0.1);
+            if ratio <= stroke.miter_limit {
+                let tip = add(point, scale(middle, side * ratio));
+                return Some(vec![point, outer0, tip, outer1]);
+            }
+        }
+    }
+    Some(vec![point, outer0, outer1])
+}
+
+// The polygon added past the end of an open stroke, where `outwards` points away from it.
+fn cap_polygon(point: Point, outwards: Point, half: f32, cap: LineCap) -> Option<Vec<Point>> {
+    match cap {
+        LineCap::Butt => None,
+        LineCap::Round => Some(circle_polygon(point, half)),
+        LineCap::Square => {
+            let normal = scale(normal(outwards), half);
+            let end = add(point, scale(outwards, half));
+            Some(vec![
+                add(point, normal),
+                add(end, normal),
+                sub(end, normal),
+                sub(point, normal),
+            ])
+        }
+    }
+}
+
+// Split subpaths into dashes, following the pattern of dash and gap lengths.
+fn dash_lines(lines: &[Polyline], dashes: &[f32], offset: f32) -> Vec<Polyline> {
+    let mut pattern = dashes.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(dashes);
+    }
+    let total: f32 = pattern.iter().sum();
+    let mut dashed = Vec::new();
+
+    for line in lines {
+        let mut points = line.points.clone();
+        if line.closed {
+            points.push(points[0]);
+        }
+
+        // Find where in the pattern the line starts.
+        let mut index = 0;
+        let mut into = offset.rem_euclid(total);
+        while into >= pattern[index] {
+            into -= pattern[index];
+            index = (index + 1) % pattern.len();
+        }
+        let mut remaining = pattern[index] - into;
+        let mut drawing = index % 2 == 0;
+        let mut dash = if drawing { vec![points[0]] } else { Vec::new() };
+
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let segment = length(sub(to, from));
+            let mut position = 0.0;
+            while segment - position > remaining {
+                position += remaining;
+                let point = add(from, scale(sub(to, from), position / segment));
+                if drawing {
+                    dash.push(point);
+                    dashed.push(Polyline {
+                        points: std::mem::take(&mut dash),
+                        closed: false,
+                    });
+                } else {
+                    dash = vec![point];
+                }
+                drawing = !drawing;
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining -= segment - position;
+            if drawing {
+                dash.push(to);
+            }
+        }
+        if drawing && dash.len() > 1 {
+            dashed.push(Polyline {
+                points: dash,
+                closed: false,
+            });
+        }
+    }
+    dashed
+}
+
+// Add a polygon, turning it to wind clockwise so that overlapping pieces of a stroke add up
+// rather than cancel out.
+fn push_polygon(polygons: &mut Vec<Vec<Point>>, mut polygon: Vec<Point>) {
+    let area: f32 = (0..polygon.len())
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum();
+    if area.abs() < 1e-9 {
+        return;
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    polygons.push(polygon);
+}
+
+fn circle_polygon(center: Point, radius: f32) -> Vec<Point> {
+    let step = (1.0 - TOLERANCE / radius).clamp(-1.0, 1.0).acos();
+    let pieces = ((TAU / step).ceil() as usize).clamp(8, 256);
+    (0..pieces)
+        .map(|i| {
+            let angle = TAU * i as f32 / pieces as f32;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+// Number of straight pieces a curve is drawn with, given how far it bends.
+fn curve_pieces(bend: f32) -> usize {
+    ((0.25 * bend / TOLERANCE).sqrt().ceil() as usize).clamp(1, 500)
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: Point, factor: f32) -> Point {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: Point) -> f32 {
+    (a.0 * a.0 + a.1 * a.1).sqrt()
+}
+
+// The unit vector pointing from one point to another.
+fn direction(from: Point, to: Point) -> Point {
+    let d = sub(to, from);
+    let len = length(d);
+    if len > 0.0 {
+        scale(d, 1.0 / len)
+    } else {
+        (1.0, 0.0)
+    }
+}
+
+// The direction turned a quarter turn clockwise, on screen.
+fn normal(direction: Point) -> Point {
+    (-direction.1, direction.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(mask: &GrayImage) -> u32 {
+        mask.pixels().map(|pixel| pixel[0] as u32).sum()
+    }
+
+    #[test]
+    fn empty_paths_cover_nothing() {
+        let path = Path::new();
+        assert!(path.is_empty());
+        assert_eq!(path.bounds(), None);
+        assert_eq!(coverage(&path.mask(FillRule::NonZero, 10, 10)), 0);
+        assert_eq!(coverage(&path.stroke_mask(&Stroke::new(2.0), 10, 10)), 0);
+        assert_eq!(Path::new().polygon(&[]).commands(), []);
+    }
+
+    #[test]
+    fn degenerate_shapes_have_no_area() {
+        let shapes = [
+            Path::new().move_to(5.0, 5.0),
+            Path::new().move_to(1.0, 1.0).line_to(9.0, 9.0).close(),
+            Path::new().rect(2.0, 2.0, 6.0, 0.0),
+            Path::new().circle(5.0, 5.0, 0.0),
+            Path::new().polygon(&[(1.0, 1.0), (5.0, 5.0), (9.0, 9.0)]),
+        ];
+        for path in &shapes {
+            assert_eq!(
+                coverage(&path.mask(FillRule::NonZero, 10, 10)),
+                0,
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn zero_sized_masks_are_empty() {
+        let square = Path::new().rect(0.0, 0.0, 10.0, 10.0);
+        let mask = square.mask(FillRule::EvenOdd, 0, 0);
+        assert_eq!(mask.dimensions(), (0, 0));
+        let mut image = DynamicImage::new_rgba8(0, 0);
+        fill_path(&mut image, &square, Rgba([0, 0, 0, 255]), FillRule::NonZero);
+    }
+
+    #[test]
+    fn shapes_outside_the_image_are_clipped() {
+        let square = Path::new().rect(-20.0, -20.0, 10.0, 10.0);
+        assert_eq!(coverage(&square.mask(FillRule::NonZero, 10, 10)), 0);
+        let half = Path::new().rect(-5.0, 0.0, 10.0, 10.0);
+        assert_eq!(
+            coverage(&half.mask(FillRule::NonZero, 10, 10)),
+            5 * 10 * 255
+        );
+    }
+
+    #[test]
+    fn degenerate_strokes_draw_nothing_or_a_solid_line() {
+        let line = Path::new().move_to(1.0, 5.0).line_to(9.0, 5.0);
+        for &width in &[0.0, -2.0, f32::NAN] {
+            let stroke = Stroke::new(width);
+            assert_eq!(coverage(&line.stroke_mask(&stroke, 10, 10)), 0);
+        }
+        let solid = coverage(&line.stroke_mask(&Stroke::new(2.0), 10, 10));
+        assert!(solid > 0);
+        for dashes in [vec![0.0, 0.0], vec![4.0, -1.0]] {
+            let stroke = Stroke::new(2.0).dashes(dashes);
+            assert_eq!(coverage(&line.stroke_mask(&stroke, 10, 10)), solid);
+        }
+        let dot = Path::new().move_to(5.0, 5.0).line_to(5.0, 5.0);
+        let round = Stroke::new(4.0).cap(LineCap::Round);
+        dot.stroke_mask(&round, 10, 10);
+    }
+
+    #[test]
+    fn even_odd_fills_leave_holes() {
+        let ring = Path::new()
+            .rect(0.0, 0.0, 10.0, 10.0)
+            .rect(2.0, 2.0, 6.0, 6.0);
+        assert_eq!(coverage(&ring.mask(FillRule::EvenOdd, 10, 10)), 64 * 255);
+        assert_eq!(coverage(&ring.mask(FillRule::NonZero, 10, 10)), 100 * 255);
+    }
+}
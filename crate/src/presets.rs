@@ -1,389 +1,1399 @@
 //! Templates for rapid graphic creation.
+//!
+//! Each preset lays out a heading, with an optional subtitle and logo, over a background image.
+//! Its fonts, colours, padding, alignment and font sizes are its own unless they are set in
+//! [`PresetOptions`]. The presets are described by data, so they can be listed at runtime with
+//! [`Preset::ALL`], along with what each one does and the inputs it takes, and drawn by name.
+//!
+#![cfg_attr(feature = "embedded-fonts", doc = "```")]
+#![cfg_attr(not(feature = "embedded-fonts"), doc = "```ignore")]
+//! use gdl::fonts::FontRegistry;
+//! use gdl::presets::{Preset, PresetOptions};
+//! use gdl::{new_with_background, Rgb};
+//! use image::{GenericImageView, Rgba};
+//!
+//! let fonts = FontRegistry::embedded();
+//! let background = new_with_background(600, 400, &Rgb::new(255, 226, 98));
+//!
+//! let preset = Preset::from_name("text_banner").unwrap();
+//! assert!(preset.inputs().iter().any(|input| input.name == "subtitle"));
+//!
+//! let options = PresetOptions::new()
+//!     .subtitle("Making great lemonade since 2002.")
+//!     .text_color(Rgba([40, 40, 40, 255]))
+//!     .font_size(30.0, 90.0);
+//! let img = preset.render(&background, &fonts, "The Lemonade Co.", &options).unwrap();
+//! assert_eq!((img.width(), img.height()), (600, 400));
+//! ```
 
-use crate::elements::*;
-use crate::text::*;
-use crate::{new_with_background, Rgb};
-use image::{DynamicImage, GenericImageView};
+use crate::compositing::{composite, BlendMode};
+use crate::contrast::{ensure_contrast, ContrastFix, ContrastLevel};
+#[cfg(feature = "embedded-fonts")]
+use crate::fonts::with_embedded_fonts;
+use crate::fonts::{FontDescriptor, FontRegistry};
+#[cfg(feature = "embedded-fonts")]
+use crate::helpers::{dyn_image_from_raw, dyn_to_photonimg};
+use crate::layout::{ParagraphOptions, TextAlign};
+use crate::metrics::measure_text;
+use crate::path::{fill_path, FillRule, Path};
+use crate::rich_text::{draw_rich_paragraph, fit_rich_paragraph, layout_rich_text, RichText};
+use crate::shaping::TextDirection;
+use crate::style::{Fill, Glow, Shadow, TextStyle};
+use crate::template::open_image_source;
+use crate::text::draw_vertical_text;
+#[cfg(feature = "embedded-fonts")]
+use crate::PhotonImage;
+use crate::{Error, Result};
+use image::{DynamicImage, GenericImageView, Rgba};
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
 
-///  Centre text, with background image.
-///
-/// # Arguments
-/// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - u32 - Desired height of final graphic
+/// Fonts, colours and layout for a preset. Anything that isn't set is left to the preset.
+#[derive(Clone, Default)]
+pub struct PresetOptions {
+    /// Font of the heading.
+    pub font: Option<FontDescriptor>,
+    /// Font of the subtitle.
+    pub subtitle_font: Option<FontDescriptor>,
+    /// Colour of the text. A fill set in `style` is used instead.
+    pub text_color: Option<Rgba<u8>>,
+    /// Colour of the panels and frames some presets draw.
+    pub accent_color: Option<Rgba<u8>>,
+    /// Space kept between the text and the edges of the area it is laid out in, in pixels.
+    pub padding: Option<f32>,
+    /// Width of the frame the postcard preset draws around the background, in pixels.
+    pub frame: Option<f32>,
+    pub align: Option<TextAlign>,
+    /// Smallest and largest sizes of the heading, in pixels. The heading is drawn as large as
+    /// fits within them.
+    pub font_size: Option<(f32, f32)>,
+    /// Smaller text drawn below the heading, which can use rich text markup.
+    pub subtitle: Option<String>,
+    /// Image drawn in the bottom corner, such as a brand's logo.
+    pub logo: Option<DynamicImage>,
+    /// Direction of the text. Right-to-left text is laid out as a mirror image of
+    /// left-to-right text.
+    pub direction: TextDirection,
+    /// Effects of the text, such as an outline or shadow. Its fill, if set, replaces the
+    /// preset's colours.
+    pub style: TextStyle,
+}
 
-pub fn centre_text(background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
+impl PresetOptions {
+    /// Options that leave everything to the preset.
+    pub fn new() -> PresetOptions {
+        PresetOptions::default()
+    }
 
-    let font_size = 150.0;
+    /// Read options from JSON, for use from JavaScript.
+    ///
+    /// Fonts are given by name, such as "Roboto-Bold", colours as CSS colours, `font_size` as
+    /// `[min, max]` and `logo` as a `data:` URL, eg:
+    /// `{"font": "Oswald", "text_color": "#222", "align": "centre", "subtitle": "Since 2002"}`.
+    ///
+    /// The text's effects are given as `style`, with any of `stroke = {width, color}`,
+    /// `shadow = {x, y, blur, color, opacity}`, `glow = {radius, color, opacity}` and
+    /// `contrast = {level, fix}`, where `level` is "aa" or "aaa" and `fix` is "pick_color"
+    /// or "scrim", eg: `{"style": {"stroke": {"width": 3, "color": "black"}}}`.
+    pub fn from_json(json: &str) -> Result<PresetOptions> {
+        let spec: OptionsSpec =
+            serde_json::from_str(json).map_err(|err| Error::InvalidPreset(err.to_string()))?;
 
-    let group_vec = text_to_vec(main_text, background_img.width(), font_size);
+        let mut options = PresetOptions {
+            font: spec.font.as_deref().map(FontDescriptor::from),
+            subtitle_font: spec.subtitle_font.as_deref().map(FontDescriptor::from),
+            text_color: optional_color("text_color", &spec.text_color)?,
+            accent_color: optional_color("accent_color", &spec.accent_color)?,
+            padding: spec.padding,
+            frame: spec.frame,
+            font_size: spec.font_size,
+            subtitle: spec.subtitle,
+            ..PresetOptions::default()
+        };
+        if let Some(style) = &spec.style {
+            options.style = style.to_style()?;
+        }
+        if let Some(align) = &spec.align {
+            options.align = Some(TextAlign::from_name(align).ok_or_else(|| {
+                Error::InvalidPreset(format!("align: unknown alignment \"{}\"", align))
+            })?);
+        }
+        if let Some(logo) = &spec.logo {
+            options.logo = Some(
+                open_image_source(logo, None)
+                    .map_err(|reason| Error::InvalidPreset(format!("logo: {}", reason)))?,
+            );
+        }
+        options.direction = match spec.direction.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("auto") => TextDirection::Auto,
+            Some("ltr") => TextDirection::LeftToRight,
+            Some("rtl") => TextDirection::RightToLeft,
+            Some(other) => {
+                return Err(Error::InvalidPreset(format!(
+                    "direction: unknown direction \"{}\", expected \"auto\", \"ltr\" or \"rtl\"",
+                    other
+                )))
+            }
+        };
+        Ok(options)
+    }
 
-    let mut height_mul: f32 = 0.05;
-    let white_rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-    for word_vec in group_vec {
-        let text = word_vec.join(" ");
-        draw_text(
-            background_img,
-            &text,
-            (width as f32 * 0.3) as u32,
-            (height as f32 * height_mul) as u32,
-            "BebasKai",
-            font_size,
-            &white_rgb,
-        );
-        height_mul += 0.15;
+    /// Set the font of the heading.
+    pub fn font(mut self, font: FontDescriptor) -> PresetOptions {
+        self.font = Some(font);
+        self
+    }
+
+    /// Set the font of the subtitle.
+    pub fn subtitle_font(mut self, font: FontDescriptor) -> PresetOptions {
+        self.subtitle_font = Some(font);
+        self
+    }
+
+    /// Set the colour of the text.
+    pub fn text_color(mut self, color: impl Into<crate::Rgba>) -> PresetOptions {
+        self.text_color = Some(color.into().into());
+        self
+    }
+
+    /// Set the colour of the panels and frames some presets draw.
+    pub fn accent_color(mut self, color: impl Into<crate::Rgba>) -> PresetOptions {
+        self.accent_color = Some(color.into().into());
+        self
+    }
+
+    /// Set the space kept around the text, in pixels.
+    pub fn padding(mut self, padding: f32) -> PresetOptions {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Set the width of the frame drawn around the background by the postcard preset, in
+    /// pixels.
+    pub fn frame(mut self, frame: f32) -> PresetOptions {
+        self.frame = Some(frame);
+        self
+    }
+
+    /// Set how the text is aligned.
+    pub fn align(mut self, align: TextAlign) -> PresetOptions {
+        self.align = Some(align);
+        self
+    }
+
+    /// Set the smallest and largest sizes of the heading, in pixels.
+    pub fn font_size(mut self, min_size: f32, max_size: f32) -> PresetOptions {
+        self.font_size = Some((min_size, max_size));
+        self
+    }
+
+    /// Add a subtitle below the heading.
+    pub fn subtitle(mut self, subtitle: &str) -> PresetOptions {
+        self.subtitle = Some(subtitle.to_string());
+        self
+    }
+
+    /// Add a logo to the bottom corner.
+    pub fn logo(mut self, logo: DynamicImage) -> PresetOptions {
+        self.logo = Some(logo);
+        self
+    }
+
+    /// Set the direction of the text.
+    pub fn direction(mut self, direction: TextDirection) -> PresetOptions {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the effects and fill of the text.
+    pub fn style(mut self, style: TextStyle) -> PresetOptions {
+        self.style = style;
+        self
     }
 }
 
-///  Repeat the same text on each line, with each line changing in shade.
+/// A built-in preset.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Preset {
+    CentreText,
+    TextShades,
+    RepeatText,
+    TextBanner,
+    VerticalText,
+    RhsText,
+    LhsText,
+    VerticalTextRhs,
+    Quote,
+    Postcard,
+}
+
+/// Something a preset is made from, such as its heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PresetInput {
+    /// Name of the input, which is the name of its field in [`PresetOptions`] for the
+    /// optional inputs.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether the preset needs the input. Optional inputs can be left out.
+    pub required: bool,
+}
+
+impl Preset {
+    /// Every preset.
+    pub const ALL: [Preset; 10] = [
+        Preset::CentreText,
+        Preset::TextShades,
+        Preset::RepeatText,
+        Preset::TextBanner,
+        Preset::VerticalText,
+        Preset::RhsText,
+        Preset::LhsText,
+        Preset::VerticalTextRhs,
+        Preset::Quote,
+        Preset::Postcard,
+    ];
+
+    /// Name of the preset, which is the name of its function, such as "text_banner".
+    pub fn name(self) -> &'static str {
+        self.spec().name
+    }
+
+    /// Find a preset by name. Case, hyphens and spaces are ignored, so "Text Banner" finds
+    /// "text_banner".
+    pub fn from_name(name: &str) -> Option<Preset> {
+        let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+        Preset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.name() == name)
+    }
+
+    /// What the preset looks like.
+    pub fn description(self) -> &'static str {
+        self.spec().description
+    }
+
+    /// The inputs the preset takes, with the required ones first.
+    pub fn inputs(self) -> &'static [PresetInput] {
+        self.spec().inputs
+    }
+
+    /// Draw the preset over a background, returning a new image.
+    ///
+    /// ### Arguments
+    /// * `background` - Image the preset is drawn over.
+    /// * `fonts` - FontRegistry the fonts are taken from.
+    /// * `main_text` - Main heading for the graphic, which can use rich text markup.
+    /// * `options` - Fonts, colours and layout that replace the preset's own.
+    pub fn render(
+        self,
+        background: &DynamicImage,
+        fonts: &FontRegistry,
+        main_text: &str,
+        options: &PresetOptions,
+    ) -> Result<DynamicImage> {
+        match self.spec().layout {
+            Layout::Postcard { .. } => postcard(
+                background,
+                fonts,
+                main_text,
+                background.width(),
+                background.height(),
+                options,
+            ),
+            _ => {
+                let mut img = background.clone();
+                self.draw(&mut img, fonts, main_text, options)?;
+                Ok(img)
+            }
+        }
+    }
+
+    fn spec(self) -> &'static PresetSpec {
+        &PRESETS[self as usize]
+    }
+
+    fn draw(
+        self,
+        img: &mut DynamicImage,
+        fonts: &FontRegistry,
+        main_text: &str,
+        options: &PresetOptions,
+    ) -> Result<()> {
+        let settings = Settings::new(self.spec(), options, img, main_text);
+        match self.spec().layout {
+            Layout::Block(area) | Layout::Postcard { area, .. } => {
+                draw_block(img, fonts, main_text, area, options, &settings)?
+            }
+            Layout::Repeat { shade } => draw_repeated(img, fonts, main_text, shade, &settings)?,
+            Layout::Turned { panel } => draw_turned(img, fonts, main_text, panel, &settings)?,
+        }
+        if let Some(logo) = &options.logo {
+            draw_logo(img, logo, &settings);
+        }
+        Ok(())
+    }
+}
+
+/// Every preset, with its description and inputs, as a JSON array for listing in a UI.
+///
+/// Each preset is written as `{"name": ..., "description": ..., "inputs": [...]}`, and each
+/// input as `{"name": ..., "description": ..., "required": ...}`.
+#[wasm_bindgen]
+pub fn preset_catalog() -> String {
+    let catalog: Vec<_> = Preset::ALL
+        .iter()
+        .map(|preset| {
+            serde_json::json!({
+                "name": preset.name(),
+                "description": preset.description(),
+                "inputs": preset.inputs(),
+            })
+        })
+        .collect();
+    serde_json::Value::from(catalog).to_string()
+}
+
+/// Draw a preset by name with the embedded fonts, for use from JavaScript.
+///
+/// ### Arguments
+/// * `name` - Name of the preset, as listed by `preset_catalog`.
+/// * `background` - Image the preset is drawn over.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - JSON object of options, as read by `PresetOptions::from_json`.
+#[cfg(feature = "embedded-fonts")]
+#[wasm_bindgen]
+pub fn render_preset(
+    name: &str,
+    background: &PhotonImage,
+    main_text: &str,
+    options: &str,
+) -> std::result::Result<PhotonImage, JsValue> {
+    let preset = Preset::from_name(name).ok_or_else(|| Error::UnknownPreset(name.to_string()))?;
+    let options = PresetOptions::from_json(options)?;
+    let background = dyn_image_from_raw(background)?;
+    let img = with_embedded_fonts(|fonts| preset.render(&background, fonts, main_text, &options))?;
+    Ok(dyn_to_photonimg(&img))
+}
+
+///  Centre text, with background image.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - ù32 - Desired height of final graphic
-
-pub fn text_shades(background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own.
+pub fn centre_text(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::CentreText.draw(background_img, fonts, main_text, options)
+}
 
-    let mut height_mul: f32 = 0.05;
-    let white_rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-    for _ in 0..(height / 50) as usize {
-        draw_text(
-            background_img,
-            main_text,
-            (width as f32 * 0.05) as u32,
-            (height as f32 * height_mul) as u32,
-            "BebasKai",
-            110.0,
-            &white_rgb,
-        );
-        height_mul += 0.1;
-    }
+///  Repeat the same text on each line, with each line changing in shade.
+///
+/// # Arguments
+/// * `img` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own.
+pub fn text_shades(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::TextShades.draw(background_img, fonts, main_text, options)
 }
 
 ///  Repeat the same text on each line.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - ù32 - Desired height of final graphic
-pub fn repeat_text(background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let mut height_mul: f32 = 0.05;
-    let white_rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-    for _ in 0..(height / 50) as usize {
-        draw_text(
-            background_img,
-            main_text,
-            (width as f32 * 0.05) as u32,
-            (height as f32 * height_mul) as u32,
-            "BebasKai",
-            110.0,
-            &white_rgb,
-        );
-        height_mul += 0.1;
-    }
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own.
+pub fn repeat_text(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::RepeatText.draw(background_img, fonts, main_text, options)
 }
 
 ///  Text banner.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - u32 - Desired height of final graphic
-pub fn text_banner(background_img: &mut DynamicImage, main_text: &str, small_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let height_mul: f32 = 0.4;
-    let black_rgb = Rgb { r: 0, g: 0, b: 0 };
-
-    draw_text(
-        background_img,
-        main_text,
-        (width as f32 * 0.15) as u32,
-        (height as f32 * height_mul) as u32,
-        "BebasKai",
-        110.0,
-        &black_rgb,
-    );
-    draw_text(
-        background_img,
-        small_text,
-        (width as f32 * 0.28) as u32,
-        (height as f32 * (height_mul + 0.15)) as u32,
-        "BebasKai",
-        30.0,
-        &black_rgb,
-    );
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own. Its subtitle is
+///   drawn below the heading.
+pub fn text_banner(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::TextBanner.draw(background_img, fonts, main_text, options)
 }
 
 ///  Vertical text banner.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - ù32 - Desired height of final graphic
-pub fn vertical_text(mut background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let height_mul: f32 = 0.4;
-    let black_rgb = Rgb { r: 0, g: 0, b: 0 };
-    draw_solid_rect(
-        &mut background_img,
-        &black_rgb,
-        height,
-        (width as f32 * 0.2) as u32,
-        0,
-        0,
-    );
-
-    let white_rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-
-    draw_vertical_text(
-        background_img,
-        main_text,
-        (width as f32 * 0.15) as u32,
-        (height as f32 * height_mul) as u32,
-        "BebasKai",
-        110.0,
-        "right",
-        &white_rgb,
-    );
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic. Any rich text markup is removed, as
+///   vertical text is drawn in a single style.
+/// * `options` - Fonts, colours and layout that replace the preset's own.
+pub fn vertical_text(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::VerticalText.draw(background_img, fonts, main_text, options)
 }
 
 ///  Right-hand side text.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-pub fn rhs_text(background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let height_mul: f32 = 0.4;
-    let rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-
-    draw_text(
-        background_img,
-        main_text,
-        (width as f32 * 0.65) as u32,
-        (height as f32 * height_mul) as u32,
-        "BebasKai",
-        130.0,
-        &rgb,
-    );
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own. Right-to-left
+///   text is placed on the left-hand side, mirroring left-to-right text.
+pub fn rhs_text(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::RhsText.draw(background_img, fonts, main_text, options)
 }
 
 ///  Left-hand side text.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - u32 - Desired height of final graphic
-pub fn lhs_text(background_img: &mut DynamicImage, main_text: &str, _small_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let height_mul: f32 = 0.4;
-    let black_rgb = Rgb { r: 0, g: 0, b: 0 };
-
-    draw_text(
-        background_img,
-        main_text,
-        (width as f32 * 0.15) as u32,
-        (height as f32 * height_mul) as u32,
-        "BebasKai",
-        130.0,
-        &black_rgb,
-    );
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own. Right-to-left
+///   text is placed on the right-hand side, mirroring left-to-right text.
+pub fn lhs_text(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::LhsText.draw(background_img, fonts, main_text, options)
 }
 
 ///  Right-hand side vertical text.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-pub fn vertical_text_rhs(mut background_img: &mut DynamicImage, main_text: &str) {
-    let width = background_img.width();
-    let height = background_img.height();
-
-    let red_rgb = Rgb {
-        r: 200,
-        g: 20,
-        b: 50,
-    };
-    let white_rgb = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
-
-    let height_mul = 0.1;
-    draw_solid_rect(
-        &mut background_img,
-        &white_rgb,
-        (width as f32 * 0.2) as u32,
-        height,
-        (width as f32 * 0.8) as i32,
-        0,
-    );
-    draw_vertical_text(
-        &mut background_img,
-        main_text,
-        (width as f32 * 0.85) as u32,
-        (height as f32 * height_mul) as u32,
-        "BebasKai",
-        100.0,
-        "right",
-        &red_rgb,
-    );
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic. Any rich text markup is removed, as
+///   vertical text is drawn in a single style.
+/// * `options` - Fonts, colours and layout that replace the preset's own.
+pub fn vertical_text_rhs(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::VerticalTextRhs.draw(background_img, fonts, main_text, options)
 }
 
 ///  Quote-style graphic, featuring prominence on the main text.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
-/// * `width` - u32 - Desired width of final graphic
-/// * `height` - ù32 - Desired height of final graphic
-pub fn quote(mut background_img: &mut DynamicImage, main_text: &str, _small_text: &str) {
-    let _width = background_img.width();
-    let height = background_img.height();
-
-    let black_rgb = Rgb { r: 0, g: 0, b: 0 };
-
-    let mut height_mul = 0.1;
-    let font_size = 100.0;
-
-    let group_vec = text_to_vec(main_text, background_img.width(), font_size);
-    for word_vec in group_vec {
-        let text = word_vec.join(" ");
-        draw_text(
-            &mut background_img,
-            &text,
-            0,
-            (height as f32 * height_mul) as u32,
-            "Oswald",
-            font_size,
-            &black_rgb,
-        );
-        height_mul += 0.1;
-    }
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
+/// * `options` - Fonts, colours and layout that replace the preset's own. Its subtitle,
+///   such as who is being quoted, is drawn below the quote.
+pub fn quote(
+    background_img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    options: &PresetOptions,
+) -> Result<()> {
+    Preset::Quote.draw(background_img, fonts, main_text, options)
 }
 
 ///  Postcard-style image, featuring main text overlayed onto the image.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
-/// * `main_text` - Main heading for the graphic.
-/// * `small_text` - Sub-heading/smaller text.
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `main_text` - Main heading for the graphic, which can use rich text markup.
 /// * `width` - u32 - Desired width of final graphic
-/// * `height` - ù32 - Desired height of final graphic
+/// * `height` - u32 - Desired height of final graphic
+/// * `options` - Fonts, colours and layout that replace the preset's own. The image is
+///   framed in the accent colour, as wide as `options.frame`.
 pub fn postcard(
     background_img: &DynamicImage,
+    fonts: &FontRegistry,
     main_text: &str,
-    _small_text: &str,
     width: u32,
     height: u32,
-) -> DynamicImage {
-    let white = Rgb {
-        r: 255,
-        g: 255,
-        b: 255,
+    options: &PresetOptions,
+) -> Result<DynamicImage> {
+    let spec = Preset::Postcard.spec();
+    let default_frame = match spec.layout {
+        Layout::Postcard { frame, .. } => frame,
+        _ => 0.0,
     };
+    let frame = options
+        .frame
+        .unwrap_or(default_frame * width.min(height) as f32)
+        .round()
+        .max(0.0) as u32;
+    let accent = options.accent_color.unwrap_or(Rgba(spec.accent_color));
 
-    let mut container_img = new_with_background(width, height, &white);
-    let sampling_filter = image::imageops::FilterType::Nearest;
+    // The background image is inset by the frame on every side. The frame comes from the
+    // options, so it can be too wide to double without overflowing.
+    let inset = match frame.checked_mul(2) {
+        Some(inset) if inset < width.min(height) => inset,
+        _ => {
+            return Err(Error::OutOfBounds {
+                x: frame as i64,
+                y: frame as i64,
+                width,
+                height,
+            })
+        }
+    };
 
+    let mut container_img =
+        DynamicImage::ImageRgba8(image::ImageBuffer::from_pixel(width, height, accent));
     let resized_img = image::DynamicImage::ImageRgba8(image::imageops::resize(
         background_img,
-        width - 20,
-        height - 20,
-        sampling_filter,
+        width - inset,
+        height - inset,
+        image::imageops::FilterType::Triangle,
     ));
+    composite(
+        &mut container_img,
+        &resized_img,
+        frame as i32,
+        frame as i32,
+        BlendMode::Normal,
+        1.0,
+    );
 
-    let main_img_width = background_img.width();
-    let main_img_height = background_img.height();
+    Preset::Postcard.draw(&mut container_img, fonts, main_text, options)?;
+    Ok(container_img)
+}
 
-    image::imageops::overlay(&mut container_img, &resized_img, 10, 10);
+// A part of the graphic, as fractions of its width and height: x, y, width and height.
+type Area = (f32, f32, f32, f32);
 
-    let height_mul: f32 = 0.2;
-    let black_rgb = Rgb { r: 0, g: 0, b: 0 };
+// How a preset arranges its text.
+#[derive(Clone, Copy)]
+enum Layout {
+    // The heading and subtitle fitted to an area, and centred within it vertically.
+    Block(Area),
+    // The heading repeated on each line, changing in shade with each line if `shade` is set.
+    Repeat { shade: bool },
+    // A panel in the accent colour, with the heading turned to read from top to bottom on it.
+    Turned { panel: Area },
+    // The heading and subtitle fitted to an area of the background, which is framed in the
+    // accent colour. The frame's width is a fraction of the smaller side of the graphic.
+    Postcard { area: Area, frame: f32 },
+}
 
-    draw_text(
-        &mut container_img,
-        main_text,
-        (main_img_width as f32 * 0.15) as u32,
-        (main_img_height as f32 * height_mul) as u32,
-        "MrDafoe-Regular",
-        (width / 4) as f32,
-        &black_rgb,
+// Everything about a preset that options can change, along with how it is described.
+struct PresetSpec {
+    name: &'static str,
+    description: &'static str,
+    inputs: &'static [PresetInput],
+    layout: Layout,
+    font: &'static str,
+    subtitle_font: &'static str,
+    text_color: [u8; 4],
+    accent_color: [u8; 4],
+    font_size: (f32, f32),
+    align: TextAlign,
+    // Space around the text, as a fraction of the shorter side of the graphic.
+    padding: f32,
+}
+
+const BACKGROUND: PresetInput = PresetInput {
+    name: "background",
+    description: "Image the text is drawn over.",
+    required: true,
+};
+const MAIN_TEXT: PresetInput = PresetInput {
+    name: "main_text",
+    description: "Main heading, which can use rich text markup.",
+    required: true,
+};
+const PLAIN_MAIN_TEXT: PresetInput = PresetInput {
+    name: "main_text",
+    description: "Main heading. Any markup is removed, as turned text has a single style.",
+    required: true,
+};
+const SUBTITLE: PresetInput = PresetInput {
+    name: "subtitle",
+    description: "Smaller text below the heading.",
+    required: false,
+};
+const LOGO: PresetInput = PresetInput {
+    name: "logo",
+    description: "Image drawn in the bottom corner.",
+    required: false,
+};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+// The presets, in the order of `Preset`.
+const PRESETS: [PresetSpec; 10] = [
+    PresetSpec {
+        name: "centre_text",
+        description: "Large heading filling most of the graphic, with room on the left.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Block((0.3, 0.05, 0.65, 0.9)),
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: WHITE,
+        accent_color: BLACK,
+        font_size: (40.0, 150.0),
+        align: TextAlign::Start,
+        padding: 0.0,
+    },
+    PresetSpec {
+        name: "text_shades",
+        description: "Heading repeated down the graphic, changing in shade with each line.",
+        inputs: &[BACKGROUND, MAIN_TEXT, LOGO],
+        layout: Layout::Repeat { shade: true },
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: WHITE,
+        accent_color: BLACK,
+        font_size: (40.0, 110.0),
+        align: TextAlign::Start,
+        padding: 0.05,
+    },
+    PresetSpec {
+        name: "repeat_text",
+        description: "Heading repeated down the graphic.",
+        inputs: &[BACKGROUND, MAIN_TEXT, LOGO],
+        layout: Layout::Repeat { shade: false },
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: WHITE,
+        accent_color: BLACK,
+        font_size: (40.0, 110.0),
+        align: TextAlign::Start,
+        padding: 0.05,
+    },
+    PresetSpec {
+        name: "text_banner",
+        description: "Heading across the middle of the graphic, with a subtitle below it.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Block((0.1, 0.25, 0.8, 0.5)),
+        font: "Bebas Kai",
+        subtitle_font: "Bebas Kai",
+        text_color: BLACK,
+        accent_color: BLACK,
+        font_size: (40.0, 110.0),
+        align: TextAlign::Centre,
+        padding: 0.0,
+    },
+    PresetSpec {
+        name: "vertical_text",
+        description: "Panel down the left-hand side, with the heading turned to run down it.",
+        inputs: &[BACKGROUND, PLAIN_MAIN_TEXT, LOGO],
+        layout: Layout::Turned {
+            panel: (0.0, 0.0, 0.2, 1.0),
+        },
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: WHITE,
+        accent_color: BLACK,
+        font_size: (40.0, 110.0),
+        align: TextAlign::Centre,
+        padding: 0.05,
+    },
+    PresetSpec {
+        name: "rhs_text",
+        description: "Heading on the right-hand side of the graphic.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Block((0.6, 0.1, 0.35, 0.8)),
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: WHITE,
+        accent_color: BLACK,
+        font_size: (40.0, 130.0),
+        align: TextAlign::Start,
+        padding: 0.0,
+    },
+    PresetSpec {
+        name: "lhs_text",
+        description: "Heading on the left-hand side of the graphic.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Block((0.1, 0.1, 0.45, 0.8)),
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: BLACK,
+        accent_color: BLACK,
+        font_size: (40.0, 130.0),
+        align: TextAlign::Start,
+        padding: 0.0,
+    },
+    PresetSpec {
+        name: "vertical_text_rhs",
+        description: "Panel down the right-hand side, with the heading turned to run down it.",
+        inputs: &[BACKGROUND, PLAIN_MAIN_TEXT, LOGO],
+        layout: Layout::Turned {
+            panel: (0.8, 0.0, 0.2, 1.0),
+        },
+        font: "Bebas Kai",
+        subtitle_font: "Lato",
+        text_color: [200, 20, 50, 255],
+        accent_color: WHITE,
+        font_size: (40.0, 100.0),
+        align: TextAlign::Start,
+        padding: 0.05,
+    },
+    PresetSpec {
+        name: "quote",
+        description: "Quote filling the graphic, with who said it below.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Block((0.0, 0.1, 1.0, 0.8)),
+        font: "Oswald",
+        subtitle_font: "Lato",
+        text_color: BLACK,
+        accent_color: BLACK,
+        font_size: (30.0, 100.0),
+        align: TextAlign::Start,
+        padding: 0.0125,
+    },
+    PresetSpec {
+        name: "postcard",
+        description: "Framed image with a handwritten heading across the top.",
+        inputs: &[BACKGROUND, MAIN_TEXT, SUBTITLE, LOGO],
+        layout: Layout::Postcard {
+            area: (0.1, 0.1, 0.8, 0.5),
+            frame: 0.0125,
+        },
+        font: "Mr Dafoe",
+        subtitle_font: "Lato",
+        text_color: BLACK,
+        accent_color: WHITE,
+        font_size: (40.0, 200.0),
+        align: TextAlign::Start,
+        padding: 0.0125,
+    },
+];
+
+// Options as they are written in JSON.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OptionsSpec {
+    font: Option<String>,
+    subtitle_font: Option<String>,
+    text_color: Option<String>,
+    accent_color: Option<String>,
+    padding: Option<f32>,
+    frame: Option<f32>,
+    align: Option<String>,
+    font_size: Option<(f32, f32)>,
+    subtitle: Option<String>,
+    logo: Option<String>,
+    direction: Option<String>,
+    style: Option<StyleSpec>,
+}
+
+// A text style's effects as they are written in JSON.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleSpec {
+    stroke: Option<StrokeSpec>,
+    shadow: Option<ShadowSpec>,
+    glow: Option<GlowSpec>,
+    contrast: Option<ContrastSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StrokeSpec {
+    width: u8,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShadowSpec {
+    x: Option<i32>,
+    y: Option<i32>,
+    blur: Option<f32>,
+    color: Option<String>,
+    opacity: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GlowSpec {
+    radius: u32,
+    color: Option<String>,
+    opacity: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContrastSpec {
+    level: Option<String>,
+    fix: Option<String>,
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<TextStyle> {
+        let mut style = TextStyle::new();
+        if let Some(stroke) = &self.stroke {
+            let color = optional_color("style.stroke.color", &stroke.color)?;
+            style = style.stroke(stroke.width, color.unwrap_or(Rgba([0, 0, 0, 255])));
+        }
+        if let Some(spec) = &self.shadow {
+            let mut shadow = Shadow::new(spec.x.unwrap_or(4), spec.y.unwrap_or(4));
+            if let Some(blur) = spec.blur {
+                shadow = shadow.blur(blur.max(0.0));
+            }
+            if let Some(color) = optional_color("style.shadow.color", &spec.color)? {
+                shadow = shadow.color(color);
+            }
+            if let Some(opacity) = spec.opacity {
+                shadow = shadow.opacity(opacity.clamp(0.0, 1.0));
+            }
+            style = style.shadow(shadow);
+        }
+        if let Some(spec) = &self.glow {
+            let mut glow = Glow::new(spec.radius);
+            if let Some(color) = optional_color("style.glow.color", &spec.color)? {
+                glow = glow.color(color);
+            }
+            if let Some(opacity) = spec.opacity {
+                glow = glow.opacity(opacity.clamp(0.0, 1.0));
+            }
+            style = style.glow(glow);
+        }
+        if let Some(contrast) = &self.contrast {
+            let level = match contrast.level.as_deref().map(str::to_lowercase).as_deref() {
+                None | Some("aa") => ContrastLevel::Aa,
+                Some("aaa") => ContrastLevel::Aaa,
+                Some(other) => {
+                    return Err(Error::InvalidPreset(format!(
+                        "style.contrast.level: unknown level \"{}\", expected \"aa\" or \"aaa\"",
+                        other
+                    )))
+                }
+            };
+            let fix = match contrast.fix.as_deref().map(str::to_lowercase).as_deref() {
+                None | Some("pick_color") => ContrastFix::PickColor,
+                Some("scrim") => ContrastFix::Scrim,
+                Some(other) => {
+                    return Err(Error::InvalidPreset(format!(
+                    "style.contrast.fix: unknown fix \"{}\", expected \"pick_color\" or \"scrim\"",
+                    other
+                )))
+                }
+            };
+            style = style.auto_contrast(level, fix);
+        }
+        Ok(style)
+    }
+}
+
+// Parse an option's colour, if it is set.
+fn optional_color(name: &str, color: &Option<String>) -> Result<Option<Rgba<u8>>> {
+    color
+        .as_deref()
+        .map(|color| {
+            color.parse::<crate::Rgba>().map(Rgba::from).map_err(|_| {
+                Error::InvalidPreset(format!("{}: invalid colour \"{}\"", name, color))
+            })
+        })
+        .transpose()
+}
+
+// A preset's settings, with the options given in place of its own.
+struct Settings {
+    font: FontDescriptor,
+    subtitle_font: FontDescriptor,
+    accent_color: Rgba<u8>,
+    padding: f32,
+    align: TextAlign,
+    font_size: (f32, f32),
+    // The direction of the heading, resolved.
+    direction: TextDirection,
+    style: TextStyle,
+}
+
+impl Settings {
+    fn new(
+        spec: &PresetSpec,
+        options: &PresetOptions,
+        img: &DynamicImage,
+        main_text: &str,
+    ) -> Settings {
+        let (width, height) = img.dimensions();
+        let (min_size, max_size) = options.font_size.unwrap_or(spec.font_size);
+        let mut style = options.style.clone();
+        if style.fill.is_none() {
+            style = style.color(options.text_color.unwrap_or(Rgba(spec.text_color)));
+        }
+        Settings {
+            font: options
+                .font
+                .clone()
+                .unwrap_or_else(|| FontDescriptor::new(spec.font)),
+            subtitle_font: options
+                .subtitle_font
+                .clone()
+                .unwrap_or_else(|| FontDescriptor::new(spec.subtitle_font)),
+            accent_color: options.accent_color.unwrap_or(Rgba(spec.accent_color)),
+            padding: options
+                .padding
+                .unwrap_or(spec.padding * width.min(height) as f32)
+                .max(0.0),
+            align: options.align.unwrap_or(spec.align),
+            font_size: (min_size.max(1.0), max_size.max(min_size).max(1.0)),
+            direction: options
+                .direction
+                .resolve(&RichText::parse(main_text).plain_text()),
+            style,
+        }
+    }
+
+    // An area of the image in pixels, inset by the padding and mirrored for right-to-left text.
+    fn area(&self, img: &DynamicImage, (x, y, width, height): Area) -> (f32, f32, f32, f32) {
+        let (img_width, img_height) = img.dimensions();
+        let x = match self.direction {
+            TextDirection::RightToLeft => 1.0 - x - width,
+            _ => x,
+        };
+        let padding = self.padding;
+        (
+            x * img_width as f32 + padding,
+            y * img_height as f32 + padding,
+            (width * img_width as f32 - padding * 2.0).max(1.0),
+            (height * img_height as f32 - padding * 2.0).max(1.0),
+        )
+    }
+
+    fn paragraph_options(&self) -> ParagraphOptions {
+        ParagraphOptions::new()
+            .line_height(1.0)
+            .direction(self.direction)
+            .align(self.align)
+    }
+}
+
+// Fit the heading, and any subtitle below it, to an area, centring them within it vertically.
+fn draw_block(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    area: Area,
+    options: &PresetOptions,
+    settings: &Settings,
+) -> Result<()> {
+    let (x, y, width, height) = settings.area(img, area);
+    let (min_size, max_size) = settings.font_size;
+    let paragraph_options = settings.paragraph_options().max_width(width);
+
+    // Space is kept for the subtitle, which is drawn at most half the size of the heading.
+    let subtitle = options
+        .subtitle
+        .as_deref()
+        .filter(|subtitle| !subtitle.trim().is_empty())
+        .map(RichText::parse);
+    let subtitle_size = (max_size * 0.3).max(12.0);
+    let subtitle_height = match &subtitle {
+        Some(subtitle) => {
+            let paragraph = layout_rich_text(
+                fonts,
+                subtitle,
+                &settings.subtitle_font,
+                subtitle_size,
+                &paragraph_options,
+            )?;
+            paragraph.height + subtitle_size * 0.5
+        }
+        None => 0.0,
+    };
+
+    let fitted = fit_rich_paragraph(
+        fonts,
+        &RichText::parse(main_text),
+        &settings.font,
+        width,
+        (height - subtitle_height).max(1.0),
+        min_size,
+        max_size,
+        &paragraph_options,
+    )?;
+    let heading = &fitted.paragraph;
+    let subtitle = match &subtitle {
+        Some(subtitle) => Some(layout_rich_text(
+            fonts,
+            subtitle,
+            &settings.subtitle_font,
+            subtitle_size.min(fitted.font_size * 0.5).max(8.0),
+            &paragraph_options,
+        )?),
+        None => None,
+    };
+
+    let gap = subtitle
+        .as_ref()
+        .map_or(0.0, |_| subtitle_size.min(fitted.font_size * 0.5) * 0.5);
+    let total_height = heading.height + gap + subtitle.as_ref().map_or(0.0, |sub| sub.height);
+    let mut top = y + ((height - total_height) / 2.0).max(0.0);
+
+    let left = x.round() as i32;
+    let bounds = text_rect(left, top.round() as i32, width, heading.height);
+    let style = ensure_contrast(img, bounds, &settings.style, fitted.font_size);
+    draw_rich_paragraph(img, heading, left, top.round() as i32, &style);
+
+    if let Some(subtitle) = &subtitle {
+        top += heading.height + gap;
+        let bounds = text_rect(left, top.round() as i32, width, subtitle.height);
+        let size = subtitle_size.min(fitted.font_size * 0.5);
+        let style = ensure_contrast(img, bounds, &settings.style, size);
+        draw_rich_paragraph(img, subtitle, left, top.round() as i32, &style);
+    }
+    Ok(())
+}
+
+// Repeat the heading on each line, from the top of the image to the bottom.
+fn draw_repeated(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    shade: bool,
+    settings: &Settings,
+) -> Result<()> {
+    let (x, y, width, height) = settings.area(img, (0.0, 0.0, 1.0, 1.0));
+    let (min_size, max_size) = settings.font_size;
+    let text = RichText::parse(main_text);
+
+    // The heading is made as large as fits on one line.
+    let unwrapped = layout_rich_text(
+        fonts,
+        &text,
+        &settings.font,
+        max_size,
+        &settings.paragraph_options(),
+    )?;
+    let font_size = (max_size * width / unwrapped.widest_line().max(1.0)).clamp(min_size, max_size);
+    let paragraph = layout_rich_text(
+        fonts,
+        &text,
+        &settings.font,
+        font_size,
+        &settings.paragraph_options().max_width(width),
+    )?;
+
+    let step = paragraph.height.max(1.0);
+    let lines = ((height / step).ceil() as usize).max(1);
+    for line in 0..lines {
+        let mut style = settings.style.clone();
+        if let (true, Some(Fill::Solid(color))) = (shade, style.fill.clone()) {
+            // Each line is a shade further from the colour: darker for light text, and lighter
+            // for dark text.
+            let color = crate::Rgba::from(color);
+            let amount = 0.6 * line as f32 / lines.max(2).saturating_sub(1) as f32;
+            let shade = match color.to_oklab()[0] > 0.5 {
+                true => color.darken(amount),
+                false => color.lighten(amount),
+            };
+            style = style.color(shade);
+        }
+        let top = (y + step * line as f32).round() as i32;
+        let bounds = text_rect(x.round() as i32, top, width, paragraph.height);
+        let style = ensure_contrast(img, bounds, &style, font_size);
+        draw_rich_paragraph(img, &paragraph, x.round() as i32, top, &style);
+    }
+    Ok(())
+}
+
+// Fill a panel with the accent colour, and draw the heading turned to read down it.
+fn draw_turned(
+    img: &mut DynamicImage,
+    fonts: &FontRegistry,
+    main_text: &str,
+    panel: Area,
+    settings: &Settings,
+) -> Result<()> {
+    let (img_width, img_height) = img.dimensions();
+    let panel_x = match settings.direction {
+        TextDirection::RightToLeft => 1.0 - panel.0 - panel.2,
+        _ => panel.0,
+    };
+    fill_path(
+        img,
+        &Path::new().rect(
+            panel_x * img_width as f32,
+            panel.1 * img_height as f32,
+            panel.2 * img_width as f32,
+            panel.3 * img_height as f32,
+        ),
+        settings.accent_color,
+        FillRule::NonZero,
+    );
+
+    // Turned text runs along the panel, so it is fitted to the panel's length and breadth.
+    let (x, y, breadth, length) = settings.area(img, panel);
+    let (min_size, max_size) = settings.font_size;
+    let text = RichText::parse(main_text).plain_text();
+    let measure_options = ParagraphOptions::new();
+    let metrics = measure_text(fonts, &text, &settings.font, max_size, &measure_options)?;
+    let scale = (length / metrics.width.max(1.0)).min(breadth / metrics.height.max(1.0));
+    let font_size = (max_size * scale).clamp(min_size, max_size);
+    let metrics = measure_text(fonts, &text, &settings.font, font_size, &measure_options)?;
+
+    let left = x + ((breadth - metrics.height) / 2.0).max(0.0);
+    let top = match settings.align {
+        TextAlign::Centre => y + ((length - metrics.width) / 2.0).max(0.0),
+        TextAlign::End | TextAlign::Right => y + (length - metrics.width).max(0.0),
+        _ => y,
+    };
+    let bounds = text_rect(
+        left.round() as i32,
+        top.round() as i32,
+        metrics.height,
+        metrics.width,
     );
-    return container_img;
-}
-
-// Convert a string of text to a vector containing vecs of words,
-// which will fit on an individual line or within a constraint.
-fn text_to_vec(text: &str, width: u32, font_size: f32) -> Vec<Vec<&str>> {
-    let mut word_vec = vec![];
-    let font_size: f32 = font_size as f32 * 0.8;
-    let mut group_vec = vec![];
-    let mut total_width = 0;
-    for word in text.split_whitespace() {
-        // get len of word, num of chars, and multiply by font size.
-        let width_word = font_size * word.len() as f32;
-        total_width += width_word as u32;
-
-        if total_width > width {
-            group_vec.push(word_vec);
-            word_vec = vec![];
-            word_vec.push(word);
-            total_width = width_word as u32;
-        } else {
-            word_vec.push(word);
+    let style = ensure_contrast(img, bounds, &settings.style, font_size);
+    draw_vertical_text(
+        img,
+        fonts,
+        &text,
+        left.max(0.0).round() as u32,
+        top.max(0.0).round() as u32,
+        &settings.font,
+        font_size,
+        "right",
+        &style,
+    )
+}
+
+// Draw a logo in the bottom corner at the end of the text's lines, scaled to an eighth of the
+// shorter side of the image.
+fn draw_logo(img: &mut DynamicImage, logo: &DynamicImage, settings: &Settings) {
+    let (width, height) = img.dimensions();
+    let (logo_width, logo_height) = logo.dimensions();
+    if logo_width == 0 || logo_height == 0 {
+        return;
+    }
+    let size = (width.min(height) as f32 / 8.0).max(1.0);
+    let scale = size / logo_width.max(logo_height) as f32;
+    let scaled_width = ((logo_width as f32 * scale).round() as u32).max(1);
+    let scaled_height = ((logo_height as f32 * scale).round() as u32).max(1);
+    let scaled = DynamicImage::ImageRgba8(image::imageops::resize(
+        logo,
+        scaled_width,
+        scaled_height,
+        image::imageops::FilterType::Triangle,
+    ));
+
+    let margin = settings
+        .padding
+        .max(width.min(height) as f32 * 0.03)
+        .round() as i32;
+    let x = match settings.direction {
+        TextDirection::RightToLeft => margin,
+        _ => width as i32 - margin - scaled_width as i32,
+    };
+    let y = height as i32 - margin - scaled_height as i32;
+    composite(img, &scaled, x, y, BlendMode::Normal, 1.0);
+}
+
+// The box around text drawn at the given position.
+fn text_rect(x: i32, y: i32, width: f32, height: f32) -> Rect {
+    Rect::at(x, y).of_size((width.ceil() as u32).max(1), (height.ceil() as u32).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn reason(result: Result<PresetOptions>) -> String {
+        match result {
+            Err(Error::InvalidPreset(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the options were accepted"),
         }
     }
 
-    group_vec.push(word_vec);
+    #[test]
+    fn empty_options_leave_everything_to_the_preset() {
+        let options = PresetOptions::from_json("{}").unwrap();
+        assert!(options.text_color.is_none());
+        assert!(options.padding.is_none() && options.frame.is_none());
+        assert!(options.style.fill.is_none() && options.style.stroke.is_none());
+    }
 
-    return group_vec;
+    #[test]
+    fn malformed_options_are_rejected() {
+        reason(PresetOptions::from_json(""));
+        reason(PresetOptions::from_json("[]"));
+        reason(PresetOptions::from_json(r#"{"colour": "red"}"#));
+        reason(PresetOptions::from_json(r#"{"font_size": [10]}"#));
+        let bad_colour = reason(PresetOptions::from_json(r##"{"text_color": "#12"}"##));
+        assert!(bad_colour.starts_with("text_color"), "{}", bad_colour);
+        let bad_align = reason(PresetOptions::from_json(r#"{"align": "middle"}"#));
+        assert!(bad_align.starts_with("align"), "{}", bad_align);
+    }
+
+    #[test]
+    fn style_is_read_from_json() {
+        let options = PresetOptions::from_json(
+            r#"{"style": {
+                "stroke": {"width": 3, "color": "white"},
+                "shadow": {"x": 2, "blur": 0, "opacity": 2},
+                "glow": {"radius": 6, "color": "gold"},
+                "contrast": {"level": "AAA", "fix": "scrim"}
+            }}"#,
+        )
+        .unwrap();
+        let style = options.style;
+        let stroke = style.stroke.unwrap();
+        assert_eq!(
+            (stroke.width, stroke.color),
+            (3, Rgba([255, 255, 255, 255]))
+        );
+        let shadow = style.shadow.unwrap();
+        assert_eq!((shadow.offset_x, shadow.offset_y), (2, 4));
+        assert_eq!((shadow.blur, shadow.opacity), (0.0, 1.0));
+        assert_eq!(style.glow.unwrap().color, Rgba([255, 215, 0, 255]));
+        let contrast = style.contrast.unwrap();
+        assert_eq!(contrast.level, ContrastLevel::Aaa);
+        assert_eq!(contrast.fix, ContrastFix::Scrim);
+        assert!(style.fill.is_none());
+    }
+
+    #[test]
+    fn malformed_styles_are_rejected() {
+        reason(PresetOptions::from_json(
+            r#"{"style": {"stroke": {"color": "red"}}}"#,
+        ));
+        reason(PresetOptions::from_json(
+            r#"{"style": {"outline": {"width": 2}}}"#,
+        ));
+        let bad_colour = reason(PresetOptions::from_json(
+            r#"{"style": {"shadow": {"color": "nope"}}}"#,
+        ));
+        assert!(
+            bad_colour.starts_with("style.shadow.color"),
+            "{}",
+            bad_colour
+        );
+        let bad_level = reason(PresetOptions::from_json(
+            r#"{"style": {"contrast": {"level": "a"}}}"#,
+        ));
+        assert!(
+            bad_level.starts_with("style.contrast.level"),
+            "{}",
+            bad_level
+        );
+    }
+
+    #[test]
+    fn preset_names_are_forgiving() {
+        assert_eq!(Preset::from_name("Text Banner"), Some(Preset::TextBanner));
+        assert_eq!(
+            Preset::from_name(" vertical-text-rhs "),
+            Some(Preset::VerticalTextRhs)
+        );
+        assert_eq!(Preset::from_name(""), None);
+        for preset in Preset::ALL.iter() {
+            assert_eq!(Preset::from_name(preset.name()), Some(*preset));
+            assert!(preset.inputs()[0].required);
+        }
+    }
+
+    #[test]
+    fn postcard_needs_room_inside_its_frame() {
+        let background = DynamicImage::new_rgba8(10, 10);
+        let options = PresetOptions::new().frame(10.0).padding(0.0);
+        let framed = postcard(&background, &FontRegistry::new(), "", 20, 20, &options);
+        assert!(matches!(framed, Err(Error::OutOfBounds { .. })));
+        for &frame in &[1e12, f32::MAX, f32::INFINITY, (u32::MAX / 2 + 1) as f32] {
+            let options = PresetOptions::new().frame(frame);
+            let framed = postcard(&background, &FontRegistry::new(), "", 20, 20, &options);
+            assert!(
+                matches!(framed, Err(Error::OutOfBounds { .. })),
+                "{}",
+                frame
+            );
+        }
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn postcard_is_framed_by_the_frame_option() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let background = DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 100, red));
+        let fonts = FontRegistry::embedded();
+        let options = PresetOptions::new()
+            .frame(10.0)
+            .padding(40.0)
+            .accent_color(blue);
+        let img = postcard(&background, &fonts, "Hi", 200, 100, &options).unwrap();
+        assert_eq!(img.get_pixel(9, 50), blue);
+        assert_eq!(img.get_pixel(10, 99 - 10), red);
+        assert_eq!(img.get_pixel(190, 50), blue);
+    }
+
+    // The mean x-coordinate of the pixels a preset changed, as a share of the image's width.
+    #[cfg(feature = "embedded-fonts")]
+    fn ink_centre(preset: Preset, text: &str) -> f32 {
+        let background =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 200, Rgba([0, 0, 0, 255])));
+        let mut img = background.clone();
+        let fonts = FontRegistry::embedded();
+        let options = PresetOptions::new().text_color(Rgba([255, 255, 255, 255]));
+        preset.draw(&mut img, &fonts, text, &options).unwrap();
+        let inked: Vec<u32> = img
+            .pixels()
+            .filter(|&(x, y, pixel)| pixel != background.get_pixel(x, y))
+            .map(|(x, _, _)| x)
+            .collect();
+        assert!(!inked.is_empty(), "nothing was drawn for {:?}", text);
+        inked.iter().sum::<u32>() as f32 / inked.len() as f32 / 400.0
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn right_to_left_text_is_mirrored() {
+        // Hebrew and Arabic text is placed on the opposite side to English.
+        for &(preset, ltr_side) in &[(Preset::LhsText, 0.25), (Preset::RhsText, 0.75)] {
+            let ltr = ink_centre(preset, "Hello there");
+            assert!((ltr - ltr_side).abs() < 0.1, "{}: {}", preset.name(), ltr);
+            for text in &["שלום עולם", "مرحبا بالعالم"] {
+                let rtl = ink_centre(preset, text);
+                let mirrored = 1.0 - ltr_side;
+                assert!(
+                    (rtl - mirrored).abs() < 0.1,
+                    "{} {:?}: {}",
+                    preset.name(),
+                    text,
+                    rtl
+                );
+            }
+        }
+    }
 }
@@ -1,6 +1,6 @@
 //! Resize images to specific sizes/for various social media platforms.
 
-use crate::{helpers, PhotonImage};
+use crate::{helpers, PhotonImage, Result};
 use image::GenericImageView;
 use wasm_bindgen::prelude::*;
 
@@ -14,10 +14,11 @@ use wasm_bindgen::prelude::*;
 /// ```ignore
 /// resize_socialmedia(&mut img, "linkedin_banner");
 /// ```
-#[wasm_bindgen]
-pub fn resize_socialmedia(img: &PhotonImage, format: &str) -> PhotonImage {
+///
+/// Returns an error if the image's pixels don't match its width and height.
+pub fn resize_socialmedia(img: &PhotonImage, format: &str) -> Result<PhotonImage> {
     let sampling_filter = image::imageops::FilterType::Nearest;
-    let dynimage = helpers::dyn_image_from_raw(&img);
+    let dynimage = helpers::dyn_image_from_raw(img)?;
     let (width, height) = match format {
         "linkedin_banner" => (1400, 425),
         "pinterest" => (735, 1102),
@@ -35,26 +36,36 @@ pub fn resize_socialmedia(img: &PhotonImage, format: &str) -> PhotonImage {
         sampling_filter,
     ));
     let raw_pixels = resized_img.to_bytes();
-    return PhotonImage {
+    Ok(PhotonImage {
         raw_pixels: raw_pixels,
         width: width,
         height: height,
-    };
+    })
+}
+
+/// Resize an image for a particular format on social media, for use from JavaScript. See
+/// [`resize_socialmedia`].
+#[wasm_bindgen(js_name = resize_socialmedia)]
+pub fn resize_socialmedia_web(
+    img: &PhotonImage,
+    format: &str,
+) -> std::result::Result<PhotonImage, JsValue> {
+    Ok(resize_socialmedia(img, format)?)
 }
 
 /// Resizes each image in a vec of PhotonImages to the desired social media format.
-pub fn resize_socialmedia_vec(imgs: Vec<PhotonImage>, format: &str) -> Vec<PhotonImage> {
+pub fn resize_socialmedia_vec(imgs: Vec<PhotonImage>, format: &str) -> Result<Vec<PhotonImage>> {
     let mut resized_imgs = vec![];
     for img in imgs {
-        let resized_img = resize_socialmedia(&img, format);
+        let resized_img = resize_socialmedia(&img, format)?;
         resized_imgs.push(resized_img);
     }
-    return resized_imgs;
+    Ok(resized_imgs)
 }
 
 /// Resizes each image in a vec of PhotonImages to each of
 /// the available social media formats, and a vec of all new images is returned.
-pub fn resize_socialmedia_all(img: &PhotonImage) -> Vec<PhotonImage> {
+pub fn resize_socialmedia_all(img: &PhotonImage) -> Result<Vec<PhotonImage>> {
     let formats = [
         "linkedin_banner",
         "pinterest",
@@ -67,17 +78,17 @@ pub fn resize_socialmedia_all(img: &PhotonImage) -> Vec<PhotonImage> {
     ];
     let mut resized_imgs = vec![];
     for format in &formats {
-        let new_img = resize_socialmedia(&img, format);
+        let new_img = resize_socialmedia(img, format)?;
         resized_imgs.push(new_img)
     }
-    return resized_imgs;
+    Ok(resized_imgs)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage {
+pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> Result<PhotonImage> {
     let sampling_filter = image::imageops::FilterType::Nearest;
 
-    let dyn_img = helpers::dyn_image_from_raw(&photon_img);
+    let dyn_img = helpers::dyn_image_from_raw(photon_img)?;
     let resized_img = image::DynamicImage::ImageRgba8(image::imageops::resize(
         &dyn_img,
         width,
@@ -85,18 +96,18 @@ pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage
         sampling_filter,
     ));
 
-    return PhotonImage {
+    Ok(PhotonImage {
         raw_pixels: resized_img.to_bytes(),
         width: resized_img.width(),
         height: resized_img.height(),
-    };
+    })
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage {
+pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> Result<PhotonImage> {
     let sampling_filter = image::imageops::FilterType::Nearest;
 
-    let dyn_img = helpers::dyn_image_from_raw(&photon_img);
+    let dyn_img = helpers::dyn_image_from_raw(photon_img)?;
     let resized_img = image::DynamicImage::ImageRgba8(image::imageops::resize(
         &dyn_img,
         width,
@@ -104,11 +115,11 @@ pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage
         sampling_filter,
     ));
 
-    return PhotonImage {
+    Ok(PhotonImage {
         raw_pixels: resized_img.to_bytes(),
         width: resized_img.width(),
         height: resized_img.height(),
-    };
+    })
 }
 
 // #[cfg(not(target_arch = "wasm32"))]
@@ -122,3 +133,21 @@ pub fn resize(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage
 // pub fn webfunc(num: u16) {
 //     num * 2
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn mismatched_buffers_are_an_error() {
+        let img = PhotonImage::new_from_rawpixels(vec![0; 7], 2, 2);
+        assert!(matches!(
+            resize_socialmedia(&img, "fb_ad"),
+            Err(Error::BufferSizeMismatch { .. })
+        ));
+        let img = PhotonImage::new_from_rawpixels(vec![0; 16], 2, 2);
+        let resized = resize_socialmedia(&img, "unknown").unwrap();
+        assert_eq!(resized.raw_pix().len(), 192 * 120 * 4);
+    }
+}
@@ -0,0 +1,796 @@
+//! Rich text: paragraphs made of spans, each with its own font, size, colour and decoration.
+//!
+//! Rich text can be built from [`Span`]s, or parsed from a small Markdown-like markup:
+//!
+//! * `**bold**`
+//! * `*italic*` or `_italic_`
+//! * `__underlined__`
+//! * `~~struck through~~`
+//! * `[coloured](#e91e63)`, with a colour given as a hex code or a CSS colour name, such as
+//!   `[coloured](tomato)`
+//!
+//! A backslash escapes the character after it, so `\*` draws an asterisk.
+//!
+//! ```
+//! use gdl::rich_text::{RichText, Span};
+//! use image::Rgba;
+//!
+//! let text = RichText::new()
+//!     .push(Span::new("Visit "))
+//!     .push(Span::new("Stockholm").bold().color(Rgba([233, 30, 99, 255])));
+//! assert_eq!(text, RichText::parse("Visit [**Stockholm**](#e91e63)"));
+//! ```
+
+use crate::emoji::EmojiLayer;
+use crate::fonts::{weight, FontDescriptor, FontFace, FontRegistry, FontStyle};
+use crate::layout::{apply_letter_spacing, fit_font_size, FittedText, ParagraphOptions, TextAlign};
+use crate::shaping::{
+    draw_shaped_outlines, draw_shaped_text, shape_text, ShapedText, TextDirection,
+};
+use crate::style::{draw_layer, render_mask_styled, StyleCanvas, TextStyle};
+use crate::Result;
+use image::{DynamicImage, Rgba};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+
+/// A run of text with a single style.
+///
+/// Anything not set on the span is taken from the font, size and style the rich text is
+/// drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    /// Font to use in place of the base font.
+    pub font: Option<FontDescriptor>,
+    /// Weight to use in place of the font's weight.
+    pub weight: Option<u16>,
+    /// Style to use in place of the font's style.
+    pub style: Option<FontStyle>,
+    /// Size of the span, as a multiple of the base font size.
+    pub scale: f32,
+    /// Colour to fill the span with, in place of the base style's fill.
+    pub color: Option<Rgba<u8>>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl Span {
+    /// A span of text in the base font, size and style.
+    pub fn new(text: &str) -> Span {
+        Span {
+            text: text.to_string(),
+            font: None,
+            weight: None,
+            style: None,
+            scale: 1.0,
+            color: None,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    /// Set the font of the span.
+    pub fn font(mut self, font: FontDescriptor) -> Span {
+        self.font = Some(font);
+        self
+    }
+
+    /// Set the weight of the span, eg: 400 for regular, 700 for bold.
+    pub fn weight(mut self, weight: u16) -> Span {
+        self.weight = Some(weight);
+        self
+    }
+
+    /// Shorthand for `.weight(700)`.
+    pub fn bold(self) -> Span {
+        self.weight(weight::BOLD)
+    }
+
+    /// Draw the span in italics.
+    pub fn italic(mut self) -> Span {
+        self.style = Some(FontStyle::Italic);
+        self
+    }
+
+    /// Set the size of the span, as a multiple of the base font size.
+    pub fn scale(mut self, scale: f32) -> Span {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the colour of the span.
+    pub fn color(mut self, color: Rgba<u8>) -> Span {
+        self.color = Some(color);
+        self
+    }
+
+    /// Underline the span.
+    pub fn underline(mut self) -> Span {
+        self.underline = true;
+        self
+    }
+
+    /// Strike through the span.
+    pub fn strikethrough(mut self) -> Span {
+        self.strikethrough = true;
+        self
+    }
+
+    // The font the span is drawn with, given the base font.
+    fn resolve_font(&self, base: &FontDescriptor) -> FontDescriptor {
+        let mut font = self.font.clone().unwrap_or_else(|| base.clone());
+        if let Some(weight) = self.weight {
+            font.weight = weight;
+        }
+        if let Some(style) = self.style {
+            font.style = style;
+        }
+        font
+    }
+}
+
+/// A sequence of spans, laid out as one paragraph.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+}
+
+impl RichText {
+    /// Rich text with no spans.
+    pub fn new() -> RichText {
+        RichText::default()
+    }
+
+    /// Add a span to the end of the text.
+    pub fn push(mut self, span: Span) -> RichText {
+        self.spans.push(span);
+        self
+    }
+
+    /// Parse rich text from markup. See the [module documentation](self) for the syntax.
+    ///
+    /// Markup that isn't closed applies to the end of the text, and brackets that aren't
+    /// followed by a valid colour are drawn as they are.
+    pub fn parse(markup: &str) -> RichText {
+        let mut parser = MarkupParser {
+            spans: Vec::new(),
+            current: Span::new(""),
+        };
+        parser.parse(markup);
+        parser.flush();
+        RichText {
+            spans: parser.spans,
+        }
+    }
+
+    /// The text of all the spans, without any styling.
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl From<&str> for RichText {
+    fn from(text: &str) -> RichText {
+        RichText::new().push(Span::new(text))
+    }
+}
+
+struct MarkupParser {
+    spans: Vec<Span>,
+    current: Span,
+}
+
+impl MarkupParser {
+    fn parse(&mut self, markup: &str) {
+        let mut rest = markup;
+        while let Some(c) = rest.chars().next() {
+            if c == '\\' {
+                let escaped = rest[1..].chars().next();
+                if let Some(escaped) = escaped {
+                    self.current.text.push(escaped);
+                    rest = &rest[1 + escaped.len_utf8()..];
+                } else {
+                    self.current.text.push('\\');
+                    rest = "";
+                }
+            } else if let Some(after) = rest.strip_prefix("**") {
+                let weight = match self.current.weight {
+                    Some(_) => None,
+                    None => Some(weight::BOLD),
+                };
+                self.restyle(|span| span.weight = weight);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("__") {
+                self.restyle(|span| span.underline = !span.underline);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("~~") {
+                self.restyle(|span| span.strikethrough = !span.strikethrough);
+                rest = after;
+            } else if c == '*' || c == '_' {
+                let style = match self.current.style {
+                    Some(_) => None,
+                    None => Some(FontStyle::Italic),
+                };
+                self.restyle(|span| span.style = style);
+                rest = &rest[1..];
+            } else if let Some((inner, color, after)) = colored_link(rest) {
+                let outer = self.current.color;
+                self.restyle(|span| span.color = Some(color));
+                self.parse(inner);
+                self.restyle(|span| span.color = outer);
+                rest = after;
+            } else {
+                self.current.text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    // Start a new span, with the style of the current one changed.
+    fn restyle<F: FnOnce(&mut Span)>(&mut self, change: F) {
+        self.flush();
+        change(&mut self.current);
+    }
+
+    fn flush(&mut self) {
+        if !self.current.text.is_empty() {
+            let mut span = self.current.clone();
+            span.text = std::mem::take(&mut self.current.text);
+            self.spans.push(span);
+        }
+    }
+}
+
+// Split `[text](#colour)` at the start of some markup into the text, colour and the rest of
+// the markup.
+fn colored_link(markup: &str) -> Option<(&str, Rgba<u8>, &str)> {
+    let inner = markup.strip_prefix('[')?;
+    let close = inner.find("](")?;
+    let after = &inner[close + 2..];
+    let end = after.find(')')?;
+    let color = after[..end].parse::<crate::Rgba>().ok()?.into();
+    Some((&inner[..close], color, &after[end + 1..]))
+}
+
+/// Part of a span placed on a line.
+#[derive(Debug, Clone)]
+pub struct RichRun<'a> {
+    /// Index of the span the run is part of.
+    pub span: usize,
+    pub face: &'a FontFace,
+    pub font_size: f32,
+    pub shaped: ShapedText,
+    /// X-coordinate of the left of the run, relative to the left of the paragraph.
+    pub x: f32,
+}
+
+/// A single laid out line of rich text.
+///
+/// Positions are in pixels, relative to the top-left corner of the paragraph.
+#[derive(Debug, Clone)]
+pub struct RichLine<'a> {
+    /// The runs on the line, from left to right.
+    pub runs: Vec<RichRun<'a>>,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Y-coordinate of the line's baseline, shared by every run on the line.
+    pub baseline: f32,
+}
+
+/// Rich text laid out into lines.
+#[derive(Debug, Clone)]
+pub struct RichParagraph<'a> {
+    pub lines: Vec<RichLine<'a>>,
+    /// The spans the paragraph was laid out from.
+    pub spans: Vec<Span>,
+    /// Width of the paragraph: the maximum width if one was given, otherwise the widest line.
+    pub width: f32,
+    pub height: f32,
+    /// The resolved direction of the paragraph.
+    pub direction: TextDirection,
+}
+
+impl RichParagraph<'_> {
+    /// Width of the widest line, which can be wider than the paragraph if a word overflows.
+    pub fn widest_line(&self) -> f32 {
+        self.lines.iter().map(|line| line.width).fold(0.0, f32::max)
+    }
+}
+
+// A word, a run of spaces, or a line break, from a single span.
+struct Piece<'a> {
+    span: usize,
+    kind: PieceKind,
+    face: &'a FontFace,
+    font_size: f32,
+    shaped: ShapedText,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PieceKind {
+    Word,
+    Space,
+    Break,
+}
+
+/// Lay out rich text into lines.
+///
+/// Lines are broken at newlines, and at spaces wherever the next word would make the line
+/// wider than `options.max_width`. All the runs on a line share a baseline, and the line is
+/// tall enough for its largest run. Hyphenation isn't applied to rich text.
+///
+/// ### Arguments
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `text` - Rich text to lay out.
+/// * `font` - The base font, used by spans that don't set their own.
+/// * `font_size` - The base font size, which spans are scaled relative to.
+/// * `options` - Wrapping, spacing and alignment options.
+///
+/// Returns an error if a span's font, or any of its fallbacks, aren't in the registry.
+pub fn layout_rich_text<'a>(
+    fonts: &'a FontRegistry,
+    text: &RichText,
+    font: &FontDescriptor,
+    font_size: f32,
+    options: &ParagraphOptions,
+) -> Result<RichParagraph<'a>> {
+    let direction = options.direction.resolve(&text.plain_text());
+    let base_face = fonts.face(font)?;
+
+    let mut pieces = Vec::new();
+    for (index, span) in text.spans.iter().enumerate() {
+        let face = fonts.face(&span.resolve_font(font))?;
+        let size = font_size * span.scale;
+        let mut push = |kind: PieceKind, piece: &str| {
+            let mut shaped = shape_text(face, piece, size, direction);
+            if kind != PieceKind::Break {
+                apply_letter_spacing(&mut shaped, options.letter_spacing);
+                if !shaped.glyphs.is_empty() {
+                    shaped.width += options.letter_spacing;
+                }
+            }
+            pieces.push(Piece {
+                span: index,
+                kind,
+                face,
+                font_size: size,
+                shaped,
+            });
+        };
+        for (i, hard_line) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                push(PieceKind::Break, "");
+            }
+            let mut start = 0;
+            let mut in_space = None;
+            for (offset, c) in hard_line.char_indices() {
+                let is_space = c.is_whitespace();
+                if in_space.is_some_and(|was_space| was_space != is_space) {
+                    let kind = if is_space {
+                        PieceKind::Word
+                    } else {
+                        PieceKind::Space
+                    };
+                    push(kind, &hard_line[start..offset]);
+                    start = offset;
+                }
+                in_space = Some(is_space);
+            }
+            if let Some(was_space) = in_space {
+                let kind = if was_space {
+                    PieceKind::Space
+                } else {
+                    PieceKind::Word
+                };
+                push(kind, &hard_line[start..]);
+            }
+        }
+    }
+
+    // Break the pieces into lines, a word at a time. A word can span several pieces when its
+    // style changes part-way through.
+    let mut lines: Vec<(Vec<Piece>, bool)> = Vec::new();
+    let mut line: Vec<Piece> = Vec::new();
+    let mut line_width = 0.0;
+    let mut pieces = pieces.into_iter().peekable();
+    while let Some(piece) = pieces.next() {
+        match piece.kind {
+            PieceKind::Break => {
+                lines.push((std::mem::take(&mut line), true));
+                line_width = 0.0;
+            }
+            PieceKind::Space => {
+                line_width += piece.shaped.width;
+                line.push(piece);
+            }
+            PieceKind::Word => {
+                let mut word = vec![piece];
+                while let Some(next) = pieces.next_if(|next| next.kind == PieceKind::Word) {
+                    word.push(next);
+                }
+                let word_width: f32 = word.iter().map(|piece| piece.shaped.width).sum();
+                let has_words = line.iter().any(|piece| piece.kind == PieceKind::Word);
+                if let Some(max_width) = options.max_width {
+                    if has_words && line_width + word_width > max_width {
+                        trim_spaces(&mut line);
+                        lines.push((std::mem::take(&mut line), false));
+                        line_width = 0.0;
+                    }
+                }
+                line_width += word_width;
+                line.extend(word);
+            }
+        }
+    }
+    lines.push((line, true));
+
+    // Place each line's runs on a shared baseline.
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|(line, _)| line.iter().map(|piece| piece.shaped.width).sum())
+        .collect();
+    let width = options
+        .max_width
+        .unwrap_or_else(|| widths.iter().cloned().fold(0.0, f32::max));
+    let line_count = lines.len();
+    let mut rich_lines = Vec::with_capacity(line_count);
+    let mut y = 0.0;
+    for (i, ((mut line, ends_paragraph), line_width)) in lines.into_iter().zip(widths).enumerate() {
+        if direction == TextDirection::RightToLeft {
+            line.reverse();
+        }
+
+        // The metrics of the tallest run, or of the base font on an empty line.
+        let (ascent, descent, size) = if line.is_empty() {
+            let ascent = font_size * ascent_ratio(base_face);
+            (ascent, font_size - ascent, font_size)
+        } else {
+            let max = |metric: &dyn Fn(&Piece) -> f32| line.iter().map(metric).fold(0.0, f32::max);
+            (
+                max(&|piece| piece.shaped.ascent),
+                max(&|piece| piece.font_size - piece.shaped.ascent),
+                max(&|piece| piece.font_size),
+            )
+        };
+        let height = options.line_height * size;
+        let baseline = y + (height - ascent - descent) / 2.0 + ascent;
+
+        let align = match (options.align, ends_paragraph || i + 1 == line_count) {
+            (TextAlign::Justify, true) => TextAlign::Start,
+            (align, _) => align,
+        };
+        let spaces = line
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Space)
+            .count();
+        let extra_space = if align == TextAlign::Justify && spaces > 0 {
+            ((width - line_width) / spaces as f32).max(0.0)
+        } else {
+            0.0
+        };
+        let line_width = line_width + extra_space * spaces as f32;
+        let x = match (align, direction) {
+            (TextAlign::Left, _)
+            | (TextAlign::Start, TextDirection::LeftToRight)
+            | (TextAlign::End, TextDirection::RightToLeft)
+            | (TextAlign::Justify, _) => 0.0,
+            (TextAlign::Centre, _) => (width - line_width) / 2.0,
+            _ => width - line_width,
+        };
+
+        let mut pen = x;
+        let mut runs = Vec::with_capacity(line.len());
+        for piece in line {
+            let advance = piece.shaped.width
+                + if piece.kind == PieceKind::Space {
+                    extra_space
+                } else {
+                    0.0
+                };
+            runs.push(RichRun {
+                span: piece.span,
+                face: piece.face,
+                font_size: piece.font_size,
+                shaped: piece.shaped,
+                x: pen,
+            });
+            pen += advance;
+        }
+
+        rich_lines.push(RichLine {
+            runs,
+            x,
+            y,
+            width: line_width,
+            height,
+            baseline,
+        });
+        y += height;
+    }
+
+    Ok(RichParagraph {
+        lines: rich_lines,
+        spans: text.spans.clone(),
+        width,
+        height: y,
+        direction,
+    })
+}
+
+/// Find the largest base font size at which rich text, wrapped to `width`, fits within `height`.
+///
+/// Spans keep their sizes relative to the base font size. See
+/// [`crate::layout::fit_paragraph`].
+///
+/// ### Arguments
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `text` - Rich text to lay out.
+/// * `font` - The base font, used by spans that don't set their own.
+/// * `width` - Width of the box, in pixels.
+/// * `height` - Height of the box, in pixels.
+/// * `min_size` - Smallest base font size that can be used.
+/// * `max_size` - Largest base font size that can be used.
+/// * `options` - Spacing and alignment options.
+///
+/// Returns an error if a span's font, or any of its fallbacks, aren't in the registry, or if
+/// either size isn't a finite, positive number.
+#[allow(clippy::too_many_arguments)]
+pub fn fit_rich_paragraph<'a>(
+    fonts: &'a FontRegistry,
+    text: &RichText,
+    font: &FontDescriptor,
+    width: f32,
+    height: f32,
+    min_size: f32,
+    max_size: f32,
+    options: &ParagraphOptions,
+) -> Result<FittedText<RichParagraph<'a>>> {
+    let options = options.clone().max_width(width);
+    let fitted = fit_font_size(
+        width,
+        height,
+        min_size,
+        max_size,
+        |font_size| match layout_rich_text(fonts, text, font, font_size, &options) {
+            Ok(paragraph) => {
+                let (widest, height) = (paragraph.widest_line(), paragraph.height);
+                (Ok(paragraph), widest, height)
+            }
+            Err(error) => (Err(error), 0.0, 0.0),
+        },
+    )?;
+    Ok(FittedText {
+        font_size: fitted.font_size,
+        paragraph: fitted.paragraph?,
+        overflow_x: fitted.overflow_x,
+        overflow_y: fitted.overflow_y,
+    })
+}
+
+// The share of the font size above the baseline.
+fn ascent_ratio(face: &FontFace) -> f32 {
+    let v_metrics = face.font().v_metrics_unscaled();
+    v_metrics.ascent / (v_metrics.ascent - v_metrics.descent)
+}
+
+fn trim_spaces(line: &mut Vec<Piece>) {
+    while line
+        .last()
+        .is_some_and(|piece| piece.kind == PieceKind::Space)
+    {
+        line.pop();
+    }
+}
+
+/// Draw laid out rich text onto an image.
+///
+/// Each span is filled with its own colour, or with the style's fill if it has none. The
+/// style's outline, shadow and glow are drawn around the whole paragraph.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `paragraph` - The laid out rich text.
+/// * `x` - X-coordinate of the left of the paragraph.
+/// * `y` - Y-coordinate of the top of the paragraph.
+/// * `style` - Fill and effects of the text.
+pub fn draw_rich_paragraph(
+    image: &mut DynamicImage,
+    paragraph: &RichParagraph,
+    x: i32,
+    y: i32,
+    style: &TextStyle,
+) {
+    if let Some(base_color) = style.solid_color() {
+        paint_runs(image, paragraph, x, y, draw_shaped_text, |span| {
+            span.color.unwrap_or(base_color)
+        });
+        return;
+    }
+
+    // Leave room for glyphs that reach outside their line boxes.
+    let largest = paragraph
+        .lines
+        .iter()
+        .flat_map(|line| line.runs.iter().map(|run| run.font_size))
+        .fold(0.0, f32::max);
+    let overhang = (largest / 2.0).ceil() as i32;
+    let canvas = StyleCanvas::new(
+        style,
+        -overhang,
+        -overhang,
+        paragraph.width.max(paragraph.widest_line()).ceil() as i32 + overhang,
+        paragraph.height.ceil() as i32 + overhang,
+    );
+
+    let white = Rgba([255, 255, 255, 255]);
+    let mut mask = DynamicImage::new_luma8(canvas.width, canvas.height);
+    paint_runs(
+        &mut mask,
+        paragraph,
+        canvas.origin_x,
+        canvas.origin_y,
+        draw_shaped_outlines,
+        |_| white,
+    );
+    // Spans with their own colour are painted into a separate image, which overrides the fill.
+    let mut colors = DynamicImage::new_rgba8(canvas.width, canvas.height);
+    paint_runs(
+        &mut colors,
+        paragraph,
+        canvas.origin_x,
+        canvas.origin_y,
+        draw_shaped_outlines,
+        |span| span.color.unwrap_or(Rgba([0, 0, 0, 0])),
+    );
+    let colors = colors.to_rgba8();
+    let mut emoji = EmojiLayer::new(canvas.width, canvas.height);
+    for line in &paragraph.lines {
+        for run in &line.runs {
+            let run_x = canvas.origin_x + run.x.round() as i32;
+            let run_y = canvas.origin_y + (line.baseline - run.shaped.ascent).round() as i32;
+            emoji.draw(&run.shaped, run_x as f32, run_y as f32);
+        }
+    }
+    let mut mask = mask.to_luma8();
+    emoji.add_to_mask(&mut mask);
+
+    let layer = render_mask_styled(&canvas, &mask, style, |cx, cy| {
+        if let Some(color) = emoji.color_at(cx, cy) {
+            return Some(color);
+        }
+        let color = colors.get_pixel(cx, cy);
+        if color[3] == 0 {
+            return None;
+        }
+        // Glyphs are blended with the transparent image, so undo the blend at their edges.
+        let coverage = color[3] as f32 / 255.0;
+        let channel = |c: u8| (c as f32 / coverage).round().min(255.0) as u8;
+        Some(Rgba([
+            channel(color[0]),
+            channel(color[1]),
+            channel(color[2]),
+            255,
+        ]))
+    });
+    draw_layer(image, &layer, x - canvas.origin_x, y - canvas.origin_y);
+}
+
+// Draw every run with `draw_glyphs`, and its decorations, in the colour given for its span.
+fn paint_runs<F: Fn(&Span) -> Rgba<u8>>(
+    image: &mut DynamicImage,
+    paragraph: &RichParagraph,
+    x: i32,
+    y: i32,
+    draw_glyphs: fn(&mut DynamicImage, &FontFace, &ShapedText, i32, i32, Rgba<u8>),
+    color: F,
+) {
+    for line in &paragraph.lines {
+        for run in &line.runs {
+            let span = &paragraph.spans[run.span];
+            let run_color = color(span);
+            if run_color[3] == 0 {
+                continue;
+            }
+            draw_glyphs(
+                image,
+                run.face,
+                &run.shaped,
+                x + run.x.round() as i32,
+                y + (line.baseline - run.shaped.ascent).round() as i32,
+                run_color,
+            );
+
+            let thickness = (run.font_size / 15.0).max(1.0);
+            let mut decorations = Vec::new();
+            if span.underline {
+                decorations.push(line.baseline + run.font_size * 0.1);
+            }
+            if span.strikethrough {
+                decorations.push(line.baseline - run.shaped.ascent * 0.3);
+            }
+            for decoration_y in decorations {
+                let rect = Rect::at(
+                    x + run.x.round() as i32,
+                    y + (decoration_y - thickness / 2.0).round() as i32,
+                )
+                .of_size(
+                    run.shaped.width.round().max(1.0) as u32,
+                    thickness.round() as u32,
+                );
+                draw_filled_rect_mut(image, rect, run_color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_markup_has_no_spans() {
+        assert_eq!(RichText::parse(""), RichText::new());
+        assert_eq!(RichText::parse("****"), RichText::new());
+    }
+
+    #[test]
+    fn unclosed_markup_applies_to_the_end() {
+        let text = RichText::parse("plain **bold");
+        assert_eq!(text.spans.len(), 2);
+        assert_eq!(text.spans[1].text, "bold");
+        assert_eq!(text.spans[1].weight, Some(weight::BOLD));
+        let struck = RichText::parse("~~struck");
+        assert!(struck.spans[0].strikethrough);
+    }
+
+    #[test]
+    fn malformed_colours_are_drawn_as_text() {
+        for markup in &["[red](not a colour)", "[red](#12", "[red]", "[red"] {
+            let text = RichText::parse(markup);
+            assert_eq!(text.plain_text(), *markup);
+            assert!(text.spans.iter().all(|span| span.color.is_none()));
+        }
+    }
+
+    #[test]
+    fn escapes_are_drawn_as_text() {
+        assert_eq!(
+            RichText::parse(r"\*not italic\*").plain_text(),
+            "*not italic*"
+        );
+        assert_eq!(RichText::parse(r"trailing\").plain_text(), r"trailing\");
+        assert_eq!(RichText::parse(r"\é").plain_text(), "é");
+    }
+
+    #[test]
+    fn coloured_text_keeps_its_inner_markup() {
+        let text = RichText::parse("[a **b**](tomato) c");
+        let colors: Vec<_> = text.spans.iter().map(|span| span.color).collect();
+        let tomato = Some(Rgba([255, 99, 71, 255]));
+        assert_eq!(colors, [tomato, tomato, None]);
+        assert_eq!(text.spans[1].weight, Some(weight::BOLD));
+        assert_eq!(text.plain_text(), "a b c");
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn empty_rich_text_lays_out_as_one_empty_line() {
+        crate::fonts::with_embedded_fonts(|fonts| {
+            let font = FontDescriptor::new("Roboto");
+            let paragraph = layout_rich_text(
+                fonts,
+                &RichText::new(),
+                &font,
+                40.0,
+                &ParagraphOptions::new(),
+            )
+            .unwrap();
+            assert_eq!(paragraph.lines.len(), 1);
+            assert_eq!(paragraph.widest_line(), 0.0);
+            let mut image = DynamicImage::new_rgba8(10, 10);
+            draw_rich_paragraph(&mut image, &paragraph, 0, 0, &TextStyle::new());
+        });
+    }
+}
@@ -0,0 +1,363 @@
+//! Shape text into positioned glyphs, with kerning, ligatures and complex script support.
+//!
+//! Shaping is done by [rustybuzz](https://github.com/RazrFalcon/rustybuzz), a pure Rust port of
+//! HarfBuzz, so text in scripts such as Arabic, Devanagari or Thai is drawn correctly both
+//! natively and in WebAssembly.
+//!
+//! Text mixing left-to-right and right-to-left scripts is split into runs and reordered with the
+//! Unicode Bidirectional Algorithm before it is shaped, so the glyphs come out in visual order.
+//!
+//! Emoji the font's registry has images for are shaped as a single glyph drawn from the image;
+//! see [`crate::emoji`].
+
+use crate::emoji::{draw_shaped_emoji, substitute_emoji};
+use crate::fonts::FontFace;
+use crate::helpers::blend_over;
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba, RgbaImage};
+use rusttype::{point, GlyphId, Scale};
+use std::sync::Arc;
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+/// The direction text is laid out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    /// Use the direction of the first strong character in the text, as the Unicode
+    /// Bidirectional Algorithm does, defaulting to left-to-right.
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    /// Resolve `Auto` to the direction of the given text. Other directions are returned unchanged.
+    pub fn resolve(self, text: &str) -> TextDirection {
+        match self {
+            TextDirection::Auto => match unicode_bidi::get_base_direction(text) {
+                unicode_bidi::Direction::Rtl => TextDirection::RightToLeft,
+                _ => TextDirection::LeftToRight,
+            },
+            direction => direction,
+        }
+    }
+
+    /// Whether the direction, once resolved for the given text, is right-to-left.
+    pub fn is_rtl(self, text: &str) -> bool {
+        self.resolve(text) == TextDirection::RightToLeft
+    }
+}
+
+/// A glyph placed by the shaper.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapedGlyph {
+    /// The glyph's ID within its font.
+    pub id: u16,
+    /// Byte index of the first character in the text this glyph was shaped from.
+    pub cluster: usize,
+    /// Horizontal position in pixels, relative to the start of the text.
+    pub x: f32,
+    /// Vertical position in pixels, relative to the baseline. Positive values are below it.
+    pub y: f32,
+    /// How far the next glyph is moved along, in pixels.
+    pub advance: f32,
+    /// Index of the colour image in [`ShapedText::images`] drawn in place of the glyph, for emoji.
+    pub image: Option<usize>,
+}
+
+/// A line of text that has been shaped with a particular font and size.
+#[derive(Debug, Clone)]
+pub struct ShapedText {
+    /// The glyphs, in the order they are drawn from left to right.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// Total advance width of the text, in pixels.
+    pub width: f32,
+    /// Distance from the top of the line to the baseline, in pixels.
+    pub ascent: f32,
+    /// The rusttype scale the glyphs are rendered at.
+    pub scale: Scale,
+    /// The direction the text was laid out in, either left-to-right or right-to-left.
+    pub direction: TextDirection,
+    /// Colour images of the emoji in the text.
+    pub images: Vec<Arc<RgbaImage>>,
+}
+
+/// Shape a single line of text.
+///
+/// The text is split into runs of a single direction with the Unicode Bidirectional Algorithm,
+/// using `direction` as the paragraph's base direction, and each run's script is guessed from
+/// its text. The font's default features, including kerning and standard ligatures, are applied.
+///
+/// ### Arguments
+/// * `face` - The font face to shape with.
+/// * `text` - Text to shape.
+/// * `font_size` - Height of the font in pixels, as used by the text drawing functions.
+/// * `direction` - Base direction of the text.
+pub fn shape_text(
+    face: &FontFace,
+    text: &str,
+    font_size: f32,
+    direction: TextDirection,
+) -> ShapedText {
+    let scale = Scale::uniform(font_size);
+    let v_metrics = face.font().v_metrics_unscaled();
+    // rusttype scales fonts so that the distance from descent to ascent is the font size.
+    let px_per_unit = font_size / (v_metrics.ascent - v_metrics.descent);
+    let ascent = v_metrics.ascent * px_per_unit;
+
+    let mut glyphs = Vec::new();
+    let mut pen = 0.0;
+
+    let base_level = match direction {
+        TextDirection::Auto => None,
+        TextDirection::LeftToRight => Some(Level::ltr()),
+        TextDirection::RightToLeft => Some(Level::rtl()),
+    };
+    let bidi = ParagraphBidiInfo::new(text, base_level);
+    // unicode-bidi can't reorder an empty line.
+    let (levels, runs) = if text.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        bidi.visual_runs(0..text.len())
+    };
+
+    match face.shaper() {
+        Some(shaper) => {
+            for run in runs {
+                let mut buffer = rustybuzz::UnicodeBuffer::new();
+                buffer.push_str(&text[run.clone()]);
+                buffer.guess_segment_properties();
+                buffer.set_direction(if levels[run.start].is_rtl() {
+                    rustybuzz::Direction::RightToLeft
+                } else {
+                    rustybuzz::Direction::LeftToRight
+                });
+                let output = rustybuzz::shape(&shaper, &[], buffer);
+
+                for (info, pos) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+                    let advance = pos.x_advance as f32 * px_per_unit;
+                    glyphs.push(ShapedGlyph {
+                        id: info.glyph_id as u16,
+                        cluster: run.start + info.cluster as usize,
+                        x: pen + pos.x_offset as f32 * px_per_unit,
+                        y: -pos.y_offset as f32 * px_per_unit,
+                        advance,
+                        image: None,
+                    });
+                    pen += advance;
+                }
+            }
+        }
+        // The font's tables couldn't be read for shaping, so place glyphs one after another,
+        // applying only kerning pairs.
+        None => {
+            let mut last = None;
+            for run in runs {
+                let mut chars: Vec<(usize, char)> = text[run.clone()].char_indices().collect();
+                if levels[run.start].is_rtl() {
+                    chars.reverse();
+                }
+                for (cluster, c) in chars {
+                    let glyph = face.font().glyph(c).scaled(scale);
+                    let id = glyph.id();
+                    if let Some(last) = last {
+                        pen += face.font().pair_kerning(scale, last, id);
+                    }
+                    let advance = glyph.h_metrics().advance_width;
+                    glyphs.push(ShapedGlyph {
+                        id: id.0,
+                        cluster: run.start + cluster,
+                        x: pen,
+                        y: 0.0,
+                        advance,
+                        image: None,
+                    });
+                    pen += advance;
+                    last = Some(id);
+                }
+            }
+        }
+    }
+
+    let mut shaped = ShapedText {
+        glyphs,
+        width: pen,
+        ascent,
+        scale,
+        direction: if bidi.paragraph_level.is_rtl() {
+            TextDirection::RightToLeft
+        } else {
+            TextDirection::LeftToRight
+        },
+        images: Vec::new(),
+    };
+    substitute_emoji(face, text, &mut shaped);
+    shaped
+}
+
+/// Draw shaped text onto an image, blending it with the pixels underneath.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `face` - The font face the text was shaped with.
+/// * `shaped` - The shaped text.
+/// * `x` - X-coordinate of the left of the text.
+/// * `y` - Y-coordinate of the top of the text.
+/// * `color` - Text colour. Emoji are drawn in their own colours, with its opacity.
+pub fn draw_shaped_text(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    shaped: &ShapedText,
+    x: i32,
+    y: i32,
+    color: Rgba<u8>,
+) {
+    draw_shaped_outlines(image, face, shaped, x, y, color);
+    let transform = [1.0, 0.0, 0.0, 1.0, x as f32, y as f32];
+    draw_shaped_emoji(image, shaped, transform, color[3] as f32 / 255.0);
+}
+
+// Draw the glyphs of shaped text from their outlines, leaving out emoji.
+pub(crate) fn draw_shaped_outlines(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    shaped: &ShapedText,
+    x: i32,
+    y: i32,
+    color: Rgba<u8>,
+) {
+    let (width, height) = image.dimensions();
+    let opacity = color[3] as f32 / 255.0;
+    for glyph in shaped.glyphs.iter().filter(|glyph| glyph.image.is_none()) {
+        let positioned = face
+            .font()
+            .glyph(GlyphId(glyph.id))
+            .scaled(shaped.scale)
+            .positioned(point(glyph.x, shaped.ascent + glyph.y));
+
+        if let Some(bb) = positioned.pixel_bounding_box() {
+            positioned.draw(|gx, gy, gv| {
+                let image_x = gx as i32 + bb.min.x + x;
+                let image_y = gy as i32 + bb.min.y + y;
+                let alpha = gv * opacity;
+                if alpha > 0.0
+                    && image_x >= 0
+                    && image_x < width as i32
+                    && image_y >= 0
+                    && image_y < height as i32
+                {
+                    let pixel = image.get_pixel(image_x as u32, image_y as u32);
+                    let blended = blend_over(pixel, color, alpha);
+                    image.put_pixel(image_x as u32, image_y as u32, blended);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "embedded-fonts"))]
+mod tests {
+    use super::*;
+    use crate::fonts::{FontDescriptor, FontRegistry};
+
+    fn draw(color: Rgba<u8>) -> RgbaImage {
+        let fonts = FontRegistry::embedded();
+        let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+        let shaped = shape_text(face, "I", 40.0, TextDirection::LeftToRight);
+        let mut image =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 40, Rgba([255, 255, 255, 255])));
+        draw_shaped_text(&mut image, face, &shaped, 0, 0, color);
+        image.to_rgba8()
+    }
+
+    fn darkest(image: &RgbaImage) -> u8 {
+        image.pixels().map(|pixel| pixel[0]).min().unwrap()
+    }
+
+    #[test]
+    fn text_is_drawn_with_the_colours_opacity() {
+        assert_eq!(darkest(&draw(Rgba([0, 0, 0, 255]))), 0);
+        let half = darkest(&draw(Rgba([0, 0, 0, 128])));
+        assert!((126..=128).contains(&half), "{}", half);
+        assert_eq!(darkest(&draw(Rgba([0, 0, 0, 0]))), 255);
+    }
+
+    #[test]
+    fn text_keeps_an_opaque_background_opaque() {
+        let image = draw(Rgba([0, 0, 0, 128]));
+        assert!(image.pixels().all(|pixel| pixel[3] == 255));
+    }
+
+    fn shape(text: &str, direction: TextDirection) -> ShapedText {
+        let fonts = FontRegistry::embedded();
+        let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+        shape_text(face, text, 40.0, direction)
+    }
+
+    #[test]
+    fn empty_text_has_no_glyphs() {
+        let shaped = shape("", TextDirection::Auto);
+        assert!(shaped.glyphs.is_empty());
+        assert_eq!(shaped.width, 0.0);
+        assert!(shaped.ascent > 0.0);
+    }
+
+    #[test]
+    fn pairs_are_kerned() {
+        let pair = shape("AV", TextDirection::LeftToRight).width;
+        let apart = shape("A", TextDirection::LeftToRight).width
+            + shape("V", TextDirection::LeftToRight).width;
+        assert!(pair < apart, "{} >= {}", pair, apart);
+    }
+
+    #[test]
+    fn glyphs_follow_the_text() {
+        let shaped = shape("Hello, world", TextDirection::Auto);
+        assert_eq!(shaped.glyphs.len(), 12);
+        assert!(shaped
+            .glyphs
+            .windows(2)
+            .all(|pair| pair[0].cluster < pair[1].cluster && pair[0].x < pair[1].x));
+        let advances: f32 = shaped.glyphs.iter().map(|glyph| glyph.advance).sum();
+        assert!((advances - shaped.width).abs() < 0.01);
+    }
+
+    #[test]
+    fn direction_comes_from_the_first_strong_character() {
+        assert_eq!(TextDirection::Auto.resolve(""), TextDirection::LeftToRight);
+        assert_eq!(
+            TextDirection::Auto.resolve("123 !"),
+            TextDirection::LeftToRight
+        );
+        assert_eq!(
+            TextDirection::Auto.resolve("123 \u{5e9}\u{5dc}\u{5d5}\u{5dd} abc"),
+            TextDirection::RightToLeft
+        );
+        assert_eq!(
+            TextDirection::LeftToRight.resolve("\u{5e9}\u{5dc}\u{5d5}\u{5dd}"),
+            TextDirection::LeftToRight
+        );
+        assert!(TextDirection::RightToLeft.is_rtl(""));
+    }
+
+    #[test]
+    fn runs_are_reordered_for_display() {
+        // "abc" then two Hebrew letters, in a right-to-left paragraph: the Hebrew is drawn on
+        // the left, right to left, and "abc" on the right.
+        let shaped = shape("abc \u{5e9}\u{5dc}", TextDirection::RightToLeft);
+        assert_eq!(shaped.direction, TextDirection::RightToLeft);
+        let clusters: Vec<usize> = shaped.glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters.first(), Some(&6));
+        assert_eq!(clusters.last(), Some(&2));
+
+        let shaped = shape("abc \u{5e9}\u{5dc}", TextDirection::LeftToRight);
+        let clusters: Vec<usize> = shaped.glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, [0, 1, 2, 3, 6, 4]);
+    }
+
+    #[test]
+    fn empty_right_to_left_text_has_no_glyphs() {
+        let shaped = shape("", TextDirection::RightToLeft);
+        assert!(shaped.glyphs.is_empty());
+        assert_eq!(shaped.width, 0.0);
+    }
+}
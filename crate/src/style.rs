@@ -0,0 +1,590 @@
+//! Styles for text: fills, outlines, drop shadows and glows.
+//!
+//! A [`TextStyle`] is passed to the text drawing functions in place of a plain colour. Any
+//! `Rgb` can be used where a style is expected, giving a solid fill with no effects. Colours
+//! can be given as a [`crate::Rgba`], parsed from CSS, or as an `image::Rgba<u8>`.
+//!
+//! ```
+//! use gdl::style::{Fill, Glow, Shadow, TextStyle};
+//! use image::Rgba;
+//!
+//! let style = TextStyle::new()
+//!     .fill(Fill::LinearGradient {
+//!         start: Rgba([255, 200, 0, 255]),
+//!         end: Rgba([255, 60, 120, 255]),
+//!         angle: 90.0,
+//!     })
+//!     .stroke(4, Rgba([0, 0, 0, 255]))
+//!     .shadow(Shadow::new(6, 6).blur(8.0).opacity(0.6))
+//!     .glow(Glow::new(10).color("white".parse::<gdl::Rgba>().unwrap()));
+//! ```
+
+use crate::contrast::{AutoContrast, ContrastFix, ContrastLevel};
+use crate::emoji::EmojiLayer;
+use crate::fonts::FontFace;
+use crate::gradient::Gradient;
+use crate::helpers::blend_over;
+use crate::layout::{draw_paragraph, Paragraph};
+use crate::metrics::measure_paragraph;
+use crate::shaping::draw_shaped_outlines;
+use crate::Rgb;
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Rgba, RgbaImage};
+use imageproc::distance_transform::euclidean_squared_distance_transform;
+use imageproc::filter::gaussian_blur_f32;
+use std::rc::Rc;
+
+/// How the inside of text is painted.
+#[derive(Debug, Clone)]
+pub enum Fill {
+    Solid(Rgba<u8>),
+    /// A gradient from `start` to `end` across the text's box. `angle` is the direction of
+    /// the gradient in degrees, clockwise from left-to-right, so 90 runs from top to bottom.
+    LinearGradient {
+        start: Rgba<u8>,
+        end: Rgba<u8>,
+        angle: f32,
+    },
+    /// Any gradient, drawn across the text's box.
+    Gradient(Gradient),
+    /// An image tiled across the text, starting at the top-left corner of its box.
+    Image(Rc<RgbaImage>),
+}
+
+impl From<Gradient> for Fill {
+    fn from(gradient: Gradient) -> Fill {
+        Fill::Gradient(gradient)
+    }
+}
+
+impl Default for Fill {
+    fn default() -> Fill {
+        Fill::Solid(Rgba([0, 0, 0, 255]))
+    }
+}
+
+/// A blurred copy of the text drawn behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    /// Horizontal offset of the shadow, in pixels.
+    pub offset_x: i32,
+    /// Vertical offset of the shadow, in pixels.
+    pub offset_y: i32,
+    /// Radius of the blur, in pixels. A radius of 0 gives a hard shadow.
+    pub blur: f32,
+    pub color: Rgba<u8>,
+    /// Opacity of the shadow, from 0 to 1.
+    pub opacity: f32,
+}
+
+impl Shadow {
+    /// A black shadow at the given offset, with a 4 pixel blur and an opacity of 0.5.
+    pub fn new(offset_x: i32, offset_y: i32) -> Shadow {
+        Shadow {
+            offset_x,
+            offset_y,
+            blur: 4.0,
+            color: Rgba([0, 0, 0, 255]),
+            opacity: 0.5,
+        }
+    }
+
+    /// Set the radius of the blur, in pixels.
+    pub fn blur(mut self, blur: f32) -> Shadow {
+        self.blur = blur;
+        self
+    }
+
+    /// Set the colour of the shadow.
+    pub fn color(mut self, color: impl Into<crate::Rgba>) -> Shadow {
+        self.color = color.into().into();
+        self
+    }
+
+    /// Set the opacity of the shadow, from 0 to 1.
+    pub fn opacity(mut self, opacity: f32) -> Shadow {
+        self.opacity = opacity;
+        self
+    }
+}
+
+/// A soft halo of colour around the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glow {
+    /// How far the glow extends from the text, in pixels.
+    pub radius: u32,
+    pub color: Rgba<u8>,
+    /// Opacity of the glow, from 0 to 1.
+    pub opacity: f32,
+}
+
+impl Glow {
+    /// A white glow of the given radius, with an opacity of 0.8.
+    pub fn new(radius: u32) -> Glow {
+        Glow {
+            radius,
+            color: Rgba([255, 255, 255, 255]),
+            opacity: 0.8,
+        }
+    }
+
+    /// Set the colour of the glow.
+    pub fn color(mut self, color: impl Into<crate::Rgba>) -> Glow {
+        self.color = color.into().into();
+        self
+    }
+
+    /// Set the opacity of the glow, from 0 to 1.
+    pub fn opacity(mut self, opacity: f32) -> Glow {
+        self.opacity = opacity;
+        self
+    }
+}
+
+/// An outline drawn around the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    /// Width of the outline outside the glyphs, in pixels.
+    pub width: u8,
+    pub color: Rgba<u8>,
+}
+
+/// How text is painted: its fill, and any outline, drop shadow and glow.
+#[derive(Debug, Clone, Default)]
+pub struct TextStyle {
+    /// The fill of the text. If none is set, the drawing function picks one: presets use
+    /// their own colours, and other functions fill with black.
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+    pub shadow: Option<Shadow>,
+    pub glow: Option<Glow>,
+    /// The contrast the text should have with what it's drawn on. Presets check it, and fix
+    /// text that falls short of it, before drawing.
+    pub contrast: Option<AutoContrast>,
+}
+
+impl TextStyle {
+    /// A style with no fill set and no effects.
+    pub fn new() -> TextStyle {
+        TextStyle::default()
+    }
+
+    /// Set the fill of the text.
+    pub fn fill(mut self, fill: Fill) -> TextStyle {
+        self.fill = Some(fill);
+        self
+    }
+
+    /// Fill the text with a solid colour.
+    pub fn color(self, color: impl Into<crate::Rgba>) -> TextStyle {
+        self.fill(Fill::Solid(color.into().into()))
+    }
+
+    /// Outline the text with a stroke of the given width and colour.
+    pub fn stroke(mut self, width: u8, color: impl Into<crate::Rgba>) -> TextStyle {
+        self.stroke = Some(Stroke {
+            width,
+            color: color.into().into(),
+        });
+        self
+    }
+
+    /// Draw a shadow behind the text.
+    pub fn shadow(mut self, shadow: Shadow) -> TextStyle {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Draw a glow around the text.
+    pub fn glow(mut self, glow: Glow) -> TextStyle {
+        self.glow = Some(glow);
+        self
+    }
+
+    /// Ask for the text to meet a WCAG contrast level against what it's drawn on, and say how
+    /// to fix it if it doesn't. See [`crate::contrast::ensure_contrast`].
+    pub fn auto_contrast(mut self, level: ContrastLevel, fix: ContrastFix) -> TextStyle {
+        self.contrast = Some(AutoContrast { level, fix });
+        self
+    }
+
+    /// This style, filled with the given colour if it has no fill of its own.
+    pub fn or_fill(&self, rgb: &Rgb) -> TextStyle {
+        let mut style = self.clone();
+        if style.fill.is_none() {
+            style.fill = Some(Fill::Solid(Rgba::from(rgb)));
+        }
+        style
+    }
+
+    // The colour to draw with, if the style is a solid fill with no effects.
+    pub(crate) fn solid_color(&self) -> Option<Rgba<u8>> {
+        if self.stroke.is_some() || self.shadow.is_some() || self.glow.is_some() {
+            return None;
+        }
+        match &self.fill {
+            Some(Fill::Solid(color)) => Some(*color),
+            None => Some(Rgba([0, 0, 0, 255])),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Rgb> for TextStyle {
+    fn from(rgb: &Rgb) -> TextStyle {
+        TextStyle::new().color(rgb)
+    }
+}
+
+impl From<&TextStyle> for TextStyle {
+    fn from(style: &TextStyle) -> TextStyle {
+        style.clone()
+    }
+}
+
+impl Fill {
+    // Prepare to paint the fill across a box of the given size. The painter gives the colour
+    // at a point within the box.
+    fn painter(&self, width: u32, height: u32) -> Box<dyn Fn(u32, u32) -> Rgba<u8> + '_> {
+        match self {
+            Fill::Solid(color) => Box::new(move |_, _| *color),
+            Fill::LinearGradient { start, end, angle } => {
+                let gradient = Gradient::linear(*angle).colors(&[*start, *end]);
+                let (w, h) = (width as f32, height as f32);
+                Box::new(move |x, y| {
+                    gradient.color_at(gradient.position_at(x as f32 + 0.5, y as f32 + 0.5, w, h))
+                })
+            }
+            Fill::Gradient(gradient) => {
+                let sampler = gradient.sampler(width, height);
+                Box::new(move |x, y| sampler.sample(x, y))
+            }
+            Fill::Image(texture) => {
+                if texture.width() == 0 || texture.height() == 0 {
+                    return Box::new(|_, _| Rgba([0, 0, 0, 0]));
+                }
+                Box::new(move |x, y| *texture.get_pixel(x % texture.width(), y % texture.height()))
+            }
+        }
+    }
+}
+
+/// Draw a laid out paragraph onto an image with a style.
+///
+/// Effects are drawn beneath the text in the order shadow, glow, outline, so an outline
+/// always sits on top of a glow. Emoji keep their own colours in place of the fill, with the
+/// effects drawn around them.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `face` - The font face the paragraph was laid out with.
+/// * `paragraph` - The laid out paragraph.
+/// * `x` - X-coordinate of the left of the paragraph.
+/// * `y` - Y-coordinate of the top of the paragraph.
+/// * `style` - Fill and effects of the text.
+pub fn draw_paragraph_styled(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    paragraph: &Paragraph,
+    x: i32,
+    y: i32,
+    style: &TextStyle,
+) {
+    if let Some(color) = style.solid_color() {
+        draw_paragraph(image, face, paragraph, x, y, color);
+        return;
+    }
+    let (layer, offset_x, offset_y) = render_paragraph_styled(face, paragraph, style);
+    draw_layer(image, &layer, x + offset_x, y + offset_y);
+}
+
+/// Render a styled paragraph onto a transparent image.
+///
+/// The image is large enough to hold the text and all of its effects. The position of its
+/// top-left corner, relative to the top-left of the paragraph, is returned along with it.
+///
+/// ### Arguments
+/// * `face` - The font face the paragraph was laid out with.
+/// * `paragraph` - The laid out paragraph.
+/// * `style` - Fill and effects of the text.
+pub fn render_paragraph_styled(
+    face: &FontFace,
+    paragraph: &Paragraph,
+    style: &TextStyle,
+) -> (RgbaImage, i32, i32) {
+    // The box holding the glyphs: the paragraph's box, grown to fit any ink outside it.
+    let font_size = paragraph
+        .lines
+        .first()
+        .map_or(0.0, |line| line.shaped.scale.y);
+    let metrics = measure_paragraph(face, paragraph, font_size);
+    let first_baseline = paragraph.lines.first().map_or(0.0, |line| line.baseline);
+    let left = (-metrics.actual_bounding_box_left).min(0.0).floor() as i32;
+    let top = (first_baseline - metrics.actual_bounding_box_ascent)
+        .min(0.0)
+        .floor() as i32;
+    let right = metrics.actual_bounding_box_right.max(metrics.width).ceil() as i32;
+    let bottom = (first_baseline + metrics.actual_bounding_box_descent)
+        .max(paragraph.height)
+        .ceil() as i32;
+
+    let canvas = StyleCanvas::new(style, left, top, right, bottom);
+    let mut mask = DynamicImage::new_luma8(canvas.width, canvas.height);
+    let mut emoji = EmojiLayer::new(canvas.width, canvas.height);
+    for line in &paragraph.lines {
+        let line_x = canvas.origin_x + line.x.round() as i32;
+        let line_y = canvas.origin_y + (line.baseline - line.shaped.ascent).round() as i32;
+        let white = Rgba([255, 255, 255, 255]);
+        draw_shaped_outlines(&mut mask, face, &line.shaped, line_x, line_y, white);
+        emoji.draw(&line.shaped, line_x as f32, line_y as f32);
+    }
+    let mut mask = mask.to_luma8();
+    emoji.add_to_mask(&mut mask);
+    let layer = render_mask_styled(&canvas, &mask, style, |cx, cy| emoji.color_at(cx, cy));
+    (layer, -canvas.origin_x, -canvas.origin_y)
+}
+
+// An image large enough to hold text and all of its effects.
+pub(crate) struct StyleCanvas {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    // Where the top-left of the text's box is placed on the canvas.
+    pub(crate) origin_x: i32,
+    pub(crate) origin_y: i32,
+    // The box the fill is stretched across, in canvas coordinates.
+    fill_box: (i32, i32, u32, u32),
+}
+
+impl StyleCanvas {
+    // A canvas for glyphs within the given box, relative to the top-left of the text.
+    pub(crate) fn new(
+        style: &TextStyle,
+        left: i32,
+        top: i32,
+        right: i32,
+        bottom: i32,
+    ) -> StyleCanvas {
+        // Leave room around the glyphs for each effect.
+        let stroke = style.stroke.map_or(0, |stroke| stroke.width as i32);
+        let glow = style.glow.map_or(0, |glow| glow.radius as i32 * 2);
+        let blur = style
+            .shadow
+            .map_or(0, |shadow| (shadow.blur * 2.0).ceil() as i32);
+        let shadow_x = style.shadow.map_or(0, |shadow| shadow.offset_x.abs());
+        let shadow_y = style.shadow.map_or(0, |shadow| shadow.offset_y.abs());
+        let pad_x = stroke + glow.max(blur + shadow_x) + 1;
+        let pad_y = stroke + glow.max(blur + shadow_y) + 1;
+
+        let (box_width, box_height) = ((right - left) as u32, (bottom - top) as u32);
+        StyleCanvas {
+            width: box_width + pad_x as u32 * 2,
+            height: box_height + pad_y as u32 * 2,
+            origin_x: pad_x - left,
+            origin_y: pad_y - top,
+            fill_box: (pad_x, pad_y, box_width, box_height),
+        }
+    }
+}
+
+// Paint the effects and fill of text onto a transparent canvas, given the text's coverage.
+// `fill_override` can give the fill colour of a pixel, in place of the style's fill.
+pub(crate) fn render_mask_styled<F: Fn(u32, u32) -> Option<Rgba<u8>>>(
+    canvas: &StyleCanvas,
+    mask: &GrayImage,
+    style: &TextStyle,
+    fill_override: F,
+) -> RgbaImage {
+    let outline = match style.stroke {
+        Some(stroke) if stroke.width > 0 => spread_mask(mask, stroke.width as f32),
+        _ => mask.clone(),
+    };
+
+    let mut layer = RgbaImage::new(canvas.width, canvas.height);
+    if let Some(shadow) = style.shadow {
+        let blurred = blur_mask(&outline, shadow.blur / 2.0);
+        paint_mask(
+            &mut layer,
+            &blurred,
+            shadow.offset_x,
+            shadow.offset_y,
+            shadow.opacity,
+            |_, _| shadow.color,
+        );
+    }
+    if let Some(glow) = style.glow {
+        let spread = spread_mask(&outline, glow.radius as f32 / 2.0);
+        let blurred = blur_mask(&spread, glow.radius as f32 / 2.0);
+        paint_mask(&mut layer, &blurred, 0, 0, glow.opacity, |_, _| glow.color);
+    }
+    if let Some(stroke) = style.stroke {
+        paint_mask(&mut layer, &outline, 0, 0, 1.0, |_, _| stroke.color);
+    }
+    let fill = style.fill.clone().unwrap_or_default();
+    let (box_x, box_y, box_width, box_height) = canvas.fill_box;
+    let painter = fill.painter(box_width.max(1), box_height.max(1));
+    paint_mask(&mut layer, mask, 0, 0, 1.0, |mx, my| {
+        fill_override(mx, my).unwrap_or_else(|| {
+            let fx = (mx as i32 - box_x).max(0) as u32;
+            let fy = (my as i32 - box_y).max(0) as u32;
+            painter(fx, fy)
+        })
+    });
+    layer
+}
+
+/// Draw a translucent image onto another, blending it with the pixels underneath.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `layer` - The image to draw.
+/// * `x` - X-coordinate of the left of the layer. May be negative.
+/// * `y` - Y-coordinate of the top of the layer. May be negative.
+pub fn draw_layer(image: &mut DynamicImage, layer: &RgbaImage, x: i32, y: i32) {
+    let (width, height) = image.dimensions();
+    for (lx, ly, pixel) in layer.enumerate_pixels() {
+        let (image_x, image_y) = (x + lx as i32, y + ly as i32);
+        if pixel[3] == 0
+            || image_x < 0
+            || image_y < 0
+            || image_x >= width as i32
+            || image_y >= height as i32
+        {
+            continue;
+        }
+        let under = image.get_pixel(image_x as u32, image_y as u32);
+        let blended = blend_over(under, *pixel, pixel[3] as f32 / 255.0);
+        image.put_pixel(image_x as u32, image_y as u32, blended);
+    }
+}
+
+// Grow a coverage mask outwards by a distance, with round corners and anti-aliased edges.
+fn spread_mask(mask: &GrayImage, distance: f32) -> GrayImage {
+    let solid = GrayImage::from_fn(mask.width(), mask.height(), |x, y| {
+        Luma([if mask.get_pixel(x, y)[0] >= 128 {
+            255
+        } else {
+            0
+        }])
+    });
+    let distances = euclidean_squared_distance_transform(&solid);
+    GrayImage::from_fn(mask.width(), mask.height(), |x, y| {
+        let distance_to_text = distances.get_pixel(x, y)[0].sqrt() as f32;
+        let coverage = (distance + 0.5 - distance_to_text).clamp(0.0, 1.0);
+        Luma([((coverage * 255.0) as u8).max(mask.get_pixel(x, y)[0])])
+    })
+}
+
+fn blur_mask(mask: &GrayImage, sigma: f32) -> GrayImage {
+    if sigma > 0.0 {
+        gaussian_blur_f32(mask, sigma)
+    } else {
+        mask.clone()
+    }
+}
+
+// Paint the colour given for each pixel through a coverage mask, shifted by an offset.
+fn paint_mask<F: Fn(u32, u32) -> Rgba<u8>>(
+    layer: &mut RgbaImage,
+    mask: &GrayImage,
+    offset_x: i32,
+    offset_y: i32,
+    opacity: f32,
+    color: F,
+) {
+    let (width, height) = layer.dimensions();
+    for (mx, my, coverage) in mask.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+        let (lx, ly) = (mx as i32 + offset_x, my as i32 + offset_y);
+        if lx < 0 || ly < 0 || lx >= width as i32 || ly >= height as i32 {
+            continue;
+        }
+        let color = color(mx, my);
+        let alpha = coverage[0] as f32 / 255.0 * opacity * color[3] as f32 / 255.0;
+        let pixel = layer.get_pixel_mut(lx as u32, ly as u32);
+        *pixel = blend_over(*pixel, color, alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_fills_have_a_solid_color() {
+        assert_eq!(TextStyle::new().solid_color(), Some(Rgba([0, 0, 0, 255])));
+        let red = TextStyle::new().color(Rgba([255, 0, 0, 255]));
+        assert_eq!(red.solid_color(), Some(Rgba([255, 0, 0, 255])));
+        assert_eq!(
+            red.clone().stroke(0, Rgba([0, 0, 0, 255])).solid_color(),
+            None
+        );
+        assert_eq!(red.glow(Glow::new(0)).solid_color(), None);
+    }
+
+    #[test]
+    fn or_fill_keeps_an_existing_fill() {
+        let white = Rgb::new(255, 255, 255);
+        let filled = TextStyle::new().or_fill(&white);
+        assert!(matches!(
+            filled.fill,
+            Some(Fill::Solid(Rgba([255, 255, 255, 255])))
+        ));
+        let red = TextStyle::new()
+            .color(Rgba([255, 0, 0, 255]))
+            .or_fill(&white);
+        assert!(matches!(
+            red.fill,
+            Some(Fill::Solid(Rgba([255, 0, 0, 255])))
+        ));
+    }
+
+    #[test]
+    fn empty_image_fills_are_transparent() {
+        let fill = Fill::Image(Rc::new(RgbaImage::new(0, 0)));
+        assert_eq!(fill.painter(10, 10)(3, 4), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn layers_are_clipped_to_the_image() {
+        let mut image = DynamicImage::new_rgba8(4, 4);
+        let layer = RgbaImage::from_pixel(3, 3, Rgba([255, 0, 0, 255]));
+        draw_layer(&mut image, &layer, -2, 3);
+        assert_eq!(image.get_pixel(0, 3), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(1, 3), Rgba([0, 0, 0, 0]));
+        draw_layer(&mut image, &layer, 10, -10);
+        draw_layer(&mut image, &RgbaImage::new(0, 0), 0, 0);
+    }
+
+    #[test]
+    fn empty_masks_stay_empty() {
+        let mask = GrayImage::new(8, 8);
+        assert!(spread_mask(&mask, 3.0).pixels().all(|pixel| pixel[0] == 0));
+        assert!(blur_mask(&mask, 0.0).pixels().all(|pixel| pixel[0] == 0));
+        let canvas = StyleCanvas::new(&TextStyle::new(), 0, 0, 0, 0);
+        let layer = render_mask_styled(
+            &canvas,
+            &GrayImage::new(canvas.width, canvas.height),
+            &TextStyle::new(),
+            |_, _| None,
+        );
+        assert!(layer.pixels().all(|pixel| pixel[3] == 0));
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn empty_paragraphs_render_without_ink() {
+        use crate::fonts::{with_embedded_fonts, FontDescriptor};
+        use crate::layout::{layout_paragraph, ParagraphOptions};
+
+        with_embedded_fonts(|fonts| {
+            let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+            let paragraph = layout_paragraph(face, "", 40.0, &ParagraphOptions::new());
+            let style = TextStyle::new()
+                .stroke(3, Rgba([0, 0, 0, 255]))
+                .shadow(Shadow::new(4, 4).blur(2.0));
+            let (layer, _, _) = render_paragraph_styled(face, &paragraph, &style);
+            assert!(layer.pixels().all(|pixel| pixel[3] == 0));
+        });
+    }
+}
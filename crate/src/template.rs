@@ -0,0 +1,1269 @@
+//! Designs written as JSON or TOML templates, with placeholders filled in when they are rendered.
+//!
+//! A template describes a [`Document`]: the canvas size, a background that is a colour,
+//! gradient, pattern or image, and layers of text, shapes, images, gradients and groups. Any
+//! string in a template can contain placeholders such as `{{title}}`, which are replaced by
+//! variables when the template is rendered, so one template can make a graphic for every post.
+//!
+//! Templates are checked when they are loaded. Every problem found is reported at once, along
+//! with where it is, such as `layers["content"].elements["title"].color: invalid colour "#ggg"`.
+//!
+//! ```
+//! use gdl::template::Template;
+//! use std::collections::HashMap;
+//!
+//! let template = Template::from_toml(
+//!     r##"
+//!     width = 1200
+//!     height = 630
+//!
+//!     [background]
+//!     gradient = "pinkblue"
+//!
+//!     [[layers]]
+//!     name = "content"
+//!
+//!     [[layers.elements]]
+//!     type = "rect"
+//!     x = 60
+//!     y = 60
+//!     width = 1080
+//!     height = 510
+//!     radius = 32
+//!     color = "rgba(255, 255, 255, 0.85)"
+//!
+//!     [[layers.elements]]
+//!     type = "text"
+//!     name = "title"
+//!     text = "{{title}}"
+//!     x = 120
+//!     y = 200
+//!     width = 960
+//!     font = "Roboto"
+//!     weight = 700
+//!     size = 72
+//!     align = "centre"
+//!     color = "{{accent}}"
+//!     "##,
+//! )
+//! .unwrap();
+//! assert_eq!(template.placeholders(), vec!["accent", "title"]);
+//!
+//! let mut variables = HashMap::new();
+//! variables.insert("title".to_string(), "Ten Days In Lisbon".to_string());
+//! variables.insert("accent".to_string(), "#c2185b".to_string());
+//! let document = template.to_document(&variables).unwrap();
+//! assert!(document.find("title").is_some());
+//!
+//! // Values are checked once the placeholders are filled in.
+//! variables.insert("accent".to_string(), "not a colour".to_string());
+//! assert!(template.to_document(&variables).is_err());
+//! ```
+//!
+//! ### Format
+//!
+//! The top level has `width`, `height`, an optional `background` and a list of `layers`. The
+//! canvas can be at most [`MAX_CANVAS_SIZE`] pixels wide and high.
+//!
+//! * `background` - One of `color`, `gradient`, `pattern` or `image`. A pattern, which is one
+//!   of "circles", "spaced_circles", "lines" or "grid", is drawn in `color`.
+//! * `layers` - Each has a `name`, `opacity`, `blend_mode`, `visible` and a list of `elements`.
+//! * `elements` - Each has a `type` and optional `name`, `x`, `y`, `rotation` (degrees, around
+//!   the centre of the element), `scale`, `opacity`, `blend_mode` and `visible`.
+//!   * `text` - `text`, with `**bold**` and other [rich text](crate::rich_text) markup, and
+//!     `font`, `weight`, `italic`, `size`, `width` (to wrap at), `align`, `line_height`,
+//!     `letter_spacing`, `color` or `gradient`, `stroke = { width, color }` and
+//!     `shadow = { x, y, blur, color, opacity }`.
+//!   * `rect`, `ellipse` and `polygon` - `width` and `height`, or a list of `points` for a
+//!     polygon, `radius` for rounded corners, `color` or `gradient`, and `stroke`.
+//!   * `image` - `src`, a `data:` URL, shown at `width` and `height` with `fit` "cover",
+//!     "contain" or "stretch", and `radius` for rounded corners. Files can be used too, by a
+//!     path relative to the directory given to [`Template::image_dir`].
+//!   * `gradient` - `gradient` across `width` and `height`, which default to the canvas size.
+//!   * `group` - A list of `elements`, placed, faded and blended together.
+//!
+//! Colours are any CSS colour [`Rgba`](crate::Rgba) can parse. A gradient is the name of a
+//! [preset](crate::palettes), or a table written like the gradients in a preset file.
+
+use crate::background::{
+    circle_background, grid_background, lined_background, spaced_circle_background,
+};
+use crate::compositing::BlendMode;
+use crate::document::{
+    Document, GradientNode, ImageNode, Layer, Node, ShapeNode, TextNode, Transform,
+};
+#[cfg(feature = "embedded-fonts")]
+use crate::fonts::with_embedded_fonts;
+use crate::fonts::{FontDescriptor, FontRegistry, FontStyle};
+use crate::gradient::Gradient;
+use crate::helpers::dyn_to_photonimg;
+use crate::layout::{ParagraphOptions, TextAlign};
+use crate::palettes::{self, GradientSpec};
+use crate::path::{self, Path, Stroke};
+use crate::style::{self, Shadow, TextStyle};
+use crate::{Error, PhotonImage, Result};
+use image::{DynamicImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "embedded-fonts")]
+use wasm_bindgen::prelude::*;
+
+/// The largest width and height a template's canvas can have, in pixels.
+pub const MAX_CANVAS_SIZE: u32 = 16_384;
+
+/// A design template, loaded from JSON or TOML.
+#[derive(Clone)]
+pub struct Template {
+    file: TemplateFile,
+    image_dir: Option<PathBuf>,
+}
+
+impl Template {
+    /// Load and check a template written in JSON.
+    pub fn from_json(json: &str) -> Result<Template> {
+        let file = serde_json::from_str(json).map_err(|err| invalid(err.to_string()))?;
+        Template::new(file)
+    }
+
+    /// Load and check a template written in TOML.
+    pub fn from_toml(toml: &str) -> Result<Template> {
+        let file = toml::from_str(toml).map_err(|err| invalid(err.to_string()))?;
+        Template::new(file)
+    }
+
+    /// Load and check a template from a `.json` or `.toml` file.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Template> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Template::from_toml(&contents),
+            _ => Template::from_json(&contents),
+        }
+    }
+
+    /// Let images be opened from files in a directory, as well as from `data:` URLs.
+    ///
+    /// Without a directory, a template can't read any files. With one, an image's `src` is a
+    /// path relative to it, and paths that lead outside it are rejected.
+    pub fn image_dir<P: AsRef<std::path::Path>>(mut self, dir: P) -> Template {
+        self.image_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// The template written as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.file).map_err(|err| invalid(err.to_string()))
+    }
+
+    /// The template written as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(&self.file).map_err(|err| invalid(err.to_string()))
+    }
+
+    /// Width of the canvas, in pixels.
+    pub fn width(&self) -> u32 {
+        self.file.width
+    }
+
+    /// Height of the canvas, in pixels.
+    pub fn height(&self) -> u32 {
+        self.file.height
+    }
+
+    /// Names of the variables the template's placeholders use, in alphabetical order.
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        visit_strings(&self.value(), "", &mut |_, text| {
+            if let Ok(found) = placeholders_in(text) {
+                names.extend(found.into_iter().map(String::from));
+            }
+        });
+        names.into_iter().collect()
+    }
+
+    /// Check the template, reporting every problem found.
+    ///
+    /// Values that contain placeholders are checked once they are filled in, when the template
+    /// is rendered.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        visit_strings(&self.value(), "", &mut |location, text| {
+            if let Err(reason) = placeholders_in(text) {
+                errors.push(format!("{}: {}", location, reason));
+            }
+        });
+        let mut builder = Builder::new(false, None);
+        builder.document(&self.file);
+        errors.extend(builder.errors);
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(invalid(errors.join("; "))),
+        }
+    }
+
+    /// Fill in the template's placeholders and build the design it describes.
+    ///
+    /// ### Arguments
+    /// * `variables` - Values for the placeholders, by name.
+    ///
+    /// Returns an error listing any placeholders without a variable, or any values that are
+    /// invalid once filled in, such as an image that can't be opened.
+    pub fn to_document(&self, variables: &HashMap<String, String>) -> Result<Document> {
+        let mut missing = BTreeSet::new();
+        let mut value = self.value();
+        fill_placeholders(&mut value, variables, &mut missing);
+        if !missing.is_empty() {
+            return Err(Error::MissingVariables(missing.into_iter().collect()));
+        }
+        let file: TemplateFile =
+            serde_json::from_value(value).map_err(|err| invalid(err.to_string()))?;
+
+        let mut builder = Builder::new(true, self.image_dir.clone());
+        let document = builder.document(&file);
+        match builder.errors.is_empty() {
+            true => Ok(document),
+            false => Err(invalid(builder.errors.join("; "))),
+        }
+    }
+
+    /// Fill in the template's placeholders and render it.
+    ///
+    /// ### Arguments
+    /// * `fonts` - FontRegistry the template's fonts are taken from.
+    /// * `variables` - Values for the placeholders, by name.
+    pub fn render(
+        &self,
+        fonts: &FontRegistry,
+        variables: &HashMap<String, String>,
+    ) -> Result<DynamicImage> {
+        self.to_document(variables)?.render(fonts)
+    }
+
+    /// Fill in the template's placeholders and render it as a PhotonImage.
+    pub fn render_photon(
+        &self,
+        fonts: &FontRegistry,
+        variables: &HashMap<String, String>,
+    ) -> Result<PhotonImage> {
+        Ok(dyn_to_photonimg(&self.render(fonts, variables)?))
+    }
+
+    fn new(file: TemplateFile) -> Result<Template> {
+        let template = Template {
+            file,
+            image_dir: None,
+        };
+        template.validate()?;
+        Ok(template)
+    }
+
+    // The template as a JSON value, whose strings can be searched and filled in.
+    fn value(&self) -> Value {
+        serde_json::to_value(&self.file).unwrap_or(Value::Null)
+    }
+}
+
+/// Render a JSON template with the embedded fonts.
+///
+/// ### Arguments
+/// * `template` - The template, as JSON.
+/// * `variables` - A JSON object of strings, giving the values of the template's placeholders.
+#[cfg(feature = "embedded-fonts")]
+#[wasm_bindgen]
+pub fn render_template(
+    template: &str,
+    variables: &str,
+) -> std::result::Result<PhotonImage, JsValue> {
+    let variables: HashMap<String, String> =
+        serde_json::from_str(variables).map_err(|err| invalid(format!("variables: {}", err)))?;
+    let template = Template::from_json(template)?;
+    Ok(with_embedded_fonts(|fonts| {
+        template.render_photon(fonts, &variables)
+    })?)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    width: u32,
+    height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<BackgroundSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<LayerSpec>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackgroundSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gradient: Option<GradientRef>,
+}
+
+// A gradient preset's name, or a gradient written out in full.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum GradientRef {
+    Preset(String),
+    Inline(GradientSpec),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opacity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blend_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    elements: Vec<ElementSpec>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ElementKind {
+    Text,
+    Rect,
+    Ellipse,
+    Polygon,
+    Image,
+    Gradient,
+    Group,
+}
+
+// Every field an element can have. Which ones are used depends on its type. Tables come
+// last, as TOML needs them after plain values.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ElementSpec {
+    #[serde(rename = "type")]
+    kind: ElementKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opacity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blend_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    font: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weight: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    align: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line_height: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    letter_spacing: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    radius: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    points: Option<Vec<(f32, f32)>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    src: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gradient: Option<GradientRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stroke: Option<StrokeSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shadow: Option<ShadowSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    elements: Option<Vec<ElementSpec>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StrokeSpec {
+    width: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShadowSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blur: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opacity: Option<f32>,
+}
+
+// Builds a document from a template, collecting every problem found along the way.
+struct Builder {
+    // Whether placeholders have been filled in. Before then, values with placeholders are
+    // skipped and images aren't opened.
+    resolved: bool,
+    // The directory images can be opened from, if files can be read at all.
+    image_dir: Option<PathBuf>,
+    errors: Vec<String>,
+    canvas: (f32, f32),
+}
+
+impl Builder {
+    fn new(resolved: bool, image_dir: Option<PathBuf>) -> Builder {
+        Builder {
+            resolved,
+            image_dir,
+            errors: Vec::new(),
+            canvas: (0.0, 0.0),
+        }
+    }
+
+    fn error(&mut self, location: &str, message: impl Display) {
+        self.errors.push(format!("{}: {}", location, message));
+    }
+
+    // Whether a value can be checked yet.
+    fn ready(&self, value: &str) -> bool {
+        self.resolved || !value.contains("{{")
+    }
+
+    fn document(&mut self, file: &TemplateFile) -> Document {
+        if file.width == 0 {
+            self.error("width", "the canvas must be at least 1 pixel wide");
+        }
+        if file.height == 0 {
+            self.error("height", "the canvas must be at least 1 pixel high");
+        }
+        if file.width > MAX_CANVAS_SIZE {
+            self.error(
+                "width",
+                format!("the canvas can be at most {} pixels wide", MAX_CANVAS_SIZE),
+            );
+        }
+        if file.height > MAX_CANVAS_SIZE {
+            self.error(
+                "height",
+                format!("the canvas can be at most {} pixels high", MAX_CANVAS_SIZE),
+            );
+        }
+        self.canvas = (file.width as f32, file.height as f32);
+        let mut document = Document::new(file.width, file.height);
+        if let Some(background) = &file.background {
+            self.background(&mut document, background);
+        }
+        for (index, spec) in file.layers.iter().enumerate() {
+            let location = locate("layers", index, spec.name.as_deref());
+            let layer = self.layer(&location, spec);
+            document = document.layer(layer);
+        }
+        document
+    }
+
+    fn background(&mut self, document: &mut Document, spec: &BackgroundSpec) {
+        let color = spec
+            .color
+            .as_ref()
+            .and_then(|color| self.color("background.color", color));
+        let set = [
+            spec.pattern.is_some(),
+            spec.image.is_some(),
+            spec.gradient.is_some(),
+        ];
+        if set.iter().filter(|&&set| set).count() > 1 {
+            self.error(
+                "background",
+                "use only one of \"pattern\", \"image\" and \"gradient\"",
+            );
+            return;
+        }
+        let (width, height) = (document.width, document.height);
+        let (canvas_width, canvas_height) = self.canvas;
+
+        let node = if let Some(pattern) = &spec.pattern {
+            let rgb = crate::Rgba::from(color.unwrap_or(Rgba([255, 255, 255, 255]))).into();
+            let image = match normalise(pattern).as_str() {
+                "circles" => circle_background(width, height),
+                "spacedcircles" => spaced_circle_background(width, height),
+                "lines" => lined_background(width, height, rgb),
+                "grid" => grid_background(width, height, rgb),
+                _ if !self.ready(pattern) => return,
+                _ => {
+                    self.error(
+                        "background.pattern",
+                        format!(
+                            "unknown pattern \"{}\", expected \"circles\", \"spaced_circles\", \
+                             \"lines\" or \"grid\"",
+                            pattern
+                        ),
+                    );
+                    return;
+                }
+            };
+            Node::new(ImageNode::new(&image))
+        } else if let Some(src) = &spec.image {
+            match self.image("background.image", src) {
+                Some(image) => Node::new(
+                    ImageNode::new(&cover(&image, canvas_width, canvas_height))
+                        .size(canvas_width, canvas_height),
+                ),
+                None => Node::group(Vec::new()),
+            }
+        } else if let Some(gradient) = &spec.gradient {
+            match self.gradient("background.gradient", gradient) {
+                Some(gradient) => {
+                    Node::new(GradientNode::new(gradient, canvas_width, canvas_height))
+                }
+                None => return,
+            }
+        } else {
+            document.background = color;
+            return;
+        };
+        document.background = color;
+        document
+            .layers
+            .insert(0, Layer::new("background").node(node.name("background")));
+    }
+
+    fn layer(&mut self, location: &str, spec: &LayerSpec) -> Layer {
+        let mut layer =
+            Layer::new(spec.name.as_deref().unwrap_or("")).visible(spec.visible.unwrap_or(true));
+        if let Some(opacity) = spec.opacity {
+            layer.opacity = self.opacity(location, opacity);
+        }
+        if let Some(mode) = &spec.blend_mode {
+            layer.blend_mode = self.blend_mode(location, mode);
+        }
+        for (index, element) in spec.elements.iter().enumerate() {
+            let element_location = format!(
+                "{}.{}",
+                location,
+                locate("elements", index, element.name.as_deref())
+            );
+            if let Some(node) = self.element(&element_location, element) {
+                layer = layer.node(node);
+            }
+        }
+        layer
+    }
+
+    fn element(&mut self, location: &str, spec: &ElementSpec) -> Option<Node> {
+        let field = |name: &str| format!("{}.{}", location, name);
+        let mut size = (spec.width, spec.height);
+
+        let node = match spec.kind {
+            ElementKind::Text => {
+                let text = self.require(location, "text", spec.text.as_ref())?;
+                let mut font = FontDescriptor::new(spec.font.as_deref().unwrap_or("Roboto"));
+                if let Some(weight) = spec.weight {
+                    font = font.weight(weight);
+                }
+                if spec.italic == Some(true) {
+                    font = font.style(FontStyle::Italic);
+                }
+                let mut options = ParagraphOptions::new();
+                if let Some(width) = spec.width {
+                    options = options.max_width(width);
+                }
+                if let Some(align) = &spec.align {
+                    options = options.align(self.align(&field("align"), align));
+                }
+                if let Some(line_height) = spec.line_height {
+                    options = options.line_height(line_height);
+                }
+                if let Some(letter_spacing) = spec.letter_spacing {
+                    options = options.letter_spacing(letter_spacing);
+                }
+                let style = self.text_style(location, spec);
+                Node::new(
+                    TextNode::new(text, font, spec.size.unwrap_or(32.0))
+                        .style(style)
+                        .options(options),
+                )
+            }
+            ElementKind::Rect | ElementKind::Ellipse | ElementKind::Polygon => {
+                let path = match spec.kind {
+                    ElementKind::Polygon => {
+                        let points = self.require(location, "points", spec.points.as_ref())?;
+                        if points.len() < 3 {
+                            self.error(&field("points"), "a polygon needs at least 3 points");
+                            return None;
+                        }
+                        Path::new().polygon(points)
+                    }
+                    _ => {
+                        let width = *self.require(location, "width", spec.width.as_ref())?;
+                        let height = *self.require(location, "height", spec.height.as_ref())?;
+                        match spec.kind {
+                            ElementKind::Ellipse => Path::new().ellipse(
+                                width / 2.0,
+                                height / 2.0,
+                                width / 2.0,
+                                height / 2.0,
+                            ),
+                            _ => Path::new().rounded_rect(
+                                0.0,
+                                0.0,
+                                width,
+                                height,
+                                spec.radius.unwrap_or(0.0),
+                            ),
+                        }
+                    }
+                };
+                let mut shape = ShapeNode::new(path);
+                if let Some(fill) = self.fill(location, spec) {
+                    shape = shape.fill(fill);
+                }
+                if let Some(stroke) = &spec.stroke {
+                    let color = self.stroke_color(location, stroke);
+                    shape = shape.stroke(color, Stroke::new(stroke.width));
+                }
+                if shape.fill.is_none() && shape.stroke.is_none() && self.resolved {
+                    self.error(
+                        location,
+                        "give the shape a \"color\", \"gradient\" or \"stroke\"",
+                    );
+                }
+                Node::new(shape)
+            }
+            ElementKind::Image => {
+                let src = self.require(location, "src", spec.src.as_ref())?;
+                let image = self.image(&field("src"), src)?;
+                let (natural_width, natural_height) = image.dimensions();
+                let width = spec.width.unwrap_or(natural_width as f32);
+                let height = spec.height.unwrap_or(natural_height as f32);
+                size = (Some(width), Some(height));
+
+                let fit = spec.fit.as_deref().unwrap_or("cover");
+                let mut node = match normalise(fit).as_str() {
+                    "cover" => {
+                        Node::new(ImageNode::new(&cover(&image, width, height)).size(width, height))
+                    }
+                    "stretch" => Node::new(ImageNode::new(&image).size(width, height)),
+                    "contain" => {
+                        let scale =
+                            (width / natural_width as f32).min(height / natural_height as f32);
+                        let (shown_width, shown_height) =
+                            (natural_width as f32 * scale, natural_height as f32 * scale);
+                        Node::new(ImageNode::new(&image).size(shown_width, shown_height))
+                            .translate((width - shown_width) / 2.0, (height - shown_height) / 2.0)
+                    }
+                    _ => {
+                        self.error(
+                            &field("fit"),
+                            format!(
+                                "unknown fit \"{}\", expected \"cover\", \"contain\" or \"stretch\"",
+                                fit
+                            ),
+                        );
+                        return None;
+                    }
+                };
+                if let Some(radius) = spec.radius {
+                    node = Node::group(vec![node])
+                        .clip(Path::new().rounded_rect(0.0, 0.0, width, height, radius));
+                }
+                node
+            }
+            ElementKind::Gradient => {
+                let gradient = self.require(location, "gradient", spec.gradient.as_ref())?;
+                let gradient = self.gradient(&field("gradient"), gradient)?;
+                let (canvas_width, canvas_height) = self.canvas;
+                let width = spec.width.unwrap_or(canvas_width);
+                let height = spec.height.unwrap_or(canvas_height);
+                size = (Some(width), Some(height));
+                Node::new(GradientNode::new(gradient, width, height))
+            }
+            ElementKind::Group => {
+                let elements = self.require(location, "elements", spec.elements.as_ref())?;
+                let children = elements
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, element)| {
+                        let child_location = format!(
+                            "{}.{}",
+                            location,
+                            locate("elements", index, element.name.as_deref())
+                        );
+                        self.element(&child_location, element)
+                    })
+                    .collect();
+                Node::group(children)
+            }
+        };
+
+        // Rotate and scale the element around its centre, then move it into place.
+        let (centre_x, centre_y) = (size.0.unwrap_or(0.0) / 2.0, size.1.unwrap_or(0.0) / 2.0);
+        let scale = spec.scale.unwrap_or(1.0);
+        let placement = Transform::translation(-centre_x, -centre_y)
+            .then(&Transform::scaling(scale, scale))
+            .then(&Transform::rotation(spec.rotation.unwrap_or(0.0)))
+            .then(&Transform::translation(
+                spec.x.unwrap_or(0.0) + centre_x,
+                spec.y.unwrap_or(0.0) + centre_y,
+            ));
+        // Keep any offset the node already has, such as that of a contained image.
+        let transform = node.transform.then(&placement);
+        let mut node = node
+            .transform(transform)
+            .visible(spec.visible.unwrap_or(true));
+        if let Some(name) = &spec.name {
+            node = node.name(name);
+        }
+        if let Some(opacity) = spec.opacity {
+            node.opacity = self.opacity(location, opacity);
+        }
+        if let Some(mode) = &spec.blend_mode {
+            node.blend_mode = self.blend_mode(location, mode);
+        }
+        Some(node)
+    }
+
+    fn text_style(&mut self, location: &str, spec: &ElementSpec) -> TextStyle {
+        let mut style = TextStyle::new();
+        if let Some(gradient) = &spec.gradient {
+            if let Some(gradient) = self.gradient(&format!("{}.gradient", location), gradient) {
+                style = style.fill(style::Fill::Gradient(gradient));
+            }
+        } else if let Some(color) = &spec.color {
+            if let Some(color) = self.color(&format!("{}.color", location), color) {
+                style = style.color(color);
+            }
+        }
+        if let Some(stroke) = &spec.stroke {
+            let color = self.stroke_color(location, stroke);
+            style = style.stroke(stroke.width.round().clamp(0.0, 255.0) as u8, color);
+        }
+        if let Some(spec) = &spec.shadow {
+            let mut shadow = Shadow::new(spec.x.unwrap_or(4), spec.y.unwrap_or(4));
+            if let Some(blur) = spec.blur {
+                shadow = shadow.blur(blur);
+            }
+            if let Some(color) = &spec.color {
+                if let Some(color) = self.color(&format!("{}.shadow.color", location), color) {
+                    shadow = shadow.color(color);
+                }
+            }
+            if let Some(opacity) = spec.opacity {
+                shadow = shadow.opacity(self.opacity(&format!("{}.shadow", location), opacity));
+            }
+            style = style.shadow(shadow);
+        }
+        style
+    }
+
+    // What a shape is filled with: a gradient if it has one, otherwise its colour.
+    fn fill(&mut self, location: &str, spec: &ElementSpec) -> Option<path::Fill> {
+        if let Some(gradient) = &spec.gradient {
+            let gradient = self.gradient(&format!("{}.gradient", location), gradient)?;
+            return Some(path::Fill::Gradient(gradient));
+        }
+        let color = spec.color.as_ref()?;
+        self.color(&format!("{}.color", location), color)
+            .map(path::Fill::Solid)
+    }
+
+    fn stroke_color(&mut self, location: &str, stroke: &StrokeSpec) -> Rgba<u8> {
+        let black = Rgba([0, 0, 0, 255]);
+        match &stroke.color {
+            Some(color) => self
+                .color(&format!("{}.stroke.color", location), color)
+                .unwrap_or(black),
+            None => black,
+        }
+    }
+
+    fn require<'a, T>(
+        &mut self,
+        location: &str,
+        field: &str,
+        value: Option<&'a T>,
+    ) -> Option<&'a T> {
+        if value.is_none() {
+            self.error(location, format!("missing \"{}\"", field));
+        }
+        value
+    }
+
+    fn color(&mut self, location: &str, color: &str) -> Option<Rgba<u8>> {
+        if !self.ready(color) {
+            return None;
+        }
+        match color.parse::<crate::Rgba>() {
+            Ok(color) => Some(color.into()),
+            Err(_) => {
+                self.error(location, format!("invalid colour \"{}\"", color));
+                None
+            }
+        }
+    }
+
+    fn gradient(&mut self, location: &str, gradient: &GradientRef) -> Option<Gradient> {
+        let result = match gradient {
+            GradientRef::Preset(name) if !self.ready(name) => return None,
+            GradientRef::Preset(name) => palettes::gradient(name).map_err(|err| err.to_string()),
+            GradientRef::Inline(spec) if !self.resolved => {
+                // Colours with placeholders are checked once they are filled in.
+                let spec_value = serde_json::to_value(spec).unwrap_or(Value::Null);
+                let mut has_placeholders = false;
+                visit_strings(&spec_value, "", &mut |_, text| {
+                    has_placeholders |= text.contains("{{")
+                });
+                if has_placeholders {
+                    return None;
+                }
+                spec.build()
+            }
+            GradientRef::Inline(spec) => spec.build(),
+        };
+        match result {
+            Ok(gradient) => Some(gradient),
+            Err(reason) => {
+                self.error(location, reason);
+                None
+            }
+        }
+    }
+
+    fn image(&mut self, location: &str, src: &str) -> Option<DynamicImage> {
+        if !self.resolved {
+            return None;
+        }
+        let result = open_image_source(src, self.image_dir.as_deref());
+        match result {
+            Ok(image) => Some(image),
+            Err(reason) => {
+                let shown: String = src.chars().take(40).collect();
+                self.error(
+                    location,
+                    format!("unable to open \"{}\": {}", shown, reason),
+                );
+                None
+            }
+        }
+    }
+
+    fn opacity(&mut self, location: &str, opacity: f32) -> f32 {
+        if !(0.0..=1.0).contains(&opacity) {
+            self.error(
+                location,
+                format!("opacity {} is not between 0 and 1", opacity),
+            );
+        }
+        opacity.clamp(0.0, 1.0)
+    }
+
+    fn blend_mode(&mut self, location: &str, name: &str) -> BlendMode {
+        if !self.ready(name) {
+            return BlendMode::Normal;
+        }
+        BlendMode::from_name(name).unwrap_or_else(|| {
+            let names: Vec<&str> = BlendMode::ALL.iter().map(|mode| mode.name()).collect();
+            self.error(
+                location,
+                format!(
+                    "unknown blend mode \"{}\", expected one of {}",
+                    name,
+                    names.join(", ")
+                ),
+            );
+            BlendMode::Normal
+        })
+    }
+
+    fn align(&mut self, location: &str, align: &str) -> TextAlign {
+        match TextAlign::from_name(align) {
+            Some(align) => align,
+            None if !self.ready(align) => TextAlign::Start,
+            None => {
+                self.error(
+                    location,
+                    format!(
+                        "unknown alignment \"{}\", expected \"start\", \"end\", \"left\", \
+                         \"right\", \"centre\" or \"justify\"",
+                        align
+                    ),
+                );
+                TextAlign::Start
+            }
+        }
+    }
+}
+
+// Where an item of a list is, by name if it has one.
+fn locate(list: &str, index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{}[\"{}\"]", list, name),
+        None => format!("{}[{}]", list, index),
+    }
+}
+
+// Open an image from a `data:` URL, or from a file inside `image_dir` if one is given, or say
+// why it can't be opened.
+pub(crate) fn open_image_source(
+    src: &str,
+    image_dir: Option<&std::path::Path>,
+) -> std::result::Result<DynamicImage, String> {
+    if let Some(data_url) = src.strip_prefix("data:") {
+        let data = data_url.split_once(',').map_or(data_url, |(_, data)| data);
+        return base64::decode(data)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| image::load_from_memory(&bytes).map_err(|err| err.to_string()));
+    }
+    let dir = image_dir.ok_or("only data: URLs can be used without an image directory")?;
+    // Resolving both paths follows any `..` and symbolic links, so the check below sees where
+    // the file really is.
+    let dir = dir.canonicalize().map_err(|err| err.to_string())?;
+    let path = dir
+        .join(src)
+        .canonicalize()
+        .map_err(|err| err.to_string())?;
+    if !path.starts_with(&dir) {
+        return Err("the file is outside the image directory".to_string());
+    }
+    image::open(path).map_err(|err| err.to_string())
+}
+
+// Crop an image to the shape of a box, keeping its centre, so it covers the box when scaled.
+fn cover(image: &DynamicImage, width: f32, height: f32) -> DynamicImage {
+    let (image_width, image_height) = image.dimensions();
+    if width <= 0.0 || height <= 0.0 || image_width == 0 || image_height == 0 {
+        return image.clone();
+    }
+    let scale = (image_width as f32 / width).min(image_height as f32 / height);
+    let crop_width = ((width * scale).round() as u32).clamp(1, image_width);
+    let crop_height = ((height * scale).round() as u32).clamp(1, image_height);
+    image.crop_imm(
+        (image_width - crop_width) / 2,
+        (image_height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    )
+}
+
+// The names of the placeholders in a string, or what is wrong with one.
+fn placeholders_in(text: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| "a placeholder is missing its closing \"}}\"".to_string())?;
+        let name = after[..end].trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(format!("invalid placeholder \"{{{{{}}}}}\"", &after[..end]));
+        }
+        names.push(name);
+        rest = &after[end + 2..];
+    }
+    Ok(names)
+}
+
+// Replace the placeholders in every string of a value, noting any without a variable.
+fn fill_placeholders(
+    value: &mut Value,
+    variables: &HashMap<String, String>,
+    missing: &mut BTreeSet<String>,
+) {
+    match value {
+        Value::String(text) => {
+            let mut filled = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let after = &rest[start + 2..];
+                let end = match after.find("}}") {
+                    Some(end) => end,
+                    None => break,
+                };
+                let name = after[..end].trim();
+                filled.push_str(&rest[..start]);
+                match variables.get(name) {
+                    Some(variable) => filled.push_str(variable),
+                    None => {
+                        missing.insert(name.to_string());
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            filled.push_str(rest);
+            *text = filled;
+        }
+        Value::Array(items) => {
+            for item in items {
+                fill_placeholders(item, variables, missing);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                fill_placeholders(field, variables, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Call a function with every string in a value, and where it is.
+fn visit_strings(value: &Value, location: &str, visit: &mut impl FnMut(&str, &str)) {
+    match value {
+        Value::String(text) => visit(location, text),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let name = item.get("name").and_then(Value::as_str);
+                visit_strings(item, &locate(location, index, name), visit);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let location = match location {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", location, key),
+                };
+                visit_strings(field, &location, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Lowercase a name and remove spaces, hyphens and underscores.
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn invalid(reason: impl Display) -> Error {
+    Error::InvalidTemplate(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(result: Result<Template>) -> String {
+        match result {
+            Err(Error::InvalidTemplate(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the template was accepted"),
+        }
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn malformed_json_and_toml_are_rejected() {
+        reason(Template::from_json(""));
+        reason(Template::from_json("{\"width\": 100, \"height\": "));
+        reason(Template::from_json("[]"));
+        reason(Template::from_toml("width = "));
+        reason(Template::from_toml("height = 10"));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let reason = reason(Template::from_json(
+            r#"{"width": 10, "height": 10, "colour": "red"}"#,
+        ));
+        assert!(reason.contains("colour"), "{}", reason);
+    }
+
+    #[test]
+    fn empty_canvas_is_rejected() {
+        let reason = reason(Template::from_json(r#"{"width": 0, "height": 0}"#));
+        assert!(
+            reason.contains("width") && reason.contains("height"),
+            "{}",
+            reason
+        );
+    }
+
+    #[test]
+    fn oversized_canvas_is_rejected() {
+        let reason = reason(Template::from_json(r#"{"width": 100000, "height": 16384}"#));
+        assert!(
+            reason.contains("width") && !reason.contains("height"),
+            "{}",
+            reason
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported_with_its_location() {
+        let reason = reason(Template::from_json(
+            r##"{
+                "width": 100,
+                "height": 100,
+                "background": {"color": "#ggg"},
+                "layers": [{"name": "main", "elements": [
+                    {"type": "rect", "name": "box", "width": 10, "height": 10, "color": "nope"},
+                    {"type": "text", "text": "{{title"}
+                ]}]
+            }"##,
+        ));
+        assert!(reason.contains("background.color"), "{}", reason);
+        assert!(
+            reason.contains(r#"layers["main"].elements["box"].color"#),
+            "{}",
+            reason
+        );
+        assert!(reason.contains("closing"), "{}", reason);
+    }
+
+    #[test]
+    fn invalid_placeholder_names_are_rejected() {
+        for text in &["{{}}", "{{ }}", "{{two words}}", "{{a/b}}"] {
+            let json = format!(
+                r#"{{"width": 10, "height": 10, "layers": [{{"elements": [{{"type": "text", "text": "{}"}}]}}]}}"#,
+                text
+            );
+            let reason = reason(Template::from_json(&json));
+            assert!(reason.contains("invalid placeholder"), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn placeholders_are_listed_once_in_order() {
+        let template = Template::from_json(
+            r#"{"width": 10, "height": 10, "layers": [{"elements": [
+                {"type": "text", "text": "{{b}} {{ a }} {{b}}"},
+                {"type": "text", "text": "plain"}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(template.placeholders(), ["a", "b"]);
+    }
+
+    #[test]
+    fn missing_variables_are_listed() {
+        let template = Template::from_json(
+            r#"{"width": 10, "height": 10, "layers": [{"elements": [
+                {"type": "text", "text": "{{title}} by {{author}}", "color": "{{accent}}"}
+            ]}]}"#,
+        )
+        .unwrap();
+        match template.to_document(&variables(&[("title", "Hi")])) {
+            Err(Error::MissingVariables(names)) => assert_eq!(names, ["accent", "author"]),
+            other => panic!("expected missing variables, got {:?}", other.err()),
+        }
+        let filled = variables(&[("title", "Hi"), ("author", "Me"), ("accent", "tomato")]);
+        assert!(template.to_document(&filled).is_ok());
+        let bad_colour = variables(&[("title", "Hi"), ("author", "Me"), ("accent", "")]);
+        assert!(matches!(
+            template.to_document(&bad_colour),
+            Err(Error::InvalidTemplate(_))
+        ));
+    }
+
+    #[test]
+    fn templates_round_trip() {
+        let json = r#"{"width": 320, "height": 200, "background": {"gradient": "pinkblue"},
+            "layers": [{"name": "shapes", "opacity": 0.5, "elements": [
+                {"type": "ellipse", "x": 10, "y": 10, "width": 50, "height": 30, "color": "red"}
+            ]}]}"#;
+        let template = Template::from_json(json).unwrap();
+        let from_json = Template::from_json(&template.to_json().unwrap()).unwrap();
+        let from_toml = Template::from_toml(&template.to_toml().unwrap()).unwrap();
+        assert_eq!(from_json.value(), template.value());
+        assert_eq!(from_toml.value(), template.value());
+        assert_eq!((from_toml.width(), from_toml.height()), (320, 200));
+    }
+
+    #[test]
+    fn files_are_only_opened_from_the_image_directory() {
+        let root = std::env::temp_dir().join(format!("gdl-template-{}", std::process::id()));
+        let dir = root.join("images");
+        fs::create_dir_all(&dir).unwrap();
+        let image = DynamicImage::new_rgba8(4, 4);
+        image.save(dir.join("inside.png")).unwrap();
+        image.save(root.join("outside.png")).unwrap();
+
+        let template = Template::from_json(
+            r#"{"width": 10, "height": 10, "layers": [{"elements": [
+                {"type": "image", "src": "{{src}}", "width": 10, "height": 10}
+            ]}]}"#,
+        )
+        .unwrap();
+        let mut png = Vec::new();
+        image
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let data_url = format!("data:image/png;base64,{}", base64::encode(&png));
+        let open = |template: &Template, src: &str| {
+            template
+                .to_document(&variables(&[("src", src)]))
+                .map(|_| ())
+        };
+
+        assert!(open(&template, &data_url).is_ok());
+        let outside = root.join("outside.png");
+        for src in &["inside.png", outside.to_str().unwrap()] {
+            assert!(matches!(
+                open(&template, src),
+                Err(Error::InvalidTemplate(_))
+            ));
+        }
+
+        let template = template.image_dir(&dir);
+        assert!(open(&template, &data_url).is_ok());
+        assert!(open(&template, "inside.png").is_ok());
+        for src in &["../outside.png", outside.to_str().unwrap(), "missing.png"] {
+            assert!(matches!(
+                open(&template, src),
+                Err(Error::InvalidTemplate(_))
+            ));
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn empty_images_are_covered_without_cropping() {
+        let image = DynamicImage::new_rgba8(0, 0);
+        assert_eq!(cover(&image, 10.0, 10.0).dimensions(), (0, 0));
+    }
+}
@@ -1,239 +1,663 @@
 //! Draw text onto images.
 
-use crate::Rgb;
+use crate::fonts::{FontDescriptor, FontRegistry};
+use crate::layout::{
+    apply_letter_spacing, draw_paragraph_rotated, fit_paragraph, layout_paragraph, FittedText,
+    Paragraph, ParagraphOptions, TextAnchor,
+};
+use crate::metrics::measure_paragraph;
+use crate::rich_text::{
+    draw_rich_paragraph, fit_rich_paragraph, layout_rich_text, RichParagraph, RichText,
+};
+use crate::shaping::{shape_text, TextDirection};
+use crate::style::{draw_layer, draw_paragraph_styled, render_paragraph_styled, TextStyle};
+use crate::text_path::{draw_shaped_text_on_path, PathTextOptions, TextPath};
+use crate::{Result, Rgb};
 use image::imageops::{rotate180, rotate270, rotate90};
-use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
-use imageproc::distance_transform::Norm;
-use imageproc::drawing::draw_text_mut;
-use imageproc::morphology::dilate_mut;
-use rusttype::{Font, Scale};
-use std::fs;
+use image::{DynamicImage, Rgba};
+use imageproc::rect::Rect;
 
 /// Draw text onto an image with a border around the text.
 ///
+/// This is shorthand for [`draw_text`] with a style that outlines the text.
+///
 /// ### Arguments
 /// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
 /// * `text` - Text string to be drawn.
 /// * `x` - X-coordinate of top corner of text.
 /// * `y` - Y coordinae of top corner of text.
-pub fn draw_text_with_border(image: &mut DynamicImage, font: &str, text: &str, x: u32, y: u32) {
-    let mut image2: DynamicImage = DynamicImage::new_luma8(image.width(), image.height());
-
-    let font_vec = open_font(font);
-    let font = Font::try_from_bytes(&font_vec).unwrap();
-    let height = 90f32;
-    let scale = Scale {
-        x: height * 1.0,
-        y: height,
-    };
-    draw_text_mut(
-        &mut image2,
-        Rgba([255u8, 255u8, 255u8, 255u8]),
-        x,
-        y,
-        scale,
-        &font,
-        text,
-    );
-
-    let mut image2 = image2.to_luma8();
-    dilate_mut(&mut image2, Norm::LInf, 14u8);
-
-    // Add a border to the text.
-    for x in 0..image2.width() {
-        for y in 0..image2.height() {
-            let pixval = 255 - image2.get_pixel(x, y).0[0];
-            if pixval != 255 {
-                let new_pix = Rgba([234, 23, 123, 255]);
-                image.put_pixel(x, y, new_pix);
-            }
-        }
-    }
-    draw_text_mut(
-        image,
-        Rgba([193u8, 255u8, 255u8, 255u8]),
-        x + 10,
-        y - 10,
-        scale,
-        &font,
-        text,
-    );
+/// * `font_size`: f32 that represents the font's size.
+/// * `rgb`: Rgb text color.
+/// * `border_rgb`: Rgb color of the border.
+/// * `border_width`: Width of the border, in pixels.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_with_border(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    font: &FontDescriptor,
+    text: &str,
+    x: u32,
+    y: u32,
+    font_size: f32,
+    rgb: &Rgb,
+    border_rgb: &Rgb,
+    border_width: u8,
+) -> Result<()> {
+    let style = TextStyle::from(rgb).stroke(border_width, Rgba::from(border_rgb));
+    draw_text(image, fonts, text, x, y, font, font_size, &style)
 }
 
 /// Draw text onto an image.
 ///
 /// ### Arguments
 /// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
 /// * `text` - Text string to be drawn.
 /// * `x` - X-coordinate of top corner of text.
 /// * `y` - Y coordinae of top corner of text.
-/// * `font` - Font name. Fonts available include Roboto-Regular, BebasKai, Roboto-Light, among many others.
-/// Full list of fonts available coming soon.
+/// * `font` - FontDescriptor giving the family, weight and style of the font, eg:
+///   `FontDescriptor::new("Roboto").bold()`.
 /// * `font_size`: f32 that represents the font's size.
-/// * `rgb`: Rgb text color.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_text(
     image: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     x: u32,
     y: u32,
-    font: &str,
+    font: &FontDescriptor,
     font_size: f32,
-    rgb: &Rgb,
-) {
-    // include_bytes! only takes a string literal
-    let font_vec = open_font(font);
-
-    let font = Font::try_from_bytes(&font_vec).unwrap();
-    let height = font_size;
-    let scale = Scale {
-        x: height * 1.0,
-        y: height,
+    style: impl Into<TextStyle>,
+) -> Result<()> {
+    let face = fonts.face(font)?;
+    let options = ParagraphOptions::new().line_height(1.0);
+    let paragraph = layout_paragraph(face, text, font_size, &options);
+    draw_paragraph_styled(
+        image,
+        face,
+        &paragraph,
+        x as i32 + 10,
+        y.saturating_sub(10) as i32,
+        &style.into(),
+    );
+    Ok(())
+}
+
+/// Draw text onto an image in a given direction.
+///
+/// Runs of left-to-right and right-to-left text are ordered with the Unicode Bidirectional
+/// Algorithm. `x` is where the text starts: its left edge for left-to-right text, and its
+/// right edge for right-to-left text, so mirroring `x` mirrors the layout.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text string to be drawn.
+/// * `x` - X-coordinate of the start of the text.
+/// * `y` - Y coordinate of top of text.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `font_size`: f32 that represents the font's size.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+/// * `direction`: Base direction of the text. `TextDirection::Auto` takes it from the text.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_with_direction(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    text: &str,
+    x: u32,
+    y: u32,
+    font: &FontDescriptor,
+    font_size: f32,
+    style: impl Into<TextStyle>,
+    direction: TextDirection,
+) -> Result<()> {
+    let face = fonts.face(font)?;
+    let options = ParagraphOptions::new()
+        .line_height(1.0)
+        .direction(direction);
+    let paragraph = layout_paragraph(face, text, font_size, &options);
+    let x = match paragraph.direction {
+        TextDirection::RightToLeft => x as i32 - 10 - paragraph.width.round() as i32,
+        _ => x as i32 + 10,
     };
+    draw_paragraph_styled(
+        image,
+        face,
+        &paragraph,
+        x,
+        y.saturating_sub(10) as i32,
+        &style.into(),
+    );
+    Ok(())
+}
 
-    draw_text_mut(
+/// Draw a block of text onto an image, wrapping it into lines.
+///
+/// The text is wrapped to `options.max_width` using the advances of its shaped glyphs, and
+/// each line is aligned within the block. The laid out lines are returned, so that other
+/// elements can be placed around the text.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text string to be drawn. Newlines start a new line.
+/// * `x` - X-coordinate of the left of the block.
+/// * `y` - Y coordinate of the top of the block.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `font_size`: f32 that represents the font's size.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+/// * `options`: Wrapping, line height, letter-spacing, alignment and direction of the text.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_block(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    text: &str,
+    x: u32,
+    y: u32,
+    font: &FontDescriptor,
+    font_size: f32,
+    style: impl Into<TextStyle>,
+    options: &ParagraphOptions,
+) -> Result<Paragraph> {
+    let face = fonts.face(font)?;
+    let paragraph = layout_paragraph(face, text, font_size, options);
+    draw_paragraph_styled(image, face, &paragraph, x as i32, y as i32, &style.into());
+    Ok(paragraph)
+}
+
+/// Draw text at the largest size that fits inside a rectangle.
+///
+/// The text is wrapped to the rectangle's width, and the largest font size between `min_size`
+/// and `max_size` at which it fits is found with a binary search. The text is centred
+/// vertically within the rectangle, and aligned horizontally according to `options.align`.
+///
+/// If the text doesn't fit even at `min_size`, it is drawn at `min_size`, overflowing the
+/// rectangle. The chosen size and any overflow are returned.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text string to be drawn.
+/// * `rect` - Rectangle the text should fit inside.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `min_size`: Smallest font size that can be used.
+/// * `max_size`: Largest font size that can be used.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+/// * `options`: Line height, letter-spacing, alignment and direction of the text.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry,
+/// or if either size isn't a finite, positive number.
+#[allow(clippy::too_many_arguments)]
+pub fn fit_text(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    text: &str,
+    rect: Rect,
+    font: &FontDescriptor,
+    min_size: f32,
+    max_size: f32,
+    style: impl Into<TextStyle>,
+    options: &ParagraphOptions,
+) -> Result<FittedText> {
+    let face = fonts.face(font)?;
+    let fitted = fit_paragraph(
+        face,
+        text,
+        rect.width() as f32,
+        rect.height() as f32,
+        min_size,
+        max_size,
+        options,
+    )?;
+    let y_offset = ((rect.height() as f32 - fitted.paragraph.height) / 2.0).max(0.0);
+    draw_paragraph_styled(
         image,
-        Rgba([rgb.r as u8, rgb.g as u8, rgb.b as u8, 255u8]),
-        x + 10,
-        y - 10,
-        scale,
-        &font,
+        face,
+        &fitted.paragraph,
+        rect.left(),
+        rect.top() + y_offset.round() as i32,
+        &style.into(),
+    );
+    Ok(fitted)
+}
+
+/// Draw rich text onto an image, wrapping it into lines.
+///
+/// Rich text mixes fonts, sizes, colours and decorations in one paragraph. It can be built from
+/// spans, or parsed from markup with [`RichText::parse`].
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `text` - Rich text to be drawn.
+/// * `x` - X-coordinate of the left of the block.
+/// * `y` - Y coordinate of the top of the block.
+/// * `font` - The base font, used by spans that don't set their own.
+/// * `font_size`: The base font size, which spans are scaled relative to.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects. Spans with their own
+///   colour replace the fill.
+/// * `options`: Wrapping, line height, letter-spacing, alignment and direction of the text.
+///
+/// Returns an error if a span's font, or any of its fallbacks, aren't in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text<'a>(
+    image: &mut DynamicImage,
+    fonts: &'a FontRegistry,
+    text: &RichText,
+    x: i32,
+    y: i32,
+    font: &FontDescriptor,
+    font_size: f32,
+    style: impl Into<TextStyle>,
+    options: &ParagraphOptions,
+) -> Result<RichParagraph<'a>> {
+    let paragraph = layout_rich_text(fonts, text, font, font_size, options)?;
+    draw_rich_paragraph(image, &paragraph, x, y, &style.into());
+    Ok(paragraph)
+}
+
+/// Draw rich text at the largest size that fits inside a rectangle.
+///
+/// Works like [`fit_text`], scaling every span by the same amount.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the fonts are taken from.
+/// * `text` - Rich text to be drawn.
+/// * `rect` - Rectangle the text should fit inside.
+/// * `font` - The base font, used by spans that don't set their own.
+/// * `min_size`: Smallest base font size that can be used.
+/// * `max_size`: Largest base font size that can be used.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+/// * `options`: Line height, letter-spacing, alignment and direction of the text.
+///
+/// Returns an error if a span's font, or any of its fallbacks, aren't in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn fit_rich_text<'a>(
+    image: &mut DynamicImage,
+    fonts: &'a FontRegistry,
+    text: &RichText,
+    rect: Rect,
+    font: &FontDescriptor,
+    min_size: f32,
+    max_size: f32,
+    style: impl Into<TextStyle>,
+    options: &ParagraphOptions,
+) -> Result<FittedText<RichParagraph<'a>>> {
+    let fitted = fit_rich_paragraph(
+        fonts,
         text,
+        font,
+        rect.width() as f32,
+        rect.height() as f32,
+        min_size,
+        max_size,
+        options,
+    )?;
+    let y_offset = ((rect.height() as f32 - fitted.paragraph.height) / 2.0).max(0.0);
+    draw_rich_paragraph(
+        image,
+        &fitted.paragraph,
+        rect.left(),
+        rect.top() + y_offset.round() as i32,
+        &style.into(),
     );
+    Ok(fitted)
 }
 
 /// Draw vertical text onto an image.
 ///
-/// This is done by drawing the text horizontally onto an image,
-/// then rotating this image by 90 degrees.
+/// The text is rotated by 90 degrees, so that it reads from bottom to top when facing left,
+/// or top to bottom when facing right.
 ///
 /// ### Arguments
 /// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
 /// * `text` - Text string to be drawn.
 /// * `x` - X-coordinate of top corner of text.
 /// * `y` - Y coordinae of top corner of text.
-/// * `font` - Font name. Fonts available include Roboto-Regular, BebasKai, Roboto-Light, among many others.
-/// Full list of fonts available coming soon.
+/// * `font` - FontDescriptor giving the family, weight and style of the font, eg:
+///   `FontDescriptor::new("Roboto").bold()`.
 /// * `font_size`: f32 that represents the font's size.
 /// * `direction`: The direction the text should be facing, either "left" or "right".
-/// * `rgb`: Rgb text color.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_vertical_text(
     img: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     x: u32,
     y: u32,
-    font: &str,
+    font: &FontDescriptor,
     font_size: f32,
     direction: &str,
-    rgb: &Rgb,
-) {
+    style: impl Into<TextStyle>,
+) -> Result<()> {
     if direction == "left" {
-        draw_rotated_text(img, text, x, y, font, font_size, "270", rgb);
+        draw_turned_text(img, fonts, text, x, y, font, font_size, 3, &style.into())?;
     } else if direction == "right" {
-        draw_rotated_text(img, text, x, y, font, font_size, "90", rgb);
+        draw_turned_text(img, fonts, text, x, y, font, font_size, 1, &style.into())?;
     }
+    Ok(())
 }
 
 /// Draw single letters in a vertical column to create a vertical-text effect.
 ///
 /// # Arguments
 /// * `img` - A mutable ref to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
 /// * `text` - The text to be drawn onto the image.
-/// * `font` - The font type.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
 /// * `font_size` - The size of the font.
-/// * `rgb` - Rgb color.
+/// * `style` - Text colour, or a TextStyle giving its fill and effects.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_vertical_text_single(
     img: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     x: u32,
     mut y: u32,
-    font: &str,
+    font: &FontDescriptor,
     font_size: f32,
-    rgb: &Rgb,
-) {
+    style: impl Into<TextStyle>,
+) -> Result<()> {
+    let style = style.into();
     for c in text.split("") {
-        draw_text(img, c, x, y, font, font_size, rgb);
+        draw_text(img, fonts, c, x, y, font, font_size, &style)?;
         y += (font_size * 0.8) as u32;
     }
+    Ok(())
 }
 
 /// Draw upside-down text.
 ///
 /// ### Arguments
 /// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
 /// * `text` - Text string to be drawn.
 /// * `x` - X-coordinate of top corner of text.
 /// * `y` - Y coordinae of top corner of text.
-/// * `font` - Font name. Fonts available include Roboto-Regular, BebasKai, Roboto-Light, among many others.
-/// Full list of fonts available coming soon.
+/// * `font` - FontDescriptor giving the family, weight and style of the font, eg:
+///   `FontDescriptor::new("Roboto").bold()`.
 /// * `font_size`: f32 that represents the font's size.
-/// * `rgb`: Rgb text color.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_upsidedown_text(
     img: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     x: u32,
     y: u32,
-    font: &str,
+    font: &FontDescriptor,
     font_size: f32,
-    rgb: &Rgb,
-) {
-    draw_rotated_text(img, text, x, y, font, font_size, "180", rgb);
+    style: impl Into<TextStyle>,
+) -> Result<()> {
+    draw_turned_text(img, fonts, text, x, y, font, font_size, 2, &style.into())
 }
 
-// Draw rotated text. Available: 90, 180, 270.
-fn draw_rotated_text(
+/// Draw text rotated by any angle.
+///
+/// The text is rotated clockwise around its anchor, which is placed at `(x, y)`. Glyphs are
+/// anti-aliased directly onto the image, and the alpha of `color` sets the text's opacity.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text string to be drawn. Newlines start new lines.
+/// * `x` - X-coordinate of the anchor.
+/// * `y` - Y-coordinate of the anchor.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `font_size`: f32 that represents the font's size.
+/// * `angle`: Clockwise rotation, in degrees.
+/// * `anchor`: The point of the text placed at `(x, y)`: its top-left corner, its centre, or
+///   the start of its baseline.
+/// * `color`: Rgba text color.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rotated_text(
     image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    text: &str,
+    x: i32,
+    y: i32,
+    font: &FontDescriptor,
+    font_size: f32,
+    angle: f32,
+    anchor: TextAnchor,
+    color: Rgba<u8>,
+) -> Result<()> {
+    let face = fonts.face(font)?;
+    let paragraph = layout_paragraph(face, text, font_size, &ParagraphOptions::new());
+    draw_paragraph_rotated(
+        image, face, &paragraph, x as f32, y as f32, angle, anchor, color,
+    );
+    Ok(())
+}
+
+/// Draw text along a circle, an arc or a Bézier curve.
+///
+/// Each glyph sits with the middle of its baseline on the path, turned to follow it. See
+/// [`crate::text_path`] for the paths and options.
+///
+/// ### Arguments
+/// * `img` - Mutable reference to a DynamicImage.
+/// * `fonts` - FontRegistry the font is taken from.
+/// * `text` - Text string to be drawn, on a single line.
+/// * `path` - The path to draw the text along.
+/// * `font` - FontDescriptor giving the family, weight and style of the font.
+/// * `font_size`: f32 that represents the font's size.
+/// * `style`: Text colour, or a TextStyle giving its fill and effects.
+/// * `options`: Where the text starts along the path, its alignment, and which side of the
+///   path it is drawn on.
+///
+/// Returns an error if neither the font's family nor any of its fallbacks are in the registry.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_on_path(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
+    text: &str,
+    path: &TextPath,
+    font: &FontDescriptor,
+    font_size: f32,
+    style: impl Into<TextStyle>,
+    options: &PathTextOptions,
+) -> Result<()> {
+    let face = fonts.face(font)?;
+    let mut shaped = shape_text(face, text, font_size, options.direction);
+    apply_letter_spacing(&mut shaped, options.letter_spacing);
+    draw_shaped_text_on_path(image, face, &shaped, path, options, &style.into());
+    Ok(())
+}
+
+// Draw text turned clockwise by a number of quarter turns, so that its box, with a 10 pixel
+// margin, has its top-left corner at `(x, y)`.
+#[allow(clippy::too_many_arguments)]
+fn draw_turned_text(
+    image: &mut DynamicImage,
+    fonts: &FontRegistry,
     text: &str,
     x: u32,
     y: u32,
-    font: &str,
+    font: &FontDescriptor,
     font_size: f32,
-    rotation: &str,
-    rgb: &Rgb,
-) {
-    // Since the image will be rotated, the height of the container image will be the width of the
-    // text image.
+    quarter_turns: u8,
+    style: &TextStyle,
+) -> Result<()> {
+    let face = fonts.face(font)?;
+    let paragraph = layout_paragraph(face, text, font_size, &ParagraphOptions::new());
+    let metrics = measure_paragraph(face, &paragraph, font_size);
 
-    let font_img_height = text.len() as f32 * (font_size * 0.48);
-    let font_img_width = font_size * 1.3;
-    let mut image2: DynamicImage =
-        DynamicImage::new_luma8(font_img_height as u32, font_img_width as u32);
+    let (box_width, box_height) = if quarter_turns % 2 == 1 {
+        (metrics.height + 20.0, metrics.width + 20.0)
+    } else {
+        (metrics.width + 20.0, metrics.height + 20.0)
+    };
+    let (centre_x, centre_y) = (x as f32 + box_width / 2.0, y as f32 + box_height / 2.0);
 
-    let font_vec = open_font(font);
+    if let Some(color) = style.solid_color() {
+        draw_paragraph_rotated(
+            image,
+            face,
+            &paragraph,
+            centre_x,
+            centre_y,
+            quarter_turns as f32 * 90.0,
+            TextAnchor::Centre,
+            color,
+        );
+        return Ok(());
+    }
 
-    let font = Font::try_from_bytes(&font_vec).unwrap();
-    let scale = Scale {
-        x: font_size * 1.0,
-        y: font_size,
+    // Render the styled text upright, then turn it, which loses nothing for quarter turns.
+    // Turn the shadow's offset the other way, so it falls in the same direction on the image.
+    let mut style = style.clone();
+    if let Some(shadow) = style.shadow.as_mut() {
+        let (dx, dy) = (shadow.offset_x, shadow.offset_y);
+        (shadow.offset_x, shadow.offset_y) = match quarter_turns % 4 {
+            1 => (dy, -dx),
+            2 => (-dx, -dy),
+            3 => (-dy, dx),
+            _ => (dx, dy),
+        };
+    }
+    let (layer, offset_x, offset_y) = render_paragraph_styled(face, &paragraph, &style);
+    let (layer_width, layer_height) = (layer.width() as f32, layer.height() as f32);
+    // The centre of the text within the layer.
+    let (text_x, text_y) = (
+        metrics.width / 2.0 - offset_x as f32,
+        metrics.height / 2.0 - offset_y as f32,
+    );
+    let (layer, text_x, text_y) = match quarter_turns % 4 {
+        1 => (rotate90(&layer), layer_height - text_y, text_x),
+        2 => (
+            rotate180(&layer),
+            layer_width - text_x,
+            layer_height - text_y,
+        ),
+        3 => (rotate270(&layer), text_y, layer_width - text_x),
+        _ => (layer, text_x, text_y),
     };
-    draw_text_mut(
-        &mut image2,
-        Rgba([rgb.r as u8, rgb.g as u8, rgb.b as u8, 255u8]),
-        10,
-        10,
-        scale,
-        &font,
-        &text,
+    draw_layer(
+        image,
+        &layer,
+        (centre_x - text_x).round() as i32,
+        (centre_y - text_y).round() as i32,
     );
+    Ok(())
+}
+
+#[cfg(all(test, feature = "embedded-fonts"))]
+mod tests {
+    use super::*;
+    use crate::Error;
 
-    let image2 = image2.to_rgba8();
+    fn fit(text: &str, width: u32, height: u32, min_size: f32, max_size: f32) -> FittedText {
+        try_fit(text, width, height, min_size, max_size).unwrap()
+    }
 
-    // draw_text_mut(&mut image, Rgba([rgb.r as u8, rgb.g as u8, rgb.b as u8, 255u8]), x + 10, y - 10, scale, &font, text);
+    fn try_fit(
+        text: &str,
+        width: u32,
+        height: u32,
+        min_size: f32,
+        max_size: f32,
+    ) -> Result<FittedText> {
+        let mut image = DynamicImage::new_rgba8(400, 400);
+        let fonts = FontRegistry::embedded();
+        fit_text(
+            &mut image,
+            &fonts,
+            text,
+            Rect::at(10, 10).of_size(width, height),
+            &FontDescriptor::new("Roboto"),
+            min_size,
+            max_size,
+            &Rgb::new(255, 255, 255),
+            &ParagraphOptions::new(),
+        )
+    }
 
-    let rotated_img = match rotation {
-        "90" => rotate90(&image2),
-        "180" => rotate180(&image2),
-        "270" => rotate270(&image2),
-        _ => rotate90(&image2),
-    };
+    #[test]
+    fn empty_text_uses_the_largest_size() {
+        let fitted = fit("", 200, 100, 8.0, 64.0);
+        assert_eq!(fitted.font_size, 64.0);
+        assert!(!fitted.overflows());
+    }
 
-    let mut image2 = image::DynamicImage::ImageRgba8(rotated_img);
+    #[test]
+    fn text_that_fits_at_the_largest_size_uses_it() {
+        let fitted = fit("Hi", 300, 300, 8.0, 40.0);
+        assert_eq!(fitted.font_size, 40.0);
+        assert!(!fitted.overflows());
+    }
 
-    image::imageops::overlay(image, &mut image2, x, y);
-}
+    #[test]
+    fn fitted_text_is_within_half_a_pixel_of_the_best_size() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let fitted = fit(text, 200, 120, 8.0, 200.0);
+        assert!(!fitted.overflows());
+        assert!(fitted.font_size > 8.0 && fitted.font_size < 200.0);
+        let larger = fitted.font_size + 0.5;
+        assert!(fit(text, 200, 120, larger, larger).overflows());
+    }
 
-fn open_font(font: &str) -> std::vec::Vec<u8> {
-    let font_path = format!("fonts/{}.ttf", font);
-    let font_vec1 = fs::read(font_path).expect("Unable to read file");
+    #[test]
+    fn text_too_big_for_the_box_reports_its_overflow() {
+        let fitted = fit("Unbreakable", 20, 5, 12.0, 48.0);
+        assert_eq!(fitted.font_size, 12.0);
+        assert!(fitted.overflow_x > 0.0);
+        assert!(fitted.overflow_y > 0.0);
+    }
 
-    return font_vec1;
+    #[test]
+    fn minimum_above_maximum_uses_the_maximum() {
+        let fitted = fit("Hi", 10, 10, 48.0, 24.0);
+        assert_eq!(fitted.font_size, 24.0);
+    }
+
+    #[test]
+    fn empty_box_overflows() {
+        let fitted = fit("Hi", 1, 1, 8.0, 16.0);
+        assert!(fitted.overflows());
+    }
+
+    #[test]
+    fn sizes_that_are_not_finite_and_positive_are_an_error() {
+        let invalid = [f32::INFINITY, f32::NEG_INFINITY, f32::NAN, 0.0, -12.0];
+        for &size in &invalid {
+            for &(min_size, max_size) in &[(8.0, size), (size, 64.0), (size, size)] {
+                assert!(matches!(
+                    try_fit("Hi", 200, 100, min_size, max_size),
+                    Err(Error::InvalidFontSize { .. })
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_font_is_an_error() {
+        let mut image = DynamicImage::new_rgba8(10, 10);
+        let result = fit_text(
+            &mut image,
+            &FontRegistry::new(),
+            "Hi",
+            Rect::at(0, 0).of_size(10, 10),
+            &FontDescriptor::new("Nope"),
+            8.0,
+            16.0,
+            &Rgb::new(255, 255, 255),
+            &ParagraphOptions::new(),
+        );
+        assert!(matches!(result, Err(Error::FontNotFound(_))));
+    }
 }
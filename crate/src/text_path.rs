@@ -0,0 +1,590 @@
+//! Draw text along a path: a circle, an arc or a cubic Bézier curve.
+//!
+//! Each glyph is placed with the middle of its baseline on the path and turned to follow the
+//! path's direction there, as SVG's `<textPath>` does, so text can run around badges, stamps
+//! and circular logos.
+//!
+#![cfg_attr(feature = "embedded-fonts", doc = "```")]
+#![cfg_attr(not(feature = "embedded-fonts"), doc = "```ignore")]
+//! use gdl::fonts::{FontDescriptor, FontRegistry};
+//! use gdl::layout::TextAlign;
+//! use gdl::text::draw_text_on_path;
+//! use gdl::text_path::{PathSide, PathTextOptions, TextPath};
+//! use gdl::Rgb;
+//!
+//! let fonts = FontRegistry::embedded();
+//! let mut image = gdl::new_with_background(400, 400, &Rgb { r: 255, g: 255, b: 255 });
+//! let font = FontDescriptor::new("Oswald");
+//! let black = Rgb { r: 0, g: 0, b: 0 };
+//! let ring = TextPath::Circle { cx: 200.0, cy: 200.0, radius: 140.0, start_angle: -90.0 };
+//!
+//! // Centred across the top of the ring, reading clockwise.
+//! let top = PathTextOptions::new().align(TextAlign::Centre);
+//! draw_text_on_path(&mut image, &fonts, "HANDMADE", &ring, &font, 40.0, &black, &top)?;
+//!
+//! // Centred across the bottom, inside the ring, reading from left to right.
+//! let bottom = top.side(PathSide::Right).start_offset(ring.length() / 2.0);
+//! draw_text_on_path(&mut image, &fonts, "SINCE 1999", &ring, &font, 40.0, &black, &bottom)?;
+//! # Ok::<(), gdl::Error>(())
+//! ```
+
+use crate::emoji::{draw_emoji_glyph, EmojiLayer};
+use crate::fonts::FontFace;
+use crate::layout::{draw_glyphs_transformed, TextAlign};
+use crate::shaping::{ShapedGlyph, ShapedText, TextDirection};
+use crate::style::{draw_layer, render_mask_styled, StyleCanvas, TextStyle};
+use image::{DynamicImage, Rgba};
+use rusttype::GlyphId;
+use std::f32::consts::TAU;
+
+// Number of straight pieces a Bézier curve is measured with.
+const CURVE_SAMPLES: usize = 256;
+
+/// A path text can be drawn along.
+///
+/// Angles are in degrees, clockwise from the positive x-axis, so 0° is three o'clock and
+/// -90° is twelve o'clock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextPath {
+    /// A whole circle, starting at `start_angle` and running clockwise. Text that runs past
+    /// the start wraps around.
+    Circle {
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        start_angle: f32,
+    },
+    /// Part of a circle from `start_angle` to `end_angle`, running clockwise if `end_angle` is
+    /// the larger, and anticlockwise otherwise.
+    Arc {
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    },
+    /// A cubic Bézier curve from `from` to `to`.
+    Cubic {
+        from: (f32, f32),
+        control1: (f32, f32),
+        control2: (f32, f32),
+        to: (f32, f32),
+    },
+}
+
+impl TextPath {
+    /// Length of the path, in pixels.
+    pub fn length(&self) -> f32 {
+        match *self {
+            TextPath::Circle { radius, .. } => TAU * radius,
+            TextPath::Arc {
+                radius,
+                start_angle,
+                end_angle,
+                ..
+            } => radius * (end_angle - start_angle).abs().to_radians(),
+            TextPath::Cubic { .. } => self.curve_lengths()[CURVE_SAMPLES],
+        }
+    }
+
+    /// The point a distance along the path, and the direction the path runs in there, as a
+    /// clockwise angle in degrees.
+    ///
+    /// Distances before the start or past the end of an arc or curve are clamped to it, and
+    /// those on a circle wrap around.
+    pub fn point_at(&self, distance: f32) -> (f32, f32, f32) {
+        match *self {
+            TextPath::Circle {
+                cx,
+                cy,
+                radius,
+                start_angle,
+            } => {
+                let turned = (distance / radius.max(f32::EPSILON)).to_degrees();
+                circle_point(cx, cy, radius, start_angle + turned, 1.0)
+            }
+            TextPath::Arc {
+                cx,
+                cy,
+                radius,
+                start_angle,
+                end_angle,
+            } => {
+                let sign = if end_angle >= start_angle { 1.0 } else { -1.0 };
+                let distance = distance.clamp(0.0, self.length());
+                let turned = (distance / radius.max(f32::EPSILON)).to_degrees();
+                circle_point(cx, cy, radius, start_angle + sign * turned, sign)
+            }
+            TextPath::Cubic { .. } => {
+                // Find the curve's parameter at the distance from the lengths of its pieces.
+                let lengths = self.curve_lengths();
+                let distance = distance.clamp(0.0, lengths[CURVE_SAMPLES]);
+                let piece = lengths
+                    .iter()
+                    .position(|&length| length >= distance)
+                    .unwrap_or(CURVE_SAMPLES)
+                    .max(1);
+                let (before, after) = (lengths[piece - 1], lengths[piece]);
+                let within = if after > before {
+                    (distance - before) / (after - before)
+                } else {
+                    0.0
+                };
+                let t = (piece as f32 - 1.0 + within) / CURVE_SAMPLES as f32;
+                let (x, y) = self.curve_point(t);
+                let (dx, dy) = self.curve_direction(t);
+                (x, y, dy.atan2(dx).to_degrees())
+            }
+        }
+    }
+
+    /// Whether the path is closed, so text running past its end wraps around to its start.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, TextPath::Circle { .. })
+    }
+
+    // The distance along a curve at each of its sample points.
+    fn curve_lengths(&self) -> Vec<f32> {
+        let mut lengths = Vec::with_capacity(CURVE_SAMPLES + 1);
+        let mut total = 0.0;
+        let mut last = self.curve_point(0.0);
+        lengths.push(0.0);
+        for i in 1..=CURVE_SAMPLES {
+            let p = self.curve_point(i as f32 / CURVE_SAMPLES as f32);
+            total += ((p.0 - last.0).powi(2) + (p.1 - last.1).powi(2)).sqrt();
+            lengths.push(total);
+            last = p;
+        }
+        lengths
+    }
+
+    fn curve_point(&self, t: f32) -> (f32, f32) {
+        match *self {
+            TextPath::Cubic {
+                from,
+                control1,
+                control2,
+                to,
+            } => {
+                let u = 1.0 - t;
+                let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                (
+                    a * from.0 + b * control1.0 + c * control2.0 + d * to.0,
+                    a * from.1 + b * control1.1 + c * control2.1 + d * to.1,
+                )
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    fn curve_direction(&self, t: f32) -> (f32, f32) {
+        match *self {
+            TextPath::Cubic {
+                from,
+                control1,
+                control2,
+                to,
+            } => {
+                let u = 1.0 - t;
+                let (a, b, c) = (3.0 * u * u, 6.0 * u * t, 3.0 * t * t);
+                (
+                    a * (control1.0 - from.0)
+                        + b * (control2.0 - control1.0)
+                        + c * (to.0 - control2.0),
+                    a * (control1.1 - from.1)
+                        + b * (control2.1 - control1.1)
+                        + c * (to.1 - control2.1),
+                )
+            }
+            _ => (1.0, 0.0),
+        }
+    }
+}
+
+// A point on a circle, with the direction of travel when the angle is increasing (`sign` 1) or
+// decreasing (`sign` -1).
+fn circle_point(cx: f32, cy: f32, radius: f32, angle: f32, sign: f32) -> (f32, f32, f32) {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    (cx + radius * cos, cy + radius * sin, angle + 90.0 * sign)
+}
+
+/// Which side of a path text is drawn on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathSide {
+    /// On the left of the path, facing the way it runs: above a path running left to right,
+    /// and outside a circle.
+    #[default]
+    Left,
+    /// On the right of the path. The path is followed backwards from its end, so the text
+    /// still reads upright: inside a circle, from left to right along its bottom.
+    Right,
+}
+
+/// Options for drawing text along a path.
+///
+/// ```
+/// use gdl::layout::TextAlign;
+/// use gdl::text_path::{PathSide, PathTextOptions};
+///
+/// let options = PathTextOptions::new()
+///     .start_offset(40.0)
+///     .align(TextAlign::Centre)
+///     .side(PathSide::Right)
+///     .letter_spacing(2.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTextOptions {
+    /// Distance along the path, in pixels, the text is aligned to.
+    pub start_offset: f32,
+    /// Whether the text starts, is centred or ends at the start offset. `Justify` spreads the
+    /// text along the whole path from the start offset.
+    pub align: TextAlign,
+    pub side: PathSide,
+    /// Extra space added after each glyph, in pixels. Can be negative.
+    pub letter_spacing: f32,
+    /// How far the baseline is moved away from the path, towards the tops of the glyphs, in
+    /// pixels. Negative values move the text below the path.
+    pub baseline_shift: f32,
+    pub direction: TextDirection,
+}
+
+impl Default for PathTextOptions {
+    fn default() -> PathTextOptions {
+        PathTextOptions {
+            start_offset: 0.0,
+            align: TextAlign::Start,
+            side: PathSide::Left,
+            letter_spacing: 0.0,
+            baseline_shift: 0.0,
+            direction: TextDirection::Auto,
+        }
+    }
+}
+
+impl PathTextOptions {
+    /// Options for start-aligned text at the start of the path, on its left side.
+    pub fn new() -> PathTextOptions {
+        PathTextOptions::default()
+    }
+
+    /// Set the distance along the path the text is aligned to, in pixels.
+    pub fn start_offset(mut self, start_offset: f32) -> PathTextOptions {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Set how the text is aligned to the start offset.
+    pub fn align(mut self, align: TextAlign) -> PathTextOptions {
+        self.align = align;
+        self
+    }
+
+    /// Set the side of the path the text is drawn on.
+    pub fn side(mut self, side: PathSide) -> PathTextOptions {
+        self.side = side;
+        self
+    }
+
+    /// Set the extra space added after each glyph, in pixels.
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> PathTextOptions {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Set how far the baseline is moved away from the path, in pixels.
+    pub fn baseline_shift(mut self, baseline_shift: f32) -> PathTextOptions {
+        self.baseline_shift = baseline_shift;
+        self
+    }
+
+    /// Set the base direction of the text.
+    pub fn direction(mut self, direction: TextDirection) -> PathTextOptions {
+        self.direction = direction;
+        self
+    }
+}
+
+/// Draw shaped text along a path.
+///
+/// Glyphs that would fall before the start or past the end of an arc or curve are left out.
+///
+/// ### Arguments
+/// * `image` - Mutable reference to a DynamicImage.
+/// * `face` - The font face the text was shaped with.
+/// * `shaped` - The shaped text, with any letter spacing applied.
+/// * `path` - The path to draw the text along.
+/// * `options` - Alignment, side and offsets of the text. Its letter spacing and direction are
+///   ignored, as they are applied when the text is shaped.
+/// * `style` - Fill and effects of the text.
+pub fn draw_shaped_text_on_path(
+    image: &mut DynamicImage,
+    face: &FontFace,
+    shaped: &ShapedText,
+    path: &TextPath,
+    options: &PathTextOptions,
+    style: &TextStyle,
+) {
+    let placed = place_glyphs(shaped, path, options);
+
+    if let Some(color) = style.solid_color() {
+        let outlines = placed
+            .iter()
+            .filter(|(glyph, _)| glyph.image.is_none())
+            .map(|(glyph, transform)| (GlyphId(glyph.id), shaped.scale, *transform));
+        draw_glyphs_transformed(image, face, outlines, color);
+        for (glyph, transform) in &placed {
+            let transform = emoji_transform(shaped, glyph, *transform);
+            draw_emoji_glyph(image, shaped, glyph, transform, color[3] as f32 / 255.0);
+        }
+        return;
+    }
+
+    // Find the box the glyphs are drawn in, from the corners of their line boxes.
+    let descent = shaped.scale.y - shaped.ascent;
+    let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for (glyph, [a, b, c, d, e, f]) in &placed {
+        let width = glyph.advance.max(shaped.scale.y / 2.0);
+        for &(x, y) in &[
+            (0.0, -shaped.ascent),
+            (width, -shaped.ascent),
+            (0.0, descent),
+            (width, descent),
+        ] {
+            let (px, py) = (a * x + b * y + e, c * x + d * y + f);
+            left = left.min(px);
+            top = top.min(py);
+            right = right.max(px);
+            bottom = bottom.max(py);
+        }
+    }
+    if placed.is_empty() {
+        return;
+    }
+
+    let (left, top) = (left.floor() as i32, top.floor() as i32);
+    let canvas = StyleCanvas::new(style, left, top, right.ceil() as i32, bottom.ceil() as i32);
+    let shift = |[a, b, c, d, e, f]: [f32; 6]| {
+        [
+            a,
+            b,
+            c,
+            d,
+            e + canvas.origin_x as f32,
+            f + canvas.origin_y as f32,
+        ]
+    };
+    let mut mask = DynamicImage::new_luma8(canvas.width, canvas.height);
+    let outlines = placed
+        .iter()
+        .filter(|(glyph, _)| glyph.image.is_none())
+        .map(|(glyph, transform)| (GlyphId(glyph.id), shaped.scale, shift(*transform)));
+    draw_glyphs_transformed(&mut mask, face, outlines, Rgba([255, 255, 255, 255]));
+    let mut emoji = EmojiLayer::new(canvas.width, canvas.height);
+    for (glyph, transform) in &placed {
+        emoji.draw_glyph(
+            shaped,
+            glyph,
+            shift(emoji_transform(shaped, glyph, *transform)),
+        );
+    }
+
+    let mut mask = mask.to_luma8();
+    emoji.add_to_mask(&mut mask);
+    let layer = render_mask_styled(&canvas, &mask, style, |cx, cy| emoji.color_at(cx, cy));
+    draw_layer(image, &layer, -canvas.origin_x, -canvas.origin_y);
+}
+
+// Place each glyph on the path, giving the transform from a point relative to the glyph's
+// origin on the baseline onto the image. Glyphs that fall off an open path are left out.
+fn place_glyphs<'a>(
+    shaped: &'a ShapedText,
+    path: &TextPath,
+    options: &PathTextOptions,
+) -> Vec<(&'a ShapedGlyph, [f32; 6])> {
+    let length = path.length();
+    let spaced = shaped
+        .glyphs
+        .iter()
+        .filter(|glyph| glyph.advance > 0.0)
+        .count();
+    let gaps = if path.is_closed() {
+        spaced
+    } else {
+        spaced.saturating_sub(1)
+    };
+
+    let offset = options.start_offset;
+    let (start, spacing) = match (options.align, shaped.direction) {
+        (TextAlign::Left, _)
+        | (TextAlign::Start, TextDirection::LeftToRight)
+        | (TextAlign::End, TextDirection::RightToLeft) => (offset, 0.0),
+        (TextAlign::Centre, _) => (offset - shaped.width / 2.0, 0.0),
+        (TextAlign::Justify, _) if gaps > 0 => {
+            (offset, (length - shaped.width).max(0.0) / gaps as f32)
+        }
+        (TextAlign::Justify, _) => (offset, 0.0),
+        _ => (offset - shaped.width, 0.0),
+    };
+
+    let mut placed = Vec::with_capacity(shaped.glyphs.len());
+    let mut extra = 0.0;
+    for glyph in &shaped.glyphs {
+        let middle = start + glyph.x + extra + glyph.advance / 2.0;
+        if glyph.advance > 0.0 {
+            extra += spacing;
+        }
+        let distance = if path.is_closed() {
+            middle.rem_euclid(length.max(f32::EPSILON))
+        } else if (0.0..=length).contains(&middle) {
+            middle
+        } else {
+            continue;
+        };
+
+        let (x, y, angle) = match options.side {
+            PathSide::Left => path.point_at(distance),
+            PathSide::Right => {
+                let (x, y, angle) = path.point_at(length - distance);
+                (x, y, angle + 180.0)
+            }
+        };
+        // The glyph's origin is half its advance back along the path from the point.
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (dx, dy) = (-glyph.advance / 2.0, glyph.y - options.baseline_shift);
+        placed.push((
+            glyph,
+            [
+                cos,
+                -sin,
+                sin,
+                cos,
+                x + dx * cos - dy * sin,
+                y + dx * sin + dy * cos,
+            ],
+        ));
+    }
+    placed
+}
+
+// Emoji are placed relative to the top-left of their text, rather than their glyph's origin.
+fn emoji_transform(shaped: &ShapedText, glyph: &ShapedGlyph, transform: [f32; 6]) -> [f32; 6] {
+    let [a, b, c, d, e, f] = transform;
+    let (x, y) = (-glyph.x, -shaped.ascent - glyph.y);
+    [a, b, c, d, e + a * x + b * y, f + c * x + d * y]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINT: (f32, f32) = (10.0, 20.0);
+
+    fn finite((x, y, angle): (f32, f32, f32)) -> bool {
+        x.is_finite() && y.is_finite() && angle.is_finite()
+    }
+
+    #[test]
+    fn zero_length_paths_have_finite_points() {
+        let paths = [
+            TextPath::Circle {
+                cx: 10.0,
+                cy: 20.0,
+                radius: 0.0,
+                start_angle: 0.0,
+            },
+            TextPath::Arc {
+                cx: 10.0,
+                cy: 20.0,
+                radius: 50.0,
+                start_angle: 45.0,
+                end_angle: 45.0,
+            },
+            TextPath::Cubic {
+                from: POINT,
+                control1: POINT,
+                control2: POINT,
+                to: POINT,
+            },
+        ];
+        for path in &paths {
+            assert!(path.length() < 1e-3, "{:?}", path);
+            for &distance in &[-10.0, 0.0, 10.0] {
+                assert!(finite(path.point_at(distance)), "{:?}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn arcs_are_clamped_to_their_ends() {
+        let arc = TextPath::Arc {
+            cx: 0.0,
+            cy: 0.0,
+            radius: 100.0,
+            start_angle: 0.0,
+            end_angle: 90.0,
+        };
+        let (x, y, _) = arc.point_at(-50.0);
+        assert!((x - 100.0).abs() < 1e-3 && y.abs() < 1e-3);
+        let (x, y, _) = arc.point_at(1000.0);
+        assert!(x.abs() < 1e-3 && (y - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn straight_curves_are_measured_exactly() {
+        let line = TextPath::Cubic {
+            from: (0.0, 0.0),
+            control1: (100.0, 0.0),
+            control2: (200.0, 0.0),
+            to: (300.0, 0.0),
+        };
+        assert!((line.length() - 300.0).abs() < 1e-2);
+        let (x, y, angle) = line.point_at(150.0);
+        assert!((x - 150.0).abs() < 0.5 && y.abs() < 1e-3 && angle.abs() < 1e-3);
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    #[test]
+    fn glyphs_off_the_ends_of_open_paths_are_left_out() {
+        use crate::fonts::{with_embedded_fonts, FontDescriptor};
+        use crate::shaping::shape_text;
+
+        with_embedded_fonts(|fonts| {
+            let face = fonts.face(&FontDescriptor::new("Roboto")).unwrap();
+            let shaped = shape_text(face, "text", 40.0, TextDirection::LeftToRight);
+            let point = TextPath::Cubic {
+                from: POINT,
+                control1: POINT,
+                control2: POINT,
+                to: POINT,
+            };
+            assert!(place_glyphs(&shaped, &point, &PathTextOptions::new()).is_empty());
+            let empty = shape_text(face, "", 40.0, TextDirection::LeftToRight);
+            assert!(place_glyphs(&empty, &point, &PathTextOptions::new()).is_empty());
+
+            let mut image = DynamicImage::new_rgba8(20, 20);
+            let style = TextStyle::new().glow(crate::style::Glow::new(4));
+            draw_shaped_text_on_path(
+                &mut image,
+                face,
+                &shaped,
+                &point,
+                &PathTextOptions::new(),
+                &style,
+            );
+            let ring = TextPath::Circle {
+                cx: 10.0,
+                cy: 10.0,
+                radius: 0.0,
+                start_angle: 0.0,
+            };
+            draw_shaped_text_on_path(
+                &mut image,
+                face,
+                &shaped,
+                &ring,
+                &PathTextOptions::new(),
+                &style,
+            );
+        });
+    }
+}
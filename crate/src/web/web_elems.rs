@@ -4,6 +4,8 @@ extern crate imageproc;
 extern crate rusttype;
 use imageproc::drawing::*;
 use crate::{Rgb, helpers, PhotonImage};
+use crate::gradient::{Gradient, GradientShape, Interpolation};
+use crate::palettes;
 use image::{Rgba};
 use wasm_bindgen::JsCast;
 use imageproc::rect::Rect;
@@ -144,29 +146,52 @@ pub fn text_graphic(ctx: &CanvasRenderingContext2d, text: &str) {
 }
 
 
+/// Fill a rectangle with a gradient from the shared preset registry, the same gradient
+/// `elements::draw_preset_rect_gradient` draws natively.
 #[wasm_bindgen]
-pub fn draw_preset_gradient(ctx: &CanvasRenderingContext2d, x_pos: f64, y_pos: f64, width: f64, height: f64, preset: &str) {
-
-    let (color1, color2) = match preset {
-        "lemongrass" => ("blue", "yellow"),
-        "flames" => ("#f12711", "#f5af19"),
-        "pastel" => ("#8360c3", "#2ebf91"),
-        "pink" => ("#DD5E89", "#F7BB97"),
-        "ocean" => ("#4CB8C4", "#3CD3AD"),
-        "aquamarine" => ("#1A2980", "#26D0CE"),
-        "rosewater" => ("#E55D87", "#5FC3E4"),
-        "zigzag" => ("#3CA55C", "#B5AC49"), 
-        "seaweed" => ("#348F50", "#56B4D3"),
-        "tropics" => ("#16A085", "#F4D03F"),
-        _ => ("magenta", "black")
-    };
-
-    let gradient = ctx.create_linear_gradient(x_pos, y_pos, x_pos + width, y_pos + height);
-    gradient.add_color_stop(0.0, color1).unwrap();
-    gradient.add_color_stop(1.0, color2).unwrap();
+pub fn draw_preset_gradient(ctx: &CanvasRenderingContext2d, x_pos: f64, y_pos: f64, width: f64, height: f64, preset: &str) -> Result<(), JsValue> {
+    let preset = palettes::gradient(preset)?;
+    let gradient = canvas_gradient(ctx, &preset, x_pos, y_pos, width, height)?;
 
     ctx.set_fill_style(&gradient);
-    ctx.fill_rect(x_pos, y_pos, width, height); 
+    ctx.fill_rect(x_pos, y_pos, width, height);
+    Ok(())
+}
+
+// Build a canvas gradient drawn across the given box, like `Gradient::render`.
+fn canvas_gradient(ctx: &CanvasRenderingContext2d, preset: &Gradient, x_pos: f64, y_pos: f64, width: f64, height: f64) -> Result<CanvasGradient, JsValue> {
+    let canvas_gradient = match preset.shape {
+        GradientShape::Linear { angle } => {
+            let (sin, cos) = (angle as f64).to_radians().sin_cos();
+            let half_length = ((width * cos).abs() + (height * sin).abs()) / 2.0;
+            let (cx, cy) = (x_pos + width / 2.0, y_pos + height / 2.0);
+            ctx.create_linear_gradient(cx - cos * half_length, cy - sin * half_length, cx + cos * half_length, cy + sin * half_length)
+        }
+        GradientShape::Radial { center, radius } => {
+            let (cx, cy) = (x_pos + center.0 as f64 * width, y_pos + center.1 as f64 * height);
+            let furthest = [(x_pos, y_pos), (x_pos + width, y_pos), (x_pos, y_pos + height), (x_pos + width, y_pos + height)]
+                .iter()
+                .map(|&(x, y)| ((x - cx).powi(2) + (y - cy).powi(2)).sqrt())
+                .fold(0.0, f64::max);
+            ctx.create_radial_gradient(cx, cy, 0.0, cx, cy, radius as f64 * furthest)?
+        }
+        GradientShape::Conic { center, angle } => {
+            let (cx, cy) = (x_pos + center.0 as f64 * width, y_pos + center.1 as f64 * height);
+            ctx.create_conic_gradient((angle as f64).to_radians(), cx, cy)
+        }
+    };
+
+    // Canvas gradients are always mixed in sRGB, so other colour spaces are approximated
+    // with extra stops taken from the preset.
+    let stops: Vec<(f32, Rgba<u8>)> = match preset.interpolation {
+        Interpolation::Srgb => preset.stops().iter().map(|stop| (stop.offset, stop.color)).collect(),
+        _ => (0..=32).map(|i| i as f32 / 32.0).map(|offset| (offset, preset.color_at(offset))).collect(),
+    };
+    for (offset, color) in stops {
+        let css = crate::Rgba::from(color).to_css();
+        canvas_gradient.add_color_stop(offset, &css)?;
+    }
+    Ok(canvas_gradient)
 }
 
 #[wasm_bindgen]